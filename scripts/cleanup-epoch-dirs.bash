@@ -0,0 +1,41 @@
+#!/bin/bash
+
+set -e
+
+work_dir="$1"
+keep_epochs="$2"
+dry_run="$3"
+
+if [[ -z $work_dir ]] || ! [[ $keep_epochs =~ ^[0-9]+$ ]] || { [[ -n $dry_run ]] && [[ $dry_run != "--dry-run" ]]; }
+then
+    echo "Usage: $0 <work-dir> <keep-epochs> [--dry-run]" >&2
+    exit 1
+fi
+
+if ! [[ -d $work_dir ]]
+then
+    echo "Work directory ($work_dir) does not exist." >&2
+    exit 1
+fi
+
+# per-epoch directories are named by the epoch number, anything else is left untouched
+epoch_dirs=$(find "$work_dir" -mindepth 1 -maxdepth 1 -type d -regex '.*/[0-9]+' -printf '%f\n' | sort -n -r)
+expired_epoch_dirs=$(<<<"$epoch_dirs" tail -n +$((keep_epochs + 1)))
+
+if [[ -z $expired_epoch_dirs ]]
+then
+    echo "No epoch directories to remove, keeping the last $keep_epochs." >&2
+    exit 0
+fi
+
+for epoch in $expired_epoch_dirs
+do
+    epoch_dir="$work_dir/$epoch"
+    if [[ $dry_run == "--dry-run" ]]
+    then
+        echo "Would remove: $epoch_dir ($(du -sh "$epoch_dir" | cut -f1))"
+    else
+        echo "Removing: $epoch_dir" >&2
+        rm -rf -- "$epoch_dir"
+    fi
+done
@@ -0,0 +1,59 @@
+// Decode paths run once per scanned account, see the Benchmarks section of the README.
+mod fixtures;
+
+use anchor_lang::AnchorDeserialize;
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use snapshot_parser_tokens_cli::accounts::{Registrar, Voter};
+use snapshot_parser_tokens_cli::processors::{
+    mint_row, token_row, unpack_mint, unpack_token_account, vemnde_row,
+};
+use solana_program::pubkey::Pubkey;
+
+fn token(c: &mut Criterion) {
+    let token_program = spl_token::id();
+    let pubkey = Pubkey::new_unique();
+    let token_account = fixtures::token_account();
+    let mint = fixtures::mint();
+    let mut group = c.benchmark_group("token");
+    group.bench_function("unpack_token_account", |b| {
+        b.iter(|| unpack_token_account(black_box(&token_account)).unwrap())
+    });
+    group.bench_function("token_row", |b| {
+        b.iter(|| {
+            let token_account = unpack_token_account(black_box(&token_account)).unwrap();
+            token_row(&pubkey, &token_program, &token_account)
+        })
+    });
+    group.bench_function("unpack_mint", |b| {
+        b.iter(|| unpack_mint(black_box(&mint)).unwrap())
+    });
+    group.bench_function("mint_row", |b| {
+        b.iter(|| {
+            let mint = unpack_mint(black_box(&mint)).unwrap();
+            mint_row(&pubkey, &token_program, &mint, 600)
+        })
+    });
+    group.finish();
+}
+
+fn vsr(c: &mut Criterion) {
+    let registrar = Registrar::deserialize(&mut fixtures::vsr_registrar().as_slice()).unwrap();
+    let (pubkey, owner) = (Pubkey::new_unique(), Pubkey::new_unique());
+    let mut group = c.benchmark_group("vsr");
+    for used_deposits in [1, 8, 32] {
+        let voter = fixtures::vsr_voter(used_deposits);
+        group.bench_function(format!("voter_deserialize/{}", used_deposits), |b| {
+            b.iter(|| Voter::deserialize(&mut black_box(voter.as_slice())).unwrap())
+        });
+        group.bench_function(format!("voter_voting_power/{}", used_deposits), |b| {
+            b.iter(|| {
+                let voter = Voter::deserialize(&mut black_box(voter.as_slice())).unwrap();
+                vemnde_row(&pubkey, &owner, &registrar, &voter, fixtures::CURRENT_TS).unwrap()
+            })
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, token, vsr);
+criterion_main!(benches);
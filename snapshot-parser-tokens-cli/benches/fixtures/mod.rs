@@ -0,0 +1,195 @@
+// Account data of the benchmarked decode paths, laid out byte by byte as the programs store them,
+// so the benchmarks do not depend on a snapshot.
+#![allow(dead_code)]
+
+use solana_program::program_option::COption;
+use solana_program::program_pack::Pack;
+use solana_program::pubkey::Pubkey;
+use spl_token_2022::state::{Account, AccountState, Mint};
+
+/// Unix timestamp the voting power is computed at.
+pub const CURRENT_TS: i64 = 1_700_000_000;
+
+const SECS_PER_DAY: i64 = 86_400;
+
+/// Little-endian writer of the borsh layout of the accounts.
+#[derive(Default)]
+pub struct AccountData(Vec<u8>);
+
+impl AccountData {
+    pub fn u8(mut self, value: u8) -> Self {
+        self.0.push(value);
+        self
+    }
+
+    pub fn u16(mut self, value: u16) -> Self {
+        self.0.extend_from_slice(&value.to_le_bytes());
+        self
+    }
+
+    pub fn u32(mut self, value: u32) -> Self {
+        self.0.extend_from_slice(&value.to_le_bytes());
+        self
+    }
+
+    pub fn u64(mut self, value: u64) -> Self {
+        self.0.extend_from_slice(&value.to_le_bytes());
+        self
+    }
+
+    pub fn i64(mut self, value: i64) -> Self {
+        self.0.extend_from_slice(&value.to_le_bytes());
+        self
+    }
+
+    pub fn pubkey(mut self, value: &Pubkey) -> Self {
+        self.0.extend_from_slice(value.as_ref());
+        self
+    }
+
+    pub fn zeros(mut self, len: usize) -> Self {
+        self.0.resize(self.0.len() + len, 0);
+        self
+    }
+
+    /// Borsh string NUL-padded to `padded_len` bytes, as the token metadata program stores it.
+    pub fn padded_string(self, value: &str, padded_len: usize) -> Self {
+        let mut data = self.u32(padded_len as u32);
+        data.0.extend_from_slice(value.as_bytes());
+        data.zeros(padded_len - value.len())
+    }
+
+    /// The data zero-padded to the account length.
+    pub fn build(mut self, account_len: usize) -> Vec<u8> {
+        assert!(self.0.len() <= account_len, "account data over its length");
+        self.0.resize(account_len, 0);
+        self.0
+    }
+}
+
+/// Initialized token account of the spl-token layout (165 bytes).
+pub fn token_account() -> Vec<u8> {
+    let account = Account {
+        mint: Pubkey::new_unique(),
+        owner: Pubkey::new_unique(),
+        amount: 1_234_567_890,
+        delegate: COption::None,
+        state: AccountState::Initialized,
+        is_native: COption::None,
+        delegated_amount: 0,
+        close_authority: COption::None,
+    };
+    let mut data = vec![0; Account::LEN];
+    Account::pack(account, &mut data).expect("token account packs");
+    data
+}
+
+/// Initialized mint of the spl-token layout (82 bytes).
+pub fn mint() -> Vec<u8> {
+    let mint = Mint {
+        mint_authority: COption::Some(Pubkey::new_unique()),
+        supply: 1_000_000_000_000_000,
+        decimals: 9,
+        is_initialized: true,
+        freeze_authority: COption::None,
+    };
+    let mut data = vec![0; Mint::LEN];
+    Mint::pack(mint, &mut data).expect("mint packs");
+    data
+}
+
+/// VSR registrar with one voting mint of no baseline vote weight and the locked vote weight saturating in 5 years.
+pub fn vsr_registrar() -> Vec<u8> {
+    let mut data = AccountData::default()
+        .zeros(8) // discriminator
+        .pubkey(&Pubkey::new_unique()) // governance_program_id
+        .pubkey(&Pubkey::new_unique()) // realm
+        .pubkey(&Pubkey::new_unique()) // realm_governing_token_mint
+        .pubkey(&Pubkey::new_unique()) // realm_authority
+        .zeros(32); // reserved1
+    for index in 0..4 {
+        data = data
+            .pubkey(&Pubkey::new_unique()) // mint
+            .pubkey(&Pubkey::new_unique()) // grant_authority
+            .u64(0) // baseline_vote_weight_scaled_factor
+            .u64(if index == 0 { 1_000_000_000 } else { 0 }) // max_extra_lockup_vote_weight_scaled_factor
+            .u64(5 * 365 * SECS_PER_DAY as u64) // lockup_saturation_secs
+            .u8(0) // digit_shift
+            .zeros(7 + 7 * 8); // reserved1, reserved2
+    }
+    data.i64(0) // time_offset
+        .u8(255) // bump
+        .zeros(7 + 11 * 8) // reserved2, reserved3
+        .build(880)
+}
+
+/// VSR voter of 2728 bytes with `used_deposits` used deposits cycling through the lockup kinds.
+pub fn vsr_voter(used_deposits: usize) -> Vec<u8> {
+    let mut data = AccountData::default()
+        .zeros(8) // discriminator
+        .pubkey(&Pubkey::new_unique()) // voter_authority
+        .pubkey(&Pubkey::new_unique()); // registrar
+    for index in 0..32 {
+        let is_used = index < used_deposits;
+        // None, Daily, Monthly, Cliff, Constant
+        let kind = (index % 5) as u8;
+        let (start_ts, end_ts) = match kind {
+            0 => (0, 0),
+            2 => (
+                CURRENT_TS - 100 * SECS_PER_DAY,
+                CURRENT_TS + 265 * SECS_PER_DAY,
+            ),
+            _ => (
+                CURRENT_TS - 30 * SECS_PER_DAY,
+                CURRENT_TS + 335 * SECS_PER_DAY,
+            ),
+        };
+        let amount = 1_000_000_000 * (index as u64 + 1);
+        data = data
+            .i64(start_ts)
+            .i64(end_ts)
+            .u8(kind)
+            .zeros(15) // lockup reserved
+            .u64(if is_used { amount } else { 0 }) // amount_deposited_native
+            .u64(if is_used { amount } else { 0 }) // amount_initially_locked_native
+            .u8(is_used as u8)
+            .u8(0) // allow_clawback
+            .u8(0) // voting_mint_config_idx
+            .zeros(29); // reserved
+    }
+    data.u8(255) // voter_bump
+        .u8(254) // voter_weight_record_bump
+        .zeros(94) // reserved
+        .build(2728)
+}
+
+/// Token metadata (MetadataV1) of a fungible token with one creator, padded to the maximum metadata length.
+pub fn token_metadata() -> Vec<u8> {
+    AccountData::default()
+        .u8(4) // key: MetadataV1
+        .pubkey(&Pubkey::new_unique()) // update_authority
+        .pubkey(&Pubkey::new_unique()) // mint
+        .padded_string("Marinade staked SOL (mSOL)", 32)
+        .padded_string("mSOL", 10)
+        .padded_string(
+            "https://raw.githubusercontent.com/marinade-finance/msol-metadata/main/msol.json",
+            200,
+        )
+        .u16(0) // seller_fee_basis_points
+        .u8(1) // creators: Some
+        .u32(1)
+        .pubkey(&Pubkey::new_unique()) // creator address
+        .u8(1) // verified
+        .u8(100) // share
+        .u8(1) // primary_sale_happened
+        .u8(1) // is_mutable
+        .u8(1) // edition_nonce: Some
+        .u8(254)
+        .u8(1) // token_standard: Some
+        .u8(2) // Fungible
+        .u8(0) // collection: None
+        .u8(0) // uses: None
+        .u8(0) // collection_details: None
+        .u8(0) // programmable_config: None
+        .build(679)
+}
@@ -0,0 +1,27 @@
+// Token metadata decode path, built with the metaplex feature only.
+mod fixtures;
+
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use mpl_token_metadata::accounts::Metadata;
+use snapshot_parser_tokens_cli::processors::{token_metadata_row, MetadataSanitization};
+use solana_program::pubkey::Pubkey;
+
+fn token_metadata(c: &mut Criterion) {
+    let pubkey = Pubkey::new_unique();
+    let metadata = fixtures::token_metadata();
+    let sanitization = MetadataSanitization::default();
+    let mut group = c.benchmark_group("token_metadata");
+    group.bench_function("safe_deserialize", |b| {
+        b.iter(|| Metadata::safe_deserialize(black_box(&metadata)).unwrap())
+    });
+    group.bench_function("token_metadata_row", |b| {
+        b.iter(|| {
+            let decoded = Metadata::safe_deserialize(black_box(&metadata)).unwrap();
+            token_metadata_row(&pubkey, metadata.len(), &decoded, &sanitization, None)
+        })
+    });
+    group.finish();
+}
+
+criterion_group!(benches, token_metadata);
+criterion_main!(benches);
@@ -0,0 +1,9 @@
+-- Owners holding at least :min_amount of the :mint summed over all their token accounts.
+SELECT owner,
+       SUM(amount)   AS amount,
+       COUNT(*)      AS token_accounts
+FROM token_account
+WHERE mint = :mint
+GROUP BY owner
+HAVING SUM(amount) >= CAST(:min_amount AS INTEGER)
+ORDER BY amount DESC, owner;
@@ -0,0 +1,7 @@
+-- Active delegation of the native stake accounts summed per withdraw authority.
+SELECT withdraw_authority,
+       SUM(CAST(amount AS INTEGER)) AS amount,
+       COUNT(*)                     AS stake_accounts
+FROM native_stake_accounts
+GROUP BY withdraw_authority
+ORDER BY amount DESC, withdraw_authority;
@@ -0,0 +1,8 @@
+-- Voting power of the VSR voter accounts summed per voter authority.
+SELECT voter_authority,
+       SUM(CAST(voting_power AS INTEGER)) AS voting_power,
+       COUNT(*)                           AS voters
+FROM vemnde_accounts
+GROUP BY voter_authority
+HAVING SUM(CAST(voting_power AS INTEGER)) >= CAST(:min_voting_power AS INTEGER)
+ORDER BY voting_power DESC, voter_authority;
@@ -0,0 +1,216 @@
+use anchor_lang::prelude::*;
+use solana_program::hash::hash;
+use solana_program::pubkey::Pubkey;
+
+// imported from https://github.com/marinade-finance/liquid-staking-program/blob/v2.0.0/programs/marinade-finance/src/state/mod.rs
+// the state is an anchor account, the validator and stake lists are raw accounts of fixed size items
+pub const VALIDATOR_LIST_DISCRIMINATOR: &[u8; 8] = b"validatr";
+pub const STAKE_LIST_DISCRIMINATOR: &[u8; 8] = b"staker__";
+
+/// Anchor discriminator of the `State` account.
+pub fn marinade_state_discriminator() -> [u8; 8] {
+    let mut discriminator = [0u8; 8];
+    discriminator.copy_from_slice(&hash(b"account:State").to_bytes()[..8]);
+    discriminator
+}
+
+#[derive(AnchorDeserialize)]
+pub struct MarinadeState {
+    pub discriminator: [u8; 8],
+    pub msol_mint: Pubkey,
+
+    pub admin_authority: Pubkey,
+
+    // Target for withdrawing rent reserve SOLs. Save bot wallet account here
+    pub operational_sol_account: Pubkey,
+    // treasury - external accounts managed by marinade DAO
+    pub treasury_msol_account: Pubkey,
+
+    // Bump seeds:
+    pub reserve_bump_seed: u8,
+    pub msol_mint_authority_bump_seed: u8,
+
+    pub rent_exempt_for_token_acc: u64, // Token-Account For rent exempt
+
+    // fee applied on rewards
+    pub reward_fee: MarinadeFee,
+
+    pub stake_system: StakeSystem,
+    pub validator_system: ValidatorSystem, //includes total_balance = total stake under management
+
+    // sum of all the orders received in this epoch
+    // must not be used for stake-unstake amount calculation
+    // only for reference
+    // epoch_stake_orders: u64,
+    // epoch_unstake_orders: u64,
+    pub liq_pool: LiqPool,
+    pub available_reserve_balance: u64, // reserve_pda.lamports() - self.rent_exempt_for_token_acc. Virtual value (real may be > because of transfers into reserve). Use Update* to align
+    pub msol_supply: u64, // Virtual value (may be < because of token burn). Use Update* to align
+    // For FE. Don't use it for token amount calculation
+    pub msol_price: u64,
+
+    ///count tickets for delayed-unstake
+    pub circulating_ticket_count: u64,
+    ///total lamports amount of generated and not claimed yet tickets
+    pub circulating_ticket_balance: u64,
+    pub lent_from_reserve: u64,
+    pub min_deposit: u64,
+    pub min_withdraw: u64,
+    pub staking_sol_cap: u64,
+
+    pub emergency_cooling_down: u64,
+
+    /// emergency pause
+    pub pause_authority: Pubkey,
+    pub paused: bool,
+
+    // delayed unstake account fee
+    // to avoid economic attacks this value should not be zero
+    // (this is required because tickets are ready at the end of the epoch)
+    // preferred value is one epoch rewards
+    pub delayed_unstake_fee: FeeCents,
+
+    // withdraw stake account fee
+    // to avoid economic attacks this value should not be zero
+    // (this is required because stake accounts are delivered immediately)
+    // preferred value is one epoch rewards
+    pub withdraw_stake_account_fee: FeeCents,
+    pub withdraw_stake_account_enabled: bool,
+
+    // Limit moving stakes from one validator to another
+    // by calling redelegate, emergency_unstake and partial_unstake
+    // in case of stolen validator manager key or broken delegation strategy bot
+    pub last_stake_move_epoch: u64, // epoch of the last stake move action
+    pub stake_moved: u64,           // total amount of moved SOL during the epoch #stake_move_epoch
+    pub max_stake_moved_per_epoch: MarinadeFee, // % of total_lamports_under_control
+}
+
+#[derive(AnchorDeserialize)]
+pub struct MarinadeFee {
+    pub basis_points: u32,
+}
+
+#[derive(AnchorDeserialize)]
+pub struct FeeCents {
+    pub bp_cents: u32,
+}
+
+#[derive(AnchorDeserialize)]
+pub struct List {
+    pub account: Pubkey,
+    pub item_size: u32,
+    pub count: u32,
+    // For chunked change account
+    pub reserved1: Pubkey,
+    pub reserved2: u32,
+}
+
+#[derive(AnchorDeserialize)]
+pub struct StakeSystem {
+    pub stake_list: List,
+    //pub last_update_epoch: u64,
+    //pub updated_during_last_epoch: u32,
+    pub delayed_unstake_cooling_down: u64,
+    pub stake_deposit_bump_seed: u8,
+    pub stake_withdraw_bump_seed: u8,
+
+    /// set by admin, how much slots before the end of the epoch, stake-delta can start
+    pub slots_for_stake_delta: u64,
+    /// Marks the start of stake-delta operations, meaning that if somebody starts a delayed-unstake ticket
+    /// after this var is set with epoch_num the ticket will have epoch_created = current_epoch+1
+    /// (the user must wait one more epoch, because their unstake-delta will be execute in this epoch)
+    pub last_stake_delta_epoch: u64,
+    pub min_stake: u64, // Minimal stake account delegation
+    /// can be set by validator-manager-auth to allow a second run of stake-delta to stake late stakers in the last minute of the epoch
+    /// so we maximize user's rewards
+    pub extra_stake_delta_runs: u32,
+}
+
+#[derive(AnchorDeserialize)]
+pub struct ValidatorSystem {
+    pub validator_list: List,
+    pub manager_authority: Pubkey,
+    pub total_validator_score: u32,
+    /// sum of all active lamports staked
+    pub total_active_balance: u64,
+    /// allow & auto-add validator when a user deposits a stake-account of a non-listed validator
+    pub auto_add_validator_enabled: u8,
+}
+
+#[derive(AnchorDeserialize)]
+pub struct LiqPool {
+    pub lp_mint: Pubkey,
+    pub lp_mint_authority_bump_seed: u8,
+    pub sol_leg_bump_seed: u8,
+    pub msol_leg_authority_bump_seed: u8,
+    pub msol_leg: Pubkey,
+
+    //The next 3 values define the SOL/mSOL Liquidity pool fee curve params
+    // We assume this pool is always UNBALANCED, there should be more SOL than mSOL 99% of the time
+    ///Liquidity target. If the Liquidity reach this amount, the fee reaches lp_min_discount_fee
+    pub lp_liquidity_target: u64, // 10_000 SOL initially
+    /// Liquidity pool max fee
+    pub lp_max_fee: MarinadeFee, //3% initially
+    /// SOL/mSOL Liquidity pool min fee
+    pub lp_min_fee: MarinadeFee, //0.3% initially
+    /// Treasury cut
+    pub treasury_cut: MarinadeFee, //2500 => 25% how much of the Liquid unstake fee goes to treasury_msol_account
+
+    pub lp_supply: u64, // virtual lp token supply. May be > real supply because of burning tokens. Use UpdateLiqPool to align it with real value
+    pub lent_from_sol_leg: u64,
+    pub liquidity_sol_cap: u64,
+}
+
+#[derive(AnchorDeserialize)]
+pub struct ValidatorRecord {
+    /// Validator vote pubkey
+    pub validator_account: Pubkey,
+
+    /// Validator total balance in lamports
+    pub active_balance: u64, // must be 0 for removing
+    pub score: u32,
+    pub last_stake_delta_epoch: u64,
+    pub duplication_flag_bump_seed: u8,
+}
+
+#[derive(AnchorDeserialize)]
+pub struct StakeRecord {
+    pub stake_account: Pubkey,
+    pub last_update_delegated_lamports: u64,
+    pub last_update_epoch: u64,
+    pub is_emergency_unstaking: u8, // 1 for cooling down after emergency unstake, 0 otherwise
+}
+
+/// Items of a validator or stake list account, each decoded from its prefix of `item_size` bytes
+/// after the 8 bytes of the list discriminator.
+pub fn list_items<T: AnchorDeserialize>(
+    data: &[u8],
+    discriminator: &[u8; 8],
+    list: &List,
+) -> anyhow::Result<Vec<T>> {
+    if data.len() < 8 || &data[..8] != discriminator {
+        anyhow::bail!("List account {} has a wrong discriminator", list.account);
+    }
+    let item_size = list.item_size as usize;
+    (0..list.count as usize)
+        .map(|index| {
+            let start = 8 + index * item_size;
+            let mut item = data.get(start..start + item_size).ok_or_else(|| {
+                anyhow::anyhow!(
+                    "List account {} is too short for item {} of {}",
+                    list.account,
+                    index,
+                    list.count
+                )
+            })?;
+            T::deserialize(&mut item).map_err(|e| {
+                anyhow::anyhow!(
+                    "Failed to unpack item {} of list {}: {}",
+                    index,
+                    list.account,
+                    e
+                )
+            })
+        })
+        .collect()
+}
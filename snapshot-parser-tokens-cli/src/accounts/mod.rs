@@ -1,3 +1,7 @@
+pub mod marinade;
+pub mod stake_pool;
 pub mod vsr;
 
+pub use marinade::*;
+pub use stake_pool::*;
 pub use vsr::*;
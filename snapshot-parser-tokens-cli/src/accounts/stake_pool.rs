@@ -0,0 +1,169 @@
+use anchor_lang::prelude::*;
+use solana_program::pubkey::Pubkey;
+
+// imported from https://github.com/solana-labs/solana-program-library/blob/stake-pool-v2.0.0/stake-pool/program/src/state.rs
+// the accounts are allocated larger than their data, so they are deserialized from the prefix without checking the length
+pub const STAKE_POOL_ACCOUNT_TYPE: u8 = 1;
+pub const VALIDATOR_LIST_ACCOUNT_TYPE: u8 = 2;
+
+#[derive(AnchorDeserialize)]
+pub struct StakePool {
+    /// Account type, must be StakePool currently
+    pub account_type: u8,
+
+    /// Manager authority, allows for updating the staker, manager, and fee account
+    pub manager: Pubkey,
+
+    /// Staker authority, allows for adding and removing validators, and managing stake distribution
+    pub staker: Pubkey,
+
+    /// Stake deposit authority
+    pub stake_deposit_authority: Pubkey,
+
+    /// Stake withdrawal authority bump seed
+    pub stake_withdraw_bump_seed: u8,
+
+    /// Validator stake list storage account
+    pub validator_list: Pubkey,
+
+    /// Reserve stake account, holds deactivated stake
+    pub reserve_stake: Pubkey,
+
+    /// Pool Mint
+    pub pool_mint: Pubkey,
+
+    /// Manager fee account
+    pub manager_fee_account: Pubkey,
+
+    /// Pool token program id
+    pub token_program_id: Pubkey,
+
+    /// Total stake under management.
+    pub total_lamports: u64,
+
+    /// Total supply of pool tokens (should always match the supply in the Pool Mint)
+    pub pool_token_supply: u64,
+
+    /// Last epoch the `total_lamports` field was updated
+    pub last_update_epoch: u64,
+
+    /// Lockup that all stakes in the pool must have
+    pub lockup: StakePoolLockup,
+
+    /// Fee taken as a proportion of rewards each epoch
+    pub epoch_fee: Fee,
+
+    /// Fee for next epoch
+    pub next_epoch_fee: FutureEpochFee,
+
+    /// Preferred deposit validator vote account pubkey
+    pub preferred_deposit_validator_vote_address: Option<Pubkey>,
+
+    /// Preferred withdraw validator vote account pubkey
+    pub preferred_withdraw_validator_vote_address: Option<Pubkey>,
+
+    /// Fee assessed on stake deposits
+    pub stake_deposit_fee: Fee,
+
+    /// Fee assessed on withdrawals
+    pub stake_withdrawal_fee: Fee,
+
+    /// Future stake withdrawal fee, to be set for the following epoch
+    pub next_stake_withdrawal_fee: FutureEpochFee,
+
+    /// Fees paid out to referrers on referred stake deposits.
+    pub stake_referral_fee: u8,
+
+    /// Toggles whether the `DepositSol` instruction requires a signature from this `sol_deposit_authority`
+    pub sol_deposit_authority: Option<Pubkey>,
+
+    /// Fee assessed on SOL deposits
+    pub sol_deposit_fee: Fee,
+
+    /// Fees paid out to referrers on referred SOL deposits.
+    pub sol_referral_fee: u8,
+
+    /// Toggles whether the `WithdrawSol` instruction requires a signature from the `deposit_authority`
+    pub sol_withdraw_authority: Option<Pubkey>,
+
+    /// Fee assessed on SOL withdrawals
+    pub sol_withdrawal_fee: Fee,
+
+    /// Future SOL withdrawal fee, to be set for the following epoch
+    pub next_sol_withdrawal_fee: FutureEpochFee,
+
+    /// Last epoch's total pool tokens, used only for APR estimation
+    pub last_epoch_pool_token_supply: u64,
+
+    /// Last epoch's total lamports, used only for APR estimation
+    pub last_epoch_total_lamports: u64,
+}
+
+/// `solana_program::stake::state::Lockup`
+#[derive(AnchorDeserialize)]
+pub struct StakePoolLockup {
+    pub unix_timestamp: i64,
+    pub epoch: u64,
+    pub custodian: Pubkey,
+}
+
+/// Fee rate as a ratio, minted on `UpdateStakePoolBalance` as a proportion of the rewards
+#[derive(AnchorDeserialize)]
+pub struct Fee {
+    pub denominator: u64,
+    pub numerator: u64,
+}
+
+/// `FutureEpoch<Fee>`, a fee that takes effect after an epoch boundary
+#[derive(AnchorDeserialize)]
+pub enum FutureEpochFee {
+    None,
+    One(Fee),
+    Two(Fee),
+}
+
+#[derive(AnchorDeserialize)]
+pub struct ValidatorList {
+    /// Data outside of the validator list, separated out for cheaper deserializations
+    pub header: ValidatorListHeader,
+
+    /// List of stake info for each validator in the pool
+    pub validators: Vec<ValidatorStakeInfo>,
+}
+
+#[derive(AnchorDeserialize)]
+pub struct ValidatorListHeader {
+    /// Account type, must be ValidatorList currently
+    pub account_type: u8,
+
+    /// Maximum allowable number of validators
+    pub max_validators: u32,
+}
+
+#[derive(AnchorDeserialize)]
+pub struct ValidatorStakeInfo {
+    /// Amount of lamports on the validator stake account, including rent
+    pub active_stake_lamports: u64,
+
+    /// Amount of transient stake delegated to this validator
+    pub transient_stake_lamports: u64,
+
+    /// Last epoch the active and transient stake lamports fields were updated
+    pub last_update_epoch: u64,
+
+    /// Transient account seed suffix, used to derive the transient stake account address
+    pub transient_seed_suffix: u64,
+
+    /// Unused space, initially meant to specify the end of seed suffixes
+    pub unused: u32,
+
+    /// Validator account seed suffix
+    pub validator_seed_suffix: u32,
+
+    /// Status of the validator stake account
+    /// (0 active, 1 deactivating transient, 2 ready for removal, 3 deactivating validator, 4 deactivating all)
+    pub status: u8,
+
+    /// Validator vote account address
+    pub vote_account_address: Pubkey,
+}
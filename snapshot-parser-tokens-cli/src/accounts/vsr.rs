@@ -26,6 +26,21 @@ pub struct Registrar {
     pub reserved3: [u64; 11], // split because `Default` does not support [u8; 95]
 }
 
+impl Registrar {
+    /// Voting mint config the deposit is weighted by.
+    pub fn voting_mint_config(&self, deposit: &DepositEntry) -> anyhow::Result<&VotingMintConfig> {
+        self.voting_mints
+            .get(deposit.voting_mint_config_idx as usize)
+            .ok_or_else(|| {
+                anyhow!(
+                    "Deposit voting mint config index {} is out of the {} voting mints of the registrar",
+                    deposit.voting_mint_config_idx,
+                    self.voting_mints.len()
+                )
+            })
+    }
+}
+
 #[derive(AnchorDeserialize)]
 pub struct VotingMintConfig {
     /// Mint for this entry.
@@ -304,6 +319,19 @@ impl DepositEntry {
         voting_mint_config: &VotingMintConfig,
         curr_ts: i64,
     ) -> anyhow::Result<u64> {
+        let (baseline_vote_weight, locked_vote_weight) =
+            self.vote_weights(voting_mint_config, curr_ts)?;
+        baseline_vote_weight
+            .checked_add(locked_vote_weight)
+            .ok_or_else(|| anyhow::anyhow!("VoterWeightOverflow"))
+    }
+
+    /// Baseline and locked vote weight of the deposit, their sum is the voting power.
+    pub fn vote_weights(
+        &self,
+        voting_mint_config: &VotingMintConfig,
+        curr_ts: i64,
+    ) -> anyhow::Result<(u64, u64)> {
         let baseline_vote_weight =
             voting_mint_config.baseline_vote_weight(self.amount_deposited_native)?;
         let max_locked_vote_weight =
@@ -320,9 +348,7 @@ impl DepositEntry {
                 locked_vote_weight
             ));
         }
-        baseline_vote_weight
-            .checked_add(locked_vote_weight)
-            .ok_or_else(|| anyhow::anyhow!("VoterWeightOverflow"))
+        Ok((baseline_vote_weight, locked_vote_weight))
     }
 }
 
@@ -421,6 +447,21 @@ pub enum LockupKind {
 pub const SECS_PER_DAY: u64 = 86_400;
 pub const SECS_PER_MONTH: u64 = 365 * SECS_PER_DAY / 12;
 
+impl TryFrom<u8> for LockupKind {
+    type Error = anyhow::Error;
+
+    fn try_from(kind: u8) -> anyhow::Result<Self> {
+        Ok(match kind {
+            0 => LockupKind::None,
+            1 => LockupKind::Daily,
+            2 => LockupKind::Monthly,
+            3 => LockupKind::Cliff,
+            4 => LockupKind::Constant,
+            _ => return Err(anyhow!("Unknown lockup kind {}", kind)),
+        })
+    }
+}
+
 impl LockupKind {
     /// The lockup length is specified by passing the number of lockup periods
     /// to create_deposit_entry. This describes a period's length.
@@ -0,0 +1,55 @@
+use clap::Parser;
+use snapshot_parser::bank_loader::create_bank_from_ledger;
+use snapshot_parser::cli::path_parser;
+use snapshot_parser::telemetry::{init_telemetry, TelemetryArgs};
+use snapshot_parser_tokens_cli::minimize::{collect_accounts, write_account_dir};
+use solana_program::pubkey::Pubkey;
+use std::path::PathBuf;
+use std::str::FromStr;
+use tracing::info;
+
+#[derive(Parser, Debug)]
+#[command(author, version, about, long_about = None)]
+struct Args {
+    /// Path to the directory where the snapshot is unpacked (e.g., from .tar.zst)
+    #[arg(long, env, value_parser = path_parser)]
+    ledger_path: PathBuf,
+
+    /// Directory to write one JSON file per account to, loadable by `solana-test-validator --account-dir`
+    #[arg(long, env)]
+    output_dir: PathBuf,
+
+    /// Comma-separated pubkeys of the accounts to extract
+    #[arg(long, env, value_delimiter = ',', value_parser = parse_pubkey)]
+    pubkeys: Vec<Pubkey>,
+
+    /// Comma-separated program ids whose owned accounts are extracted
+    #[arg(long, env, value_delimiter = ',', value_parser = parse_pubkey)]
+    programs: Vec<Pubkey>,
+}
+
+fn parse_pubkey(value: &str) -> Result<Pubkey, String> {
+    Pubkey::from_str(value.trim()).map_err(|e| format!("Invalid pubkey '{}': {}", value, e))
+}
+
+fn main() -> anyhow::Result<()> {
+    let _telemetry = init_telemetry("snapshot-parser-minimize", &TelemetryArgs::default())?;
+    let args: Args = Args::parse();
+    if args.pubkeys.is_empty() && args.programs.is_empty() {
+        anyhow::bail!("At least one of --pubkeys or --programs is required");
+    }
+
+    info!("Creating bank from ledger path: {:?}", &args.ledger_path);
+    let bank = create_bank_from_ledger(&args.ledger_path)?;
+    info!(
+        "Bank created. Epoch: {}, slot: {}",
+        bank.epoch(),
+        bank.slot()
+    );
+
+    let accounts = collect_accounts(&bank, &args.pubkeys, &args.programs)?;
+    write_account_dir(&accounts, &args.output_dir)?;
+
+    info!("Finished.");
+    Ok(())
+}
@@ -0,0 +1,254 @@
+use crate::db_message::DbMessage;
+use crate::sql_row::{create_table, SqlRow};
+use rusqlite::{Connection, OpenFlags};
+use snapshot_parser_types::decoded_accounts::{ProcessorCompletionRow, ScanCheckpointRow};
+use std::collections::{HashMap, HashSet};
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+use tokio::sync::mpsc::Sender;
+use tokio::sync::oneshot;
+use tracing::info;
+
+pub async fn create_checkpoint_table(db_sender: &Sender<DbMessage>) -> anyhow::Result<usize> {
+    create_table::<ScanCheckpointRow>(db_sender).await
+}
+
+/// Commits all rows inserted so far and records the checkpoint of the processor,
+/// a resumed scan skips the pubkeys up to its last one, see [`ProcessorCompletion::checkpoint`].
+/// A checkpoint of a newer slot, e.g., from a reprocessed DB, is never replaced by an older one.
+pub async fn commit_checkpoint(
+    db_sender: &Sender<DbMessage>,
+    checkpoint: &ScanCheckpointRow,
+) -> anyhow::Result<usize> {
+    let (response_tx, response_rx) = oneshot::channel();
+    db_sender
+        .send(DbMessage::CommitCheckpoint {
+            checkpoint: checkpoint.clone(),
+            response: response_tx,
+        })
+        .await?;
+    response_rx.await?
+}
+
+/// Processors completed by a previous run into the temporary DB, see `--resume`.
+/// Completion of every processor is committed together with its rows, so a failed run
+/// can be resumed from its temporary DB skipping the tables already written.
+/// Scan checkpoints of the processors not completed are loaded as well, to resume their scans.
+pub struct ProcessorCompletion {
+    db_sender: Sender<DbMessage>,
+    slot: u64,
+    completed: HashSet<String>,
+    checkpoints: HashMap<String, ScanCheckpointRow>,
+}
+
+fn table_exists(db: &Connection, table: &str) -> rusqlite::Result<bool> {
+    db.query_row(
+        "SELECT EXISTS (SELECT 1 FROM sqlite_master WHERE type = 'table' AND name = ?1);",
+        [table],
+        |row| row.get(0),
+    )
+}
+
+impl ProcessorCompletion {
+    /// Reads the processors completed in the temporary DB at `db_temp_path`, none when it does not exist.
+    /// Completions or checkpoints of another slot mean the temporary DB was written from another snapshot
+    /// and it cannot be resumed.
+    pub fn load(
+        db_temp_path: &Path,
+        slot: u64,
+        db_sender: Sender<DbMessage>,
+    ) -> anyhow::Result<Self> {
+        let mut completed = HashSet::new();
+        let mut checkpoints = HashMap::new();
+        if db_temp_path.exists() {
+            let db = Connection::open_with_flags(db_temp_path, OpenFlags::SQLITE_OPEN_READ_ONLY)?;
+            if table_exists(&db, ProcessorCompletionRow::TABLE)? {
+                let mut stmt = db.prepare(&format!(
+                    "SELECT processor, slot FROM {};",
+                    ProcessorCompletionRow::TABLE
+                ))?;
+                let rows = stmt.query_map([], |row| {
+                    Ok((row.get::<_, String>(0)?, row.get::<_, i64>(1)?))
+                })?;
+                for row in rows {
+                    let (processor, completed_slot) = row?;
+                    if completed_slot as u64 != slot {
+                        anyhow::bail!(
+                            "Temporary DB {:?} was written from slot {}, not {}; remove it to start over",
+                            db_temp_path,
+                            completed_slot,
+                            slot
+                        );
+                    }
+                    completed.insert(processor);
+                }
+            }
+            if table_exists(&db, ScanCheckpointRow::TABLE)? {
+                let mut stmt = db.prepare(&format!(
+                    "SELECT processor, last_pubkey, processed, slot, filter_hash FROM {};",
+                    ScanCheckpointRow::TABLE
+                ))?;
+                let rows = stmt.query_map([], |row| {
+                    Ok(ScanCheckpointRow {
+                        processor: row.get(0)?,
+                        last_pubkey: row.get(1)?,
+                        processed: row.get::<_, i64>(2)? as u64,
+                        slot: row.get::<_, i64>(3)? as u64,
+                        filter_hash: row.get(4)?,
+                    })
+                })?;
+                for row in rows {
+                    let checkpoint = row?;
+                    if checkpoint.slot != slot {
+                        anyhow::bail!(
+                            "Temporary DB {:?} was checkpointed at slot {}, not {}; remove it to start over",
+                            db_temp_path,
+                            checkpoint.slot,
+                            slot
+                        );
+                    }
+                    if !completed.contains(&checkpoint.processor) {
+                        checkpoints.insert(checkpoint.processor.clone(), checkpoint);
+                    }
+                }
+            }
+            info!(
+                "Resuming from temporary DB {:?}, completed processors: {:?}, scan checkpoints: {:?}",
+                db_temp_path,
+                completed,
+                checkpoints.values().collect::<Vec<_>>()
+            );
+        }
+        Ok(Self {
+            db_sender,
+            slot,
+            completed,
+            checkpoints,
+        })
+    }
+
+    pub async fn create_table(&self) -> anyhow::Result<usize> {
+        create_table::<ProcessorCompletionRow>(&self.db_sender).await
+    }
+
+    pub fn is_completed(&self, processor: &str) -> bool {
+        self.completed.contains(processor)
+    }
+
+    /// Last scan checkpoint the previous run committed for the processor, when it did not complete.
+    pub fn checkpoint(&self, processor: &str) -> Option<&ScanCheckpointRow> {
+        self.checkpoints.get(processor)
+    }
+
+    /// Commits all rows inserted so far and records the processor as completed.
+    pub async fn record(&self, processor: &str) -> anyhow::Result<usize> {
+        let completion = ProcessorCompletionRow {
+            processor: processor.to_string(),
+            slot: self.slot,
+            completed_at: SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs() as i64,
+        };
+        let (response_tx, response_rx) = oneshot::channel();
+        self.db_sender
+            .send(DbMessage::RecordCompletion {
+                completion,
+                response: response_tx,
+            })
+            .await?;
+        response_rx.await?
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::sync::mpsc;
+
+    struct TempDb(std::path::PathBuf);
+
+    impl Drop for TempDb {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_file(&self.0);
+        }
+    }
+
+    fn temp_db(name: &str, completions: &[(&str, u64)], checkpoints: &[(&str, u64)]) -> TempDb {
+        let path =
+            std::env::temp_dir().join(format!("checkpoint_{}_{}.db", name, std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        let db = Connection::open(&path).unwrap();
+        db.execute_batch(&ProcessorCompletionRow::create_table_query())
+            .unwrap();
+        db.execute_batch(&ScanCheckpointRow::create_table_query())
+            .unwrap();
+        for (processor, slot) in completions {
+            let row = ProcessorCompletionRow {
+                processor: processor.to_string(),
+                slot: *slot,
+                completed_at: 0,
+            };
+            db.execute(
+                &ProcessorCompletionRow::insert_query(),
+                rusqlite::params_from_iter(row.to_values().iter()),
+            )
+            .unwrap();
+        }
+        for (processor, slot) in checkpoints {
+            let row = ScanCheckpointRow {
+                processor: processor.to_string(),
+                last_pubkey: "11111111111111111111111111111111".to_string(),
+                processed: 10,
+                slot: *slot,
+                filter_hash: "filters".to_string(),
+            };
+            db.execute(
+                &ScanCheckpointRow::insert_query(),
+                rusqlite::params_from_iter(row.to_values().iter()),
+            )
+            .unwrap();
+        }
+        TempDb(path)
+    }
+
+    fn load(db: &TempDb, slot: u64) -> anyhow::Result<ProcessorCompletion> {
+        ProcessorCompletion::load(&db.0, slot, mpsc::channel(1).0)
+    }
+
+    #[test]
+    fn load_without_db() {
+        let db = TempDb(std::env::temp_dir().join("checkpoint_missing.db"));
+        let completion = load(&db, 100).unwrap();
+        assert!(!completion.is_completed("Token"));
+        assert!(completion.checkpoint("Token").is_none());
+    }
+
+    #[test]
+    fn load_rejects_completion_of_another_slot() {
+        let db = temp_db("completion_slot", &[("Mint", 99)], &[]);
+        assert!(load(&db, 100).is_err());
+    }
+
+    #[test]
+    fn load_rejects_checkpoint_of_another_slot() {
+        let db = temp_db("checkpoint_slot", &[], &[("Token", 99)]);
+        assert!(load(&db, 100).is_err());
+    }
+
+    #[test]
+    fn load_skips_checkpoint_of_completed_processor() {
+        let db = temp_db("completed", &[("Token", 100)], &[("Token", 100)]);
+        let completion = load(&db, 100).unwrap();
+        assert!(completion.is_completed("Token"));
+        assert!(completion.checkpoint("Token").is_none());
+    }
+
+    #[test]
+    fn load_pending_checkpoint() {
+        let db = temp_db("pending", &[("Mint", 100)], &[("Token", 100)]);
+        let completion = load(&db, 100).unwrap();
+        assert!(completion.is_completed("Mint"));
+        assert!(!completion.is_completed("Token"));
+        let checkpoint = completion.checkpoint("Token").unwrap();
+        assert_eq!(checkpoint.processed, 10);
+        assert_eq!(checkpoint.filter_hash, "filters");
+    }
+}
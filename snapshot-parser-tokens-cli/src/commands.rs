@@ -0,0 +1,295 @@
+// Subcommands of the tokens CLI working on the filters or on the outputs of earlier runs without loading a bank.
+use crate::cross_check::{cross_check, ValidatorArtifacts};
+use crate::db_spool::replay_db_spool_into_sqlite;
+use crate::eligibility::{eligible_owners, write_eligible_owners, EligibilityRules};
+use crate::extract::{
+    parse_params, query_template, run_template, write_extracted, ExtractFormat, QUERY_TEMPLATES,
+};
+use crate::filters::Filters;
+use crate::progress_bar::define_counter;
+use crate::stats::Stats;
+use crate::validate_filters::validate_filters;
+use crate::voting_power::{read_vemnde_deposits, recompute_voting_power};
+use indicatif::MultiProgress;
+use snapshot_parser::cli::path_parser;
+use snapshot_parser::program_registry::{ProgramRegistry, MARINADE_NATIVE_STAKE_AUTHORITY};
+use snapshot_parser::utils::write_to_json_file;
+use solana_program::pubkey::Pubkey;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use tracing::info;
+
+#[derive(clap::Subcommand, Debug)]
+pub enum Command {
+    /// Check the filters without loading a bank: pubkeys, the VSR registrar data, owner and mint overlaps,
+    /// and optionally that the mints exist on chain
+    ValidateFilters {
+        /// Path to filters file generated by solana-snapshot-manager CLI
+        #[arg(long, env, value_parser = path_parser, required_unless_present = "filters_json", conflicts_with = "filters_json")]
+        filters: Option<PathBuf>,
+
+        /// Filters passed inline as JSON string with the same structure as the filters file
+        #[arg(long, env)]
+        filters_json: Option<String>,
+
+        /// RPC URL to check that all filtered mints exist, the check is skipped when not set
+        #[arg(long, env)]
+        rpc_url: Option<String>,
+
+        /// Path to JSON config extending and overriding the built-in program registry
+        #[arg(long, env, value_parser = path_parser)]
+        program_registry: Option<PathBuf>,
+    },
+    /// Recompute the vemnde voting power at another timestamp from the vemnde_deposits table of an output DB,
+    /// e.g., as of a governance proposal creation, without parsing the snapshot again
+    RecomputeVotingPower {
+        /// Path to SQLite DB written by the tokens CLI
+        #[arg(long, env, value_parser = path_parser)]
+        db: PathBuf,
+
+        /// Unix timestamp to compute the voting power at
+        #[arg(long, env)]
+        timestamp: i64,
+
+        /// Path to write JSON file to with the voting power per voter (e.g., voting-power.json)
+        #[arg(long, env)]
+        output: String,
+    },
+    /// Evaluate the eligibility rules of a distribution campaign (minimum balance, held-through snapshots,
+    /// excluded owners and owner types) over an output DB and write the eligible_owners table into it,
+    /// with the owners closed since the latest held-through snapshot in the closed_owners table
+    EligibleOwners {
+        /// Path to SQLite DB written by the tokens CLI
+        #[arg(long, env, value_parser = path_parser)]
+        db: PathBuf,
+
+        /// Path to JSON file with the eligibility rules
+        #[arg(long, env, value_parser = path_parser)]
+        rules: PathBuf,
+    },
+    /// Run a named SQL template shipped with the parser against an output DB and write the rows as CSV or JSON
+    Extract {
+        /// Path to SQLite DB written by the tokens CLI
+        #[arg(long, env, value_parser = path_parser, required_unless_present = "list_templates")]
+        db: Option<PathBuf>,
+
+        /// Name of the query template, see --list-templates
+        #[arg(long, env, required_unless_present = "list_templates")]
+        template: Option<String>,
+
+        /// Template parameters in the name=value form, may be repeated or comma separated
+        #[arg(long, env, value_delimiter = ',')]
+        param: Vec<String>,
+
+        #[arg(long, env, value_enum, default_value_t = ExtractFormat::Csv)]
+        format: ExtractFormat,
+
+        /// Path to write the rows to, stdout when not set
+        #[arg(long, env)]
+        output: Option<PathBuf>,
+
+        /// Print JSON description of the query templates and their parameters, then exit
+        #[arg(long, exclusive = true)]
+        list_templates: bool,
+    },
+    /// Check that the artifacts of the validator CLI were parsed from the same snapshot as an output DB
+    /// (epoch, slot, bank hash, capitalization) and that the native stake accounts of the DB match the stake metas
+    CrossCheck {
+        /// Path to SQLite DB written by the tokens CLI
+        #[arg(long, env, value_parser = path_parser)]
+        db: PathBuf,
+
+        /// Path to the validator meta collection JSON file written by the validator CLI
+        #[arg(long, env, value_parser = path_parser)]
+        validator_meta_collection: PathBuf,
+
+        /// Path to the stake meta collection JSON file written by the validator CLI
+        #[arg(long, env, value_parser = path_parser)]
+        stake_meta_collection: PathBuf,
+
+        /// Path to the vote account state collection JSON file written by the validator CLI, not checked when not set
+        #[arg(long, env, value_parser = path_parser)]
+        vote_account_state_collection: Option<PathBuf>,
+
+        /// Stake authority of the native stake accounts the DB was written with,
+        /// `marinade_native_stake_authority` of the program registry when not set
+        #[arg(long, env)]
+        native_stake_authority: Option<Pubkey>,
+
+        /// Path to JSON config extending and overriding the built-in program registry
+        #[arg(long, env, value_parser = path_parser)]
+        program_registry: Option<PathBuf>,
+    },
+    /// Rebuild the output DB from the spool of a run (see --db-spool) without loading the bank;
+    /// the spool of a crashed run is replayed into the temporary DB to be completed by a --resume run
+    ReplaySpool {
+        /// Path to the spool written by --db-spool
+        #[arg(long, env, value_parser = path_parser)]
+        db_spool: PathBuf,
+
+        /// Path to the output SQLite DB the spool was written for
+        #[arg(long, env)]
+        output_sqlite: PathBuf,
+
+        /// Processing in transaction bulks. This is number of inserts in one transaction.
+        #[arg(long, env)]
+        sqlite_tx_bulk: Option<u16>,
+    },
+}
+
+impl Command {
+    pub async fn run(self) -> anyhow::Result<()> {
+        match self {
+            Command::ValidateFilters {
+                filters,
+                filters_json,
+                rpc_url,
+                program_registry,
+            } => {
+                run_validate_filters(
+                    filters.as_ref(),
+                    filters_json.as_deref(),
+                    rpc_url.as_deref(),
+                    program_registry.as_deref(),
+                )
+                .await
+            }
+            Command::RecomputeVotingPower {
+                db,
+                timestamp,
+                output,
+            } => run_recompute_voting_power(&db, timestamp, &output),
+            Command::EligibleOwners { db, rules } => run_eligible_owners(&db, &rules),
+            Command::Extract {
+                list_templates: true,
+                ..
+            } => {
+                println!("{}", serde_json::to_string_pretty(QUERY_TEMPLATES)?);
+                Ok(())
+            }
+            Command::Extract {
+                db,
+                template,
+                param,
+                format,
+                output,
+                ..
+            } => {
+                let (Some(db), Some(template)) = (db, template) else {
+                    unreachable!("db and template are required without --list-templates");
+                };
+                run_extract(&db, &template, &param, format, output.as_deref())
+            }
+            Command::CrossCheck {
+                db,
+                validator_meta_collection,
+                stake_meta_collection,
+                vote_account_state_collection,
+                native_stake_authority,
+                program_registry,
+            } => run_cross_check(
+                &db,
+                ValidatorArtifacts {
+                    validator_meta_collection,
+                    stake_meta_collection,
+                    vote_account_state_collection,
+                },
+                native_stake_authority,
+                program_registry.as_deref(),
+            ),
+            Command::ReplaySpool {
+                db_spool,
+                output_sqlite,
+                sqlite_tx_bulk,
+            } => run_replay_spool(&db_spool, output_sqlite, sqlite_tx_bulk).await,
+        }
+    }
+}
+
+pub async fn run_validate_filters(
+    filters: Option<&PathBuf>,
+    filters_json: Option<&str>,
+    rpc_url: Option<&str>,
+    program_registry: Option<&Path>,
+) -> anyhow::Result<()> {
+    let program_registry = ProgramRegistry::load(program_registry)?;
+    let filters = Filters::load_from(filters, filters_json, &program_registry)?;
+    validate_filters(&filters, rpc_url).await?;
+    info!("Filters are valid");
+    Ok(())
+}
+
+pub fn run_recompute_voting_power(db: &Path, timestamp: i64, output: &str) -> anyhow::Result<()> {
+    let deposits = read_vemnde_deposits(db)?;
+    let voting_power = recompute_voting_power(&deposits, timestamp)?;
+    info!(
+        "Voting power at {}: {} of {} voters",
+        timestamp,
+        voting_power.total_voting_power,
+        voting_power.voters.len()
+    );
+    write_to_json_file(&voting_power, output)?;
+    Ok(())
+}
+
+pub fn run_eligible_owners(db: &Path, rules: &Path) -> anyhow::Result<()> {
+    let rules = EligibilityRules::load(rules)?;
+    let owners = eligible_owners(db, &rules)?;
+    write_eligible_owners(db, &owners)?;
+    Ok(())
+}
+
+/// Writes the rows of the template into `output`, stdout when not set.
+pub fn run_extract(
+    db: &Path,
+    template: &str,
+    params: &[String],
+    format: ExtractFormat,
+    output: Option<&Path>,
+) -> anyhow::Result<()> {
+    let template = query_template(template)?;
+    let extracted = run_template(db, template, &parse_params(params)?)?;
+    info!(
+        "Template {} returned {} rows",
+        template.name,
+        extracted.rows.len()
+    );
+    match output {
+        Some(output) => write_extracted(
+            &extracted,
+            format,
+            std::io::BufWriter::new(std::fs::File::create(output)?),
+        ),
+        None => write_extracted(&extracted, format, std::io::stdout().lock()),
+    }
+}
+
+/// The native stake authority defaults to `marinade_native_stake_authority` of the program registry.
+pub fn run_cross_check(
+    db: &Path,
+    artifacts: ValidatorArtifacts,
+    native_stake_authority: Option<Pubkey>,
+    program_registry: Option<&Path>,
+) -> anyhow::Result<()> {
+    let native_stake_authority = match native_stake_authority {
+        Some(native_stake_authority) => native_stake_authority,
+        None => {
+            ProgramRegistry::load(program_registry)?.address(MARINADE_NATIVE_STAKE_AUTHORITY)?
+        }
+    };
+    cross_check(db, &artifacts, &native_stake_authority)
+}
+
+pub async fn run_replay_spool(
+    db_spool: &Path,
+    output_sqlite: PathBuf,
+    sqlite_tx_bulk: Option<u16>,
+) -> anyhow::Result<()> {
+    let stats = Arc::new(Stats::new());
+    let multi_progress = MultiProgress::new();
+    let db_progress_counter = define_counter("db_execute", &multi_progress, &stats).await;
+    replay_db_spool_into_sqlite(db_spool, output_sqlite, sqlite_tx_bulk, db_progress_counter)
+        .await?;
+    stats.print_info().await;
+    Ok(())
+}
@@ -0,0 +1,167 @@
+// Cross-check of the artifacts of the validator CLI against the output DB of the tokens CLI
+// when both pipelines parsed the same snapshot.
+use crate::schema::{NATIVE_STAKE_ACCOUNT_TABLE, SNAPSHOT_INFO_TABLE};
+use rusqlite::{Connection, OpenFlags, OptionalExtension};
+use snapshot_parser::utils::read_from_json_file;
+use snapshot_parser_types::stake_meta::StakeMetaCollection;
+use snapshot_parser_types::validator_meta::ValidatorMetaCollection;
+use snapshot_parser_types::vote_account_state::VoteAccountStateCollection;
+use solana_program::pubkey::Pubkey;
+use std::path::{Path, PathBuf};
+use tracing::{error, info};
+
+/// Artifacts written by the validator CLI for the snapshot.
+pub struct ValidatorArtifacts {
+    pub validator_meta_collection: PathBuf,
+    pub stake_meta_collection: PathBuf,
+    pub vote_account_state_collection: Option<PathBuf>,
+}
+
+struct SnapshotInfo {
+    slot: u64,
+    epoch: u64,
+    bank_hash: String,
+    capitalization: u64,
+}
+
+fn snapshot_info(db: &Connection, db_path: &Path) -> anyhow::Result<SnapshotInfo> {
+    db.query_row(
+        &format!(
+            "SELECT slot, epoch, bank_hash, capitalization FROM {} ORDER BY slot DESC LIMIT 1;",
+            SNAPSHOT_INFO_TABLE
+        ),
+        [],
+        |row| {
+            Ok(SnapshotInfo {
+                slot: row.get::<_, i64>(0)? as u64,
+                epoch: row.get::<_, i64>(1)? as u64,
+                bank_hash: row.get(2)?,
+                capitalization: row.get::<_, i64>(3)? as u64,
+            })
+        },
+    )
+    .optional()?
+    .ok_or_else(|| anyhow::anyhow!("No {} in {:?}", SNAPSHOT_INFO_TABLE, db_path))
+}
+
+/// Count and lamports of the native stake accounts of the DB.
+fn native_stake_totals(db: &Connection) -> anyhow::Result<(u64, u64)> {
+    db.query_row(
+        &format!(
+            "SELECT COUNT(*), COALESCE(SUM(amount), 0) FROM {};",
+            NATIVE_STAKE_ACCOUNT_TABLE
+        ),
+        [],
+        |row| Ok((row.get::<_, i64>(0)? as u64, row.get::<_, i64>(1)? as u64)),
+    )
+    .map_err(|e| {
+        anyhow::anyhow!(
+            "Could not read {}, was the native_stake processor run? {}",
+            NATIVE_STAKE_ACCOUNT_TABLE,
+            e
+        )
+    })
+}
+
+/// Checks that the validator artifacts and the output DB were parsed from the same bank,
+/// i.e., the epoch, slot, bank hash and capitalization match,
+/// and that the native stake accounts of the DB equal the stake metas of the `native_stake_authority`.
+/// All mismatches are logged, then the check fails.
+pub fn cross_check(
+    db_path: &Path,
+    artifacts: &ValidatorArtifacts,
+    native_stake_authority: &Pubkey,
+) -> anyhow::Result<()> {
+    let db = Connection::open_with_flags(db_path, OpenFlags::SQLITE_OPEN_READ_ONLY)?;
+    let snapshot_info = snapshot_info(&db, db_path)?;
+    let validator_meta_collection: ValidatorMetaCollection =
+        read_from_json_file(&artifacts.validator_meta_collection)?;
+    let stake_meta_collection: StakeMetaCollection =
+        read_from_json_file(&artifacts.stake_meta_collection)?;
+    let vote_account_state_collection: Option<VoteAccountStateCollection> = artifacts
+        .vote_account_state_collection
+        .as_ref()
+        .map(read_from_json_file)
+        .transpose()?;
+
+    let mut mismatches = Vec::new();
+    let mut check_bank = |artifact: &str, epoch: u64, slot: u64| {
+        if (epoch, slot) != (snapshot_info.epoch, snapshot_info.slot) {
+            mismatches.push(format!(
+                "{} is of epoch {} slot {}, the DB of epoch {} slot {}",
+                artifact, epoch, slot, snapshot_info.epoch, snapshot_info.slot
+            ));
+        }
+    };
+    check_bank(
+        "Validator meta collection",
+        validator_meta_collection.epoch,
+        validator_meta_collection.slot,
+    );
+    check_bank(
+        "Stake meta collection",
+        stake_meta_collection.epoch,
+        stake_meta_collection.slot,
+    );
+    if let Some(collection) = &vote_account_state_collection {
+        check_bank(
+            "Vote account state collection",
+            collection.epoch,
+            collection.slot,
+        );
+    }
+    match &validator_meta_collection.bank_hash {
+        Some(bank_hash) if *bank_hash != snapshot_info.bank_hash => mismatches.push(format!(
+            "Validator meta collection is of bank hash {}, the DB of {}",
+            bank_hash, snapshot_info.bank_hash
+        )),
+        Some(_) => {}
+        None => mismatches.push(
+            "Validator meta collection has no bank hash, it was generated by an older parser"
+                .to_string(),
+        ),
+    }
+    if validator_meta_collection.capitalization != snapshot_info.capitalization {
+        mismatches.push(format!(
+            "Validator meta collection capitalization is {}, the DB one {}",
+            validator_meta_collection.capitalization, snapshot_info.capitalization
+        ));
+    }
+
+    // the artifact pubkeys are of another solana-program version, they are compared as strings
+    let native_stake_authority = native_stake_authority.to_string();
+    let (stake_meta_count, stake_meta_lamports) = stake_meta_collection
+        .stake_metas
+        .iter()
+        .filter(|stake_meta| stake_meta.stake_authority.to_string() == native_stake_authority)
+        .fold((0u64, 0u64), |(count, lamports), stake_meta| {
+            (count + 1, lamports + stake_meta.active_delegation_lamports)
+        });
+    let (db_count, db_lamports) = native_stake_totals(&db)?;
+    if (db_count, db_lamports) != (stake_meta_count, stake_meta_lamports) {
+        mismatches.push(format!(
+            "Native stake of {}: {} accounts with {} lamports in the DB, {} accounts with {} lamports in the stake meta collection",
+            native_stake_authority, db_count, db_lamports, stake_meta_count, stake_meta_lamports
+        ));
+    }
+
+    if !mismatches.is_empty() {
+        for mismatch in mismatches.iter() {
+            error!("Cross-check mismatch: {}", mismatch);
+        }
+        anyhow::bail!(
+            "Validator artifacts do not match the DB {:?}: {} mismatches",
+            db_path,
+            mismatches.len()
+        );
+    }
+    info!(
+        "Validator artifacts match the DB of epoch {} slot {} bank hash {}, native stake {} accounts with {} lamports",
+        snapshot_info.epoch,
+        snapshot_info.slot,
+        snapshot_info.bank_hash,
+        db_count,
+        db_lamports
+    );
+    Ok(())
+}
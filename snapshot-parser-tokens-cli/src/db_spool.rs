@@ -0,0 +1,509 @@
+// Write-ahead spool of the output writes: the messages of the processors are appended to a file
+// before the SQLite writer applies them, so the output DB of a run whose writer crashed is rebuilt
+// by replaying the file instead of re-running the processors, see the `replay-spool` command.
+use crate::db_connection::{temp_db_path, SQLiteExecutor};
+use crate::db_message::{DbMessage, OwnedSqlValue, TableColumns};
+use crate::progress_bar::ProgressCounter;
+use crate::schema::{finalize_output, TableRow, TableRows};
+use crate::sql_row::ConfiguredRow;
+use serde::{Deserialize, Serialize};
+use snapshot_parser_types::decoded_accounts::{ProcessorCompletionRow, ScanCheckpointRow};
+use std::collections::HashMap;
+use std::fs::{File, OpenOptions};
+use std::io::{BufReader, BufWriter, ErrorKind, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use tokio::sync::mpsc::{self, Sender};
+use tokio::sync::oneshot;
+use tokio::task::JoinHandle;
+use tracing::{info, warn};
+
+/// Spooled write, the table and column names of the runtime tables are owned as they are read back by another process.
+#[derive(Serialize, Deserialize)]
+enum SpooledMessage {
+    CreateTable {
+        table: String,
+        columns: Vec<(String, String)>,
+    },
+    Insert(TableRow),
+    /// Insert of a [`TableRow::Configured`] row.
+    InsertConfigured {
+        table: String,
+        columns: Vec<(String, String)>,
+        values: Vec<OwnedSqlValue>,
+    },
+    InsertBatch(TableRows),
+    CommitCheckpoint(ScanCheckpointRow),
+    RecordCompletion(ProcessorCompletionRow),
+    /// All rows of the run were written, the spool ends with it.
+    Finalize,
+}
+
+fn owned_columns(columns: TableColumns) -> Vec<(String, String)> {
+    columns
+        .iter()
+        .map(|(name, sql_type)| (name.to_string(), sql_type.to_string()))
+        .collect()
+}
+
+impl SpooledMessage {
+    /// The write of the message, none for the messages controlling the writer.
+    fn of(msg: &DbMessage) -> Option<Self> {
+        Some(match msg {
+            DbMessage::CreateTable { table, columns, .. } => SpooledMessage::CreateTable {
+                table: table.to_string(),
+                columns: owned_columns(columns),
+            },
+            DbMessage::Insert { row, .. } => match row.as_ref() {
+                TableRow::Configured(row) => SpooledMessage::InsertConfigured {
+                    table: row.table.to_string(),
+                    columns: owned_columns(row.columns),
+                    values: row.values.clone(),
+                },
+                row => SpooledMessage::Insert(row.clone()),
+            },
+            DbMessage::InsertBatch { rows, .. } => SpooledMessage::InsertBatch(rows.clone()),
+            DbMessage::CommitCheckpoint { checkpoint, .. } => {
+                SpooledMessage::CommitCheckpoint(checkpoint.clone())
+            }
+            DbMessage::RecordCompletion { completion, .. } => {
+                SpooledMessage::RecordCompletion(completion.clone())
+            }
+            DbMessage::Finalize { .. } => SpooledMessage::Finalize,
+            DbMessage::Shutdown { .. } | DbMessage::Interrupt { .. } => return None,
+        })
+    }
+}
+
+/// Frames longer than this are rejected as a corrupt length prefix, a batch of rows is far shorter.
+const MAX_FRAME_LEN: u64 = 256 * 1024 * 1024;
+
+/// Spool file of frames of a little-endian `u32` length followed by the bincode-encoded write.
+pub struct DbSpool {
+    path: PathBuf,
+    writer: BufWriter<File>,
+    frames: u64,
+    /// frames are handed to the OS on the checkpoints, the completions and the finalization only
+    buffered: bool,
+}
+
+impl DbSpool {
+    /// Creates the spool at `path`; with `append` set, an existing spool (e.g., of the run being resumed)
+    /// is continued, with the frame torn by the crash of its run cut off.
+    /// With `buffered` set, the frames are not handed to the OS one by one, see [`DbSpool::append`].
+    pub fn open(path: &Path, append: bool, buffered: bool) -> anyhow::Result<Self> {
+        let (file, frames) = if append && path.exists() {
+            let scan = SpoolScan::of(path)?;
+            let file = OpenOptions::new().write(true).open(path)?;
+            file.set_len(scan.len)?;
+            (file, scan.frames)
+        } else {
+            (File::create(path)?, 0)
+        };
+        let mut writer = BufWriter::new(file);
+        writer.seek(SeekFrom::End(0))?;
+        if frames > 0 {
+            info!("Appending to spool {:?} of {} writes", path, frames);
+        }
+        Ok(Self {
+            path: path.to_path_buf(),
+            writer,
+            frames,
+            buffered,
+        })
+    }
+
+    /// Appends the write of the message to the file before it is applied, every frame is handed to the OS
+    /// and the finalization is synced to disk as well. A buffered spool hands the frames to the OS on the checkpoints,
+    /// the completions and the finalization only; a crash of the process loses the rows buffered since,
+    /// the processors they belong to are not completed in the spool either.
+    fn append(&mut self, msg: &DbMessage) -> anyhow::Result<()> {
+        let Some(spooled) = SpooledMessage::of(msg) else {
+            return Ok(());
+        };
+        let frame = bincode::serialize(&spooled)?;
+        self.writer
+            .write_all(&u32::try_from(frame.len())?.to_le_bytes())?;
+        self.writer.write_all(&frame)?;
+        match spooled {
+            SpooledMessage::Finalize => {
+                self.writer.flush()?;
+                self.writer.get_ref().sync_data()?;
+            }
+            SpooledMessage::CommitCheckpoint(_) | SpooledMessage::RecordCompletion(_) => {
+                self.writer.flush()?;
+            }
+            _ if !self.buffered => self.writer.flush()?,
+            _ => {}
+        }
+        self.frames += 1;
+        Ok(())
+    }
+}
+
+/// Forwards the messages to `sink`, appending the writes to the `spool` before they are sent on.
+/// The returned sender is used in place of `sink`, the task ends when all its clones are dropped
+/// and fails when the spool cannot be written.
+pub fn spawn_db_spool(
+    mut spool: DbSpool,
+    sink: Sender<DbMessage>,
+) -> (Sender<DbMessage>, JoinHandle<anyhow::Result<()>>) {
+    let (sender, mut receiver) = mpsc::channel(sink.max_capacity());
+    let handle = tokio::spawn(async move {
+        while let Some(msg) = receiver.recv().await {
+            spool
+                .append(&msg)
+                .map_err(|e| anyhow::anyhow!("Failed to write spool {:?}: {}", spool.path, e))?;
+            if sink.send(msg).await.is_err() {
+                break;
+            }
+        }
+        info!("Spooled {} writes to {:?}", spool.frames, spool.path);
+        Ok(())
+    });
+    (sender, handle)
+}
+
+/// Complete frames of a spool.
+pub struct SpoolScan {
+    pub frames: u64,
+    /// length of the complete frames, a torn frame of a crashed run follows them
+    pub len: u64,
+    /// the spool ends with the finalization, the run wrote all its rows
+    pub finalized: bool,
+}
+
+/// Reads the next frame, none at the end of the spool or at a frame torn by a crash.
+/// The frame is read into a growing buffer, so a length prefix past the end of the spool allocates no more than it holds.
+fn read_frame(reader: &mut impl Read) -> anyhow::Result<Option<Vec<u8>>> {
+    let mut len = [0; 4];
+    match reader.read_exact(&mut len) {
+        Ok(()) => {}
+        Err(e) if e.kind() == ErrorKind::UnexpectedEof => return Ok(None),
+        Err(e) => return Err(e.into()),
+    }
+    let len = u64::from(u32::from_le_bytes(len));
+    if len > MAX_FRAME_LEN {
+        anyhow::bail!(
+            "Spool frame of {} bytes is over {} bytes, its length is corrupt",
+            len,
+            MAX_FRAME_LEN
+        );
+    }
+    let mut frame = Vec::new();
+    reader.take(len).read_to_end(&mut frame)?;
+    Ok((frame.len() as u64 == len).then_some(frame))
+}
+
+impl SpoolScan {
+    pub fn of(path: &Path) -> anyhow::Result<Self> {
+        let mut reader = BufReader::new(File::open(path)?);
+        let mut scan = SpoolScan {
+            frames: 0,
+            len: 0,
+            finalized: false,
+        };
+        // a torn frame can only end the spool, a frame not decoded before the end is corrupt
+        while let Some(frame) = read_frame(&mut reader).map_err(|e| {
+            anyhow::anyhow!("Spool {:?} is corrupt at offset {}: {}", path, scan.len, e)
+        })? {
+            let spooled: SpooledMessage = bincode::deserialize(&frame).map_err(|e| {
+                anyhow::anyhow!(
+                    "Spool {:?} has a corrupt write {} at offset {}: {}",
+                    path,
+                    scan.frames,
+                    scan.len,
+                    e
+                )
+            })?;
+            scan.finalized = matches!(spooled, SpooledMessage::Finalize);
+            scan.frames += 1;
+            scan.len += 4 + frame.len() as u64;
+        }
+        let file_len = std::fs::metadata(path)?.len();
+        if file_len > scan.len {
+            warn!(
+                "Spool {:?} ends with a torn write of {} bytes, it is ignored",
+                path,
+                file_len - scan.len
+            );
+        }
+        Ok(scan)
+    }
+}
+
+/// Names of the replayed tables, leaked once per table as the messages take static ones.
+#[derive(Default)]
+struct StaticTables(HashMap<String, (&'static str, TableColumns)>);
+
+impl StaticTables {
+    fn get(
+        &mut self,
+        table: String,
+        columns: Vec<(String, String)>,
+    ) -> (&'static str, TableColumns) {
+        *self.0.entry(table).or_insert_with_key(|table| {
+            let columns: Vec<(&'static str, &'static str)> = columns
+                .into_iter()
+                .map(|(name, sql_type)| {
+                    (
+                        &*Box::leak(name.into_boxed_str()),
+                        &*Box::leak(sql_type.into_boxed_str()),
+                    )
+                })
+                .collect();
+            (
+                Box::leak(table.clone().into_boxed_str()),
+                Box::leak(columns.into_boxed_slice()),
+            )
+        })
+    }
+}
+
+async fn send<T>(
+    sink: &Sender<DbMessage>,
+    msg: impl FnOnce(oneshot::Sender<anyhow::Result<T>>) -> DbMessage,
+) -> anyhow::Result<T> {
+    let (response_tx, response_rx) = oneshot::channel();
+    sink.send(msg(response_tx)).await?;
+    response_rx.await?
+}
+
+/// Sends the complete writes of the scanned spool to `sink` in their order, awaiting each of them;
+/// the finalization is not replayed, the caller decides how to close the output by [`SpoolScan::finalized`].
+pub async fn replay_db_spool(
+    path: &Path,
+    scan: &SpoolScan,
+    sink: &Sender<DbMessage>,
+) -> anyhow::Result<()> {
+    info!(
+        "Replaying {} writes of spool {:?}{}",
+        scan.frames,
+        path,
+        if scan.finalized {
+            ""
+        } else {
+            " of an unfinished run"
+        }
+    );
+    let mut reader = BufReader::new(File::open(path)?).take(scan.len);
+    let mut tables = StaticTables::default();
+    while let Some(frame) = read_frame(&mut reader)? {
+        match bincode::deserialize(&frame)? {
+            SpooledMessage::CreateTable { table, columns } => {
+                let (table, columns) = tables.get(table, columns);
+                send(sink, |response| DbMessage::CreateTable {
+                    table,
+                    columns,
+                    response,
+                })
+                .await?;
+            }
+            SpooledMessage::Insert(row) => {
+                send(sink, |response| DbMessage::Insert {
+                    row: Box::new(row),
+                    response,
+                })
+                .await?;
+            }
+            SpooledMessage::InsertConfigured {
+                table,
+                columns,
+                values,
+            } => {
+                let (table, columns) = tables.get(table, columns);
+                let row = TableRow::Configured(ConfiguredRow {
+                    table,
+                    columns,
+                    values,
+                });
+                send(sink, |response| DbMessage::Insert {
+                    row: Box::new(row),
+                    response,
+                })
+                .await?;
+            }
+            SpooledMessage::InsertBatch(rows) => {
+                send(sink, |response| DbMessage::InsertBatch { rows, response }).await?;
+            }
+            SpooledMessage::CommitCheckpoint(checkpoint) => {
+                send(sink, |response| DbMessage::CommitCheckpoint {
+                    checkpoint,
+                    response,
+                })
+                .await?;
+            }
+            SpooledMessage::RecordCompletion(completion) => {
+                send(sink, |response| DbMessage::RecordCompletion {
+                    completion,
+                    response,
+                })
+                .await?;
+            }
+            SpooledMessage::Finalize => {}
+        }
+    }
+    Ok(())
+}
+
+/// Rebuilds the SQLite output DB at `db_path` from the spool. The spool of a finished run is finalized
+/// into the output DB; the spool of a crashed run is replayed into the temporary DB, which is kept
+/// for a `--resume` run to complete the processors left.
+pub async fn replay_db_spool_into_sqlite(
+    spool_path: &Path,
+    db_path: PathBuf,
+    tx_bulk: Option<u16>,
+    db_execute_counter: Arc<ProgressCounter>,
+) -> anyhow::Result<()> {
+    let scan = SpoolScan::of(spool_path)?;
+    // the temporary DB of the crashed run may be damaged, the spool holds all of its writes
+    let db_temp_path = temp_db_path(&db_path);
+    if db_temp_path.exists() {
+        info!("Replacing temporary DB {:?} by the replay", db_temp_path);
+        std::fs::remove_file(&db_temp_path)?;
+    }
+    let (sender, receiver) = mpsc::channel(1000);
+    let db = SQLiteExecutor::new(
+        db_path.clone(),
+        None,
+        None,
+        tx_bulk,
+        false,
+        false,
+        !scan.finalized,
+        db_execute_counter,
+        receiver,
+    )?;
+    let db_handle = tokio::spawn(db.start());
+
+    replay_db_spool(spool_path, &scan, &sender).await?;
+    if scan.finalized {
+        finalize_output(&sender).await?;
+        send(&sender, |response| DbMessage::Shutdown { response }).await?;
+        info!(
+            "Output DB {:?} rebuilt from spool {:?}",
+            db_path, spool_path
+        );
+    } else {
+        send(&sender, |response| DbMessage::Interrupt { response }).await?;
+        warn!(
+            "Spool {:?} is of an unfinished run, re-run with --resume to complete the processors into {:?}",
+            spool_path, db_path
+        );
+    }
+    drop(sender);
+    db_handle.await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct TempSpool(PathBuf);
+
+    impl TempSpool {
+        fn new(name: &str) -> Self {
+            Self(std::env::temp_dir().join(format!(
+                "db_spool_{}_{}.spool",
+                name,
+                std::process::id()
+            )))
+        }
+
+        /// Spool of a created table followed by the finalization.
+        fn write(name: &str) -> Self {
+            let spool = Self::new(name);
+            let mut db_spool = DbSpool::open(&spool.0, false, false).unwrap();
+            db_spool
+                .append(&DbMessage::CreateTable {
+                    table: "holders",
+                    columns: &[("pubkey", "TEXT NOT NULL PRIMARY KEY")],
+                    response: oneshot::channel().0,
+                })
+                .unwrap();
+            db_spool
+                .append(&DbMessage::Finalize {
+                    response: oneshot::channel().0,
+                })
+                .unwrap();
+            spool
+        }
+
+        fn append_bytes(&self, bytes: &[u8]) {
+            let mut file = OpenOptions::new().append(true).open(&self.0).unwrap();
+            file.write_all(bytes).unwrap();
+        }
+    }
+
+    impl Drop for TempSpool {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_file(&self.0);
+        }
+    }
+
+    #[test]
+    fn frames_round_trip() {
+        let spool = TempSpool::write("round_trip");
+        let scan = SpoolScan::of(&spool.0).unwrap();
+        assert_eq!(scan.frames, 2);
+        assert_eq!(scan.len, std::fs::metadata(&spool.0).unwrap().len());
+        assert!(scan.finalized);
+
+        let mut reader = BufReader::new(File::open(&spool.0).unwrap());
+        let frame = read_frame(&mut reader).unwrap().unwrap();
+        match bincode::deserialize(&frame).unwrap() {
+            SpooledMessage::CreateTable { table, columns } => {
+                assert_eq!(table, "holders");
+                assert_eq!(
+                    columns,
+                    vec![(
+                        "pubkey".to_string(),
+                        "TEXT NOT NULL PRIMARY KEY".to_string()
+                    )]
+                );
+            }
+            _ => panic!("expected the created table"),
+        }
+    }
+
+    #[test]
+    fn truncated_tail_is_ignored() {
+        let spool = TempSpool::write("truncated");
+        let complete_len = std::fs::metadata(&spool.0).unwrap().len();
+        // length prefix of a frame of 100 bytes, only 3 of them written
+        spool.append_bytes(&100u32.to_le_bytes());
+        spool.append_bytes(&[1, 2, 3]);
+
+        let scan = SpoolScan::of(&spool.0).unwrap();
+        assert_eq!(scan.frames, 2);
+        assert_eq!(scan.len, complete_len);
+
+        // the appending run cuts the torn frame off
+        drop(DbSpool::open(&spool.0, true, false).unwrap());
+        assert_eq!(std::fs::metadata(&spool.0).unwrap().len(), complete_len);
+    }
+
+    #[test]
+    fn torn_length_prefix_is_ignored() {
+        let spool = TempSpool::write("torn_length");
+        spool.append_bytes(&[1, 2]);
+        assert_eq!(SpoolScan::of(&spool.0).unwrap().frames, 2);
+    }
+
+    #[test]
+    fn corrupt_length_is_rejected() {
+        let spool = TempSpool::write("corrupt_length");
+        spool.append_bytes(&u32::MAX.to_le_bytes());
+        spool.append_bytes(&[0; 16]);
+        assert!(SpoolScan::of(&spool.0).is_err());
+    }
+
+    #[test]
+    fn corrupt_frame_is_rejected() {
+        let spool = TempSpool::write("corrupt_frame");
+        spool.append_bytes(&4u32.to_le_bytes());
+        spool.append_bytes(&[0xff; 4]);
+        assert!(SpoolScan::of(&spool.0).is_err());
+    }
+}
@@ -0,0 +1,546 @@
+// Declarative eligibility rules of a distribution campaign evaluated over the output SQLite DB,
+// so the campaigns do not reimplement the minimum balance and the exclusions in ad-hoc SQL.
+use crate::schema::{
+    table_version_row, META_ACCOUNT_TABLE, PROGRAM_LABEL_TABLE, SNAPSHOT_INFO_TABLE,
+    TOKEN_ACCOUNT_TABLE,
+};
+use crate::sql_row::SqlRow;
+use rusqlite::{Connection, OpenFlags, OptionalExtension};
+use serde::{Deserialize, Serialize};
+use snapshot_parser::serde_serialize::pubkey_string_conversion;
+use snapshot_parser::utils::read_from_json_file;
+use snapshot_parser_types::decoded_accounts::{ClosedOwnerRow, EligibleOwnerRow, SchemaVersionRow};
+use solana_program::pubkey::Pubkey;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use tracing::info;
+
+/// Owner type of an owner that is a PDA not labeled by the program registry.
+pub const OFF_CURVE_OWNER_TYPE: &str = "off_curve";
+
+/// Rules of the eligibility config file.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct EligibilityRules {
+    /// mint the balance of the owners is summed for
+    #[serde(with = "pubkey_string_conversion")]
+    pub mint: Pubkey,
+    /// the owner is eligible with at least this balance, in every held-through snapshot as well;
+    /// required and positive, so an owner of empty accounts only is never eligible
+    pub min_balance: u64,
+    /// output DBs of earlier snapshots the balance has to be held through
+    /// (relative paths are resolved against the directory of the rules file)
+    #[serde(default)]
+    pub held_through: Vec<PathBuf>,
+    /// program registry categories (e.g., `staking`, `mev`) or `off_curve`, see [`owner_types`]
+    #[serde(default)]
+    pub excluded_owner_types: HashSet<String>,
+    #[serde(default)]
+    pub excluded_owners: HashSet<String>,
+}
+
+impl EligibilityRules {
+    pub fn load(rules_path: &Path) -> anyhow::Result<Self> {
+        let mut rules: Self = read_from_json_file(&rules_path).map_err(|e| {
+            anyhow::anyhow!("Could not load eligibility rules {:?}: {}", rules_path, e)
+        })?;
+        if rules.min_balance == 0 {
+            anyhow::bail!(
+                "Eligibility rules {:?} must have a positive min_balance",
+                rules_path
+            );
+        }
+        let base_dir = rules_path.parent().unwrap_or_else(|| Path::new("."));
+        rules.held_through = rules
+            .held_through
+            .into_iter()
+            .map(|db_path| base_dir.join(db_path))
+            .collect();
+        for owner in rules.excluded_owners.iter() {
+            Pubkey::from_str(owner)
+                .map_err(|e| anyhow::anyhow!("Invalid excluded owner {}: {}", owner, e))?;
+        }
+        Ok(rules)
+    }
+}
+
+fn snapshot_slot(db: &Connection, db_path: &Path) -> anyhow::Result<u64> {
+    let slot: Option<i64> = db
+        .query_row(
+            &format!("SELECT MAX(slot) FROM {};", SNAPSHOT_INFO_TABLE),
+            [],
+            |row| row.get(0),
+        )
+        .optional()?
+        .flatten();
+    slot.map(|slot| slot as u64)
+        .ok_or_else(|| anyhow::anyhow!("No {} in {:?}", SNAPSHOT_INFO_TABLE, db_path))
+}
+
+/// Balances of the mint per owner, summed over all token accounts of the owner.
+fn owner_balances(db: &Connection, mint: &Pubkey) -> anyhow::Result<HashMap<String, u64>> {
+    let mut stmt = db.prepare(&format!(
+        "SELECT owner, amount FROM {} WHERE mint = ?1;",
+        TOKEN_ACCOUNT_TABLE
+    ))?;
+    let rows = stmt.query_map([mint.to_string()], |row| {
+        Ok((row.get::<_, String>(0)?, row.get::<_, i64>(1)? as u64))
+    })?;
+    let mut balances: HashMap<String, u64> = HashMap::new();
+    for row in rows {
+        let (owner, amount) = row?;
+        let balance = balances.entry(owner.clone()).or_default();
+        *balance = balance
+            .checked_add(amount)
+            .ok_or_else(|| anyhow::anyhow!("Balance of owner {} overflows", owner))?;
+    }
+    Ok(balances)
+}
+
+/// Owner types of the owners with a known type: the category of the owner itself when it is a labeled program
+/// or authority, otherwise the category of the program owning the owner account when the account was parsed.
+/// The owners off the curve without a label are typed `off_curve`, the wallets are not typed.
+pub fn owner_types(
+    db: &Connection,
+    owners: impl Iterator<Item = String>,
+) -> anyhow::Result<HashMap<String, String>> {
+    let mut labeled_stmt = db.prepare(&format!(
+        "SELECT category FROM {} WHERE address = ?1;",
+        PROGRAM_LABEL_TABLE
+    ))?;
+    let mut account_stmt = db.prepare(&format!(
+        "SELECT label.category FROM {} account JOIN {} label ON label.address = account.owner \
+         WHERE account.pubkey = ?1;",
+        META_ACCOUNT_TABLE, PROGRAM_LABEL_TABLE
+    ))?;
+    let mut owner_types = HashMap::new();
+    for owner in owners {
+        let owner_type = match labeled_stmt
+            .query_row([&owner], |row| row.get::<_, String>(0))
+            .optional()?
+        {
+            Some(category) => Some(category),
+            None => account_stmt
+                .query_row([&owner], |row| row.get::<_, String>(0))
+                .optional()?,
+        };
+        let owner_type = owner_type.or_else(|| {
+            Pubkey::from_str(&owner)
+                .is_ok_and(|pubkey| !pubkey.is_on_curve())
+                .then(|| OFF_CURVE_OWNER_TYPE.to_string())
+        });
+        if let Some(owner_type) = owner_type {
+            owner_types.insert(owner, owner_type);
+        }
+    }
+    Ok(owner_types)
+}
+
+/// Owner tables of the eligibility rules evaluated over the output DB of a snapshot.
+pub struct EligibleOwners {
+    pub eligible: Vec<EligibleOwnerRow>,
+    /// owners of a balance in the latest held-through snapshot without one in the snapshot,
+    /// none without held-through snapshots
+    pub closed: Vec<ClosedOwnerRow>,
+}
+
+/// Owners of a balance in the previous snapshot whose balance is zero, or whose accounts are closed, in the snapshot.
+fn closed_owners(
+    mint: &Pubkey,
+    balances: &HashMap<String, u64>,
+    previous_balances: &HashMap<String, u64>,
+    previous_slot: u64,
+) -> Vec<ClosedOwnerRow> {
+    let mut closed: Vec<ClosedOwnerRow> = previous_balances
+        .iter()
+        .filter(|(owner, previous_amount)| {
+            **previous_amount > 0 && balances.get(*owner).copied().unwrap_or_default() == 0
+        })
+        .map(|(owner, previous_amount)| ClosedOwnerRow {
+            owner: owner.clone(),
+            mint: mint.to_string(),
+            previous_amount: *previous_amount,
+            previous_slot,
+            accounts_closed: !balances.contains_key(owner),
+        })
+        .collect();
+    closed.sort_by(|a, b| a.owner.cmp(&b.owner));
+    closed
+}
+
+/// Evaluates the rules over the output DB of a snapshot.
+pub fn eligible_owners(db_path: &Path, rules: &EligibilityRules) -> anyhow::Result<EligibleOwners> {
+    let db = Connection::open_with_flags(db_path, OpenFlags::SQLITE_OPEN_READ_ONLY)?;
+    let slot = snapshot_slot(&db, db_path)?;
+    let balances = owner_balances(&db, &rules.mint)?;
+
+    // the lowest balance over the held-through snapshots, owners missing in any of them are dropped
+    let mut held: BTreeMap<String, (u64, u64)> = balances
+        .iter()
+        .filter(|(owner, amount)| {
+            **amount >= rules.min_balance && !rules.excluded_owners.contains(*owner)
+        })
+        .map(|(owner, amount)| (owner.clone(), (*amount, slot)))
+        .collect();
+    let mut previous: Option<(u64, HashMap<String, u64>)> = None;
+    for held_through_path in rules.held_through.iter() {
+        let held_through_db =
+            Connection::open_with_flags(held_through_path, OpenFlags::SQLITE_OPEN_READ_ONLY)
+                .map_err(|e| {
+                    anyhow::anyhow!(
+                        "Could not open held-through DB {:?}: {}",
+                        held_through_path,
+                        e
+                    )
+                })?;
+        let held_through_slot = snapshot_slot(&held_through_db, held_through_path)?;
+        if held_through_slot >= slot {
+            anyhow::bail!(
+                "Held-through DB {:?} of slot {} is not older than the snapshot of slot {}",
+                held_through_path,
+                held_through_slot,
+                slot
+            );
+        }
+        let held_through_balances = owner_balances(&held_through_db, &rules.mint)?;
+        held.retain(|owner, (held_amount, held_since_slot)| {
+            match held_through_balances.get(owner) {
+                Some(amount) if *amount >= rules.min_balance => {
+                    *held_amount = (*held_amount).min(*amount);
+                    *held_since_slot = (*held_since_slot).min(held_through_slot);
+                    true
+                }
+                _ => false,
+            }
+        });
+        if previous.as_ref().map_or(true, |(previous_slot, _)| {
+            held_through_slot > *previous_slot
+        }) {
+            previous = Some((held_through_slot, held_through_balances));
+        }
+    }
+    let closed = match previous {
+        Some((previous_slot, previous_balances)) => {
+            closed_owners(&rules.mint, &balances, &previous_balances, previous_slot)
+        }
+        None => vec![],
+    };
+
+    let owner_types = owner_types(&db, held.keys().cloned())?;
+    let eligible: Vec<EligibleOwnerRow> = held
+        .into_iter()
+        .filter_map(|(owner, (held_amount, held_since_slot))| {
+            let owner_type = owner_types.get(&owner).cloned();
+            if owner_type
+                .as_ref()
+                .is_some_and(|owner_type| rules.excluded_owner_types.contains(owner_type))
+            {
+                return None;
+            }
+            Some(EligibleOwnerRow {
+                amount: balances[&owner],
+                owner,
+                mint: rules.mint.to_string(),
+                held_amount,
+                held_since_slot,
+                owner_type,
+            })
+        })
+        .collect();
+    info!(
+        "{} of {} owners of mint {} are eligible at slot {}, {} owners closed since the previous snapshot",
+        eligible.len(),
+        balances.len(),
+        rules.mint,
+        slot,
+        closed.len()
+    );
+    Ok(EligibleOwners { eligible, closed })
+}
+
+/// Recreates the table of the rows and records its version into the `schema_version` table,
+/// as [`crate::sql_row::create_table`] does for the tables of the processors.
+fn replace_table<R: SqlRow>(tx: &rusqlite::Transaction, rows: &[R]) -> anyhow::Result<()> {
+    tx.execute(&format!("DROP TABLE IF EXISTS {};", R::TABLE), [])?;
+    tx.execute(&R::create_table_query(), [])?;
+    let mut stmt = tx.prepare(&R::insert_query())?;
+    for row in rows {
+        stmt.execute(rusqlite::params_from_iter(row.to_values()))?;
+    }
+    tx.execute(&SchemaVersionRow::create_table_query(), [])?;
+    tx.execute(
+        &SchemaVersionRow::insert_query(),
+        rusqlite::params_from_iter(table_version_row::<R>().to_values()),
+    )?;
+    Ok(())
+}
+
+/// Replaces the `eligible_owners` and `closed_owners` tables of the output DB,
+/// so a re-run with other rules leaves no stale owners.
+pub fn write_eligible_owners(db_path: &Path, owners: &EligibleOwners) -> anyhow::Result<()> {
+    let mut db = Connection::open(db_path)?;
+    let tx = db.transaction()?;
+    replace_table(&tx, &owners.eligible)?;
+    replace_table(&tx, &owners.closed)?;
+    tx.commit()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::schema::{CLOSED_OWNERS_TABLE, ELIGIBLE_OWNERS_TABLE, SCHEMA_VERSION_TABLE};
+    use solana_sdk::signature::{Keypair, Signer};
+
+    struct TempDb(PathBuf);
+
+    impl Drop for TempDb {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_file(&self.0);
+        }
+    }
+
+    /// Output DB with the tables the rules read, holding the (owner, mint, amount) token accounts.
+    fn temp_db(
+        name: &str,
+        slot: u64,
+        accounts: &[(&Pubkey, &Pubkey, u64)],
+        labels: &[(&Pubkey, &str)],
+    ) -> TempDb {
+        let path =
+            std::env::temp_dir().join(format!("eligibility_{}_{}.db", name, std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        let db = Connection::open(&path).unwrap();
+        db.execute_batch(&format!(
+            "CREATE TABLE {} (slot INTEGER NOT NULL);
+             CREATE TABLE {} (pubkey TEXT NOT NULL, owner TEXT NOT NULL, mint TEXT NOT NULL, amount INTEGER NOT NULL);
+             CREATE TABLE {} (address TEXT NOT NULL, category TEXT NOT NULL);
+             CREATE TABLE {} (pubkey TEXT NOT NULL, owner TEXT NOT NULL);",
+            SNAPSHOT_INFO_TABLE, TOKEN_ACCOUNT_TABLE, PROGRAM_LABEL_TABLE, META_ACCOUNT_TABLE
+        ))
+        .unwrap();
+        db.execute(
+            &format!("INSERT INTO {} VALUES (?1);", SNAPSHOT_INFO_TABLE),
+            [slot as i64],
+        )
+        .unwrap();
+        for (owner, mint, amount) in accounts {
+            db.execute(
+                &format!(
+                    "INSERT INTO {} VALUES (?1, ?2, ?3, ?4);",
+                    TOKEN_ACCOUNT_TABLE
+                ),
+                rusqlite::params![
+                    Pubkey::new_unique().to_string(),
+                    owner.to_string(),
+                    mint.to_string(),
+                    *amount as i64
+                ],
+            )
+            .unwrap();
+        }
+        for (address, category) in labels {
+            db.execute(
+                &format!("INSERT INTO {} VALUES (?1, ?2);", PROGRAM_LABEL_TABLE),
+                [address.to_string(), category.to_string()],
+            )
+            .unwrap();
+        }
+        TempDb(path)
+    }
+
+    fn rules(mint: Pubkey, held_through: &[&TempDb]) -> EligibilityRules {
+        EligibilityRules {
+            mint,
+            min_balance: 1_000,
+            held_through: held_through.iter().map(|db| db.0.clone()).collect(),
+            excluded_owner_types: HashSet::new(),
+            excluded_owners: HashSet::new(),
+        }
+    }
+
+    fn wallet() -> Pubkey {
+        Keypair::new().pubkey()
+    }
+
+    fn eligible(owners: &EligibleOwners) -> HashMap<String, &EligibleOwnerRow> {
+        owners
+            .eligible
+            .iter()
+            .map(|row| (row.owner.clone(), row))
+            .collect()
+    }
+
+    #[test]
+    fn rules_select_owners() {
+        let mint = Pubkey::new_unique();
+        let other_mint = Pubkey::new_unique();
+        let [summed, below, other_mint_holder, excluded, staking] = [(); 5].map(|_| wallet());
+        let pda = Pubkey::find_program_address(&[b"pda"], &Pubkey::new_unique()).0;
+        let db = temp_db(
+            "select",
+            200,
+            &[
+                (&summed, &mint, 600),
+                (&summed, &mint, 600),
+                (&below, &mint, 999),
+                (&other_mint_holder, &other_mint, 5_000),
+                (&excluded, &mint, 5_000),
+                (&staking, &mint, 5_000),
+                (&pda, &mint, 5_000),
+            ],
+            &[(&staking, "staking")],
+        );
+        let mut rules = rules(mint, &[]);
+        rules.excluded_owners.insert(excluded.to_string());
+        rules.excluded_owner_types.insert("staking".to_string());
+
+        let owners = eligible_owners(&db.0, &rules).unwrap();
+        let eligible = eligible(&owners);
+        assert_eq!(eligible.len(), 2);
+        let summed = eligible[&summed.to_string()];
+        assert_eq!(
+            (summed.amount, summed.held_amount, summed.held_since_slot),
+            (1_200, 1_200, 200)
+        );
+        assert_eq!(summed.owner_type, None);
+        assert_eq!(
+            eligible[&pda.to_string()].owner_type.as_deref(),
+            Some(OFF_CURVE_OWNER_TYPE)
+        );
+        assert!(owners.closed.is_empty());
+
+        rules
+            .excluded_owner_types
+            .insert(OFF_CURVE_OWNER_TYPE.to_string());
+        let owners = eligible_owners(&db.0, &rules).unwrap();
+        assert_eq!(owners.eligible.len(), 1);
+    }
+
+    #[test]
+    fn rules_require_balance_held_through() {
+        let mint = Pubkey::new_unique();
+        let [held, lowered, new, emptied, closed] = [(); 5].map(|_| wallet());
+        let earlier = temp_db(
+            "held_earlier",
+            100,
+            &[(&held, &mint, 3_000), (&lowered, &mint, 500)],
+            &[],
+        );
+        let previous = temp_db(
+            "held_previous",
+            150,
+            &[
+                (&held, &mint, 1_500),
+                (&lowered, &mint, 5_000),
+                (&emptied, &mint, 2_000),
+                (&closed, &mint, 3_000),
+            ],
+            &[],
+        );
+        let db = temp_db(
+            "held",
+            200,
+            &[
+                (&held, &mint, 2_000),
+                (&lowered, &mint, 5_000),
+                (&new, &mint, 5_000),
+                (&emptied, &mint, 0),
+            ],
+            &[],
+        );
+
+        let owners = eligible_owners(&db.0, &rules(mint, &[&earlier, &previous])).unwrap();
+        let eligible = eligible(&owners);
+        assert_eq!(eligible.len(), 1);
+        let held = eligible[&held.to_string()];
+        assert_eq!(
+            (held.amount, held.held_amount, held.held_since_slot),
+            (2_000, 1_500, 100)
+        );
+
+        // the closed owners are of the latest held-through snapshot
+        let mut expected = vec![
+            ClosedOwnerRow {
+                owner: emptied.to_string(),
+                mint: mint.to_string(),
+                previous_amount: 2_000,
+                previous_slot: 150,
+                accounts_closed: false,
+            },
+            ClosedOwnerRow {
+                owner: closed.to_string(),
+                mint: mint.to_string(),
+                previous_amount: 3_000,
+                previous_slot: 150,
+                accounts_closed: true,
+            },
+        ];
+        expected.sort_by(|a, b| a.owner.cmp(&b.owner));
+        assert_eq!(owners.closed, expected);
+    }
+
+    #[test]
+    fn rules_reject_held_through_not_older() {
+        let mint = Pubkey::new_unique();
+        let later = temp_db("not_older_later", 200, &[], &[]);
+        let db = temp_db("not_older", 200, &[], &[]);
+        assert!(eligible_owners(&db.0, &rules(mint, &[&later])).is_err());
+    }
+
+    #[test]
+    fn rules_require_positive_min_balance() {
+        let path =
+            std::env::temp_dir().join(format!("eligibility_rules_{}.json", std::process::id()));
+        let _guard = TempDb(path.clone());
+        let mint = Pubkey::new_unique();
+        for (rules, valid) in [
+            (format!(r#"{{"mint": "{}", "min_balance": 1}}"#, mint), true),
+            (
+                format!(r#"{{"mint": "{}", "min_balance": 0}}"#, mint),
+                false,
+            ),
+            (format!(r#"{{"mint": "{}"}}"#, mint), false),
+        ] {
+            std::fs::write(&path, &rules).unwrap();
+            assert_eq!(EligibilityRules::load(&path).is_ok(), valid, "{}", rules);
+        }
+    }
+
+    #[test]
+    fn write_records_table_versions() {
+        let mint = Pubkey::new_unique();
+        let owner = wallet();
+        let db = temp_db("write", 200, &[(&owner, &mint, 5_000)], &[]);
+        let owners = eligible_owners(&db.0, &rules(mint, &[])).unwrap();
+        // a re-run replaces the tables
+        write_eligible_owners(&db.0, &owners).unwrap();
+        write_eligible_owners(&db.0, &owners).unwrap();
+
+        let db = Connection::open(&db.0).unwrap();
+        let eligible: i64 = db
+            .query_row(
+                &format!("SELECT COUNT(*) FROM {};", ELIGIBLE_OWNERS_TABLE),
+                [],
+                |row| row.get(0),
+            )
+            .unwrap();
+        assert_eq!(eligible, 1);
+        for (table, version) in [
+            (ELIGIBLE_OWNERS_TABLE, EligibleOwnerRow::VERSION),
+            (CLOSED_OWNERS_TABLE, ClosedOwnerRow::VERSION),
+        ] {
+            let recorded: u32 = db
+                .query_row(
+                    &format!(
+                        "SELECT table_version FROM {} WHERE table_name = ?1;",
+                        SCHEMA_VERSION_TABLE
+                    ),
+                    [table],
+                    |row| row.get(0),
+                )
+                .unwrap();
+            assert_eq!(recorded, version);
+        }
+    }
+}
@@ -0,0 +1,229 @@
+// Named SQL templates of the common downstream queries, shipped in `queries/` next to the schema they depend on.
+// The self-test prepares all of them against the produced DB, so a change of the tables breaking a template fails there.
+use base64::engine::general_purpose::STANDARD as base64_engine;
+use base64::Engine;
+use rusqlite::types::ValueRef;
+use rusqlite::{Connection, OpenFlags};
+use serde::Serialize;
+use serde_json::{Map, Value};
+use std::collections::HashMap;
+use std::io::Write;
+use std::path::Path;
+
+#[derive(Debug, Serialize)]
+pub struct TemplateParam {
+    pub name: &'static str,
+    /// the parameter is required when there is no default
+    pub default: Option<&'static str>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct QueryTemplate {
+    pub name: &'static str,
+    pub description: &'static str,
+    pub params: &'static [TemplateParam],
+    #[serde(skip)]
+    pub sql: &'static str,
+}
+
+pub const QUERY_TEMPLATES: &[QueryTemplate] = &[
+    QueryTemplate {
+        name: "holders_over",
+        description:
+            "Owners holding at least min_amount of the mint, summed over their token accounts",
+        params: &[
+            TemplateParam {
+                name: "mint",
+                default: None,
+            },
+            TemplateParam {
+                name: "min_amount",
+                default: Some("0"),
+            },
+        ],
+        sql: include_str!("../queries/holders_over.sql"),
+    },
+    QueryTemplate {
+        name: "vemnde_by_authority",
+        description: "Voting power of the VSR voter accounts summed per voter authority",
+        params: &[TemplateParam {
+            name: "min_voting_power",
+            default: Some("0"),
+        }],
+        sql: include_str!("../queries/vemnde_by_authority.sql"),
+    },
+    QueryTemplate {
+        name: "native_stake_by_authority",
+        description: "Active delegation of the native stake accounts summed per withdraw authority",
+        params: &[],
+        sql: include_str!("../queries/native_stake_by_authority.sql"),
+    },
+];
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq, clap::ValueEnum)]
+pub enum ExtractFormat {
+    Csv,
+    Json,
+}
+
+pub fn query_template(name: &str) -> anyhow::Result<&'static QueryTemplate> {
+    QUERY_TEMPLATES
+        .iter()
+        .find(|template| template.name == name)
+        .ok_or_else(|| {
+            anyhow::anyhow!(
+                "Unknown query template {}, available: {}",
+                name,
+                QUERY_TEMPLATES
+                    .iter()
+                    .map(|template| template.name)
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            )
+        })
+}
+
+/// Parses the `name=value` parameters of the command line.
+pub fn parse_params(params: &[String]) -> anyhow::Result<HashMap<String, String>> {
+    params
+        .iter()
+        .map(|param| {
+            param
+                .split_once('=')
+                .map(|(name, value)| (name.to_string(), value.to_string()))
+                .ok_or_else(|| anyhow::anyhow!("Parameter {} is not in the name=value form", param))
+        })
+        .collect()
+}
+
+/// Result of a template, the values are kept as SQLite returned them.
+pub struct ExtractedRows {
+    pub columns: Vec<String>,
+    pub rows: Vec<Vec<Value>>,
+}
+
+fn json_value(value: ValueRef) -> Value {
+    match value {
+        ValueRef::Null => Value::Null,
+        ValueRef::Integer(value) => Value::from(value),
+        ValueRef::Real(value) => Value::from(value),
+        ValueRef::Text(value) => Value::String(String::from_utf8_lossy(value).to_string()),
+        ValueRef::Blob(value) => Value::String(base64_engine.encode(value)),
+    }
+}
+
+/// Runs the template against the output DB, the parameters missing in `params` take their defaults.
+pub fn run_template(
+    db_path: &Path,
+    template: &QueryTemplate,
+    params: &HashMap<String, String>,
+) -> anyhow::Result<ExtractedRows> {
+    if let Some(unknown) = params
+        .keys()
+        .find(|name| !template.params.iter().any(|param| param.name == *name))
+    {
+        anyhow::bail!("Template {} has no parameter {}", template.name, unknown);
+    }
+    let mut bound = Vec::with_capacity(template.params.len());
+    for param in template.params {
+        let value = params
+            .get(param.name)
+            .map(String::as_str)
+            .or(param.default)
+            .ok_or_else(|| {
+                anyhow::anyhow!(
+                    "Template {} requires parameter {}",
+                    template.name,
+                    param.name
+                )
+            })?;
+        bound.push((format!(":{}", param.name), value.to_string()));
+    }
+
+    let db = Connection::open_with_flags(db_path, OpenFlags::SQLITE_OPEN_READ_ONLY)?;
+    let mut stmt = db
+        .prepare(template.sql)
+        .map_err(|e| anyhow::anyhow!("Template {} is invalid: {}", template.name, e))?;
+    let columns: Vec<String> = stmt.column_names().iter().map(|c| c.to_string()).collect();
+    let named_params: Vec<(&str, &dyn rusqlite::ToSql)> = bound
+        .iter()
+        .map(|(name, value)| (name.as_str(), value as &dyn rusqlite::ToSql))
+        .collect();
+    let mut query_rows = stmt.query(named_params.as_slice())?;
+    let mut rows = Vec::new();
+    while let Some(row) = query_rows.next()? {
+        rows.push(
+            (0..columns.len())
+                .map(|index| row.get_ref(index).map(json_value))
+                .collect::<Result<Vec<_>, _>>()?,
+        );
+    }
+    Ok(ExtractedRows { columns, rows })
+}
+
+/// Checks that every template compiles against the tables of the output DB.
+pub fn verify_query_templates(db_path: &Path) -> anyhow::Result<()> {
+    let db = Connection::open_with_flags(db_path, OpenFlags::SQLITE_OPEN_READ_ONLY)?;
+    for template in QUERY_TEMPLATES {
+        db.prepare(template.sql).map_err(|e| {
+            anyhow::anyhow!(
+                "Query template {} does not match the DB: {}",
+                template.name,
+                e
+            )
+        })?;
+    }
+    Ok(())
+}
+
+fn csv_field(value: &Value) -> String {
+    let field = match value {
+        Value::Null => return String::new(),
+        Value::String(value) => value.clone(),
+        other => other.to_string(),
+    };
+    if field.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field
+    }
+}
+
+pub fn write_extracted<W: Write>(
+    extracted: &ExtractedRows,
+    format: ExtractFormat,
+    mut writer: W,
+) -> anyhow::Result<()> {
+    match format {
+        ExtractFormat::Csv => {
+            let header: Vec<String> = extracted
+                .columns
+                .iter()
+                .map(|column| csv_field(&Value::String(column.clone())))
+                .collect();
+            writeln!(writer, "{}", header.join(","))?;
+            for row in extracted.rows.iter() {
+                let fields: Vec<String> = row.iter().map(csv_field).collect();
+                writeln!(writer, "{}", fields.join(","))?;
+            }
+        }
+        ExtractFormat::Json => {
+            let rows: Vec<Map<String, Value>> = extracted
+                .rows
+                .iter()
+                .map(|row| {
+                    extracted
+                        .columns
+                        .iter()
+                        .cloned()
+                        .zip(row.iter().cloned())
+                        .collect()
+                })
+                .collect();
+            serde_json::to_writer_pretty(&mut writer, &rows)?;
+            writeln!(writer)?;
+        }
+    }
+    writer.flush()?;
+    Ok(())
+}
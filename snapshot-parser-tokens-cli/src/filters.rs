@@ -1,38 +1,225 @@
+use crate::processors::TOKEN_PROGRAMS;
 use base64::engine::general_purpose::STANDARD as base64_engine;
 use base64::Engine;
 use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use snapshot_parser::program_registry::{
+    ProgramRegistry, MARINADE_NATIVE_STAKE_AUTHORITY, MARINADE_VSR_PROGRAM,
+};
 use snapshot_parser::utils::read_from_json_file;
 use solana_program::pubkey::Pubkey;
-use std::path::PathBuf;
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
 use std::str::FromStr;
+use tracing::{info, warn};
+
+/// Prefix of a filter value that points to an external file with newline-separated pubkeys
+/// (relative paths are resolved against the directory of the filters file, or the working directory for inlined filters).
+const FILE_REFERENCE_PREFIX: &str = "file:";
 
 #[derive(Debug, Deserialize, Serialize)]
 struct FiltersData {
     account_owners: String,
     account_mints: String,
     vsr_registrar_data: String,
+    /// explicit accounts to fetch without scanning, used by the allowlist mode
+    #[serde(default)]
+    account_pubkeys: String,
+    /// stake authority of the native stake accounts, `marinade_native_stake_authority` of the program registry when not set
+    #[serde(default)]
+    native_stake_authority: Option<String>,
+    /// program owning the VSR voter accounts, `marinade_vsr` of the program registry when not set
+    #[serde(default)]
+    vsr_program: Option<String>,
 }
 
 #[derive(Debug, Clone)]
 pub struct Filters {
-    pub account_owners: Vec<Pubkey>,
-    pub account_mints: Vec<Pubkey>,
+    pub account_owners: HashSet<Pubkey>,
+    pub account_mints: HashSet<Pubkey>,
     pub vsr_registrar_data: Vec<u8>,
+    pub account_pubkeys: HashSet<Pubkey>,
+    pub native_stake_authority: Pubkey,
+    pub vsr_program: Pubkey,
 }
 
 impl Filters {
-    pub fn load(filters_path: &PathBuf) -> anyhow::Result<Self> {
+    pub fn load(filters_path: &PathBuf, registry: &ProgramRegistry) -> anyhow::Result<Self> {
         let data: FiltersData = read_from_json_file(filters_path)?;
-        Ok(Self {
-            account_owners: Self::split_pubkeys(&data.account_owners, "account_owners")?,
-            account_mints: Self::split_pubkeys(&data.account_mints, "account_mints")?,
+        let base_dir = filters_path.parent().unwrap_or_else(|| Path::new("."));
+        Self::from_data(data, base_dir, registry)
+    }
+
+    /// Parses filters passed inline as a JSON string with the same structure as the filters file.
+    pub fn from_json(filters_json: &str, registry: &ProgramRegistry) -> anyhow::Result<Self> {
+        let data: FiltersData = serde_json::from_str(filters_json)
+            .map_err(|e| anyhow::anyhow!("Could not parse inlined filters JSON: {}", e))?;
+        Self::from_data(data, Path::new("."), registry)
+    }
+
+    /// Loads the filters either from the filters file or from the inlined JSON.
+    /// Addresses not set by the filters are taken from the program registry.
+    pub fn load_from(
+        filters_path: Option<&PathBuf>,
+        filters_json: Option<&str>,
+        registry: &ProgramRegistry,
+    ) -> anyhow::Result<Self> {
+        match (filters_path, filters_json) {
+            (Some(filters_path), _) => {
+                info!("Loading filters from: {:?}", filters_path);
+                Self::load(filters_path, registry)
+            }
+            (None, Some(filters_json)) => {
+                info!("Loading filters from inlined JSON");
+                Self::from_json(filters_json, registry)
+            }
+            (None, None) => anyhow::bail!("One of --filters or --filters-json is required"),
+        }
+    }
+
+    /// Decodes the VSR registrar account data of the filters.
+    pub fn vsr_registrar(&self) -> anyhow::Result<Registrar> {
+        Registrar::deserialize(&mut self.vsr_registrar_data.as_slice())
+            .map_err(|e| anyhow::anyhow!("Could not decode vsr_registrar_data as Registrar: {}", e))
+    }
+
+    fn from_data(
+        data: FiltersData,
+        base_dir: &Path,
+        registry: &ProgramRegistry,
+    ) -> anyhow::Result<Self> {
+        let account_owners = Self::split_pubkeys(&data.account_owners, "account_owners")?;
+        let account_mints = Self::load_pubkeys(&data.account_mints, "account_mints", base_dir)?;
+        let account_pubkeys =
+            Self::load_pubkeys(&data.account_pubkeys, "account_pubkeys", base_dir)?;
+        let filters = Self {
+            account_owners: Self::dedup_pubkeys(account_owners, "account_owners"),
+            account_mints: Self::dedup_pubkeys(account_mints, "account_mints"),
             vsr_registrar_data: base64_engine.decode(&data.vsr_registrar_data)?,
-        })
+            account_pubkeys: Self::dedup_pubkeys(account_pubkeys, "account_pubkeys"),
+            native_stake_authority: Self::parse_address(
+                data.native_stake_authority.as_deref(),
+                registry.address(MARINADE_NATIVE_STAKE_AUTHORITY)?,
+                "native_stake_authority",
+            )?,
+            vsr_program: Self::parse_address(
+                data.vsr_program.as_deref(),
+                registry.address(MARINADE_VSR_PROGRAM)?,
+                "vsr_program",
+            )?,
+        };
+        filters.check_account_owners()?;
+        Ok(filters)
+    }
+
+    /// Overrides the addresses of the filters by the ones passed on the command line.
+    pub fn override_addresses(
+        &mut self,
+        native_stake_authority: Option<Pubkey>,
+        vsr_program: Option<Pubkey>,
+    ) {
+        if let Some(native_stake_authority) = native_stake_authority {
+            self.native_stake_authority = native_stake_authority;
+        }
+        if let Some(vsr_program) = vsr_program {
+            self.vsr_program = vsr_program;
+        }
+    }
+
+    /// Hex sha256 of the effective filters, independent of the order of the pubkeys
+    /// and of whether they were inlined or referenced from files.
+    pub fn content_hash(&self) -> String {
+        let mut hasher = Sha256::new();
+        for pubkeys in [
+            &self.account_owners,
+            &self.account_mints,
+            &self.account_pubkeys,
+        ] {
+            let mut pubkeys: Vec<&Pubkey> = pubkeys.iter().collect();
+            pubkeys.sort();
+            hasher.update((pubkeys.len() as u64).to_le_bytes());
+            pubkeys
+                .into_iter()
+                .for_each(|pubkey| hasher.update(pubkey.as_ref()));
+        }
+        hasher.update((self.vsr_registrar_data.len() as u64).to_le_bytes());
+        hasher.update(&self.vsr_registrar_data);
+        hasher.update(self.native_stake_authority.as_ref());
+        hasher.update(self.vsr_program.as_ref());
+        hasher
+            .finalize()
+            .iter()
+            .map(|byte| format!("{:02x}", byte))
+            .collect()
+    }
+
+    fn parse_address(value: Option<&str>, default: Pubkey, name: &str) -> anyhow::Result<Pubkey> {
+        match value.map(str::trim) {
+            Some(address) => Pubkey::from_str(address).map_err(|e| {
+                anyhow::anyhow!("Could not parse {} address '{}': {}", name, address, e)
+            }),
+            None => Ok(default),
+        }
+    }
+
+    fn dedup_pubkeys(pubkeys: Vec<Pubkey>, name: &str) -> HashSet<Pubkey> {
+        let mut set = HashSet::with_capacity(pubkeys.len());
+        for pubkey in pubkeys {
+            if !set.insert(pubkey) {
+                warn!("Duplicate pubkey {} in filter {}, ignoring", pubkey, name);
+            }
+        }
+        set
+    }
+
+    /// Warns about the token programs among the owners, their accounts are scanned by the token processor too,
+    /// and rejects the mints among the owners, a mint owns no accounts.
+    fn check_account_owners(&self) -> anyhow::Result<()> {
+        for owner in self.account_owners.iter() {
+            if TOKEN_PROGRAMS.contains(owner) {
+                warn!(
+                    "Filter account_owners contains token program {}; its token accounts are already stored by the token processor and will be scanned twice",
+                    owner
+                );
+            }
+            if self.account_mints.contains(owner) {
+                anyhow::bail!(
+                    "Pubkey {} is present in both account_owners and account_mints filters",
+                    owner
+                );
+            }
+        }
+        Ok(())
+    }
+
+    /// Loads pubkeys either from the inlined comma-separated string
+    /// or from the referenced file (`file:<path>`) with one pubkey per line.
+    fn load_pubkeys(value: &str, name: &str, base_dir: &Path) -> anyhow::Result<Vec<Pubkey>> {
+        match value.trim().strip_prefix(FILE_REFERENCE_PREFIX) {
+            Some(file_path) => {
+                let file_path = base_dir.join(file_path.trim());
+                let content = std::fs::read_to_string(&file_path).map_err(|e| {
+                    anyhow::anyhow!("Could not read {} file {:?}: {}", name, file_path, e)
+                })?;
+                let pubkeys = content
+                    .lines()
+                    .map(str::trim)
+                    .filter(|line| !line.is_empty() && !line.starts_with('#'))
+                    .collect::<Vec<_>>()
+                    .join(",");
+                Self::split_pubkeys(&pubkeys, name)
+            }
+            None => Self::split_pubkeys(value, name),
+        }
     }
 
     fn split_pubkeys(pubkeys_string: &str, name: &str) -> anyhow::Result<Vec<Pubkey>> {
+        if pubkeys_string.trim().is_empty() {
+            return Ok(vec![]);
+        }
         pubkeys_string
             .split(',')
+            .map(str::trim)
             .map(|s| {
                 Pubkey::from_str(s).map_err(|e| {
                     anyhow::anyhow!(
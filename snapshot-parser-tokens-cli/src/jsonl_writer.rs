@@ -0,0 +1,260 @@
+use crate::db_message::{DbMessage, OwnedSqlValue, TableColumns};
+use crate::progress_bar::{LocalCounter, ProgressCounter};
+use serde_json::Value;
+use snapshot_parser::temp_file::TempFileGuard;
+use std::collections::{HashMap, HashSet};
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use tokio::sync::mpsc::Receiver;
+use tracing::{debug, error, info, warn};
+
+/// Output directory value selecting the single multiplexed stream on stdout.
+pub const JSONL_STDOUT: &str = "-";
+
+fn json_value(value: &OwnedSqlValue) -> Value {
+    match value {
+        OwnedSqlValue::Text(value) => value.clone().map_or(Value::Null, Value::String),
+        OwnedSqlValue::Integer(value) => value.map_or(Value::Null, Value::from),
+        OwnedSqlValue::UnsignedInteger(value) => value.map_or(Value::Null, Value::from),
+        OwnedSqlValue::UnsignedU16(value) => value.map_or(Value::Null, Value::from),
+        OwnedSqlValue::Boolean(value) => value.map_or(Value::Null, Value::from),
+        OwnedSqlValue::U8(value) => value.map_or(Value::Null, Value::from),
+    }
+}
+
+/// Writes the row as one JSON object line, the keys in the order of the columns,
+/// preceded by the `table` key when the stream carries several tables.
+fn write_row<W: Write>(
+    writer: &mut W,
+    table: Option<&str>,
+    columns: TableColumns,
+    values: &[OwnedSqlValue],
+) -> anyhow::Result<()> {
+    if values.len() != columns.len() {
+        anyhow::bail!("Expected {} values, got {}", columns.len(), values.len());
+    }
+    writer.write_all(b"{")?;
+    let mut separator = "";
+    if let Some(table) = table {
+        write!(writer, "\"table\":{}", serde_json::to_string(table)?)?;
+        separator = ",";
+    }
+    for ((name, _), value) in columns.iter().zip(values) {
+        write!(
+            writer,
+            "{}{}:{}",
+            separator,
+            serde_json::to_string(name)?,
+            json_value(value)
+        )?;
+        separator = ",";
+    }
+    writer.write_all(b"}\n")?;
+    Ok(())
+}
+
+struct TableStream {
+    columns: TableColumns,
+    /// primary keys of the written rows when the first column is the primary key
+    written_keys: Option<HashSet<String>>,
+}
+
+impl TableStream {
+    fn new(columns: TableColumns) -> Self {
+        let has_primary_key = columns
+            .first()
+            .is_some_and(|(_, sql_type)| sql_type.to_uppercase().contains("PRIMARY KEY"));
+        Self {
+            columns,
+            written_keys: has_primary_key.then(HashSet::new),
+        }
+    }
+
+    /// Whether the row is to be written, i.e., no row with the same primary key was written before.
+    /// Rows of the same account are equal within a snapshot, so the first one is kept.
+    fn is_new(&mut self, values: &[OwnedSqlValue]) -> bool {
+        match (self.written_keys.as_mut(), values.first()) {
+            (Some(written_keys), Some(OwnedSqlValue::Text(Some(key)))) => {
+                written_keys.insert(key.clone())
+            }
+            _ => true,
+        }
+    }
+}
+
+struct TableFile {
+    writer: BufWriter<File>,
+    temp_guard: TempFileGuard,
+    path: PathBuf,
+}
+
+impl TableFile {
+    fn new(output_dir: &Path, table: &str) -> anyhow::Result<Self> {
+        let path = output_dir.join(format!("{}.jsonl", table));
+        let temp_path = output_dir.join(format!("_{}.jsonl.tmp", table));
+        let _ = std::fs::remove_file(&temp_path);
+        let temp_guard = TempFileGuard::new(temp_path.clone());
+        Ok(Self {
+            writer: BufWriter::new(File::create(&temp_path)?),
+            temp_guard,
+            path,
+        })
+    }
+
+    fn finalize(mut self) -> anyhow::Result<()> {
+        self.writer.flush()?;
+        self.temp_guard.promote(&self.path)?;
+        info!("JSON Lines file promoted to: {:?}", &self.path);
+        Ok(())
+    }
+}
+
+/// Where the rows go: one file per table, or one stream of all tables on stdout.
+enum JsonlOutput {
+    Files {
+        output_dir: PathBuf,
+        files: HashMap<&'static str, TableFile>,
+    },
+    Stdout(BufWriter<std::io::Stdout>),
+}
+
+/// Writes every table into its own `<table>.jsonl` file in the output directory, one JSON object per row.
+/// With the output directory `-`, all rows are streamed to stdout instead, each with the `table` key,
+/// e.g., to be piped into a message queue. The files are written as temporary files that get promoted on shutdown.
+pub struct JsonlExecutor {
+    output: JsonlOutput,
+    tables: HashMap<&'static str, TableStream>,
+    db_execute_counter: LocalCounter,
+    receiver: Receiver<DbMessage>,
+}
+
+impl JsonlExecutor {
+    pub fn new(
+        output_dir: PathBuf,
+        db_execute_counter: Arc<ProgressCounter>,
+        receiver: Receiver<DbMessage>,
+    ) -> anyhow::Result<Self> {
+        let output = if output_dir.as_os_str() == JSONL_STDOUT {
+            JsonlOutput::Stdout(BufWriter::new(std::io::stdout()))
+        } else {
+            std::fs::create_dir_all(&output_dir)?;
+            JsonlOutput::Files {
+                output_dir,
+                files: HashMap::new(),
+            }
+        };
+        Ok(Self {
+            output,
+            tables: HashMap::new(),
+            db_execute_counter: db_execute_counter.local(),
+            receiver,
+        })
+    }
+
+    fn create_table(
+        &mut self,
+        table: &'static str,
+        columns: TableColumns,
+    ) -> anyhow::Result<usize> {
+        if self.tables.contains_key(table) {
+            return Ok(0);
+        }
+        debug!("Creating JSON Lines stream for table {}", table);
+        if let JsonlOutput::Files { output_dir, files } = &mut self.output {
+            files.insert(table, TableFile::new(output_dir, table)?);
+        }
+        self.tables.insert(table, TableStream::new(columns));
+        Ok(0)
+    }
+
+    fn insert(&mut self, table: &'static str, values: &[OwnedSqlValue]) -> anyhow::Result<usize> {
+        let stream = self
+            .tables
+            .get_mut(table)
+            .ok_or_else(|| anyhow::anyhow!("Table {} was not created", table))?;
+        self.db_execute_counter.inc();
+        if !stream.is_new(values) {
+            return Ok(0);
+        }
+        match &mut self.output {
+            JsonlOutput::Files { files, .. } => {
+                let file = files
+                    .get_mut(table)
+                    .ok_or_else(|| anyhow::anyhow!("Table {} was not created", table))?;
+                write_row(&mut file.writer, None, stream.columns, values)?;
+            }
+            JsonlOutput::Stdout(writer) => {
+                write_row(writer, Some(table), stream.columns, values)?;
+            }
+        }
+        Ok(1)
+    }
+
+    fn insert_rows(
+        &mut self,
+        table: &'static str,
+        rows: &[Vec<OwnedSqlValue>],
+    ) -> anyhow::Result<usize> {
+        let mut inserted = 0;
+        for values in rows {
+            inserted += self.insert(table, values)?;
+        }
+        Ok(inserted)
+    }
+
+    pub async fn start(mut self) {
+        info!("JsonlExecutor receiver started to listen for row messages");
+        while let Some(msg) = self.receiver.recv().await {
+            match msg {
+                DbMessage::CreateTable {
+                    table,
+                    columns,
+                    response,
+                } => {
+                    let _ = response.send(self.create_table(table, columns));
+                }
+                DbMessage::Insert { row, response } => {
+                    let table = row.table();
+                    let _ = response.send(self.insert(table, &row.into_values()));
+                }
+                DbMessage::InsertBatch { rows, response } => {
+                    let table = rows.table();
+                    let _ = response.send(self.insert_rows(table, &rows.into_values()));
+                }
+                DbMessage::CommitCheckpoint { response, .. }
+                | DbMessage::RecordCompletion { response, .. } => {
+                    error!("Checkpoints are not supported by the JSON Lines output");
+                    let _ = response.send(Err(anyhow::anyhow!(
+                        "Checkpoints are not supported by the JSON Lines output"
+                    )));
+                }
+                DbMessage::Finalize { response } => {
+                    let _ = response.send(Ok(()));
+                }
+                DbMessage::Shutdown { response } => {
+                    let _ = response.send(self.finalize());
+                }
+                DbMessage::Interrupt { response } => {
+                    warn!("Interrupted, the partial JSON Lines output is not kept");
+                    let _ = response.send(Ok(()));
+                    break;
+                }
+            }
+        }
+    }
+
+    fn finalize(&mut self) -> anyhow::Result<()> {
+        match &mut self.output {
+            JsonlOutput::Files { output_dir, files } => {
+                for (_, file) in files.drain() {
+                    file.finalize()?;
+                }
+                info!("JSON Lines files finalized in {:?}", output_dir);
+            }
+            JsonlOutput::Stdout(writer) => writer.flush()?,
+        }
+        Ok(())
+    }
+}
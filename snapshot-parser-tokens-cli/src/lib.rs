@@ -1,8 +1,27 @@
 pub mod accounts;
-pub mod db_connection;
-pub mod db_message;
+pub mod checkpoint;
+pub mod commands;
+pub mod cross_check;
+pub mod db_spool;
+pub mod eligibility;
+pub mod extract;
 pub mod filters;
+pub mod jsonl_writer;
+pub mod merkle;
+pub mod minimize;
+pub mod mint_authority;
+pub mod output;
+pub mod parquet_writer;
+pub mod pipeline;
+pub mod postgres_writer;
 pub mod processors;
-pub mod progress_bar;
-pub mod stats;
-pub mod temp_file;
+pub mod program_labels;
+pub mod row_limit;
+pub mod run_config;
+pub mod sampling;
+pub mod self_test;
+pub mod snapshot_info;
+pub mod validate_filters;
+pub mod voting_power;
+
+pub use snapshot_parser::{db_connection, db_message, progress_bar, schema, sql_row, stats};
@@ -0,0 +1,335 @@
+use rusqlite::types::Value;
+use rusqlite::{Connection, OpenFlags};
+use serde::{Deserialize, Serialize};
+use snapshot_parser::serde_serialize::pubkey_string_conversion;
+use snapshot_parser::utils::write_to_json_file;
+use solana_program::hash::{hashv, Hash};
+use solana_program::pubkey::Pubkey;
+use std::collections::BTreeMap;
+use std::path::Path;
+use std::str::FromStr;
+use tracing::info;
+
+// Prefixes keep a leaf from being passed off as an intermediate node, the same as in the Jito merkle distributor.
+const LEAF_PREFIX: &[u8] = &[0];
+const INTERMEDIATE_PREFIX: &[u8] = &[1];
+
+/// CLI options of the merkle tree built over the output DB once it is finalized.
+#[derive(clap::Args, Debug, Clone)]
+pub struct MerkleArgs {
+    /// Path to write JSON file to with the merkle root and the proof of every (owner, amount) leaf
+    /// read from the output SQLite DB (e.g., merkle.json)
+    #[arg(long, env)]
+    pub output_merkle: Option<String>,
+
+    /// Table the leaves are read from
+    #[arg(long, env, default_value = "token_account")]
+    pub merkle_table: String,
+
+    /// Column of the table with the pubkey of the leaf owner
+    #[arg(long, env, default_value = "owner")]
+    pub merkle_owner_column: String,
+
+    /// Column of the table with the amount of the leaf
+    #[arg(long, env, default_value = "amount")]
+    pub merkle_amount_column: String,
+
+    /// Only rows of the mint are read, for tables with a mint column
+    #[arg(long, env)]
+    pub merkle_mint: Option<Pubkey>,
+
+    /// Sum the amounts of the rows of one owner into a single leaf
+    #[arg(long, env, default_value_t = false)]
+    pub merkle_aggregate: bool,
+}
+
+#[derive(Clone, Deserialize, Serialize, Debug, Eq, PartialEq)]
+pub struct MerkleLeaf {
+    #[serde(with = "pubkey_string_conversion")]
+    pub owner: Pubkey,
+    pub amount: u64,
+    /// sibling hashes from the leaf up to the root
+    pub proof: Vec<String>,
+}
+
+#[derive(Clone, Deserialize, Serialize, Debug, Eq, PartialEq)]
+pub struct MerkleDistribution {
+    pub table: String,
+    pub root: String,
+    pub total_amount: u64,
+    /// ordered by the owner and the amount
+    pub leaves: Vec<MerkleLeaf>,
+}
+
+/// Hash of the `(owner, amount)` leaf, `hashv([0], hashv(owner, amount_le))`.
+pub fn leaf_hash(owner: &Pubkey, amount: u64) -> Hash {
+    let node = hashv(&[owner.as_ref(), &amount.to_le_bytes()]);
+    hashv(&[LEAF_PREFIX, node.as_ref()])
+}
+
+/// Parent of two nodes, hashed in sorted order so a proof does not need the side of the siblings.
+fn intermediate_hash(left: &Hash, right: &Hash) -> Hash {
+    let (first, second) = if left <= right {
+        (left, right)
+    } else {
+        (right, left)
+    };
+    hashv(&[INTERMEDIATE_PREFIX, first.as_ref(), second.as_ref()])
+}
+
+/// Binary merkle tree over the leaf hashes, a node without a sibling is promoted to the next level as it is.
+pub struct MerkleTree {
+    /// levels from the leaves up to the root
+    levels: Vec<Vec<Hash>>,
+}
+
+impl MerkleTree {
+    pub fn new(leaves: Vec<Hash>) -> Self {
+        let mut levels = vec![leaves];
+        while levels.last().unwrap().len() > 1 {
+            let next = levels
+                .last()
+                .unwrap()
+                .chunks(2)
+                .map(|pair| match pair {
+                    [left, right] => intermediate_hash(left, right),
+                    [single] => *single,
+                    _ => unreachable!("chunks of two"),
+                })
+                .collect();
+            levels.push(next);
+        }
+        Self { levels }
+    }
+
+    /// Root of the tree, the default hash for a tree without leaves.
+    pub fn root(&self) -> Hash {
+        self.levels
+            .last()
+            .and_then(|level| level.first())
+            .copied()
+            .unwrap_or_default()
+    }
+
+    pub fn proof(&self, mut index: usize) -> Vec<Hash> {
+        let mut proof = Vec::new();
+        for level in &self.levels[..self.levels.len() - 1] {
+            if let Some(sibling) = level.get(index ^ 1) {
+                proof.push(*sibling);
+            }
+            index /= 2;
+        }
+        proof
+    }
+}
+
+/// Checks the leaf against the root by its proof, as an on-chain distributor would.
+pub fn verify_proof(root: &Hash, leaf: Hash, proof: &[Hash]) -> bool {
+    proof
+        .iter()
+        .fold(leaf, |node, sibling| intermediate_hash(&node, sibling))
+        == *root
+}
+
+fn check_identifier(identifier: &str) -> anyhow::Result<&str> {
+    if identifier.is_empty()
+        || !identifier
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '_')
+    {
+        anyhow::bail!("Invalid table or column name: {}", identifier);
+    }
+    Ok(identifier)
+}
+
+fn amount_value(value: Value) -> anyhow::Result<u64> {
+    match value {
+        Value::Integer(amount) => Ok(u64::try_from(amount)?),
+        // amounts not fitting into an i64 are stored as text, e.g. the vemnde voting power
+        Value::Text(amount) => Ok(amount.parse()?),
+        other => anyhow::bail!("Amount {:?} is not an integer", other),
+    }
+}
+
+/// Reads the `(owner, amount)` leaves of the output DB, sorted by the owner and the amount.
+pub fn read_leaves(db_path: &Path, args: &MerkleArgs) -> anyhow::Result<Vec<(Pubkey, u64)>> {
+    let db = Connection::open_with_flags(db_path, OpenFlags::SQLITE_OPEN_READ_ONLY)?;
+    let mut query = format!(
+        "SELECT {}, {} FROM {}",
+        check_identifier(&args.merkle_owner_column)?,
+        check_identifier(&args.merkle_amount_column)?,
+        check_identifier(&args.merkle_table)?
+    );
+    let params: Vec<String> = match &args.merkle_mint {
+        Some(mint) => {
+            query.push_str(" WHERE mint = ?1");
+            vec![mint.to_string()]
+        }
+        None => vec![],
+    };
+    let mut stmt = db.prepare(&query)?;
+    let rows = stmt.query_map(rusqlite::params_from_iter(params), |row| {
+        Ok((row.get::<_, String>(0)?, row.get::<_, Value>(1)?))
+    })?;
+    let mut leaves = Vec::new();
+    for row in rows {
+        let (owner, amount) = row?;
+        leaves.push((
+            Pubkey::from_str(&owner)
+                .map_err(|e| anyhow::anyhow!("Invalid owner pubkey {}: {}", owner, e))?,
+            amount_value(amount)?,
+        ));
+    }
+    if args.merkle_aggregate {
+        let mut aggregated: BTreeMap<Pubkey, u64> = BTreeMap::new();
+        for (owner, amount) in leaves {
+            let sum = aggregated.entry(owner).or_default();
+            *sum = sum
+                .checked_add(amount)
+                .ok_or_else(|| anyhow::anyhow!("Amount of owner {} overflows", owner))?;
+        }
+        leaves = aggregated.into_iter().collect();
+    }
+    leaves.sort();
+    Ok(leaves)
+}
+
+/// Builds the merkle tree over the leaves of the output DB and the proof of every leaf.
+pub fn generate_merkle_distribution(
+    db_path: &Path,
+    args: &MerkleArgs,
+) -> anyhow::Result<MerkleDistribution> {
+    let leaves = read_leaves(db_path, args)?;
+    let tree = MerkleTree::new(
+        leaves
+            .iter()
+            .map(|(owner, amount)| leaf_hash(owner, *amount))
+            .collect(),
+    );
+    let root = tree.root();
+    let total_amount = leaves.iter().try_fold(0u64, |sum, (_, amount)| {
+        sum.checked_add(*amount)
+            .ok_or_else(|| anyhow::anyhow!("Total amount of the merkle leaves overflows"))
+    })?;
+    let leaves: Vec<MerkleLeaf> = leaves
+        .into_iter()
+        .enumerate()
+        .map(|(index, (owner, amount))| {
+            let proof = tree.proof(index);
+            debug_assert!(verify_proof(&root, leaf_hash(&owner, amount), &proof));
+            MerkleLeaf {
+                owner,
+                amount,
+                proof: proof.iter().map(Hash::to_string).collect(),
+            }
+        })
+        .collect();
+    info!(
+        "Merkle tree over {} leaves of table {}: root {}, total amount {}",
+        leaves.len(),
+        args.merkle_table,
+        root,
+        total_amount
+    );
+    Ok(MerkleDistribution {
+        table: args.merkle_table.clone(),
+        root: root.to_string(),
+        total_amount,
+        leaves,
+    })
+}
+
+/// Writes the merkle distribution of the finalized output DB into `--output-merkle` when it is set,
+/// so the tree matches exactly what is published.
+pub fn write_merkle_distribution(db_path: Option<&Path>, args: &MerkleArgs) -> anyhow::Result<()> {
+    let Some(output_merkle) = &args.output_merkle else {
+        return Ok(());
+    };
+    let db_path = db_path.ok_or_else(|| {
+        anyhow::anyhow!("--output-merkle is supported by the sqlite output format only")
+    })?;
+    let merkle_distribution = generate_merkle_distribution(db_path, args)?;
+    write_to_json_file(&merkle_distribution, output_merkle)?;
+    info!("Merkle tree written to {}", output_merkle);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn leaves(count: u8) -> Vec<Hash> {
+        (1..=count)
+            .map(|i| leaf_hash(&Pubkey::new_from_array([i; 32]), u64::from(i) * 100))
+            .collect()
+    }
+
+    #[test]
+    fn empty_tree() {
+        let tree = MerkleTree::new(vec![]);
+        assert_eq!(tree.root(), Hash::default());
+        assert!(tree.proof(0).is_empty());
+    }
+
+    #[test]
+    fn single_leaf_tree() {
+        let leaves = leaves(1);
+        let tree = MerkleTree::new(leaves.clone());
+        assert_eq!(tree.root(), leaves[0]);
+        assert!(tree.proof(0).is_empty());
+        assert!(verify_proof(&tree.root(), leaves[0], &tree.proof(0)));
+    }
+
+    #[test]
+    fn odd_count_tree_promotes_last_node() {
+        let leaves = leaves(3);
+        let tree = MerkleTree::new(leaves.clone());
+        assert_eq!(
+            tree.root(),
+            intermediate_hash(&intermediate_hash(&leaves[0], &leaves[1]), &leaves[2])
+        );
+        // the promoted leaf has no sibling at the level of the leaves
+        assert_eq!(
+            tree.proof(2),
+            vec![intermediate_hash(&leaves[0], &leaves[1])]
+        );
+    }
+
+    #[test]
+    fn verify_proof_of_every_leaf() {
+        for count in 1..=9 {
+            let leaves = leaves(count);
+            let tree = MerkleTree::new(leaves.clone());
+            let root = tree.root();
+            for (index, leaf) in leaves.iter().enumerate() {
+                let proof = tree.proof(index);
+                assert!(
+                    verify_proof(&root, *leaf, &proof),
+                    "leaf {} of {}",
+                    index,
+                    count
+                );
+                assert!(!verify_proof(
+                    &root,
+                    leaf_hash(&Pubkey::new_unique(), 1),
+                    &proof
+                ));
+            }
+        }
+    }
+
+    /// Root of fixed leaves hashed as the Jito merkle distributor does:
+    /// `sha256([0] || sha256(owner || amount_le))` leaves and `sha256([1] || min || max)` nodes.
+    #[test]
+    fn fixed_root() {
+        let tree = MerkleTree::new(leaves(3));
+        assert_eq!(
+            tree.root().to_string(),
+            "CpYQq3EPnkNvbX44MUGRpvV6KrZMoZyHKaV4TCcuS8Rw"
+        );
+        assert_eq!(
+            leaf_hash(&Pubkey::new_from_array([1; 32]), 100).to_string(),
+            "8atgFCmNy6eKZGcw7hwixUfs9LGwMDWE9yDhjo2gmJbX"
+        );
+    }
+}
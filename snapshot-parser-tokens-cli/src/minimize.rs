@@ -0,0 +1,98 @@
+use base64::engine::general_purpose::STANDARD as base64_engine;
+use base64::Engine;
+use serde::{Deserialize, Serialize};
+use snapshot_parser::utils::write_to_json_file;
+use solana_accounts_db::accounts_index::ScanConfig;
+use solana_program::pubkey::Pubkey;
+use solana_runtime::bank::Bank;
+use solana_sdk::account::{AccountSharedData, ReadableAccount};
+use std::collections::BTreeMap;
+use std::path::Path;
+use std::sync::Arc;
+use tracing::{info, warn};
+
+/// Account data in the format of `solana account --output json`,
+/// loadable by `solana-test-validator --account-dir`.
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DumpedAccountData {
+    pub lamports: u64,
+    /// `[<base64 data>, "base64"]`
+    pub data: (String, String),
+    pub owner: String,
+    pub executable: bool,
+    pub rent_epoch: u64,
+    pub space: u64,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct DumpedAccount {
+    pub pubkey: String,
+    pub account: DumpedAccountData,
+}
+
+impl DumpedAccount {
+    pub fn new(pubkey: &Pubkey, account: &AccountSharedData) -> Self {
+        Self {
+            pubkey: pubkey.to_string(),
+            account: DumpedAccountData {
+                lamports: account.lamports(),
+                data: (base64_engine.encode(account.data()), "base64".to_string()),
+                owner: account.owner().to_string(),
+                executable: account.executable(),
+                rent_epoch: account.rent_epoch(),
+                space: account.data().len() as u64,
+            },
+        }
+    }
+}
+
+/// Collects the listed accounts and all accounts owned by the listed programs from the bank.
+pub fn collect_accounts(
+    bank: &Arc<Bank>,
+    pubkeys: &[Pubkey],
+    programs: &[Pubkey],
+) -> anyhow::Result<BTreeMap<Pubkey, AccountSharedData>> {
+    let mut accounts = BTreeMap::new();
+    for pubkey in pubkeys {
+        match bank.get_account(pubkey) {
+            Some(account) => {
+                accounts.insert(*pubkey, account);
+            }
+            None => warn!("Account {} not found in the snapshot", pubkey),
+        }
+    }
+    for program in programs {
+        let program_accounts = bank.get_program_accounts(
+            program,
+            &ScanConfig {
+                collect_all_unsorted: true,
+                ..ScanConfig::default()
+            },
+        )?;
+        info!(
+            "Loaded {} accounts owned by program {}",
+            program_accounts.len(),
+            program
+        );
+        accounts.extend(program_accounts);
+    }
+    Ok(accounts)
+}
+
+/// Writes one `<pubkey>.json` file per account to the directory.
+pub fn write_account_dir(
+    accounts: &BTreeMap<Pubkey, AccountSharedData>,
+    output_dir: &Path,
+) -> anyhow::Result<()> {
+    std::fs::create_dir_all(output_dir)?;
+    for (pubkey, account) in accounts {
+        let path = output_dir.join(format!("{}.json", pubkey));
+        write_to_json_file(
+            &DumpedAccount::new(pubkey, account),
+            &path.to_string_lossy(),
+        )?;
+    }
+    info!("Written {} accounts to {:?}", accounts.len(), output_dir);
+    Ok(())
+}
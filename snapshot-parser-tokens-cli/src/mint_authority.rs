@@ -0,0 +1,106 @@
+// Tracking of the mint and freeze authorities of the filtered mints between the runs into the same output DB
+// (`--reprocess`), an authority change of a tracked token is a security-relevant event.
+use crate::schema::TOKEN_MINT_TABLE;
+use rusqlite::{params, Connection, OpenFlags, OptionalExtension};
+use snapshot_parser_types::decoded_accounts::{MintAuthorityChangeRow, MintRow};
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::Mutex;
+use tracing::warn;
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+struct MintAuthorities {
+    mint_authority: Option<String>,
+    freeze_authority: Option<String>,
+}
+
+/// Authorities of the mints written by the previous run and the changes found by this one.
+pub struct MintAuthorityTracker {
+    previous: HashMap<String, MintAuthorities>,
+    changes: Mutex<Vec<MintAuthorityChangeRow>>,
+}
+
+impl MintAuthorityTracker {
+    /// Reads the authorities of the mints in the `token_mint` table of the DB written by the previous run;
+    /// a DB without the table tracks nothing.
+    pub fn from_db(db_path: &Path) -> anyhow::Result<Self> {
+        let db = Connection::open_with_flags(db_path, OpenFlags::SQLITE_OPEN_READ_ONLY)?;
+        let mut previous = HashMap::new();
+        let has_table = db
+            .query_row(
+                "SELECT 1 FROM sqlite_master WHERE type = 'table' AND name = ?;",
+                params![TOKEN_MINT_TABLE],
+                |_| Ok(()),
+            )
+            .optional()?
+            .is_some();
+        if has_table {
+            let mut stmt = db.prepare(&format!(
+                "SELECT pubkey, mint_authority, freeze_authority FROM {};",
+                TOKEN_MINT_TABLE
+            ))?;
+            let rows = stmt.query_map([], |row| {
+                Ok((
+                    row.get::<_, String>(0)?,
+                    MintAuthorities {
+                        mint_authority: row.get(1)?,
+                        freeze_authority: row.get(2)?,
+                    },
+                ))
+            })?;
+            for row in rows {
+                let (mint, authorities) = row?;
+                previous.insert(mint, authorities);
+            }
+        }
+        Ok(Self {
+            previous,
+            changes: Mutex::new(Vec::new()),
+        })
+    }
+
+    pub fn tracked_mints(&self) -> usize {
+        self.previous.len()
+    }
+
+    /// Compares the authorities of the mint row with the previous run, returning the change to be recorded.
+    /// Mints not written by the previous run are new, not changed.
+    pub fn check(&self, row: &MintRow, slot: u64) -> Option<MintAuthorityChangeRow> {
+        let previous = self.previous.get(&row.pubkey)?;
+        if previous.mint_authority == row.mint_authority
+            && previous.freeze_authority == row.freeze_authority
+        {
+            return None;
+        }
+        let change = MintAuthorityChangeRow {
+            id: format!("{}:{}", row.pubkey, slot),
+            mint: row.pubkey.clone(),
+            slot,
+            previous_mint_authority: previous.mint_authority.clone(),
+            mint_authority: row.mint_authority.clone(),
+            previous_freeze_authority: previous.freeze_authority.clone(),
+            freeze_authority: row.freeze_authority.clone(),
+        };
+        warn!(
+            "Authority of mint {} changed: mint authority {:?} -> {:?}, freeze authority {:?} -> {:?}",
+            change.mint,
+            change.previous_mint_authority,
+            change.mint_authority,
+            change.previous_freeze_authority,
+            change.freeze_authority
+        );
+        self.changes
+            .lock()
+            .expect("mint authority changes lock poisoned")
+            .push(change.clone());
+        Some(change)
+    }
+
+    /// Changes found by this run, for the run summary.
+    pub fn changes(&self) -> Vec<MintAuthorityChangeRow> {
+        self.changes
+            .lock()
+            .expect("mint authority changes lock poisoned")
+            .clone()
+    }
+}
@@ -0,0 +1,298 @@
+// Output of the tables written by the processors: the format selected by the output options of the CLI,
+// the executor task writing the rows in that format and the channel the processors send the rows through.
+use crate::db_connection::SQLiteExecutor;
+use crate::db_message::DbMessage;
+use crate::db_spool::{spawn_db_spool, DbSpool};
+use crate::jsonl_writer::JsonlExecutor;
+use crate::parquet_writer::ParquetExecutor;
+use crate::postgres_writer::PostgresExecutor;
+use crate::progress_bar::ProgressCounter;
+use crate::schema::finalize_output;
+use crate::snapshot_info::write_partial_snapshot_info;
+use snapshot_parser::runner::Cancellation;
+use snapshot_parser_types::decoded_accounts::SnapshotInfoRow;
+use std::future::Future;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use tokio::sync::mpsc::{self, Receiver, Sender};
+use tokio::sync::oneshot;
+use tokio::task::JoinHandle;
+use tracing::{debug, info, info_span, warn, Instrument, Span};
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq, clap::ValueEnum)]
+pub enum OutputFormat {
+    Sqlite,
+    Parquet,
+    /// selected by `--output-postgres-url`
+    #[value(skip)]
+    Postgres,
+    /// selected by `--output-jsonl-dir`
+    #[value(skip)]
+    Jsonl,
+}
+
+/// Output options of the run, the path of the selected format is required by [`Output::validate`].
+#[derive(Clone, Debug)]
+pub struct Output {
+    pub format: OutputFormat,
+    pub sqlite: Option<String>,
+    pub parquet_dir: Option<PathBuf>,
+    pub postgres_url: Option<String>,
+    pub jsonl_dir: Option<PathBuf>,
+}
+
+/// Options of the run supported by some of the output formats only, see [`Output::validate`].
+pub struct OutputFeatures {
+    pub checkpoints: bool,
+    pub reprocess: bool,
+    pub resume: bool,
+    pub db_spool: bool,
+    pub merkle: bool,
+}
+
+impl Output {
+    /// The PostgreSQL URL and the JSON Lines directory select their formats over `--output-format`.
+    pub fn new(
+        format: OutputFormat,
+        sqlite: Option<String>,
+        parquet_dir: Option<PathBuf>,
+        postgres_url: Option<String>,
+        jsonl_dir: Option<PathBuf>,
+    ) -> Self {
+        let format = if postgres_url.is_some() {
+            OutputFormat::Postgres
+        } else if jsonl_dir.is_some() {
+            OutputFormat::Jsonl
+        } else {
+            format
+        };
+        Self {
+            format,
+            sqlite,
+            parquet_dir,
+            postgres_url,
+            jsonl_dir,
+        }
+    }
+
+    pub fn validate(&self, features: &OutputFeatures) -> anyhow::Result<()> {
+        match self.format {
+            OutputFormat::Sqlite if self.sqlite.is_none() => {
+                anyhow::bail!("--output-sqlite is required for the sqlite output format")
+            }
+            OutputFormat::Parquet if self.parquet_dir.is_none() => {
+                anyhow::bail!("--output-parquet-dir is required for the parquet output format")
+            }
+            OutputFormat::Parquet | OutputFormat::Jsonl
+                if features.checkpoints || features.reprocess =>
+            {
+                anyhow::bail!(
+                    "--checkpoint-interval and --reprocess are supported by the sqlite output format only"
+                )
+            }
+            OutputFormat::Postgres if features.checkpoints => {
+                anyhow::bail!("--checkpoint-interval is supported by the sqlite output format only")
+            }
+            OutputFormat::Parquet | OutputFormat::Postgres | OutputFormat::Jsonl
+                if features.resume =>
+            {
+                anyhow::bail!("--resume is supported by the sqlite output format only")
+            }
+            OutputFormat::Parquet | OutputFormat::Postgres | OutputFormat::Jsonl
+                if features.db_spool =>
+            {
+                anyhow::bail!("--db-spool is supported by the sqlite output format only")
+            }
+            OutputFormat::Parquet | OutputFormat::Postgres | OutputFormat::Jsonl
+                if features.merkle =>
+            {
+                anyhow::bail!("--output-merkle is supported by the sqlite output format only")
+            }
+            _ => {}
+        }
+        // the spool of a reprocess run holds its upserts only, its replay would replace the rows of the previous runs
+        if features.db_spool && features.reprocess {
+            anyhow::bail!("--db-spool is not supported with --reprocess");
+        }
+        Ok(())
+    }
+
+    /// Path of the output SQLite DB, none with the other formats.
+    pub fn sqlite_path(&self) -> Option<&Path> {
+        match self.format {
+            OutputFormat::Sqlite => self.sqlite.as_deref().map(Path::new),
+            _ => None,
+        }
+    }
+}
+
+/// Options of the executors, see [`SQLiteExecutor::new`].
+#[derive(Clone, Copy, Debug)]
+pub struct ExecutorOptions {
+    pub sqlite_cache_size: Option<i64>,
+    pub sqlite_mmap_size: Option<u16>,
+    pub sqlite_tx_bulk: Option<u16>,
+    pub checkpoints: bool,
+    pub reprocess: bool,
+    pub resume: bool,
+}
+
+/// Channel of the processors to the output executor; the executor is started by [`OutputWriter::start`]
+/// and commits the output on [`OutputWriter::finalize`] or [`OutputWriter::interrupt`].
+pub struct OutputWriter {
+    pub sender: Sender<DbMessage>,
+    receiver: Option<Receiver<DbMessage>>,
+    db_handle: Option<JoinHandle<anyhow::Result<()>>>,
+    spool_handle: Option<JoinHandle<anyhow::Result<()>>>,
+}
+
+impl OutputWriter {
+    /// With a spool, the writes are spooled before they reach the executor.
+    pub fn new(channel_size: usize, db_spool: Option<DbSpool>) -> Self {
+        info!("Creating communication channels size {}...", channel_size);
+        let (sender, receiver) = mpsc::channel(channel_size);
+        let (sender, spool_handle) = match db_spool {
+            Some(db_spool) => {
+                let (sender, handle) = spawn_db_spool(db_spool, sender);
+                (sender, Some(handle))
+            }
+            None => (sender, None),
+        };
+        Self {
+            sender,
+            receiver: Some(receiver),
+            db_handle: None,
+            spool_handle,
+        }
+    }
+
+    /// Spawns the executor of the output format and waits until it receives.
+    pub async fn start(
+        &mut self,
+        output: &Output,
+        options: ExecutorOptions,
+        db_progress_counter: Arc<ProgressCounter>,
+        parent_span: &Span,
+    ) -> anyhow::Result<()> {
+        let receiver = self
+            .receiver
+            .take()
+            .ok_or_else(|| anyhow::anyhow!("Output executor is already started"))?;
+        let (output, format) = (output.clone(), output.format);
+        let (consumer_ready_tx, consumer_ready_rx) = oneshot::channel();
+        self.db_handle = Some(tokio::spawn(
+            async move {
+                info!("Starting {:?} executor task...", format);
+                consumer_ready_tx
+                    .send(())
+                    .expect("Failed to send ready signal");
+                match format {
+                    OutputFormat::Sqlite => {
+                        let db = SQLiteExecutor::new(
+                            PathBuf::from(output.sqlite.expect("output_sqlite is required")),
+                            options.sqlite_cache_size,
+                            options.sqlite_mmap_size,
+                            options.sqlite_tx_bulk,
+                            options.checkpoints,
+                            options.reprocess,
+                            options.resume,
+                            db_progress_counter,
+                            receiver,
+                        )?;
+                        db.start().await;
+                    }
+                    OutputFormat::Parquet => {
+                        let writer = ParquetExecutor::new(
+                            output.parquet_dir.expect("output_parquet_dir is required"),
+                            db_progress_counter,
+                            receiver,
+                        )?;
+                        writer.start().await;
+                    }
+                    OutputFormat::Jsonl => {
+                        let writer = JsonlExecutor::new(
+                            output.jsonl_dir.expect("output_jsonl_dir is required"),
+                            db_progress_counter,
+                            receiver,
+                        )?;
+                        writer.start().await;
+                    }
+                    OutputFormat::Postgres => {
+                        let writer = PostgresExecutor::new(
+                            &output
+                                .postgres_url
+                                .expect("output_postgres_url is required"),
+                            options.reprocess,
+                            db_progress_counter,
+                            receiver,
+                        )
+                        .await?;
+                        writer.start().await;
+                    }
+                }
+                debug!("{:?} executor task finished", format);
+                Ok(())
+            }
+            .instrument(info_span!(parent: parent_span, "output", format = ?format)),
+        ));
+        consumer_ready_rx
+            .await
+            .expect("Failed to receive SQLite ready signal");
+        Ok(())
+    }
+
+    /// Runs the processors until they finish or the run is cancelled.
+    /// A cancelled run keeps the rows written so far, see [`OutputWriter::interrupt`], and returns no writer.
+    pub async fn run_cancellable(
+        self,
+        processors: impl Future<Output = anyhow::Result<()>>,
+        cancellation: &Cancellation,
+        snapshot_info: &SnapshotInfoRow,
+    ) -> anyhow::Result<Option<Self>> {
+        tokio::select! {
+            result = processors => result.map(|_| Some(self)),
+            _ = cancellation.cancelled() => {
+                warn!("Interrupted, stopping the processors and keeping the rows written so far");
+                self.interrupt(snapshot_info).await?;
+                Ok(None)
+            }
+        }
+    }
+
+    /// Marks the snapshot info partial and has the executor commit the rows written so far.
+    pub async fn interrupt(self, snapshot_info: &SnapshotInfoRow) -> anyhow::Result<()> {
+        write_partial_snapshot_info(&self.sender, snapshot_info).await?;
+        let (response_tx, response_rx) = oneshot::channel();
+        self.sender
+            .send(DbMessage::Interrupt {
+                response: response_tx,
+            })
+            .await?;
+        response_rx.await??;
+        self.close().await
+    }
+
+    /// The executor indexes and optimizes the output, then commits and finalizes it on the shutdown.
+    pub async fn finalize(self) -> anyhow::Result<()> {
+        finalize_output(&self.sender).await?;
+        let (response_tx, response_rx) = oneshot::channel();
+        self.sender
+            .send(DbMessage::Shutdown {
+                response: response_tx,
+            })
+            .await?;
+        let _ = response_rx.await?;
+        self.close().await
+    }
+
+    async fn close(self) -> anyhow::Result<()> {
+        drop(self.sender);
+        if let Some(db_handle) = self.db_handle {
+            db_handle.await??;
+        }
+        if let Some(spool_handle) = self.spool_handle {
+            spool_handle.await??;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,275 @@
+use crate::db_message::{DbMessage, OwnedSqlValue, TableColumns};
+use crate::progress_bar::{LocalCounter, ProgressCounter};
+use arrow_array::builder::{BooleanBuilder, Int64Builder, StringBuilder};
+use arrow_array::{ArrayRef, RecordBatch};
+use arrow_schema::{DataType, Field, Schema, SchemaRef};
+use parquet::arrow::ArrowWriter;
+use parquet::basic::Compression;
+use parquet::file::properties::WriterProperties;
+use snapshot_parser::temp_file::TempFileGuard;
+use std::collections::{HashMap, HashSet};
+use std::fs::File;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use tokio::sync::mpsc::Receiver;
+use tracing::{debug, error, info, warn};
+
+/// Number of rows buffered per table before they are written out as a record batch.
+const DEFAULT_BATCH_SIZE: usize = 65_536;
+
+enum ColumnBuilder {
+    Text(StringBuilder),
+    Integer(Int64Builder),
+    Boolean(BooleanBuilder),
+}
+
+impl ColumnBuilder {
+    /// Maps the SQL type definition of the column to the Arrow type.
+    fn for_sql_type(sql_type: &str) -> (DataType, Self) {
+        let sql_type = sql_type.to_uppercase();
+        if sql_type.starts_with("TEXT") {
+            (DataType::Utf8, ColumnBuilder::Text(StringBuilder::new()))
+        } else if sql_type.starts_with("BOOL") {
+            (
+                DataType::Boolean,
+                ColumnBuilder::Boolean(BooleanBuilder::new()),
+            )
+        } else {
+            (DataType::Int64, ColumnBuilder::Integer(Int64Builder::new()))
+        }
+    }
+
+    fn append(&mut self, value: &OwnedSqlValue) -> anyhow::Result<()> {
+        match (self, value) {
+            (ColumnBuilder::Text(builder), OwnedSqlValue::Text(value)) => {
+                builder.append_option(value.as_deref())
+            }
+            (ColumnBuilder::Integer(builder), value) => builder.append_option(value.as_i64()?),
+            (ColumnBuilder::Boolean(builder), OwnedSqlValue::Boolean(value)) => {
+                builder.append_option(*value)
+            }
+            (ColumnBuilder::Boolean(builder), value) => {
+                builder.append_option(value.as_i64()?.map(|value| value != 0))
+            }
+            (ColumnBuilder::Text(_), _) => {
+                anyhow::bail!("Non-text value cannot be stored in a text column")
+            }
+        }
+        Ok(())
+    }
+
+    fn finish(&mut self) -> ArrayRef {
+        match self {
+            ColumnBuilder::Text(builder) => Arc::new(builder.finish()),
+            ColumnBuilder::Integer(builder) => Arc::new(builder.finish()),
+            ColumnBuilder::Boolean(builder) => Arc::new(builder.finish()),
+        }
+    }
+}
+
+struct TableWriter {
+    schema: SchemaRef,
+    builders: Vec<ColumnBuilder>,
+    buffered_rows: usize,
+    writer: ArrowWriter<File>,
+    temp_guard: TempFileGuard,
+    path: PathBuf,
+    /// primary keys of the written rows when the first column is the primary key
+    written_keys: Option<HashSet<String>>,
+}
+
+impl TableWriter {
+    fn new(output_dir: &Path, table: &str, columns: TableColumns) -> anyhow::Result<Self> {
+        let (fields, builders): (Vec<Field>, Vec<ColumnBuilder>) = columns
+            .iter()
+            .map(|(name, sql_type)| {
+                let (data_type, builder) = ColumnBuilder::for_sql_type(sql_type);
+                let nullable = !sql_type.to_uppercase().contains("NOT NULL");
+                (Field::new(*name, data_type, nullable), builder)
+            })
+            .unzip();
+        let schema = Arc::new(Schema::new(fields));
+
+        let path = output_dir.join(format!("{}.parquet", table));
+        let temp_path = output_dir.join(format!("_{}.parquet.tmp", table));
+        let _ = std::fs::remove_file(&temp_path);
+        let temp_guard = TempFileGuard::new(temp_path.clone());
+        let properties = WriterProperties::builder()
+            .set_compression(Compression::SNAPPY)
+            .build();
+        let writer =
+            ArrowWriter::try_new(File::create(&temp_path)?, schema.clone(), Some(properties))?;
+
+        let has_primary_key = columns
+            .first()
+            .is_some_and(|(_, sql_type)| sql_type.to_uppercase().contains("PRIMARY KEY"));
+        Ok(Self {
+            schema,
+            builders,
+            buffered_rows: 0,
+            writer,
+            temp_guard,
+            path,
+            written_keys: has_primary_key.then(HashSet::new),
+        })
+    }
+
+    /// Appends the row unless a row with the same primary key was already written.
+    /// Rows of the same account are equal within a snapshot, so the first one is kept.
+    fn append(&mut self, values: &[OwnedSqlValue]) -> anyhow::Result<usize> {
+        if values.len() != self.builders.len() {
+            anyhow::bail!(
+                "Expected {} values, got {}",
+                self.builders.len(),
+                values.len()
+            );
+        }
+        if let (Some(written_keys), Some(OwnedSqlValue::Text(Some(key)))) =
+            (self.written_keys.as_mut(), values.first())
+        {
+            if !written_keys.insert(key.clone()) {
+                return Ok(0);
+            }
+        }
+        for (builder, value) in self.builders.iter_mut().zip(values) {
+            builder.append(value)?;
+        }
+        self.buffered_rows += 1;
+        if self.buffered_rows >= DEFAULT_BATCH_SIZE {
+            self.flush()?;
+        }
+        Ok(1)
+    }
+
+    fn flush(&mut self) -> anyhow::Result<()> {
+        if self.buffered_rows == 0 {
+            return Ok(());
+        }
+        let columns = self
+            .builders
+            .iter_mut()
+            .map(ColumnBuilder::finish)
+            .collect();
+        let batch = RecordBatch::try_new(self.schema.clone(), columns)?;
+        self.writer.write(&batch)?;
+        self.buffered_rows = 0;
+        Ok(())
+    }
+
+    fn finalize(mut self) -> anyhow::Result<()> {
+        self.flush()?;
+        self.writer.close()?;
+        self.temp_guard.promote(&self.path)?;
+        info!("Parquet file promoted to: {:?}", &self.path);
+        Ok(())
+    }
+}
+
+/// Writes every table into its own `<table>.parquet` file in the output directory.
+/// The files are written as temporary files that get promoted on shutdown.
+pub struct ParquetExecutor {
+    output_dir: PathBuf,
+    tables: HashMap<&'static str, TableWriter>,
+    db_execute_counter: LocalCounter,
+    receiver: Receiver<DbMessage>,
+}
+
+impl ParquetExecutor {
+    pub fn new(
+        output_dir: PathBuf,
+        db_execute_counter: Arc<ProgressCounter>,
+        receiver: Receiver<DbMessage>,
+    ) -> anyhow::Result<Self> {
+        std::fs::create_dir_all(&output_dir)?;
+        Ok(Self {
+            output_dir,
+            tables: HashMap::new(),
+            db_execute_counter: db_execute_counter.local(),
+            receiver,
+        })
+    }
+
+    fn create_table(
+        &mut self,
+        table: &'static str,
+        columns: TableColumns,
+    ) -> anyhow::Result<usize> {
+        if !self.tables.contains_key(table) {
+            debug!("Creating Parquet file for table {}", table);
+            self.tables
+                .insert(table, TableWriter::new(&self.output_dir, table, columns)?);
+        }
+        Ok(0)
+    }
+
+    fn insert(&mut self, table: &'static str, values: &[OwnedSqlValue]) -> anyhow::Result<usize> {
+        let result = self
+            .tables
+            .get_mut(table)
+            .ok_or_else(|| anyhow::anyhow!("Table {} was not created", table))?
+            .append(values);
+        self.db_execute_counter.inc();
+        result
+    }
+
+    fn insert_rows(
+        &mut self,
+        table: &'static str,
+        rows: &[Vec<OwnedSqlValue>],
+    ) -> anyhow::Result<usize> {
+        let mut inserted = 0;
+        for values in rows {
+            inserted += self.insert(table, values)?;
+        }
+        Ok(inserted)
+    }
+
+    pub async fn start(mut self) {
+        info!("ParquetExecutor receiver started to listen for row messages");
+        while let Some(msg) = self.receiver.recv().await {
+            match msg {
+                DbMessage::CreateTable {
+                    table,
+                    columns,
+                    response,
+                } => {
+                    let _ = response.send(self.create_table(table, columns));
+                }
+                DbMessage::Insert { row, response } => {
+                    let table = row.table();
+                    let _ = response.send(self.insert(table, &row.into_values()));
+                }
+                DbMessage::InsertBatch { rows, response } => {
+                    let table = rows.table();
+                    let _ = response.send(self.insert_rows(table, &rows.into_values()));
+                }
+                DbMessage::CommitCheckpoint { response, .. }
+                | DbMessage::RecordCompletion { response, .. } => {
+                    error!("Checkpoints are not supported by the Parquet output");
+                    let _ = response.send(Err(anyhow::anyhow!(
+                        "Checkpoints are not supported by the Parquet output"
+                    )));
+                }
+                DbMessage::Finalize { response } => {
+                    let _ = response.send(Ok(()));
+                }
+                DbMessage::Shutdown { response } => {
+                    let _ = response.send(self.finalize());
+                }
+                DbMessage::Interrupt { response } => {
+                    warn!("Interrupted, the partial Parquet output is not kept");
+                    let _ = response.send(Ok(()));
+                    break;
+                }
+            }
+        }
+    }
+
+    fn finalize(&mut self) -> anyhow::Result<()> {
+        for (_, table_writer) in self.tables.drain() {
+            table_writer.finalize()?;
+        }
+        info!("Parquet files finalized in {:?}", &self.output_dir);
+        Ok(())
+    }
+}
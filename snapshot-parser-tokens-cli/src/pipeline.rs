@@ -0,0 +1,508 @@
+// Library entry point running the processors of the tokens parser without the CLI.
+// The caller loads the bank, creates the output tables' consumer and decides which processors run;
+// the CLI is one such caller, see `src/bin/cli.rs`.
+use crate::checkpoint::ProcessorCompletion;
+use crate::db_message::DbMessage;
+use crate::filters::Filters;
+use crate::mint_authority::MintAuthorityTracker;
+use crate::processors::{
+    spawn_processor_task, Processor, ProcessorAccountOwners, ProcessorAllowlist, ProcessorConfig,
+    ProcessorConfigured, ProcessorMint, ProcessorMsol, ProcessorNativeStake,
+    ProcessorProtocolBalances, ProcessorStakePool, ProcessorSupply, ProcessorToken,
+    ProcessorVeMnde, ProcessorVoteAccounts, ProtocolAccountsConfig, ScanCache, SpillConfig,
+    SupplyConfig, UnknownMintPolicy, TOKEN_PROGRAMS,
+};
+#[cfg(feature = "metaplex")]
+use crate::processors::{MetadataSanitization, ProcessorTokenMetadata, SpoofDetector};
+use crate::progress_bar::define_counter;
+use crate::row_limit::spawn_row_limiter;
+use crate::sampling::Sampling;
+use crate::schema::{
+    META_ACCOUNT_TABLE, MSOL_STATE_TABLE, NATIVE_STAKE_ACCOUNT_TABLE, PROTOCOL_BALANCES_TABLE,
+    STAKE_ACCOUNT_TABLE, STAKE_POOL_TABLE, SUPPLY_STATS_TABLE, TOKEN_ACCOUNT_TABLE,
+    VE_MNDE_ACCOUNT_TABLE, VOTE_ACCOUNT_TABLE,
+};
+#[cfg(feature = "metaplex")]
+use crate::schema::{SYMBOL_COLLISION_TABLE, TOKEN_METADATA_ACCOUNT_TABLE};
+use crate::stats::Stats;
+use indicatif::MultiProgress;
+#[cfg(feature = "metaplex")]
+use snapshot_parser::program_registry::{builtin_address, TOKEN_METADATA_PROGRAM};
+use solana_program::pubkey::Pubkey;
+use solana_runtime::bank::Bank;
+use std::collections::{HashMap, HashSet};
+use std::path::PathBuf;
+use std::sync::Arc;
+use tokio::sync::mpsc::Sender;
+use tracing::error;
+
+/// Built-in processors, see [`crate::processors::processor_registry`] for the tables they write.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, clap::ValueEnum)]
+pub enum ProcessorKind {
+    #[value(name = "account_owners")]
+    AccountOwners,
+    Token,
+    Mint,
+    #[value(name = "vemnde")]
+    VeMnde,
+    #[value(name = "native_stake")]
+    NativeStake,
+    #[value(name = "stake_pool")]
+    StakePool,
+    Msol,
+    #[value(name = "vote_accounts")]
+    VoteAccounts,
+    #[cfg(feature = "metaplex")]
+    #[value(name = "token_metadata")]
+    TokenMetadata,
+}
+
+impl ProcessorKind {
+    pub fn all() -> HashSet<Self> {
+        HashSet::from([
+            ProcessorKind::AccountOwners,
+            ProcessorKind::Token,
+            ProcessorKind::Mint,
+            ProcessorKind::VeMnde,
+            ProcessorKind::NativeStake,
+            ProcessorKind::StakePool,
+            ProcessorKind::Msol,
+            ProcessorKind::VoteAccounts,
+            #[cfg(feature = "metaplex")]
+            ProcessorKind::TokenMetadata,
+        ])
+    }
+}
+
+/// Processors run by [`run_processors`].
+#[derive(Debug, Clone)]
+pub struct ProcessorSelection {
+    pub processors: HashSet<ProcessorKind>,
+    /// processors dumping the accounts of the programs declared by a processors config
+    pub configured: Vec<ProcessorConfig>,
+    /// balances of the protocol-owned accounts of a protocol accounts config written into protocol_balances
+    pub protocol_accounts: Option<ProtocolAccountsConfig>,
+    /// circulating supply of the mints of a supply config written into supply_stats
+    pub supply: Option<SupplyConfig>,
+    /// only the accounts of the `account_pubkeys` filter are fetched by the allowlist processor,
+    /// the `processors`, `configured`, `protocol_accounts` and `supply` are ignored then
+    pub allowlist_only: bool,
+}
+
+impl ProcessorSelection {
+    /// All built-in processors.
+    pub fn all() -> Self {
+        Self::of(ProcessorKind::all())
+    }
+
+    pub fn of(processors: HashSet<ProcessorKind>) -> Self {
+        Self {
+            processors,
+            configured: vec![],
+            protocol_accounts: None,
+            supply: None,
+            allowlist_only: false,
+        }
+    }
+
+    pub fn allowlist() -> Self {
+        Self {
+            processors: HashSet::new(),
+            configured: vec![],
+            protocol_accounts: None,
+            supply: None,
+            allowlist_only: true,
+        }
+    }
+
+    fn contains(&self, kind: ProcessorKind) -> bool {
+        self.processors.contains(&kind)
+    }
+}
+
+/// Options of the processors, [`ProcessorOptions::new`] sets the defaults of the CLI.
+#[derive(Clone)]
+pub struct ProcessorOptions {
+    /// unix timestamp the vemnde voting power is computed at
+    pub current_timestamp: i64,
+    /// threads scanning the accounts of one program, see [`crate::processors::stream_program_accounts`]
+    pub scan_threads: usize,
+    /// accounts of the programs scanned by several processors are scanned once and cached, see [`ScanCache`]
+    pub scan_cache: Option<SpillConfig>,
+    /// token accounts committed and checkpointed at once, see [`crate::checkpoint`]
+    pub checkpoint_interval: Option<usize>,
+    /// accounts buffered by the checkpointed token scan over the memory cap are spilled to disk
+    pub checkpoint_buffer: Option<SpillConfig>,
+    /// rows written per table at most, the scans stop early; to sample the output quickly
+    pub limit_rows_per_table: Option<u64>,
+    /// the token, token metadata and account owners processors write the sampled accounts only
+    pub sampling: Option<Sampling>,
+    pub require_all_mints: bool,
+    /// authorities of the mints written by the previous run into the same DB, their changes are recorded
+    pub mint_authority_tracker: Option<Arc<MintAuthorityTracker>>,
+    /// all stake accounts are written into the stake_accounts table, not only the native stake ones
+    pub all_stake_accounts: bool,
+    /// stake meta collection of the bank (e.g., written by the validator CLI) read instead of scanning the stake accounts
+    pub stake_meta_collection: Option<PathBuf>,
+    /// mints of the allowlisted token accounts that are not allowlisted are fetched or listed as unknown
+    pub unknown_mint_policy: UnknownMintPolicy,
+    /// decimals of the unknown mints of the allowlist processor
+    pub mint_decimals: HashMap<Pubkey, u8>,
+    #[cfg(feature = "metaplex")]
+    pub metadata_sanitization: MetadataSanitization,
+    #[cfg(feature = "metaplex")]
+    pub metadata_spoof_detection: bool,
+    /// completed processors are recorded and the ones completed by a previous run are skipped
+    pub completion: Option<Arc<ProcessorCompletion>>,
+}
+
+impl ProcessorOptions {
+    pub fn new(current_timestamp: i64) -> Self {
+        Self {
+            current_timestamp,
+            scan_threads: 1,
+            scan_cache: None,
+            checkpoint_interval: None,
+            checkpoint_buffer: None,
+            limit_rows_per_table: None,
+            sampling: None,
+            require_all_mints: false,
+            mint_authority_tracker: None,
+            all_stake_accounts: false,
+            stake_meta_collection: None,
+            unknown_mint_policy: UnknownMintPolicy::default(),
+            mint_decimals: HashMap::new(),
+            #[cfg(feature = "metaplex")]
+            metadata_sanitization: MetadataSanitization::default(),
+            #[cfg(feature = "metaplex")]
+            metadata_spoof_detection: false,
+            completion: None,
+        }
+    }
+}
+
+/// Runs the selected processors against the bank, the rows are sent to `db_sender`.
+///
+/// The sink is any consumer of the [`DbMessage`] channel, e.g., [`crate::db_connection::SQLiteExecutor`],
+/// which must be already receiving as the processors create their tables when constructed.
+/// All processors run to the end, the run fails afterwards when any of them failed.
+/// The progress counters are added to the `multi_progress` (use a hidden one to not draw them) and to the `stats`.
+pub async fn run_processors(
+    bank: Arc<Bank>,
+    filters: &Filters,
+    db_sender: &Sender<DbMessage>,
+    selection: ProcessorSelection,
+    options: ProcessorOptions,
+    multi_progress: &MultiProgress,
+    stats: &Stats,
+) -> anyhow::Result<()> {
+    let completion = options.completion;
+    let limited_sender;
+    let db_sender = match options.limit_rows_per_table {
+        Some(limit) => {
+            limited_sender = spawn_row_limiter(limit, db_sender.clone()).0;
+            &limited_sender
+        }
+        None => db_sender,
+    };
+    let mut handles = Vec::new();
+    if selection.allowlist_only {
+        if filters.account_pubkeys.is_empty() {
+            anyhow::bail!("Filter account_pubkeys must not be empty for the allowlist processor");
+        }
+        let allowlist_processor = ProcessorAllowlist::new(
+            bank.clone(),
+            db_sender.clone(),
+            filters,
+            define_counter(META_ACCOUNT_TABLE, multi_progress, stats).await,
+            define_counter(TOKEN_ACCOUNT_TABLE, multi_progress, stats).await,
+            options.unknown_mint_policy,
+            options.mint_decimals.clone(),
+        )
+        .await?;
+        #[cfg(feature = "metaplex")]
+        let allowlist_processor = allowlist_processor
+            .with_token_metadata(
+                define_counter(TOKEN_METADATA_ACCOUNT_TABLE, multi_progress, stats).await,
+                options.metadata_sanitization,
+                options
+                    .metadata_spoof_detection
+                    .then(|| Arc::new(SpoofDetector::load(&bank, &filters.account_mints))),
+            )
+            .await?;
+        handles.push(spawn_processor_task(allowlist_processor, completion.clone()).await?);
+        return await_processors(handles).await;
+    }
+
+    let mut scanned_programs = Vec::new();
+    if selection.contains(ProcessorKind::AccountOwners) {
+        scanned_programs.extend(filters.account_owners.iter().copied());
+    }
+    if selection.contains(ProcessorKind::Token) {
+        scanned_programs.extend(TOKEN_PROGRAMS);
+    }
+    if selection
+        .protocol_accounts
+        .as_ref()
+        .is_some_and(|config| !config.mints.is_empty())
+    {
+        scanned_programs.extend(TOKEN_PROGRAMS);
+    }
+    if selection.supply.is_some() {
+        scanned_programs.extend(TOKEN_PROGRAMS);
+    }
+    #[cfg(feature = "metaplex")]
+    if selection.contains(ProcessorKind::TokenMetadata) {
+        scanned_programs.push(builtin_address(TOKEN_METADATA_PROGRAM));
+    }
+    let scan_cache = Arc::new(ScanCache::new(
+        bank.clone(),
+        options.scan_threads,
+        options.scan_cache.clone(),
+        scanned_programs,
+        options
+            .limit_rows_per_table
+            .map(|limit| usize::try_from(limit).unwrap_or(usize::MAX)),
+        options.sampling.clone(),
+    ));
+
+    // the account owners and the token processors share the account table counter
+    let account_owners_counter = define_counter(META_ACCOUNT_TABLE, multi_progress, stats).await;
+    let token_counter = define_counter(TOKEN_ACCOUNT_TABLE, multi_progress, stats).await;
+    if selection.contains(ProcessorKind::AccountOwners) {
+        let account_owners_processor = ProcessorAccountOwners::new(
+            bank.clone(),
+            db_sender.clone(),
+            filters,
+            account_owners_counter.clone(),
+            scan_cache.clone(),
+        )
+        .await?;
+        stats
+            .add_callbacks(&account_owners_processor.per_owner_counters())
+            .await;
+        handles.push(spawn_processor_task(account_owners_processor, completion.clone()).await?);
+    }
+
+    if selection.contains(ProcessorKind::Token) {
+        handles.push(
+            spawn_processor_task(
+                ProcessorToken::new(
+                    bank.clone(),
+                    db_sender.clone(),
+                    filters,
+                    account_owners_counter,
+                    token_counter.clone(),
+                    options.checkpoint_interval,
+                    options.checkpoint_buffer.clone(),
+                    completion
+                        .as_ref()
+                        .and_then(|completion| completion.checkpoint(ProcessorToken::name()))
+                        .cloned(),
+                    scan_cache.clone(),
+                )
+                .await?,
+                completion.clone(),
+            )
+            .await?,
+        );
+    }
+
+    if selection.contains(ProcessorKind::Mint) {
+        let mint_processor = ProcessorMint::new(
+            bank.clone(),
+            db_sender.clone(),
+            filters,
+            token_counter,
+            options.require_all_mints,
+            options.mint_authority_tracker.clone(),
+        )
+        .await?;
+        stats
+            .add_callback(mint_processor.missing_mints_counter())
+            .await;
+        handles.push(spawn_processor_task(mint_processor, completion.clone()).await?);
+    }
+
+    if selection.contains(ProcessorKind::VeMnde) {
+        handles.push(
+            spawn_processor_task(
+                ProcessorVeMnde::new(
+                    bank.clone(),
+                    db_sender.clone(),
+                    filters,
+                    define_counter(VE_MNDE_ACCOUNT_TABLE, multi_progress, stats).await,
+                    options.current_timestamp,
+                )
+                .await?,
+                completion.clone(),
+            )
+            .await?,
+        );
+    }
+
+    if selection.contains(ProcessorKind::NativeStake) {
+        let all_stake_accounts_counter = if options.all_stake_accounts {
+            Some(define_counter(STAKE_ACCOUNT_TABLE, multi_progress, stats).await)
+        } else {
+            None
+        };
+        handles.push(
+            spawn_processor_task(
+                ProcessorNativeStake::new(
+                    bank.clone(),
+                    db_sender.clone(),
+                    filters.native_stake_authority,
+                    define_counter(NATIVE_STAKE_ACCOUNT_TABLE, multi_progress, stats).await,
+                    all_stake_accounts_counter,
+                    options.stake_meta_collection.clone(),
+                )
+                .await?,
+                completion.clone(),
+            )
+            .await?,
+        );
+    }
+
+    if selection.contains(ProcessorKind::StakePool) {
+        handles.push(
+            spawn_processor_task(
+                ProcessorStakePool::new(
+                    bank.clone(),
+                    db_sender.clone(),
+                    define_counter(STAKE_POOL_TABLE, multi_progress, stats).await,
+                )
+                .await?,
+                completion.clone(),
+            )
+            .await?,
+        );
+    }
+
+    if selection.contains(ProcessorKind::Msol) {
+        handles.push(
+            spawn_processor_task(
+                ProcessorMsol::new(
+                    bank.clone(),
+                    db_sender.clone(),
+                    define_counter(MSOL_STATE_TABLE, multi_progress, stats).await,
+                )
+                .await?,
+                completion.clone(),
+            )
+            .await?,
+        );
+    }
+
+    if selection.contains(ProcessorKind::VoteAccounts) {
+        handles.push(
+            spawn_processor_task(
+                ProcessorVoteAccounts::new(
+                    bank.clone(),
+                    db_sender.clone(),
+                    define_counter(VOTE_ACCOUNT_TABLE, multi_progress, stats).await,
+                )
+                .await?,
+                completion.clone(),
+            )
+            .await?,
+        );
+    }
+
+    #[cfg(feature = "metaplex")]
+    if selection.contains(ProcessorKind::TokenMetadata) {
+        let spoof_detector = options
+            .metadata_spoof_detection
+            .then(|| Arc::new(SpoofDetector::load(&bank, &filters.account_mints)));
+        handles.push(
+            spawn_processor_task(
+                ProcessorTokenMetadata::new(
+                    bank.clone(),
+                    db_sender.clone(),
+                    define_counter(TOKEN_METADATA_ACCOUNT_TABLE, multi_progress, stats).await,
+                    options.metadata_sanitization,
+                    spoof_detector,
+                    &filters.account_mints,
+                    define_counter(SYMBOL_COLLISION_TABLE, multi_progress, stats).await,
+                    scan_cache.clone(),
+                )
+                .await?,
+                completion.clone(),
+            )
+            .await?,
+        );
+    }
+
+    if let Some(protocol_accounts) = selection.protocol_accounts {
+        handles.push(
+            spawn_processor_task(
+                ProcessorProtocolBalances::new(
+                    bank.clone(),
+                    db_sender.clone(),
+                    protocol_accounts,
+                    define_counter(PROTOCOL_BALANCES_TABLE, multi_progress, stats).await,
+                    scan_cache.clone(),
+                )
+                .await?,
+                completion.clone(),
+            )
+            .await?,
+        );
+    }
+
+    if let Some(supply) = selection.supply {
+        handles.push(
+            spawn_processor_task(
+                ProcessorSupply::new(
+                    bank.clone(),
+                    db_sender.clone(),
+                    supply,
+                    define_counter(SUPPLY_STATS_TABLE, multi_progress, stats).await,
+                    scan_cache.clone(),
+                )
+                .await?,
+                completion.clone(),
+            )
+            .await?,
+        );
+    }
+
+    for processor_config in selection.configured {
+        let counter = define_counter(&processor_config.table, multi_progress, stats).await;
+        handles.push(
+            spawn_processor_task(
+                ProcessorConfigured::new(
+                    bank.clone(),
+                    db_sender.clone(),
+                    processor_config,
+                    counter,
+                )
+                .await?,
+                completion.clone(),
+            )
+            .await?,
+        );
+    }
+
+    await_processors(handles).await
+}
+
+async fn await_processors(
+    handles: Vec<tokio::task::JoinHandle<anyhow::Result<()>>>,
+) -> anyhow::Result<()> {
+    let mut failed_processors = 0;
+    for handle in handles {
+        if let Err(e) = handle
+            .await
+            .map_err(anyhow::Error::from)
+            .and_then(|result| result)
+        {
+            error!("Processor failed: {:?}", e);
+            failed_processors += 1;
+        }
+    }
+    if failed_processors > 0 {
+        anyhow::bail!("{} processors failed", failed_processors);
+    }
+    Ok(())
+}
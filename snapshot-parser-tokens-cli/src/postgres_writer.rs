@@ -0,0 +1,349 @@
+use crate::db_message::{DbMessage, OwnedSqlValue, TableColumns};
+use crate::progress_bar::ProgressCounter;
+use crate::schema::{create_index_query, TABLE_INDEXES};
+use std::collections::HashMap;
+use std::sync::Arc;
+use tokio::sync::mpsc::Receiver;
+use tokio::task::JoinHandle;
+use tokio_postgres::binary_copy::BinaryCopyInWriter;
+use tokio_postgres::types::{ToSql, Type};
+use tokio_postgres::{Client, NoTls};
+use tracing::{debug, error, info, warn};
+
+/// Number of rows buffered per table before they are copied into the DB in one transaction.
+const DEFAULT_BATCH_SIZE: usize = 50_000;
+
+type PgValue = Box<dyn ToSql + Sync + Send>;
+
+/// Maps the SQL type definition of the column (written for SQLite) to the Postgres type.
+fn postgres_type(sql_type: &str) -> Type {
+    let sql_type = sql_type.to_uppercase();
+    if sql_type.starts_with("TEXT") {
+        Type::TEXT
+    } else if sql_type.starts_with("BOOL") {
+        Type::BOOL
+    } else {
+        Type::INT8
+    }
+}
+
+fn postgres_column_definition(sql_type: &str) -> String {
+    let upper_sql_type = sql_type.to_uppercase();
+    let mut definition = match postgres_type(sql_type) {
+        Type::TEXT => "TEXT",
+        Type::BOOL => "BOOLEAN",
+        _ => "BIGINT",
+    }
+    .to_string();
+    if upper_sql_type.contains("NOT NULL") {
+        definition.push_str(" NOT NULL");
+    }
+    if upper_sql_type.contains("PRIMARY KEY") {
+        definition.push_str(" PRIMARY KEY");
+    }
+    definition
+}
+
+fn to_postgres_value(value: &OwnedSqlValue, column_type: &Type) -> anyhow::Result<PgValue> {
+    Ok(match (column_type, value) {
+        (&Type::TEXT, OwnedSqlValue::Text(value)) => Box::new(value.clone()),
+        (&Type::TEXT, _) => anyhow::bail!("Non-text value cannot be stored in a text column"),
+        (&Type::BOOL, OwnedSqlValue::Boolean(value)) => Box::new(*value),
+        (&Type::BOOL, value) => Box::new(value.as_i64()?.map(|value| value != 0)),
+        (_, value) => Box::new(value.as_i64()?),
+    })
+}
+
+struct TableBuffer {
+    columns: TableColumns,
+    column_types: Vec<Type>,
+    primary_key: Option<&'static str>,
+    rows: Vec<Vec<OwnedSqlValue>>,
+}
+
+impl TableBuffer {
+    fn new(columns: TableColumns) -> Self {
+        Self {
+            columns,
+            column_types: columns
+                .iter()
+                .map(|(_, sql_type)| postgres_type(sql_type))
+                .collect(),
+            primary_key: columns
+                .iter()
+                .find(|(_, sql_type)| sql_type.to_uppercase().contains("PRIMARY KEY"))
+                .map(|(name, _)| *name),
+            rows: Vec::new(),
+        }
+    }
+
+    fn column_names(&self) -> String {
+        self.columns
+            .iter()
+            .map(|(name, _)| *name)
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+
+    /// Moves the staged rows into the table, upserting them by the primary key.
+    /// The rows of the same account are equal within a snapshot, so any of the duplicates is kept.
+    fn merge_query(&self, table: &str) -> String {
+        let column_names = self.column_names();
+        match self.primary_key {
+            Some(primary_key) => {
+                let updates = self
+                    .columns
+                    .iter()
+                    .map(|(name, _)| *name)
+                    .filter(|name| *name != primary_key)
+                    .map(|name| format!("{} = EXCLUDED.{}", name, name))
+                    .collect::<Vec<_>>();
+                let on_conflict = if updates.is_empty() {
+                    "DO NOTHING".to_string()
+                } else {
+                    format!("DO UPDATE SET {}", updates.join(", "))
+                };
+                format!(
+                    "INSERT INTO {table} ({columns}) SELECT DISTINCT ON ({primary_key}) {columns} FROM {staging} ON CONFLICT ({primary_key}) {on_conflict};",
+                    table = table,
+                    columns = column_names,
+                    primary_key = primary_key,
+                    staging = staging_table(table),
+                    on_conflict = on_conflict,
+                )
+            }
+            None => format!(
+                "INSERT INTO {} ({}) SELECT {} FROM {};",
+                table,
+                column_names,
+                column_names,
+                staging_table(table)
+            ),
+        }
+    }
+}
+
+fn staging_table(table: &str) -> String {
+    format!("_staging_{}", table)
+}
+
+/// Writes the tables directly into a PostgreSQL DB.
+/// The rows are buffered per table and loaded in batches with binary COPY into a temporary staging table,
+/// from which they are upserted into the target table by its primary key.
+pub struct PostgresExecutor {
+    client: Client,
+    connection_handle: JoinHandle<()>,
+    /// existing rows are kept and updated when set, otherwise the tables are truncated when created
+    reprocess: bool,
+    tables: HashMap<&'static str, TableBuffer>,
+    db_execute_counter: Arc<ProgressCounter>,
+    receiver: Receiver<DbMessage>,
+}
+
+impl PostgresExecutor {
+    pub async fn new(
+        postgres_url: &str,
+        reprocess: bool,
+        db_execute_counter: Arc<ProgressCounter>,
+        receiver: Receiver<DbMessage>,
+    ) -> anyhow::Result<Self> {
+        let (client, connection) = tokio_postgres::connect(postgres_url, NoTls)
+            .await
+            .map_err(|e| anyhow::anyhow!("Could not connect to PostgreSQL: {}", e))?;
+        let connection_handle = tokio::spawn(async move {
+            if let Err(e) = connection.await {
+                error!("PostgreSQL connection error: {}", e);
+            }
+        });
+        Ok(Self {
+            client,
+            connection_handle,
+            reprocess,
+            tables: HashMap::new(),
+            db_execute_counter,
+            receiver,
+        })
+    }
+
+    async fn create_table(
+        &mut self,
+        table: &'static str,
+        columns: TableColumns,
+    ) -> anyhow::Result<usize> {
+        if self.tables.contains_key(table) {
+            return Ok(0);
+        }
+        debug!("Creating PostgreSQL table {}", table);
+        let column_definitions = columns
+            .iter()
+            .map(|(name, sql_type)| format!("{} {}", name, postgres_column_definition(sql_type)))
+            .collect::<Vec<_>>()
+            .join(", ");
+        let mut query = format!(
+            "CREATE TABLE IF NOT EXISTS {} ({});\n",
+            table, column_definitions
+        );
+        if !self.reprocess {
+            query.push_str(&format!("TRUNCATE {};\n", table));
+        }
+        query.push_str(&format!(
+            "CREATE TEMP TABLE IF NOT EXISTS {} (LIKE {}) ON COMMIT DELETE ROWS;",
+            staging_table(table),
+            table
+        ));
+        self.client.batch_execute(&query).await?;
+        self.tables.insert(table, TableBuffer::new(columns));
+        Ok(0)
+    }
+
+    async fn insert(
+        &mut self,
+        table: &'static str,
+        values: Vec<OwnedSqlValue>,
+    ) -> anyhow::Result<usize> {
+        let table_buffer = self
+            .tables
+            .get_mut(table)
+            .ok_or_else(|| anyhow::anyhow!("Table {} was not created", table))?;
+        if values.len() != table_buffer.columns.len() {
+            anyhow::bail!(
+                "Expected {} values, got {}",
+                table_buffer.columns.len(),
+                values.len()
+            );
+        }
+        table_buffer.rows.push(values);
+        if table_buffer.rows.len() >= DEFAULT_BATCH_SIZE {
+            self.flush(table).await?;
+        }
+        Ok(1)
+    }
+
+    async fn insert_rows(
+        &mut self,
+        table: &'static str,
+        rows: Vec<Vec<OwnedSqlValue>>,
+    ) -> anyhow::Result<usize> {
+        let mut inserted = 0;
+        for values in rows {
+            inserted += self.insert(table, values).await?;
+        }
+        Ok(inserted)
+    }
+
+    async fn flush(&mut self, table: &'static str) -> anyhow::Result<()> {
+        let table_buffer = match self.tables.get_mut(table) {
+            Some(table_buffer) if !table_buffer.rows.is_empty() => table_buffer,
+            _ => return Ok(()),
+        };
+        let rows = std::mem::take(&mut table_buffer.rows);
+        let transaction = self.client.transaction().await?;
+        let sink = transaction
+            .copy_in(&format!(
+                "COPY {} ({}) FROM STDIN BINARY",
+                staging_table(table),
+                table_buffer.column_names()
+            ))
+            .await?;
+        let writer = BinaryCopyInWriter::new(sink, &table_buffer.column_types);
+        tokio::pin!(writer);
+        for row in rows.iter() {
+            let values = row
+                .iter()
+                .zip(table_buffer.column_types.iter())
+                .map(|(value, column_type)| to_postgres_value(value, column_type))
+                .collect::<anyhow::Result<Vec<_>>>()?;
+            let params: Vec<&(dyn ToSql + Sync)> = values
+                .iter()
+                .map(|value| value.as_ref() as &(dyn ToSql + Sync))
+                .collect();
+            writer.as_mut().write(&params).await?;
+        }
+        writer.finish().await?;
+        transaction
+            .batch_execute(&table_buffer.merge_query(table))
+            .await?;
+        transaction.commit().await?;
+        self.db_execute_counter.add(rows.len() as u64);
+        debug!(
+            "Flushed {} rows into PostgreSQL table {}",
+            rows.len(),
+            table
+        );
+        Ok(())
+    }
+
+    pub async fn start(mut self) {
+        info!("PostgresExecutor receiver started to listen for row messages");
+        while let Some(msg) = self.receiver.recv().await {
+            match msg {
+                DbMessage::CreateTable {
+                    table,
+                    columns,
+                    response,
+                } => {
+                    let _ = response.send(self.create_table(table, columns).await);
+                }
+                DbMessage::Insert { row, response } => {
+                    let table = row.table();
+                    let _ = response.send(self.insert(table, row.into_values()).await);
+                }
+                DbMessage::InsertBatch { rows, response } => {
+                    let table = rows.table();
+                    let _ = response.send(self.insert_rows(table, rows.into_values()).await);
+                }
+                DbMessage::CommitCheckpoint { response, .. }
+                | DbMessage::RecordCompletion { response, .. } => {
+                    error!("Checkpoints are not supported by the PostgreSQL output");
+                    let _ = response.send(Err(anyhow::anyhow!(
+                        "Checkpoints are not supported by the PostgreSQL output"
+                    )));
+                }
+                DbMessage::Finalize { response } => {
+                    let _ = response.send(self.optimize().await);
+                }
+                DbMessage::Shutdown { response } => {
+                    let _ = response.send(self.finalize().await);
+                }
+                DbMessage::Interrupt { response } => {
+                    warn!("Interrupted, the buffered rows are not copied into PostgreSQL, the batches copied before are kept");
+                    let _ = response.send(Ok(()));
+                    break;
+                }
+            }
+        }
+        self.connection_handle.abort();
+    }
+
+    /// Copies the buffered rows, indexes the written tables and analyzes them.
+    async fn optimize(&mut self) -> anyhow::Result<()> {
+        let tables: Vec<&'static str> = self.tables.keys().copied().collect();
+        for &table in tables.iter() {
+            self.flush(table).await?;
+        }
+        for (table, column) in TABLE_INDEXES {
+            if self.tables.contains_key(table) {
+                debug!("Creating index of PostgreSQL table {} on {}", table, column);
+                self.client
+                    .batch_execute(&create_index_query(table, column))
+                    .await?;
+            }
+        }
+        for table in tables {
+            self.client
+                .batch_execute(&format!("ANALYZE {}", table))
+                .await?;
+        }
+        info!("PostgreSQL tables indexed and analyzed");
+        Ok(())
+    }
+
+    async fn finalize(&mut self) -> anyhow::Result<()> {
+        let tables: Vec<&'static str> = self.tables.keys().copied().collect();
+        for table in tables {
+            self.flush(table).await?;
+        }
+        info!("PostgreSQL tables finalized");
+        Ok(())
+    }
+}
@@ -1,30 +1,30 @@
-use crate::db_message::{DbMessage, OwnedSqlValue};
+use crate::db_message::DbMessage;
 use crate::filters::Filters;
 use crate::processors::processor::Processor;
+use crate::processors::scan_cache::ScanCache;
 use crate::progress_bar::ProgressCounter;
-use crate::sql_params;
-use crate::stats::ProcessorCallback;
+use crate::schema::META_ACCOUNT_TABLE;
+use crate::sql_row::{create_table, RowBatch};
+use crate::stats::{NamedCounter, ProcessorCallback};
 use async_trait::async_trait;
-use log::{debug, error};
-use rusqlite::ToSql;
-use solana_accounts_db::accounts_index::ScanConfig;
+use snapshot_parser_types::decoded_accounts::AccountRow;
 use solana_program::pubkey::Pubkey;
 use solana_runtime::bank::Bank;
 use solana_sdk::account::{AccountSharedData, ReadableAccount};
+use std::collections::{HashMap, HashSet};
 use std::future::Future;
 use std::string::ToString;
 use std::sync::Arc;
 use tokio::sync::mpsc::Sender;
-use tokio::sync::oneshot;
-
-pub const META_ACCOUNT_TABLE: &str = "account";
-pub const INSERT_META_ACCOUNT_QUERY: &str = "INSERT OR REPLACE INTO account (pubkey, data_len, owner, lamports, executable, rent_epoch) SELECT ?, ?, ?, ?, ?, ?;";
+use tracing::{debug, error, warn};
 
 pub struct ProcessorAccountOwners {
     bank: Arc<Bank>,
     db_sender: Sender<DbMessage>,
-    account_owners: Vec<Pubkey>,
+    account_owners: HashSet<Pubkey>,
     account_owners_counter: Arc<ProgressCounter>,
+    per_owner_counters: HashMap<Pubkey, Arc<NamedCounter>>,
+    scan_cache: Arc<ScanCache>,
 }
 
 impl ProcessorAccountOwners {
@@ -33,67 +33,72 @@ impl ProcessorAccountOwners {
         db_sender: Sender<DbMessage>,
         filters: &Filters,
         account_owners_progress_counter: Arc<ProgressCounter>,
+        scan_cache: Arc<ScanCache>,
     ) -> anyhow::Result<Self> {
         let account_owners = filters.account_owners.clone();
+        let per_owner_counters = account_owners
+            .iter()
+            .map(|owner| {
+                let name = format!("{}[{}]", META_ACCOUNT_TABLE, owner);
+                (*owner, Arc::new(NamedCounter::new(name)))
+            })
+            .collect();
         let processor = Self {
             bank,
             db_sender,
             account_owners_counter: account_owners_progress_counter,
             account_owners,
+            per_owner_counters,
+            scan_cache,
         };
-        processor.create_table().await?;
+        create_table::<AccountRow>(&processor.db_sender).await?;
         Ok(processor)
     }
 
-    async fn create_table(&self) -> anyhow::Result<usize> {
-        let (response_tx, response_rx) = oneshot::channel();
-        self.db_sender
-            .send(DbMessage::ExecuteSpecial {
-                query: "CREATE TABLE account  (
-                    pubkey TEXT NOT NULL PRIMARY KEY,
-                    data_len INTEGER(8) NOT NULL,
-                    owner TEXT NOT NULL,
-                    lamports INTEGER(8) NOT NULL,
-                    executable INTEGER(1) NOT NULL,
-                    rent_epoch INTEGER(8) NOT NULL
-                );"
-                .to_string(),
-                params: vec![],
-                response: response_tx,
-            })
-            .await?;
-        response_rx.await?
+    /// Counters of loaded accounts per configured owner program, to be registered in [`crate::stats::Stats`].
+    pub fn per_owner_counters(&self) -> Vec<Arc<dyn ProcessorCallback>> {
+        self.per_owner_counters
+            .values()
+            .map(|counter| counter.clone() as Arc<dyn ProcessorCallback>)
+            .collect()
     }
 
     pub async fn process(&mut self) -> anyhow::Result<()> {
-        for pubkey in self.account_owners.clone() {
-            debug!("Loading program {} account_owners from bank...", pubkey);
-            let transaction_accounts = self.bank.get_program_accounts(
-                &pubkey,
-                &ScanConfig {
-                    collect_all_unsorted: true,
-                    ..ScanConfig::default()
-                },
-            )?;
+        let mut batch = RowBatch::new(self.account_owners_counter.clone());
+        for owner in self.account_owners.clone() {
+            debug!("Streaming program {} account_owners from bank...", owner);
+            let (mut accounts_receiver, scan_handle) =
+                self.scan_cache.stream_program_accounts(owner, |_| true);
+            let mut loaded_accounts = 0u64;
+            while let Some((pubkey, account)) = accounts_receiver.recv().await {
+                loaded_accounts += 1;
+                batch
+                    .push(&self.db_sender, account_row(&pubkey, &account))
+                    .await
+                    .unwrap_or_else(|e| {
+                        error!("Failed to insert accounts up to {}: {:?}", pubkey, e);
+                        0
+                    });
+            }
+            scan_handle.await??;
             debug!(
                 "Loaded program {} {} account_owners",
-                pubkey,
-                transaction_accounts.len()
+                owner, loaded_accounts
             );
-            for (pubkey, account) in transaction_accounts {
-                insert_account_meta(
-                    &self.db_sender,
-                    &self.account_owners_counter,
-                    &pubkey,
-                    &account,
-                )
-                .await
-                .unwrap_or_else(|e| {
-                    error!("Failed to insert account {}: {:?}", pubkey, e);
-                    0
-                });
+            if loaded_accounts == 0 {
+                warn!(
+                    "No accounts found for owner program {} configured in filters, check the filters for a typo",
+                    owner
+                );
+            }
+            if let Some(counter) = self.per_owner_counters.get(&owner) {
+                counter.add(loaded_accounts);
             }
         }
+        batch.flush(&self.db_sender).await.unwrap_or_else(|e| {
+            error!("Failed to insert accounts: {:?}", e);
+            0
+        });
         Ok(())
     }
 }
@@ -105,6 +110,9 @@ impl Processor for ProcessorAccountOwners {
     fn process(&mut self) -> impl Future<Output = anyhow::Result<()>> + Send {
         self.process()
     }
+    fn release_scans(&self) {
+        self.scan_cache.release(self.account_owners.iter().copied());
+    }
 }
 
 #[async_trait]
@@ -117,28 +125,13 @@ impl ProcessorCallback for ProcessorAccountOwners {
     }
 }
 
-pub async fn insert_account_meta(
-    db_sender: &Sender<DbMessage>,
-    progress_counter: &Arc<ProgressCounter>,
-    pubkey: &Pubkey,
-    account: &AccountSharedData,
-) -> anyhow::Result<usize> {
-    let (response_tx, response_rx) = oneshot::channel();
-    let owned_params = sql_params![
-        pubkey.to_string(),
-        account.data().len() as i64,
-        account.owner().to_string(),
-        account.lamports() as i64,
-        account.executable(),
-        account.rent_epoch() as i64
-    ];
-    db_sender
-        .send(DbMessage::Execute {
-            query: INSERT_META_ACCOUNT_QUERY.to_string(),
-            params: owned_params,
-            response: response_tx,
-        })
-        .await?;
-    progress_counter.inc();
-    response_rx.await?
+pub fn account_row(pubkey: &Pubkey, account: &AccountSharedData) -> AccountRow {
+    AccountRow {
+        pubkey: pubkey.to_string(),
+        data_len: account.data().len() as u64,
+        owner: account.owner().to_string(),
+        lamports: account.lamports(),
+        executable: account.executable(),
+        rent_epoch: account.rent_epoch(),
+    }
 }
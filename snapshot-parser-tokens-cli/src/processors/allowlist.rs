@@ -0,0 +1,335 @@
+use crate::db_message::DbMessage;
+use crate::filters::Filters;
+use crate::processors::{
+    account_row, is_token_account_data, mint_row, token_row, unpack_mint, unpack_token_account,
+    Processor, TOKEN_PROGRAMS,
+};
+#[cfg(feature = "metaplex")]
+use crate::processors::{token_metadata_row, MetadataSanitization, SpoofDetector};
+use crate::progress_bar::ProgressCounter;
+use crate::schema::META_ACCOUNT_TABLE;
+use crate::sql_row::{create_table, insert_row};
+use crate::stats::ProcessorCallback;
+use async_trait::async_trait;
+#[cfg(feature = "metaplex")]
+use mpl_token_metadata::accounts::Metadata;
+use rayon::prelude::*;
+use snapshot_parser::program_registry::{builtin_address, TOKEN_METADATA_PROGRAM};
+#[cfg(feature = "metaplex")]
+use snapshot_parser_types::decoded_accounts::TokenMetadataRow;
+use snapshot_parser_types::decoded_accounts::{AccountRow, MintRow, TokenRow, UnknownMintRow};
+use solana_program::pubkey::Pubkey;
+use solana_runtime::bank::Bank;
+use solana_sdk::account::{AccountSharedData, ReadableAccount};
+use std::collections::{HashMap, HashSet};
+use std::future::Future;
+use std::str::FromStr;
+use std::string::ToString;
+use std::sync::Arc;
+use tokio::sync::mpsc::Sender;
+use tracing::{debug, error, info, warn};
+
+/// What is done with the mints referenced by the allowlisted token accounts that are not allowlisted themselves.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq, clap::ValueEnum)]
+pub enum UnknownMintPolicy {
+    /// the mint is fetched from the bank and written into the `token_mint` table
+    #[default]
+    Fetch,
+    /// the mint is not fetched, it is written into the `unknown_mint` table only
+    Unknown,
+}
+
+/// Parses the `<mint>=<decimals>` decimals override of a mint.
+pub fn parse_mint_decimals(value: &str) -> Result<(Pubkey, u8), String> {
+    let (mint, decimals) = value
+        .split_once('=')
+        .ok_or_else(|| format!("Expected <mint>=<decimals>, got '{}'", value))?;
+    let mint = Pubkey::from_str(mint.trim())
+        .map_err(|e| format!("Invalid mint pubkey '{}': {}", mint, e))?;
+    let decimals = decimals
+        .trim()
+        .parse()
+        .map_err(|e| format!("Invalid decimals '{}': {}", decimals, e))?;
+    Ok((mint, decimals))
+}
+
+/// Fetches only the accounts listed in the `account_pubkeys` filter, without scanning the programs,
+/// and decodes them by their owner. Accounts of unknown owners are stored in the `account` table only.
+/// The mints of the token accounts that are not allowlisted are handled by the [`UnknownMintPolicy`].
+pub struct ProcessorAllowlist {
+    bank: Arc<Bank>,
+    db_sender: Sender<DbMessage>,
+    pubkeys: Vec<Pubkey>,
+    account_counter: Arc<ProgressCounter>,
+    token_counter: Arc<ProgressCounter>,
+    unknown_mint_policy: UnknownMintPolicy,
+    /// decimals of the unknown mints, see [`UnknownMintRow::decimals`]
+    mint_decimals: HashMap<Pubkey, u8>,
+    /// mints written into the `token_mint` table
+    written_mints: HashSet<Pubkey>,
+    /// mints of the written token accounts with the count of their accounts
+    referenced_mints: HashMap<Pubkey, u64>,
+    /// token metadata accounts are decoded once set by [`ProcessorAllowlist::with_token_metadata`]
+    #[cfg(feature = "metaplex")]
+    token_metadata: Option<AllowlistTokenMetadata>,
+}
+
+#[cfg(feature = "metaplex")]
+struct AllowlistTokenMetadata {
+    counter: Arc<ProgressCounter>,
+    sanitization: MetadataSanitization,
+    spoof_detector: Option<Arc<SpoofDetector>>,
+}
+
+impl ProcessorAllowlist {
+    pub async fn new(
+        bank: Arc<Bank>,
+        db_sender: Sender<DbMessage>,
+        filters: &Filters,
+        account_progress_counter: Arc<ProgressCounter>,
+        token_progress_counter: Arc<ProgressCounter>,
+        unknown_mint_policy: UnknownMintPolicy,
+        mint_decimals: HashMap<Pubkey, u8>,
+    ) -> anyhow::Result<Self> {
+        let processor = Self {
+            bank,
+            db_sender,
+            pubkeys: filters.account_pubkeys.iter().copied().collect(),
+            account_counter: account_progress_counter,
+            token_counter: token_progress_counter,
+            unknown_mint_policy,
+            mint_decimals,
+            written_mints: HashSet::new(),
+            referenced_mints: HashMap::new(),
+            #[cfg(feature = "metaplex")]
+            token_metadata: None,
+        };
+        create_table::<AccountRow>(&processor.db_sender).await?;
+        create_table::<TokenRow>(&processor.db_sender).await?;
+        create_table::<MintRow>(&processor.db_sender).await?;
+        create_table::<UnknownMintRow>(&processor.db_sender).await?;
+        Ok(processor)
+    }
+
+    /// Decodes the allowlisted token metadata accounts into the `token_metadata` table.
+    #[cfg(feature = "metaplex")]
+    pub async fn with_token_metadata(
+        mut self,
+        token_metadata_progress_counter: Arc<ProgressCounter>,
+        metadata_sanitization: MetadataSanitization,
+        spoof_detector: Option<Arc<SpoofDetector>>,
+    ) -> anyhow::Result<Self> {
+        create_table::<TokenMetadataRow>(&self.db_sender).await?;
+        self.token_metadata = Some(AllowlistTokenMetadata {
+            counter: token_metadata_progress_counter,
+            sanitization: metadata_sanitization,
+            spoof_detector,
+        });
+        Ok(self)
+    }
+
+    pub async fn process(&mut self) -> anyhow::Result<()> {
+        debug!(
+            "Fetching {} allowlisted accounts from bank...",
+            self.pubkeys.len()
+        );
+        let accounts: Vec<(Pubkey, AccountSharedData)> = self
+            .pubkeys
+            .par_iter()
+            .filter_map(|pubkey| {
+                self.bank
+                    .get_account(pubkey)
+                    .map(|account| (*pubkey, account))
+            })
+            .collect();
+        info!(
+            "Allowlist processor loaded {} of {} accounts",
+            accounts.len(),
+            self.pubkeys.len()
+        );
+        if accounts.len() < self.pubkeys.len() {
+            warn!(
+                "{} allowlisted accounts not found in the snapshot",
+                self.pubkeys.len() - accounts.len()
+            );
+        }
+
+        let metadata_id = builtin_address(TOKEN_METADATA_PROGRAM);
+        for (pubkey, account) in accounts {
+            insert_row(
+                &self.db_sender,
+                &self.account_counter,
+                &account_row(&pubkey, &account),
+            )
+            .await?;
+            let result = if TOKEN_PROGRAMS.contains(account.owner()) {
+                self.insert_token_program_account(&pubkey, &account).await
+            } else if *account.owner() == metadata_id {
+                self.insert_token_metadata_account(&pubkey, &account).await
+            } else {
+                Ok(())
+            };
+            result.unwrap_or_else(|e| {
+                error!("Failed to insert allowlisted account {}: {:?}", pubkey, e);
+            });
+        }
+        self.insert_unknown_mints().await
+    }
+
+    /// Applies the [`UnknownMintPolicy`] to the referenced mints that were not written,
+    /// so every token account can be decimal-adjusted or its mint is listed in the `unknown_mint` table.
+    async fn insert_unknown_mints(&mut self) -> anyhow::Result<()> {
+        let mut unknown_mints: Vec<(Pubkey, u64)> = self
+            .referenced_mints
+            .iter()
+            .filter(|(mint, _)| !self.written_mints.contains(mint))
+            .map(|(mint, token_accounts)| (*mint, *token_accounts))
+            .collect();
+        unknown_mints.sort_unstable();
+        let mut fetched = 0;
+        for (mint, token_accounts) in unknown_mints.iter() {
+            let reason = match self.unknown_mint_policy {
+                UnknownMintPolicy::Unknown => "not_fetched",
+                UnknownMintPolicy::Fetch => {
+                    match self.bank.get_account(mint).filter(|account| {
+                        TOKEN_PROGRAMS.contains(account.owner())
+                            && unpack_mint(account.data()).is_ok()
+                    }) {
+                        Some(account) => {
+                            self.insert_token_program_account(mint, &account).await?;
+                            fetched += 1;
+                            continue;
+                        }
+                        None => "not_found",
+                    }
+                }
+            };
+            let decimals = self.mint_decimals.get(mint).copied();
+            if decimals.is_none() {
+                warn!(
+                    "Mint {} of {} allowlisted token accounts is {}, their decimals are unknown",
+                    mint,
+                    token_accounts,
+                    reason.replace('_', " ")
+                );
+            }
+            insert_row(
+                &self.db_sender,
+                &self.token_counter,
+                &UnknownMintRow {
+                    mint: mint.to_string(),
+                    decimals,
+                    reason: reason.to_string(),
+                    token_accounts: *token_accounts,
+                },
+            )
+            .await?;
+        }
+        if !unknown_mints.is_empty() {
+            info!(
+                "Allowlist processor: {} mints of the token accounts were not allowlisted, {} fetched",
+                unknown_mints.len(),
+                fetched
+            );
+        }
+        Ok(())
+    }
+
+    #[cfg(feature = "metaplex")]
+    async fn insert_token_metadata_account(
+        &self,
+        pubkey: &Pubkey,
+        account: &AccountSharedData,
+    ) -> anyhow::Result<()> {
+        let Some(token_metadata) = &self.token_metadata else {
+            return Ok(());
+        };
+        match Metadata::safe_deserialize(&mut account.data()) {
+            Ok(metadata) => {
+                insert_row(
+                    &self.db_sender,
+                    &token_metadata.counter,
+                    &token_metadata_row(
+                        pubkey,
+                        account.data().len(),
+                        &metadata,
+                        &token_metadata.sanitization,
+                        token_metadata.spoof_detector.as_deref(),
+                    ),
+                )
+                .await?;
+            }
+            Err(e) => {
+                debug!(
+                    "Allowlisted account {} is not a token metadata: {:?}",
+                    pubkey, e
+                );
+            }
+        }
+        Ok(())
+    }
+
+    #[cfg(not(feature = "metaplex"))]
+    async fn insert_token_metadata_account(
+        &self,
+        pubkey: &Pubkey,
+        _account: &AccountSharedData,
+    ) -> anyhow::Result<()> {
+        debug!(
+            "Allowlisted token metadata account {} is not decoded, built without the metaplex feature",
+            pubkey
+        );
+        Ok(())
+    }
+
+    async fn insert_token_program_account(
+        &mut self,
+        pubkey: &Pubkey,
+        account: &AccountSharedData,
+    ) -> anyhow::Result<()> {
+        if is_token_account_data(account.data()) {
+            let token_account = unpack_token_account(account.data())?;
+            insert_row(
+                &self.db_sender,
+                &self.token_counter,
+                &token_row(pubkey, account.owner(), &token_account),
+            )
+            .await?;
+            *self
+                .referenced_mints
+                .entry(token_account.base.mint)
+                .or_default() += 1;
+        } else if let Ok(token_mint) = unpack_mint(account.data()) {
+            insert_row(
+                &self.db_sender,
+                &self.token_counter,
+                &mint_row(pubkey, account.owner(), &token_mint, self.bank.epoch()),
+            )
+            .await?;
+            self.written_mints.insert(*pubkey);
+        } else {
+            debug!(
+                "Allowlisted token program account {} of length {} is neither a token account nor a mint",
+                pubkey,
+                account.data().len()
+            );
+        }
+        Ok(())
+    }
+}
+
+impl Processor for ProcessorAllowlist {
+    fn name() -> &'static str {
+        "Allowlist"
+    }
+    fn process(&mut self) -> impl Future<Output = anyhow::Result<()>> + Send {
+        self.process()
+    }
+}
+
+#[async_trait]
+impl ProcessorCallback for ProcessorAllowlist {
+    async fn get_count(&self) -> (String, u64) {
+        (META_ACCOUNT_TABLE.to_string(), self.account_counter.get())
+    }
+}
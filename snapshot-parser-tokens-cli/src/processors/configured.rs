@@ -0,0 +1,353 @@
+use crate::db_message::{DbMessage, OwnedSqlValue, TableColumns};
+use crate::processors::{processor_registry, Processor};
+use crate::progress_bar::ProgressCounter;
+use crate::sql_row::{create_table_with_columns, insert_values};
+use crate::stats::ProcessorCallback;
+use async_trait::async_trait;
+use serde::Deserialize;
+use snapshot_parser::utils::read_from_json_file;
+use solana_accounts_db::accounts_index::ScanConfig;
+use solana_program::pubkey::Pubkey;
+use solana_runtime::bank::Bank;
+use solana_sdk::account::ReadableAccount;
+use std::collections::HashSet;
+use std::future::Future;
+use std::path::Path;
+use std::str::FromStr;
+use std::sync::Arc;
+use tokio::sync::mpsc::Sender;
+use tracing::{debug, error, warn};
+
+/// Processors config file listing the programs whose accounts are dumped without a code change.
+///
+/// ```json
+/// {
+///   "processors": [{
+///     "table": "vote_delegation",
+///     "program_id": "<program pubkey>",
+///     "data_size": 80,
+///     "discriminator": "<base58 encoded bytes the account data starts with>",
+///     "fields": [
+///       { "name": "authority", "type": "pubkey", "offset": 8 },
+///       { "name": "amount", "type": "u64", "offset": 40 }
+///     ]
+///   }]
+/// }
+/// ```
+#[derive(Debug, Deserialize)]
+pub struct ProcessorsConfig {
+    pub processors: Vec<ProcessorConfig>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct ProcessorConfig {
+    /// table the accounts are written to, keyed by the account pubkey
+    pub table: String,
+    pub program_id: String,
+    /// only accounts with exactly this data length are stored
+    #[serde(default)]
+    pub data_size: Option<usize>,
+    /// only accounts whose data start with these base58 encoded bytes are stored (e.g., anchor discriminator)
+    #[serde(default)]
+    pub discriminator: Option<String>,
+    pub fields: Vec<FieldConfig>,
+}
+
+/// Column decoded from the account data at a fixed offset.
+#[derive(Debug, Clone, Deserialize)]
+pub struct FieldConfig {
+    pub name: String,
+    #[serde(rename = "type")]
+    pub field_type: FieldType,
+    pub offset: usize,
+}
+
+impl FieldConfig {
+    /// End of the field in the account data, `None` when it overflows.
+    fn end(&self) -> Option<usize> {
+        self.offset.checked_add(self.field_type.size())
+    }
+}
+
+#[derive(Debug, Clone, Copy, Deserialize, Eq, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum FieldType {
+    Pubkey,
+    Bool,
+    U8,
+    U16,
+    U32,
+    /// stored as text, it does not fit the integer column beyond `i64::MAX`
+    U64,
+    I64,
+    /// stored as text, it does not fit the integer column
+    U128,
+}
+
+impl FieldType {
+    fn size(&self) -> usize {
+        match self {
+            FieldType::Pubkey => 32,
+            FieldType::Bool | FieldType::U8 => 1,
+            FieldType::U16 => 2,
+            FieldType::U32 => 4,
+            FieldType::U64 | FieldType::I64 => 8,
+            FieldType::U128 => 16,
+        }
+    }
+
+    fn sql_type(&self) -> &'static str {
+        match self {
+            FieldType::Pubkey | FieldType::U64 | FieldType::U128 => "TEXT NOT NULL",
+            FieldType::Bool => "INTEGER(1) NOT NULL",
+            FieldType::U8 | FieldType::U16 => "INTEGER(2) NOT NULL",
+            FieldType::U32 => "INTEGER(4) NOT NULL",
+            FieldType::I64 => "INTEGER(8) NOT NULL",
+        }
+    }
+
+    fn decode(&self, bytes: &[u8]) -> OwnedSqlValue {
+        match self {
+            FieldType::Pubkey => Pubkey::try_from(bytes).unwrap().to_string().into(),
+            FieldType::Bool => (bytes[0] != 0).into(),
+            FieldType::U8 => bytes[0].into(),
+            FieldType::U16 => u16::from_le_bytes(bytes.try_into().unwrap()).into(),
+            FieldType::U32 => (u32::from_le_bytes(bytes.try_into().unwrap()) as i64).into(),
+            FieldType::U64 => u64::from_le_bytes(bytes.try_into().unwrap())
+                .to_string()
+                .into(),
+            FieldType::I64 => i64::from_le_bytes(bytes.try_into().unwrap()).into(),
+            FieldType::U128 => u128::from_le_bytes(bytes.try_into().unwrap())
+                .to_string()
+                .into(),
+        }
+    }
+}
+
+impl ProcessorsConfig {
+    pub fn load(config_path: &Path) -> anyhow::Result<Self> {
+        let config: Self = read_from_json_file(&config_path).map_err(|e| {
+            anyhow::anyhow!("Could not load processors config {:?}: {}", config_path, e)
+        })?;
+        let builtin_tables: HashSet<&str> = processor_registry()
+            .processors
+            .iter()
+            .flat_map(|processor| processor.tables.iter().map(|table| table.name))
+            .collect();
+        let mut tables = HashSet::new();
+        for processor in config.processors.iter() {
+            check_identifier(&processor.table)?;
+            if builtin_tables.contains(processor.table.as_str()) {
+                anyhow::bail!(
+                    "Configured table {} collides with a built-in table",
+                    processor.table
+                );
+            }
+            if !tables.insert(processor.table.as_str()) {
+                anyhow::bail!("Table {} is configured more than once", processor.table);
+            }
+            for field in processor.fields.iter() {
+                check_identifier(&field.name)?;
+                if field.end().is_none() {
+                    anyhow::bail!(
+                        "Field {} of table {} has an offset {} overflowing the account data",
+                        field.name,
+                        processor.table,
+                        field.offset
+                    );
+                }
+                if field.name == "pubkey" || field.name == "lamports" {
+                    anyhow::bail!(
+                        "Field {} of table {} collides with a default column",
+                        field.name,
+                        processor.table
+                    );
+                }
+            }
+        }
+        Ok(config)
+    }
+}
+
+/// Table and column names are put into the SQL as they are.
+fn check_identifier(name: &str) -> anyhow::Result<()> {
+    let valid = name
+        .chars()
+        .next()
+        .is_some_and(|c| c.is_ascii_lowercase() || c == '_')
+        && name
+            .chars()
+            .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '_');
+    if !valid {
+        anyhow::bail!(
+            "Invalid name '{}' in processors config, use lowercase letters, digits and underscores",
+            name
+        );
+    }
+    Ok(())
+}
+
+/// Dumps the accounts of a program declared in the processors config into its own table.
+/// Every row contains the account pubkey and lamports followed by the configured fields.
+pub struct ProcessorConfigured {
+    bank: Arc<Bank>,
+    db_sender: Sender<DbMessage>,
+    program_id: Pubkey,
+    data_size: Option<usize>,
+    discriminator: Vec<u8>,
+    fields: Vec<FieldConfig>,
+    table: &'static str,
+    columns: TableColumns,
+    counter: Arc<ProgressCounter>,
+}
+
+impl ProcessorConfigured {
+    pub async fn new(
+        bank: Arc<Bank>,
+        db_sender: Sender<DbMessage>,
+        config: ProcessorConfig,
+        counter: Arc<ProgressCounter>,
+    ) -> anyhow::Result<Self> {
+        let program_id = Pubkey::from_str(&config.program_id).map_err(|e| {
+            anyhow::anyhow!(
+                "Cannot parse program id {} of table {}: {:?}",
+                config.program_id,
+                config.table,
+                e
+            )
+        })?;
+        let discriminator = match &config.discriminator {
+            Some(discriminator) => bs58::decode(discriminator).into_vec().map_err(|e| {
+                anyhow::anyhow!(
+                    "Cannot decode discriminator of table {}: {:?}",
+                    config.table,
+                    e
+                )
+            })?,
+            None => vec![],
+        };
+        // the tables are created once per run, so the names are leaked to live as long as the DB messages need
+        let table: &'static str = Box::leak(config.table.into_boxed_str());
+        let columns: Vec<(&'static str, &'static str)> = [
+            ("pubkey", "TEXT NOT NULL PRIMARY KEY"),
+            ("lamports", "INTEGER(8) NOT NULL"),
+        ]
+        .into_iter()
+        .chain(config.fields.iter().map(|field| {
+            let name: &'static str = Box::leak(field.name.clone().into_boxed_str());
+            (name, field.field_type.sql_type())
+        }))
+        .collect();
+        let processor = Self {
+            bank,
+            db_sender,
+            program_id,
+            data_size: config.data_size,
+            discriminator,
+            fields: config.fields,
+            table,
+            columns: Box::leak(columns.into_boxed_slice()),
+            counter,
+        };
+        create_table_with_columns(&processor.db_sender, processor.table, processor.columns).await?;
+        Ok(processor)
+    }
+
+    fn matches(&self, data: &[u8]) -> bool {
+        self.data_size.map_or(true, |size| data.len() == size)
+            && data.starts_with(&self.discriminator)
+    }
+
+    fn values(
+        &self,
+        pubkey: &Pubkey,
+        lamports: u64,
+        data: &[u8],
+    ) -> anyhow::Result<Vec<OwnedSqlValue>> {
+        let mut values = vec![pubkey.to_string().into(), (lamports as i64).into()];
+        for field in self.fields.iter() {
+            let bytes = field
+                .end()
+                .and_then(|end| data.get(field.offset..end))
+                .ok_or_else(|| {
+                    anyhow::anyhow!(
+                        "Field {} at offset {} is out of the account data of length {}",
+                        field.name,
+                        field.offset,
+                        data.len()
+                    )
+                })?;
+            values.push(field.field_type.decode(bytes));
+        }
+        Ok(values)
+    }
+
+    pub async fn process(&mut self) -> anyhow::Result<()> {
+        debug!(
+            "Loading program {} accounts for table {} from bank...",
+            self.program_id, self.table
+        );
+        let accounts = self.bank.get_filtered_program_accounts(
+            &self.program_id,
+            |account| self.matches(account.data()),
+            &ScanConfig {
+                collect_all_unsorted: true,
+                ..ScanConfig::default()
+            },
+        )?;
+        debug!(
+            "Loaded {} accounts of program {} for table {}",
+            accounts.len(),
+            self.program_id,
+            self.table
+        );
+        if accounts.is_empty() {
+            warn!(
+                "No accounts of program {} match the processors config of table {}",
+                self.program_id, self.table
+            );
+        }
+        for (pubkey, account) in accounts {
+            let result = match self.values(&pubkey, account.lamports(), account.data()) {
+                Ok(values) => {
+                    insert_values(
+                        &self.db_sender,
+                        &self.counter,
+                        self.table,
+                        self.columns,
+                        values,
+                    )
+                    .await
+                }
+                Err(e) => Err(e),
+            };
+            result.unwrap_or_else(|e| {
+                error!(
+                    "Failed to insert account {} into {}: {:?}",
+                    pubkey, self.table, e
+                );
+                0
+            });
+        }
+        Ok(())
+    }
+}
+
+impl Processor for ProcessorConfigured {
+    fn name() -> &'static str {
+        "Configured"
+    }
+    fn process(&mut self) -> impl Future<Output = anyhow::Result<()>> + Send {
+        self.process()
+    }
+    fn completion_key(&self) -> String {
+        format!("{} {}", Self::name(), self.table)
+    }
+}
+
+#[async_trait]
+impl ProcessorCallback for ProcessorConfigured {
+    async fn get_count(&self) -> (String, u64) {
+        (self.table.to_string(), self.counter.get())
+    }
+}
@@ -1,15 +1,43 @@
 pub mod account_owners;
+pub mod allowlist;
+pub mod configured;
+pub mod msol;
 pub mod native_staking;
 pub mod processor;
+pub mod protocol_balances;
+pub mod registry;
+pub mod scan;
+pub mod scan_cache;
+pub mod spill;
+#[cfg(feature = "metaplex")]
+pub mod spoof;
+pub mod stake_pool;
+pub mod supply;
 pub mod token;
+#[cfg(feature = "metaplex")]
 pub mod token_metadata;
 pub mod token_mints;
 pub mod vemnde;
+pub mod vote_accounts;
 
 pub use account_owners::*;
+pub use allowlist::*;
+pub use configured::*;
+pub use msol::*;
 pub use native_staking::*;
 pub use processor::*;
+pub use protocol_balances::*;
+pub use registry::*;
+pub use scan::*;
+pub use scan_cache::*;
+pub use spill::*;
+#[cfg(feature = "metaplex")]
+pub use spoof::*;
+pub use stake_pool::*;
+pub use supply::*;
 pub use token::*;
+#[cfg(feature = "metaplex")]
 pub use token_metadata::*;
 pub use token_mints::*;
 pub use vemnde::*;
+pub use vote_accounts::*;
@@ -0,0 +1,224 @@
+use crate::accounts::{
+    list_items, marinade_state_discriminator, List, MarinadeState, StakeRecord, ValidatorRecord,
+    STAKE_LIST_DISCRIMINATOR, VALIDATOR_LIST_DISCRIMINATOR,
+};
+use crate::db_message::DbMessage;
+use crate::processors::Processor;
+use crate::progress_bar::ProgressCounter;
+use crate::schema::MSOL_STATE_TABLE;
+use crate::sql_row::{create_table, insert_row, insert_single_row};
+use crate::stats::ProcessorCallback;
+use anchor_lang::AnchorDeserialize;
+use async_trait::async_trait;
+use snapshot_parser::program_registry::{builtin_address, MARINADE_LIQUID_STAKING_PROGRAM};
+use snapshot_parser_types::decoded_accounts::{MsolStakeRow, MsolStateRow, MsolValidatorRow};
+use solana_accounts_db::accounts_index::ScanConfig;
+use solana_program::pubkey::Pubkey;
+use solana_runtime::bank::Bank;
+use solana_sdk::account::ReadableAccount;
+use std::future::Future;
+use std::string::ToString;
+use std::sync::Arc;
+use tokio::sync::mpsc::Sender;
+use tracing::{debug, error, warn};
+
+pub struct ProcessorMsol {
+    bank: Arc<Bank>,
+    db_sender: Sender<DbMessage>,
+    liquid_staking_program: Pubkey,
+    msol_state_counter: Arc<ProgressCounter>,
+}
+
+impl ProcessorMsol {
+    pub async fn new(
+        bank: Arc<Bank>,
+        db_sender: Sender<DbMessage>,
+        msol_state_progress_counter: Arc<ProgressCounter>,
+    ) -> anyhow::Result<Self> {
+        let processor = Self {
+            bank,
+            db_sender,
+            liquid_staking_program: builtin_address(MARINADE_LIQUID_STAKING_PROGRAM),
+            msol_state_counter: msol_state_progress_counter,
+        };
+        create_table::<MsolStateRow>(&processor.db_sender).await?;
+        create_table::<MsolValidatorRow>(&processor.db_sender).await?;
+        create_table::<MsolStakeRow>(&processor.db_sender).await?;
+        Ok(processor)
+    }
+
+    pub async fn process(&mut self) -> anyhow::Result<()> {
+        debug!("Loading Marinade liquid staking state from bank...");
+
+        let state_discriminator = marinade_state_discriminator();
+        let state_accounts = self.bank.get_filtered_program_accounts(
+            &self.liquid_staking_program,
+            |account_data| account_data.data().starts_with(&state_discriminator),
+            &ScanConfig {
+                collect_all_unsorted: true,
+                ..ScanConfig::default()
+            },
+        )?;
+        debug!(
+            "mSOL processor loaded {} state accounts",
+            state_accounts.len()
+        );
+
+        for (pubkey, account) in state_accounts {
+            let state = match MarinadeState::deserialize(&mut account.data()) {
+                Ok(state) => state,
+                Err(e) => {
+                    warn!("Failed to unpack Marinade state {}: {:?}", pubkey, e);
+                    continue;
+                }
+            };
+            insert_row(
+                &self.db_sender,
+                &self.msol_state_counter,
+                &msol_state_row(&pubkey, &state),
+            )
+            .await
+            .unwrap_or_else(|e| {
+                error!("Failed to insert Marinade state {}: {:?}", pubkey, e);
+                0
+            });
+
+            // the list accounts are owned by the program, but they are read by the addresses the state refers to
+            let validator_list = &state.validator_system.validator_list;
+            match self.load_list::<ValidatorRecord>(
+                &validator_list.account,
+                VALIDATOR_LIST_DISCRIMINATOR,
+                validator_list,
+            ) {
+                Ok(validators) => {
+                    for row in msol_validator_rows(&pubkey, &validators) {
+                        insert_single_row(&self.db_sender, &row)
+                            .await
+                            .unwrap_or_else(|e| {
+                                error!(
+                                    "Failed to insert mSOL validator {}: {:?}",
+                                    row.vote_account, e
+                                );
+                                0
+                            });
+                    }
+                }
+                Err(e) => warn!("Failed to load validator list of {}: {:?}", pubkey, e),
+            }
+
+            let stake_list = &state.stake_system.stake_list;
+            match self.load_list::<StakeRecord>(
+                &stake_list.account,
+                STAKE_LIST_DISCRIMINATOR,
+                stake_list,
+            ) {
+                Ok(stakes) => {
+                    for row in msol_stake_rows(&pubkey, &stakes) {
+                        insert_single_row(&self.db_sender, &row)
+                            .await
+                            .unwrap_or_else(|e| {
+                                error!(
+                                    "Failed to insert mSOL stake {}: {:?}",
+                                    row.stake_account, e
+                                );
+                                0
+                            });
+                    }
+                }
+                Err(e) => warn!("Failed to load stake list of {}: {:?}", pubkey, e),
+            }
+        }
+        Ok(())
+    }
+
+    fn load_list<T: AnchorDeserialize>(
+        &self,
+        pubkey: &Pubkey,
+        discriminator: &[u8; 8],
+        list: &List,
+    ) -> anyhow::Result<Vec<T>> {
+        let account = self
+            .bank
+            .get_account(pubkey)
+            .ok_or_else(|| anyhow::anyhow!("List account {} not found", pubkey))?;
+        list_items(account.data(), discriminator, list)
+    }
+}
+
+impl Processor for ProcessorMsol {
+    fn name() -> &'static str {
+        "mSOL"
+    }
+    fn process(&mut self) -> impl Future<Output = anyhow::Result<()>> + Send {
+        self.process()
+    }
+}
+
+#[async_trait]
+impl ProcessorCallback for ProcessorMsol {
+    async fn get_count(&self) -> (String, u64) {
+        (MSOL_STATE_TABLE.to_string(), self.msol_state_counter.get())
+    }
+}
+
+pub fn msol_state_row(pubkey: &Pubkey, state: &MarinadeState) -> MsolStateRow {
+    MsolStateRow {
+        pubkey: pubkey.to_string(),
+        msol_mint: state.msol_mint.to_string(),
+        admin_authority: state.admin_authority.to_string(),
+        validator_manager_authority: state.validator_system.manager_authority.to_string(),
+        operational_sol_account: state.operational_sol_account.to_string(),
+        treasury_msol_account: state.treasury_msol_account.to_string(),
+        validator_list: state.validator_system.validator_list.account.to_string(),
+        stake_list: state.stake_system.stake_list.account.to_string(),
+        reward_fee_bps: state.reward_fee.basis_points,
+        available_reserve_balance: state.available_reserve_balance,
+        msol_supply: state.msol_supply,
+        msol_price: state.msol_price,
+        circulating_ticket_count: state.circulating_ticket_count,
+        circulating_ticket_balance: state.circulating_ticket_balance,
+        lent_from_reserve: state.lent_from_reserve,
+        staking_sol_cap: state.staking_sol_cap,
+        min_stake: state.stake_system.min_stake,
+        total_active_balance: state.validator_system.total_active_balance,
+        total_validator_score: state.validator_system.total_validator_score,
+        validator_count: state.validator_system.validator_list.count,
+        stake_count: state.stake_system.stake_list.count,
+        liq_pool_lp_supply: state.liq_pool.lp_supply,
+        liq_pool_lent_from_sol_leg: state.liq_pool.lent_from_sol_leg,
+        paused: state.paused,
+    }
+}
+
+pub fn msol_validator_rows(
+    state: &Pubkey,
+    validators: &[ValidatorRecord],
+) -> Vec<MsolValidatorRow> {
+    validators
+        .iter()
+        .enumerate()
+        .map(|(index, validator)| MsolValidatorRow {
+            vote_account: validator.validator_account.to_string(),
+            state: state.to_string(),
+            list_index: index as u32,
+            active_balance: validator.active_balance,
+            score: validator.score,
+            last_stake_delta_epoch: validator.last_stake_delta_epoch,
+        })
+        .collect()
+}
+
+pub fn msol_stake_rows(state: &Pubkey, stakes: &[StakeRecord]) -> Vec<MsolStakeRow> {
+    stakes
+        .iter()
+        .enumerate()
+        .map(|(index, stake)| MsolStakeRow {
+            stake_account: stake.stake_account.to_string(),
+            state: state.to_string(),
+            list_index: index as u32,
+            last_update_delegated_lamports: stake.last_update_delegated_lamports,
+            last_update_epoch: stake.last_update_epoch,
+            is_emergency_unstaking: stake.is_emergency_unstaking != 0,
+        })
+        .collect()
+}
@@ -1,99 +1,100 @@
-use crate::db_message::{DbMessage, OwnedSqlValue};
+use crate::db_message::DbMessage;
 use crate::processors::Processor;
 use crate::progress_bar::ProgressCounter;
-use crate::sql_params;
+use crate::schema::NATIVE_STAKE_ACCOUNT_TABLE;
+use crate::sql_row::{create_table, insert_row};
 use crate::stats::ProcessorCallback;
-use anyhow::anyhow;
 use async_trait::async_trait;
-use log::{debug, error};
-use rusqlite::ToSql;
-use snapshot_parser::stake_meta::generate_stake_meta_collection;
+use snapshot_parser::stake_meta::{
+    generate_stake_meta_collection, load_stake_meta_collection, StakeMeta,
+};
+use snapshot_parser_types::decoded_accounts::{NativeStakeRow, StakeAccountRow};
 use solana_program::pubkey::Pubkey;
 use solana_runtime::bank::Bank;
 use std::future::Future;
-use std::str::FromStr;
+use std::path::PathBuf;
 use std::string::ToString;
 use std::sync::Arc;
 use tokio::sync::mpsc::Sender;
-use tokio::sync::oneshot;
-
-pub const NATIVE_STAKE_ACCOUNT_TABLE: &str = "native_stake_accounts";
-pub const INSERT_NATIVE_STAKE_ACCOUNT_QUERY: &str = "INSERT OR REPLACE INTO native_stake_accounts (pubkey, withdraw_authority, amount) SELECT ?, ?, ?;";
-const MARINADE_NATIVE_STAKE_AUTHORITY_ADDR: &str = "stWirqFCf2Uts1JBL1Jsd3r6VBWhgnpdPxCTe1MFjrq";
+use tracing::{debug, error};
 
 pub struct ProcessorNativeStake {
     bank: Arc<Bank>,
     db_sender: Sender<DbMessage>,
     native_stake_counter: Arc<ProgressCounter>,
     native_stake_authority: Pubkey,
+    /// counter of the `stake_accounts` table, all stake accounts are written only when set
+    all_stake_accounts_counter: Option<Arc<ProgressCounter>>,
+    /// stake meta collection of the bank loaded instead of scanning the stake accounts
+    stake_meta_collection: Option<PathBuf>,
 }
 
 impl ProcessorNativeStake {
     pub async fn new(
         bank: Arc<Bank>,
         db_sender: Sender<DbMessage>,
+        native_stake_authority: Pubkey,
         native_stake_counter: Arc<ProgressCounter>,
+        all_stake_accounts_counter: Option<Arc<ProgressCounter>>,
+        stake_meta_collection: Option<PathBuf>,
     ) -> anyhow::Result<Self> {
-        let native_stake_authority: Pubkey = Pubkey::from_str(MARINADE_NATIVE_STAKE_AUTHORITY_ADDR)
-            .map_err(|e| {
-                anyhow!(
-                    "Cannot parse native staking authority address {}: {:?}",
-                    MARINADE_NATIVE_STAKE_AUTHORITY_ADDR,
-                    e
-                )
-            })?;
         let processor = Self {
             bank,
             db_sender,
             native_stake_counter,
             native_stake_authority,
+            all_stake_accounts_counter,
+            stake_meta_collection,
         };
-        processor.create_native_staking_table().await?;
+        create_table::<NativeStakeRow>(&processor.db_sender).await?;
+        if processor.all_stake_accounts_counter.is_some() {
+            create_table::<StakeAccountRow>(&processor.db_sender).await?;
+        }
         Ok(processor)
     }
 
-    async fn create_native_staking_table(&self) -> anyhow::Result<usize> {
-        let (response_tx, response_rx) = oneshot::channel();
-        self.db_sender
-            .send(DbMessage::ExecuteSpecial {
-                query: "CREATE TABLE native_stake_accounts (
-                    pubkey TEXT NOT NULL PRIMARY KEY,
-                    withdraw_authority TEXT NOT NULL,
-                    amount TEXT NOT NULL
-                );"
-                .to_string(),
-                params: vec![],
-                response: response_tx,
-            })
-            .await?;
-        response_rx.await?
-    }
-
     pub async fn process(&mut self) -> anyhow::Result<()> {
         debug!(
             "Loading staking accounts for native staking authority {} from bank...",
             self.native_stake_authority
         );
-        let stake_accounts = generate_stake_meta_collection(&self.bank)?;
+        let stake_accounts = match &self.stake_meta_collection {
+            Some(collection_path) => load_stake_meta_collection(&self.bank, collection_path)?,
+            None => generate_stake_meta_collection(&self.bank)?,
+        };
 
         for stake_meta in stake_accounts.stake_metas.iter() {
-            if stake_meta.stake_authority == self.native_stake_authority {
-                insert_native_staking(
+            if let Some(all_stake_accounts_counter) = &self.all_stake_accounts_counter {
+                insert_row(
                     &self.db_sender,
-                    &self.native_stake_counter,
-                    &stake_meta.pubkey,
-                    &stake_meta.withdraw_authority,
-                    stake_meta.active_delegation_lamports,
+                    all_stake_accounts_counter,
+                    &stake_account_row(stake_meta),
                 )
                 .await
                 .unwrap_or_else(|e| {
                     error!(
-                        "Failed to insert native stake {}: {:?}",
+                        "Failed to insert stake account {}: {:?}",
                         stake_meta.pubkey, e
                     );
                     0
                 });
             }
+            if stake_meta.stake_authority == self.native_stake_authority {
+                let row = NativeStakeRow {
+                    pubkey: stake_meta.pubkey.to_string(),
+                    withdraw_authority: stake_meta.withdraw_authority.to_string(),
+                    amount: stake_meta.active_delegation_lamports,
+                };
+                insert_row(&self.db_sender, &self.native_stake_counter, &row)
+                    .await
+                    .unwrap_or_else(|e| {
+                        error!(
+                            "Failed to insert native stake {}: {:?}",
+                            stake_meta.pubkey, e
+                        );
+                        0
+                    });
+            }
         }
         Ok(())
     }
@@ -118,26 +119,17 @@ impl ProcessorCallback for ProcessorNativeStake {
     }
 }
 
-pub async fn insert_native_staking(
-    db_sender: &Sender<DbMessage>,
-    progress_counter: &Arc<ProgressCounter>,
-    pubkey: &Pubkey,
-    authorized_withdrawer: &Pubkey,
-    delegated_stake: u64,
-) -> anyhow::Result<usize> {
-    let (response_tx, response_rx) = oneshot::channel();
-    let owned_params = sql_params![
-        pubkey.to_string(),
-        authorized_withdrawer.to_string(),
-        delegated_stake.to_string(),
-    ];
-    db_sender
-        .send(DbMessage::Execute {
-            query: INSERT_NATIVE_STAKE_ACCOUNT_QUERY.to_string(),
-            params: owned_params,
-            response: response_tx,
-        })
-        .await?;
-    progress_counter.inc();
-    response_rx.await?
+pub fn stake_account_row(stake_meta: &StakeMeta) -> StakeAccountRow {
+    StakeAccountRow {
+        pubkey: stake_meta.pubkey.to_string(),
+        balance_lamports: stake_meta.balance_lamports,
+        active_delegation_lamports: stake_meta.active_delegation_lamports,
+        activating_delegation_lamports: stake_meta.activating_delegation_lamports,
+        deactivating_delegation_lamports: stake_meta.deactivating_delegation_lamports,
+        activation_epoch: stake_meta.activation_epoch,
+        deactivation_epoch: stake_meta.deactivation_epoch,
+        validator: stake_meta.validator.map(|validator| validator.to_string()),
+        stake_authority: stake_meta.stake_authority.to_string(),
+        withdraw_authority: stake_meta.withdraw_authority.to_string(),
+    }
 }
@@ -1,19 +1,52 @@
-use log::{debug, info};
+use crate::checkpoint::ProcessorCompletion;
 use std::future::Future;
+use std::sync::Arc;
 use tokio::task::JoinHandle;
+use tracing::{debug, info, info_span, Instrument};
 
 pub trait Processor {
     fn name() -> &'static str;
     fn process(&mut self) -> impl Future<Output = anyhow::Result<()>> + Send;
+
+    /// Name the completion of the processor is recorded by, unique among the processors of a run.
+    fn completion_key(&self) -> String {
+        Self::name().to_string()
+    }
+
+    /// Releases the scans the processor shares through the [`crate::processors::ScanCache`],
+    /// called instead of [`Processor::process`] when the processor is skipped.
+    fn release_scans(&self) {}
 }
 
+/// With the `completion` set, a processor completed by a previous run is skipped
+/// and the completion is recorded once the processor finishes.
+/// The task runs in a `processor` span named by the completion key, the parent of its scans.
 pub async fn spawn_processor_task<P: Processor + Send + 'static>(
     mut processor: P,
+    completion: Option<Arc<ProcessorCompletion>>,
 ) -> anyhow::Result<JoinHandle<anyhow::Result<()>>> {
-    Ok(tokio::spawn(async move {
-        info!("{} processor task started...", P::name());
-        processor.process().await?;
-        debug!("{} processor task finished", P::name());
-        Ok(())
-    }))
+    let completion_key = processor.completion_key();
+    if let Some(completion) = &completion {
+        if completion.is_completed(&completion_key) {
+            info!(
+                "{} processor skipped, completed by the resumed run",
+                completion_key
+            );
+            processor.release_scans();
+            return Ok(tokio::spawn(async { Ok(()) }));
+        }
+    }
+    let span = info_span!("processor", name = %completion_key);
+    Ok(tokio::spawn(
+        async move {
+            info!("{} processor task started...", P::name());
+            processor.process().await?;
+            if let Some(completion) = completion {
+                completion.record(&completion_key).await?;
+            }
+            debug!("{} processor task finished", P::name());
+            Ok(())
+        }
+        .instrument(span),
+    ))
 }
@@ -0,0 +1,234 @@
+use crate::db_message::DbMessage;
+use crate::processors::scan_cache::ScanCache;
+use crate::processors::{is_token_account_data, unpack_token_account, Processor, TOKEN_PROGRAMS};
+use crate::progress_bar::ProgressCounter;
+use crate::schema::PROTOCOL_BALANCES_TABLE;
+use crate::sql_row::{create_table, insert_row};
+use crate::stats::ProcessorCallback;
+use async_trait::async_trait;
+use serde::Deserialize;
+use snapshot_parser::serde_serialize::pubkey_string_conversion;
+use snapshot_parser::utils::read_from_json_file;
+use snapshot_parser_types::decoded_accounts::ProtocolBalanceRow;
+use solana_program::pubkey::Pubkey;
+use solana_runtime::bank::Bank;
+use solana_sdk::account::ReadableAccount;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::future::Future;
+use std::path::Path;
+use std::str::FromStr;
+use std::sync::Arc;
+use tokio::sync::mpsc::Sender;
+use tracing::{debug, error, warn};
+
+/// Protocol accounts config file listing the accounts owned by the protocol whose balances are reported.
+///
+/// ```json
+/// {
+///   "accounts": [
+///     { "label": "reserve", "pubkey": "<account pubkey>" },
+///     { "label": "treasury", "pubkey": "<account pubkey>" }
+///   ],
+///   "mints": ["<mint pubkey>"]
+/// }
+/// ```
+#[derive(Debug, Clone, Deserialize)]
+pub struct ProtocolAccountsConfig {
+    pub accounts: Vec<ProtocolAccount>,
+    /// mints whose token accounts owned by the protocol accounts are summed
+    #[serde(default)]
+    pub mints: Vec<String>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct ProtocolAccount {
+    /// label the balances of the account are reported by (e.g., treasury), shared by several accounts
+    pub label: String,
+    #[serde(with = "pubkey_string_conversion")]
+    pub pubkey: Pubkey,
+}
+
+impl ProtocolAccountsConfig {
+    pub fn load(config_path: &Path) -> anyhow::Result<Self> {
+        let config: Self = read_from_json_file(&config_path).map_err(|e| {
+            anyhow::anyhow!(
+                "Could not load protocol accounts config {:?}: {}",
+                config_path,
+                e
+            )
+        })?;
+        if config.accounts.is_empty() {
+            anyhow::bail!("No accounts in protocol accounts config {:?}", config_path);
+        }
+        let mut pubkeys = HashSet::new();
+        for account in config.accounts.iter() {
+            if !pubkeys.insert(account.pubkey) {
+                anyhow::bail!(
+                    "Protocol account {} is configured more than once",
+                    account.pubkey
+                );
+            }
+        }
+        config.tracked_mints()?;
+        Ok(config)
+    }
+
+    pub fn tracked_mints(&self) -> anyhow::Result<HashSet<Pubkey>> {
+        self.mints
+            .iter()
+            .map(|mint| {
+                Pubkey::from_str(mint)
+                    .map_err(|e| anyhow::anyhow!("Invalid tracked mint {}: {}", mint, e))
+            })
+            .collect()
+    }
+}
+
+/// Token balance of the tracked mint held by a protocol account over its token accounts.
+#[derive(Default)]
+struct TokenBalance {
+    amount: u64,
+    token_accounts: u64,
+}
+
+/// Sums the lamports of the configured protocol accounts and the balances of the tracked mints
+/// in the token accounts they own into the protocol_balances table, one row per account and asset.
+pub struct ProcessorProtocolBalances {
+    bank: Arc<Bank>,
+    db_sender: Sender<DbMessage>,
+    accounts: Vec<ProtocolAccount>,
+    mints: Arc<HashSet<Pubkey>>,
+    counter: Arc<ProgressCounter>,
+    scan_cache: Arc<ScanCache>,
+}
+
+impl ProcessorProtocolBalances {
+    pub async fn new(
+        bank: Arc<Bank>,
+        db_sender: Sender<DbMessage>,
+        config: ProtocolAccountsConfig,
+        counter: Arc<ProgressCounter>,
+        scan_cache: Arc<ScanCache>,
+    ) -> anyhow::Result<Self> {
+        let mints = Arc::new(config.tracked_mints()?);
+        let processor = Self {
+            bank,
+            db_sender,
+            accounts: config.accounts,
+            mints,
+            counter,
+            scan_cache,
+        };
+        create_table::<ProtocolBalanceRow>(&processor.db_sender).await?;
+        Ok(processor)
+    }
+
+    /// Balances of the tracked mints per protocol account and mint.
+    async fn token_balances(&self) -> anyhow::Result<HashMap<(Pubkey, Pubkey), TokenBalance>> {
+        let mut balances: HashMap<(Pubkey, Pubkey), TokenBalance> = HashMap::new();
+        if self.mints.is_empty() {
+            return Ok(balances);
+        }
+        let owners: Arc<HashSet<Pubkey>> =
+            Arc::new(self.accounts.iter().map(|account| account.pubkey).collect());
+        for token_program in TOKEN_PROGRAMS {
+            let (owners, mints) = (owners.clone(), self.mints.clone());
+            let (mut balances_receiver, scan_handle) = self
+                .scan_cache
+                .stream_decoded_program_accounts(token_program, move |_, account| {
+                    if !is_token_account_data(account.data()) {
+                        return None;
+                    }
+                    let token = unpack_token_account(account.data()).ok()?;
+                    (owners.contains(&token.base.owner) && mints.contains(&token.base.mint))
+                        .then_some((token.base.owner, token.base.mint, token.base.amount))
+                });
+            while let Some((owner, mint, amount)) = balances_receiver.recv().await {
+                let balance = balances.entry((owner, mint)).or_default();
+                balance.amount += amount;
+                balance.token_accounts += 1;
+            }
+            scan_handle.await??;
+        }
+        Ok(balances)
+    }
+
+    pub async fn process(&mut self) -> anyhow::Result<()> {
+        debug!(
+            "Loading balances of {} protocol accounts and {} mints from bank...",
+            self.accounts.len(),
+            self.mints.len()
+        );
+        let token_balances = self.token_balances().await?;
+        let mints: BTreeMap<String, Pubkey> = self
+            .mints
+            .iter()
+            .map(|mint| (mint.to_string(), *mint))
+            .collect();
+
+        let mut rows = Vec::new();
+        for account in self.accounts.iter() {
+            let lamports = match self.bank.get_account(&account.pubkey) {
+                Some(bank_account) => bank_account.lamports(),
+                None => {
+                    warn!(
+                        "Protocol account {} ({}) does not exist in the bank",
+                        account.pubkey, account.label
+                    );
+                    0
+                }
+            };
+            rows.push(ProtocolBalanceRow {
+                id: format!("{}:lamports", account.pubkey),
+                label: account.label.clone(),
+                account: account.pubkey.to_string(),
+                mint: None,
+                amount: lamports,
+                token_accounts: 0,
+            });
+            // every tracked mint gets a row, zero balances included, so the report has the same rows every epoch
+            for (mint_string, mint) in mints.iter() {
+                let balance = token_balances.get(&(account.pubkey, *mint));
+                rows.push(ProtocolBalanceRow {
+                    id: format!("{}:{}", account.pubkey, mint_string),
+                    label: account.label.clone(),
+                    account: account.pubkey.to_string(),
+                    mint: Some(mint_string.clone()),
+                    amount: balance.map_or(0, |balance| balance.amount),
+                    token_accounts: balance.map_or(0, |balance| balance.token_accounts),
+                });
+            }
+        }
+
+        for row in rows {
+            insert_row(&self.db_sender, &self.counter, &row)
+                .await
+                .unwrap_or_else(|e| {
+                    error!("Failed to insert protocol balance {}: {:?}", row.id, e);
+                    0
+                });
+        }
+        Ok(())
+    }
+}
+
+impl Processor for ProcessorProtocolBalances {
+    fn name() -> &'static str {
+        "ProtocolBalances"
+    }
+    fn process(&mut self) -> impl Future<Output = anyhow::Result<()>> + Send {
+        self.process()
+    }
+    fn release_scans(&self) {
+        if !self.mints.is_empty() {
+            self.scan_cache.release(TOKEN_PROGRAMS);
+        }
+    }
+}
+
+#[async_trait]
+impl ProcessorCallback for ProcessorProtocolBalances {
+    async fn get_count(&self) -> (String, u64) {
+        (PROTOCOL_BALANCES_TABLE.to_string(), self.counter.get())
+    }
+}
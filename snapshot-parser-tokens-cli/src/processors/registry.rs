@@ -0,0 +1,167 @@
+#[cfg(feature = "metaplex")]
+use crate::processors::ProcessorTokenMetadata;
+use crate::processors::{
+    Processor, ProcessorAccountOwners, ProcessorAllowlist, ProcessorMint, ProcessorMsol,
+    ProcessorNativeStake, ProcessorProtocolBalances, ProcessorStakePool, ProcessorSupply,
+    ProcessorToken, ProcessorVeMnde, ProcessorVoteAccounts,
+};
+use crate::schema::SCHEMA_VERSION;
+use crate::sql_row::SqlRow;
+use serde::Serialize;
+#[cfg(feature = "metaplex")]
+use snapshot_parser_types::decoded_accounts::SymbolCollisionRow;
+use snapshot_parser_types::decoded_accounts::{
+    AccountRow, MintAuthorityChangeRow, MintRow, MissingMintRow, MsolStakeRow, MsolStateRow,
+    MsolValidatorRow, NativeStakeRow, ProtocolBalanceRow, StakeAccountRow, StakePoolRow,
+    StakePoolValidatorRow, SupplyStatsRow, TokenMetadataRow, TokenRow, UnknownMintRow,
+    VeMndeDepositRow, VeMndeRow, VoteAccountEpochCreditsRow, VoteAccountRow,
+};
+
+#[derive(Debug, Serialize)]
+pub struct ColumnDescription {
+    pub name: &'static str,
+    pub sql_type: &'static str,
+}
+
+#[derive(Debug, Serialize)]
+pub struct TableDescription {
+    pub name: &'static str,
+    pub version: u32,
+    pub columns: Vec<ColumnDescription>,
+}
+
+impl TableDescription {
+    fn of<R: SqlRow>() -> Self {
+        Self {
+            name: R::TABLE,
+            version: R::VERSION,
+            columns: R::COLUMNS
+                .iter()
+                .map(|(name, sql_type)| ColumnDescription { name, sql_type })
+                .collect(),
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct ProcessorDescription {
+    pub name: &'static str,
+    pub tables: Vec<TableDescription>,
+    /// fields of the filters file the processor reads
+    pub required_filters: Vec<&'static str>,
+}
+
+impl ProcessorDescription {
+    fn of<P: Processor>(
+        tables: Vec<TableDescription>,
+        required_filters: Vec<&'static str>,
+    ) -> Self {
+        Self {
+            name: P::name(),
+            tables,
+            required_filters,
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct ProcessorRegistry {
+    pub schema_version: u32,
+    pub processors: Vec<ProcessorDescription>,
+}
+
+/// Describes all processors run by the tokens CLI and the tables they write,
+/// the token metadata tables are written only when built with the `metaplex` feature,
+/// the protocol balances only with a protocol accounts config and the supply stats only with a supply config.
+pub fn processor_registry() -> ProcessorRegistry {
+    let mut processors = vec![
+        ProcessorDescription::of::<ProcessorAccountOwners>(
+            vec![TableDescription::of::<AccountRow>()],
+            vec!["account_owners"],
+        ),
+        ProcessorDescription::of::<ProcessorToken>(
+            vec![
+                TableDescription::of::<AccountRow>(),
+                TableDescription::of::<TokenRow>(),
+            ],
+            vec!["account_mints"],
+        ),
+        ProcessorDescription::of::<ProcessorMint>(
+            vec![
+                TableDescription::of::<MintRow>(),
+                TableDescription::of::<MintAuthorityChangeRow>(),
+                TableDescription::of::<MissingMintRow>(),
+            ],
+            vec!["account_mints"],
+        ),
+        ProcessorDescription::of::<ProcessorVeMnde>(
+            vec![
+                TableDescription::of::<VeMndeRow>(),
+                TableDescription::of::<VeMndeDepositRow>(),
+            ],
+            vec!["vsr_registrar_data"],
+        ),
+        ProcessorDescription::of::<ProcessorNativeStake>(
+            vec![
+                TableDescription::of::<NativeStakeRow>(),
+                TableDescription::of::<StakeAccountRow>(),
+            ],
+            vec![],
+        ),
+        ProcessorDescription::of::<ProcessorStakePool>(
+            vec![
+                TableDescription::of::<StakePoolRow>(),
+                TableDescription::of::<StakePoolValidatorRow>(),
+            ],
+            vec![],
+        ),
+        ProcessorDescription::of::<ProcessorMsol>(
+            vec![
+                TableDescription::of::<MsolStateRow>(),
+                TableDescription::of::<MsolValidatorRow>(),
+                TableDescription::of::<MsolStakeRow>(),
+            ],
+            vec![],
+        ),
+        ProcessorDescription::of::<ProcessorVoteAccounts>(
+            vec![
+                TableDescription::of::<VoteAccountRow>(),
+                TableDescription::of::<VoteAccountEpochCreditsRow>(),
+            ],
+            vec![],
+        ),
+        ProcessorDescription::of::<ProcessorProtocolBalances>(
+            vec![TableDescription::of::<ProtocolBalanceRow>()],
+            vec![],
+        ),
+        ProcessorDescription::of::<ProcessorSupply>(
+            vec![TableDescription::of::<SupplyStatsRow>()],
+            vec![],
+        ),
+    ];
+    #[cfg(feature = "metaplex")]
+    processors.push(ProcessorDescription::of::<ProcessorTokenMetadata>(
+        vec![
+            TableDescription::of::<TokenMetadataRow>(),
+            TableDescription::of::<SymbolCollisionRow>(),
+        ],
+        vec!["account_mints"],
+    ));
+    let allowlist_tables = [
+        TableDescription::of::<AccountRow>(),
+        TableDescription::of::<TokenRow>(),
+        TableDescription::of::<MintRow>(),
+        TableDescription::of::<UnknownMintRow>(),
+    ]
+    .into_iter()
+    .chain(cfg!(feature = "metaplex").then(TableDescription::of::<TokenMetadataRow>))
+    .collect();
+    processors.push(ProcessorDescription::of::<ProcessorAllowlist>(
+        allowlist_tables,
+        vec!["account_pubkeys"],
+    ));
+    ProcessorRegistry {
+        schema_version: SCHEMA_VERSION,
+        processors,
+    }
+}
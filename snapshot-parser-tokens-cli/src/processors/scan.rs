@@ -0,0 +1,163 @@
+use solana_accounts_db::accounts_index::ScanConfig;
+use solana_program::pubkey::Pubkey;
+use solana_runtime::bank::Bank;
+use solana_sdk::account::{AccountSharedData, ReadableAccount};
+use std::cell::Cell;
+use std::ops::Bound;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use tokio::sync::mpsc::{self, Receiver, Sender};
+use tokio::task::JoinHandle;
+use tracing::{field, info_span, Instrument, Span};
+
+/// Number of scanned accounts buffered between the accounts-db scan and the processor.
+const SCAN_CHANNEL_SIZE: usize = 10_000;
+
+/// Scans the accounts owned by the program on blocking threads and streams those passing the filter
+/// through a bounded channel, so the processor inserts them while scanning instead of collecting
+/// all of them into memory first. The scan waits while the channel is full
+/// and it is aborted when the receiver is dropped.
+pub fn stream_program_accounts<F>(
+    bank: Arc<Bank>,
+    program_id: Pubkey,
+    scan_threads: usize,
+    filter: F,
+) -> (
+    Receiver<(Pubkey, AccountSharedData)>,
+    JoinHandle<anyhow::Result<()>>,
+)
+where
+    F: Fn(&AccountSharedData) -> bool + Send + Sync + 'static,
+{
+    stream_decoded_program_accounts(bank, program_id, scan_threads, move |pubkey, account| {
+        filter(&account).then_some((*pubkey, account))
+    })
+}
+
+/// Same as [`stream_program_accounts`], decoding the accounts on the scan threads;
+/// accounts decoded to `None` are skipped.
+///
+/// With more than one scan thread, the pubkey space is split into ranges scanned in parallel
+/// by a pool of workers, all sending into the same channel, so the accounts arrive in no particular order.
+/// The scan runs in a `scan` span with the program id and the number of the program accounts scanned.
+pub fn stream_decoded_program_accounts<T, D>(
+    bank: Arc<Bank>,
+    program_id: Pubkey,
+    scan_threads: usize,
+    decode: D,
+) -> (Receiver<T>, JoinHandle<anyhow::Result<()>>)
+where
+    T: Send + 'static,
+    D: Fn(&Pubkey, AccountSharedData) -> Option<T> + Send + Sync + 'static,
+{
+    let (sender, receiver) = mpsc::channel(SCAN_CHANNEL_SIZE);
+    let decode = Arc::new(decode);
+    let span = info_span!(
+        "scan",
+        program_id = %program_id,
+        scan_threads,
+        accounts_scanned = field::Empty
+    );
+    let scan_handle = tokio::spawn(
+        async move {
+            let abort = Arc::new(AtomicBool::new(false));
+            let workers: Vec<_> = pubkey_ranges(scan_threads)
+                .into_iter()
+                .map(|range| {
+                    let (bank, sender, decode, abort) =
+                        (bank.clone(), sender.clone(), decode.clone(), abort.clone());
+                    let span = Span::current();
+                    tokio::task::spawn_blocking(move || {
+                        let _entered = span.enter();
+                        scan_range(&bank, program_id, range, &sender, decode.as_ref(), abort)
+                    })
+                })
+                .collect();
+            drop(sender);
+            let mut accounts_scanned = 0u64;
+            for worker in workers {
+                accounts_scanned += worker.await??;
+            }
+            Span::current().record("accounts_scanned", accounts_scanned);
+            Ok(())
+        }
+        .instrument(span),
+    );
+    (receiver, scan_handle)
+}
+
+/// Splits the pubkey space into `count` contiguous ranges by the first two bytes of the pubkey.
+fn pubkey_ranges(count: usize) -> Vec<(Bound<Pubkey>, Bound<Pubkey>)> {
+    const PREFIXES: usize = 1 << 16;
+    let count = count.clamp(1, PREFIXES);
+    let boundary = |index: usize| {
+        let prefix = (index * PREFIXES / count) as u16;
+        let mut bytes = [0u8; 32];
+        bytes[..2].copy_from_slice(&prefix.to_be_bytes());
+        Pubkey::new_from_array(bytes)
+    };
+    (0..count)
+        .map(|index| {
+            let start = match index {
+                0 => Bound::Unbounded,
+                _ => Bound::Included(boundary(index)),
+            };
+            let end = match index + 1 {
+                next if next == count => Bound::Unbounded,
+                next => Bound::Excluded(boundary(next)),
+            };
+            (start, end)
+        })
+        .collect()
+}
+
+fn scan_range<T, D>(
+    bank: &Bank,
+    program_id: Pubkey,
+    range: (Bound<Pubkey>, Bound<Pubkey>),
+    sender: &Sender<T>,
+    decode: &D,
+    abort: Arc<AtomicBool>,
+) -> anyhow::Result<u64>
+where
+    D: Fn(&Pubkey, AccountSharedData) -> Option<T>,
+{
+    let accounts_scanned = Cell::new(0u64);
+    let scan_func = |scanned_account: Option<(&Pubkey, AccountSharedData, _)>| {
+        let Some((pubkey, account, _)) = scanned_account else {
+            return;
+        };
+        // zero lamport accounts are deleted, the same as in `Bank::get_program_accounts`
+        if account.lamports() == 0 || account.owner() != &program_id {
+            return;
+        }
+        accounts_scanned.set(accounts_scanned.get() + 1);
+        let Some(decoded) = decode(pubkey, account) else {
+            return;
+        };
+        if sender.blocking_send(decoded).is_err() {
+            abort.store(true, Ordering::Relaxed);
+        }
+    };
+    let config = ScanConfig {
+        abort: Some(abort.clone()),
+        collect_all_unsorted: true,
+    };
+    let accounts_db = &bank.rc.accounts.accounts_db;
+    if matches!(range, (Bound::Unbounded, Bound::Unbounded)) {
+        accounts_db
+            .scan_accounts(&bank.ancestors, bank.bank_id(), scan_func, &config)
+            .map_err(|e| {
+                anyhow::anyhow!("Scan of program {} accounts failed: {:?}", program_id, e)
+            })?;
+    } else {
+        accounts_db.range_scan_accounts(
+            "stream_program_accounts",
+            &bank.ancestors,
+            range,
+            &config,
+            scan_func,
+        );
+    }
+    Ok(accounts_scanned.get())
+}
@@ -0,0 +1,304 @@
+use crate::processors::scan::{stream_decoded_program_accounts, stream_program_accounts};
+use crate::processors::spill::{account_size, SpillConfig, SpillFile, SpillWriter};
+use crate::sampling::Sampling;
+use solana_program::pubkey::Pubkey;
+use solana_runtime::bank::Bank;
+use solana_sdk::account::AccountSharedData;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use tokio::sync::mpsc::{self, Receiver, Sender};
+use tokio::sync::OnceCell;
+use tokio::task::JoinHandle;
+use tracing::{debug, info};
+
+/// Number of cached accounts buffered between the replay and the processor.
+const REPLAY_CHANNEL_SIZE: usize = 10_000;
+
+struct CachedAccounts {
+    in_memory: Vec<(Pubkey, AccountSharedData)>,
+    in_memory_bytes: usize,
+    /// accounts of the program over the memory cap
+    spilled: Option<SpillFile>,
+    used_bytes: Arc<AtomicUsize>,
+}
+
+impl Drop for CachedAccounts {
+    fn drop(&mut self) {
+        self.used_bytes
+            .fetch_sub(self.in_memory_bytes, Ordering::Relaxed);
+    }
+}
+
+struct CachedProgram {
+    accounts: OnceCell<Arc<CachedAccounts>>,
+    /// processors yet to replay the accounts, the entry is dropped after the last one
+    remaining_consumers: AtomicUsize,
+}
+
+/// Source of the program accounts scanned by the processors.
+///
+/// The programs requested by more than one processor (e.g., spl-token listed in the `account_owners` filter
+/// and scanned by the token processor) are scanned once. Their accounts are kept in memory up to the memory cap
+/// and the rest spill to a temp file, then they are replayed to each of the processors.
+/// The cached accounts are released once the last of the processors has replayed them.
+/// All other programs are streamed from the bank directly, see [`stream_program_accounts`].
+pub struct ScanCache {
+    bank: Arc<Bank>,
+    scan_threads: usize,
+    config: Option<SpillConfig>,
+    programs: Mutex<HashMap<Pubkey, Arc<CachedProgram>>>,
+    used_bytes: Arc<AtomicUsize>,
+    /// items streamed per scan at most, see [`crate::pipeline::ProcessorOptions::limit_rows_per_table`]
+    sample_limit: Option<usize>,
+    /// accounts out of the sample are skipped by every scan, see [`Sampling::includes`]
+    sampling: Option<Arc<Sampling>>,
+}
+
+impl ScanCache {
+    /// Cache of the programs requested more than once among `requested_programs`,
+    /// one item per processor scanning the program; with no `config`, nothing is cached.
+    /// With a `sample_limit`, nothing is cached either and every scan stops after streaming that many items.
+    /// With a `sampling`, only the accounts in the sample are streamed.
+    pub fn new(
+        bank: Arc<Bank>,
+        scan_threads: usize,
+        config: Option<SpillConfig>,
+        requested_programs: impl IntoIterator<Item = Pubkey>,
+        sample_limit: Option<usize>,
+        sampling: Option<Sampling>,
+    ) -> Self {
+        let mut consumers: HashMap<Pubkey, usize> = HashMap::new();
+        for program in requested_programs {
+            *consumers.entry(program).or_default() += 1;
+        }
+        // a sampled scan stops early, there is no full scan to share
+        let programs = if config.is_some() && sample_limit.is_none() {
+            consumers
+                .into_iter()
+                .filter(|(_, count)| *count > 1)
+                .map(|(program, count)| {
+                    info!(
+                        "Scan cache: accounts of program {} are shared by {} processors",
+                        program, count
+                    );
+                    (
+                        program,
+                        Arc::new(CachedProgram {
+                            accounts: OnceCell::new(),
+                            remaining_consumers: AtomicUsize::new(count),
+                        }),
+                    )
+                })
+                .collect()
+        } else {
+            HashMap::new()
+        };
+        Self {
+            bank,
+            scan_threads,
+            config,
+            programs: Mutex::new(programs),
+            used_bytes: Arc::new(AtomicUsize::new(0)),
+            sample_limit,
+            sampling: sampling.map(Arc::new),
+        }
+    }
+
+    /// Streams the program accounts passing the filter, see [`stream_program_accounts`].
+    pub fn stream_program_accounts<F>(
+        self: &Arc<Self>,
+        program_id: Pubkey,
+        filter: F,
+    ) -> (
+        Receiver<(Pubkey, AccountSharedData)>,
+        JoinHandle<anyhow::Result<()>>,
+    )
+    where
+        F: Fn(&AccountSharedData) -> bool + Send + Sync + 'static,
+    {
+        self.stream_decoded_program_accounts(program_id, move |pubkey, account| {
+            filter(&account).then_some((*pubkey, account))
+        })
+    }
+
+    /// Streams the decoded program accounts, see [`stream_decoded_program_accounts`].
+    /// The accounts of a cached program are decoded on a single blocking thread.
+    pub fn stream_decoded_program_accounts<T, D>(
+        self: &Arc<Self>,
+        program_id: Pubkey,
+        decode: D,
+    ) -> (Receiver<T>, JoinHandle<anyhow::Result<()>>)
+    where
+        T: Send + 'static,
+        D: Fn(&Pubkey, AccountSharedData) -> Option<T> + Send + Sync + 'static,
+    {
+        let sampling = self.sampling.clone();
+        let decode = move |pubkey: &Pubkey, account: AccountSharedData| match &sampling {
+            Some(sampling) if !sampling.includes(pubkey) => None,
+            _ => decode(pubkey, account),
+        };
+        let Some(cached_program) = self.cached_program(&program_id) else {
+            let stream = stream_decoded_program_accounts(
+                self.bank.clone(),
+                program_id,
+                self.scan_threads,
+                decode,
+            );
+            return match self.sample_limit {
+                Some(limit) => take_stream(stream, limit),
+                None => stream,
+            };
+        };
+        let (sender, receiver) = mpsc::channel(REPLAY_CHANNEL_SIZE);
+        let cache = self.clone();
+        let replay_handle = tokio::spawn(async move {
+            let accounts = cached_program
+                .accounts
+                .get_or_try_init(|| cache.scan(program_id))
+                .await?
+                .clone();
+            let replayed =
+                tokio::task::spawn_blocking(move || replay(&accounts, &sender, &decode)).await?;
+            cache.release_consumer(&program_id, &cached_program);
+            replayed
+        });
+        (receiver, replay_handle)
+    }
+
+    /// Releases the programs a processor does not scan after all, e.g., one completed by the resumed run,
+    /// so their cached accounts are dropped once the other processors have replayed them.
+    pub fn release(&self, program_ids: impl IntoIterator<Item = Pubkey>) {
+        for program_id in program_ids {
+            if let Some(cached_program) = self.cached_program(&program_id) {
+                self.release_consumer(&program_id, &cached_program);
+            }
+        }
+    }
+
+    fn release_consumer(&self, program_id: &Pubkey, cached_program: &CachedProgram) {
+        let previous = cached_program.remaining_consumers.fetch_update(
+            Ordering::AcqRel,
+            Ordering::Acquire,
+            |remaining| remaining.checked_sub(1),
+        );
+        if previous == Ok(1) {
+            debug!("Scan cache: releasing accounts of program {}", program_id);
+            self.programs
+                .lock()
+                .expect("scan cache lock poisoned")
+                .remove(program_id);
+        }
+    }
+
+    fn cached_program(&self, program_id: &Pubkey) -> Option<Arc<CachedProgram>> {
+        self.programs
+            .lock()
+            .expect("scan cache lock poisoned")
+            .get(program_id)
+            .cloned()
+    }
+
+    /// Scans all accounts of the program into memory, spilling those over the memory cap to a temp file.
+    async fn scan(&self, program_id: Pubkey) -> anyhow::Result<Arc<CachedAccounts>> {
+        let config = self
+            .config
+            .as_ref()
+            .expect("only programs of a configured cache are cached");
+        debug!("Scan cache: scanning program {}", program_id);
+        let (mut accounts_receiver, scan_handle) =
+            stream_program_accounts(self.bank.clone(), program_id, self.scan_threads, |_| true);
+        let mut cached = CachedAccounts {
+            in_memory: Vec::new(),
+            in_memory_bytes: 0,
+            spilled: None,
+            used_bytes: self.used_bytes.clone(),
+        };
+        let mut spill_writer: Option<SpillWriter> = None;
+        while let Some((pubkey, account)) = accounts_receiver.recv().await {
+            let size = account_size(&account);
+            let reserved = self
+                .used_bytes
+                .fetch_update(Ordering::AcqRel, Ordering::Acquire, |used| {
+                    (used + size <= config.memory_cap_bytes).then_some(used + size)
+                })
+                .is_ok();
+            if reserved {
+                cached.in_memory_bytes += size;
+                cached.in_memory.push((pubkey, account));
+                continue;
+            }
+            if spill_writer.is_none() {
+                info!(
+                    "Scan cache: memory cap reached, spilling accounts of program {}",
+                    program_id
+                );
+                spill_writer = Some(SpillWriter::create(
+                    config,
+                    &format!("scan-cache-{}", program_id),
+                )?);
+            }
+            spill_writer
+                .as_mut()
+                .expect("spill writer was created")
+                .write(&pubkey, account)?;
+        }
+        scan_handle.await??;
+        cached.spilled = spill_writer.map(SpillWriter::finish).transpose()?;
+        info!(
+            "Scan cache: program {} cached {} accounts in memory ({} bytes), {} spilled",
+            program_id,
+            cached.in_memory.len(),
+            cached.in_memory_bytes,
+            cached.spilled.as_ref().map_or(0, SpillFile::count)
+        );
+        Ok(Arc::new(cached))
+    }
+}
+
+/// Forwards the first `limit` items of the stream, then drops its receiver, which aborts the scan.
+fn take_stream<T: Send + 'static>(
+    (mut receiver, scan_handle): (Receiver<T>, JoinHandle<anyhow::Result<()>>),
+    limit: usize,
+) -> (Receiver<T>, JoinHandle<anyhow::Result<()>>) {
+    let (sender, limited_receiver) = mpsc::channel(REPLAY_CHANNEL_SIZE);
+    let limited_handle = tokio::spawn(async move {
+        for _ in 0..limit {
+            let Some(item) = receiver.recv().await else {
+                break;
+            };
+            if sender.send(item).await.is_err() {
+                break;
+            }
+        }
+        drop(receiver);
+        scan_handle.await?
+    });
+    (limited_receiver, limited_handle)
+}
+
+/// Sends the decoded cached accounts, the in-memory ones first; stops when the receiver is dropped.
+fn replay<T, D>(accounts: &CachedAccounts, sender: &Sender<T>, decode: &D) -> anyhow::Result<()>
+where
+    D: Fn(&Pubkey, AccountSharedData) -> Option<T>,
+{
+    for (pubkey, account) in accounts.in_memory.iter() {
+        if let Some(decoded) = decode(pubkey, account.clone()) {
+            if sender.blocking_send(decoded).is_err() {
+                return Ok(());
+            }
+        }
+    }
+    let Some(spilled) = &accounts.spilled else {
+        return Ok(());
+    };
+    for spilled_account in spilled.reader()? {
+        let (pubkey, account) = spilled_account?;
+        if let Some(decoded) = decode(&pubkey, account) {
+            if sender.blocking_send(decoded).is_err() {
+                return Ok(());
+            }
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,282 @@
+use snapshot_parser::temp_file::TempFileGuard;
+use solana_program::pubkey::Pubkey;
+use solana_sdk::account::{Account, AccountSharedData, ReadableAccount};
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+use std::fs::File;
+use std::io::{BufReader, BufWriter, Write};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use tracing::{debug, info};
+
+/// Distinguishes the spill files of one run.
+static SPILL_FILE_SEQUENCE: AtomicUsize = AtomicUsize::new(0);
+
+/// Memory cap of buffered accounts and the directory the accounts over the cap are written to.
+#[derive(Clone, Debug)]
+pub struct SpillConfig {
+    /// bytes of account data (and per-account overhead) kept in memory
+    pub memory_cap_bytes: usize,
+    pub spill_dir: PathBuf,
+}
+
+/// Memory the buffered account takes, counted against [`SpillConfig::memory_cap_bytes`].
+pub fn account_size(account: &AccountSharedData) -> usize {
+    std::mem::size_of::<(Pubkey, AccountSharedData)>() + account.data().len()
+}
+
+/// Temp file of accounts written as bincode records, deleted when dropped.
+pub struct SpillFile {
+    path: PathBuf,
+    count: u64,
+    _guard: TempFileGuard,
+}
+
+impl SpillFile {
+    pub fn count(&self) -> u64 {
+        self.count
+    }
+
+    /// Reads the accounts back in the order they were written.
+    pub fn reader(&self) -> anyhow::Result<SpillReader> {
+        Ok(SpillReader {
+            reader: BufReader::new(File::open(&self.path)?),
+            remaining: self.count,
+        })
+    }
+}
+
+pub struct SpillWriter {
+    writer: BufWriter<File>,
+    file: SpillFile,
+}
+
+impl SpillWriter {
+    /// Creates the spill file `_spill-<name>-<pid>-<sequence>.bin.tmp` in the spill directory.
+    pub fn create(config: &SpillConfig, name: &str) -> anyhow::Result<Self> {
+        let path = config.spill_dir.join(format!(
+            "_spill-{}-{}-{}.bin.tmp",
+            name,
+            std::process::id(),
+            SPILL_FILE_SEQUENCE.fetch_add(1, Ordering::Relaxed)
+        ));
+        debug!("Spilling {} accounts to {:?}", name, path);
+        let writer = BufWriter::new(File::create(&path)?);
+        Ok(Self {
+            writer,
+            file: SpillFile {
+                _guard: TempFileGuard::new(path.clone()),
+                path,
+                count: 0,
+            },
+        })
+    }
+
+    pub fn write(&mut self, pubkey: &Pubkey, account: AccountSharedData) -> anyhow::Result<()> {
+        bincode::serialize_into(&mut self.writer, &(pubkey, Account::from(account)))?;
+        self.file.count += 1;
+        Ok(())
+    }
+
+    pub fn finish(mut self) -> anyhow::Result<SpillFile> {
+        self.writer.flush()?;
+        Ok(self.file)
+    }
+}
+
+pub struct SpillReader {
+    reader: BufReader<File>,
+    remaining: u64,
+}
+
+impl Iterator for SpillReader {
+    type Item = anyhow::Result<(Pubkey, AccountSharedData)>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.remaining == 0 {
+            return None;
+        }
+        self.remaining -= 1;
+        Some(
+            bincode::deserialize_from::<_, (Pubkey, Account)>(&mut self.reader)
+                .map(|(pubkey, account)| (pubkey, AccountSharedData::from(account)))
+                .map_err(anyhow::Error::from),
+        )
+    }
+}
+
+/// Buffer of accounts read back sorted by pubkey, bounding the resident memory of the largest scans.
+///
+/// The accounts are kept in memory up to the memory cap, then they are sorted and written to a spill file
+/// as one sorted run; [`SortedAccountBuffer::into_sorted`] merges the runs with the accounts left in memory.
+/// Without a config all accounts stay in memory.
+pub struct SortedAccountBuffer {
+    name: String,
+    config: Option<SpillConfig>,
+    in_memory: Vec<(Pubkey, AccountSharedData)>,
+    in_memory_bytes: usize,
+    runs: Vec<SpillFile>,
+    len: u64,
+}
+
+impl SortedAccountBuffer {
+    pub fn new(name: &str, config: Option<SpillConfig>) -> Self {
+        Self {
+            name: name.to_string(),
+            config,
+            in_memory: Vec::new(),
+            in_memory_bytes: 0,
+            runs: Vec::new(),
+            len: 0,
+        }
+    }
+
+    pub fn len(&self) -> u64 {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub fn push(&mut self, pubkey: Pubkey, account: AccountSharedData) -> anyhow::Result<()> {
+        self.in_memory_bytes += account_size(&account);
+        self.in_memory.push((pubkey, account));
+        self.len += 1;
+        if let Some(config) = &self.config {
+            if self.in_memory_bytes > config.memory_cap_bytes {
+                let config = config.clone();
+                self.spill_run(&config)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn spill_run(&mut self, config: &SpillConfig) -> anyhow::Result<()> {
+        self.in_memory.sort_unstable_by_key(|(pubkey, _)| *pubkey);
+        let mut writer = SpillWriter::create(config, &self.name)?;
+        for (pubkey, account) in self.in_memory.drain(..) {
+            writer.write(&pubkey, account)?;
+        }
+        self.runs.push(writer.finish()?);
+        info!(
+            "Buffer of {} accounts reached the memory cap, spilled run {} of {} bytes",
+            self.name,
+            self.runs.len(),
+            self.in_memory_bytes
+        );
+        self.in_memory_bytes = 0;
+        Ok(())
+    }
+
+    /// All buffered accounts in the ascending order of their pubkeys.
+    pub fn into_sorted(mut self) -> anyhow::Result<SortedAccounts> {
+        self.in_memory.sort_unstable_by_key(|(pubkey, _)| *pubkey);
+        let mut sources: Vec<AccountSource> = vec![Box::new(
+            self.in_memory.into_iter().map(Ok::<_, anyhow::Error>),
+        )];
+        for run in self.runs.iter() {
+            sources.push(Box::new(run.reader()?));
+        }
+        let mut sorted = SortedAccounts {
+            heads: BinaryHeap::with_capacity(sources.len()),
+            accounts: (0..sources.len()).map(|_| None).collect(),
+            sources,
+            _runs: self.runs,
+        };
+        for source in 0..sorted.sources.len() {
+            sorted.advance(source)?;
+        }
+        Ok(sorted)
+    }
+}
+
+type AccountSource = Box<dyn Iterator<Item = anyhow::Result<(Pubkey, AccountSharedData)>> + Send>;
+
+/// K-way merge of the sorted runs of a [`SortedAccountBuffer`].
+pub struct SortedAccounts {
+    sources: Vec<AccountSource>,
+    /// the smallest not yet returned pubkey of every source
+    heads: BinaryHeap<Reverse<(Pubkey, usize)>>,
+    accounts: Vec<Option<AccountSharedData>>,
+    _runs: Vec<SpillFile>,
+}
+
+impl SortedAccounts {
+    fn advance(&mut self, source: usize) -> anyhow::Result<()> {
+        if let Some(next) = self.sources[source].next() {
+            let (pubkey, account) = next?;
+            self.heads.push(Reverse((pubkey, source)));
+            self.accounts[source] = Some(account);
+        }
+        Ok(())
+    }
+}
+
+impl Iterator for SortedAccounts {
+    type Item = anyhow::Result<(Pubkey, AccountSharedData)>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let Reverse((pubkey, source)) = self.heads.pop()?;
+        let account = self.accounts[source]
+            .take()
+            .expect("every head has its account");
+        Some(self.advance(source).map(|_| (pubkey, account)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct TempDir(PathBuf);
+
+    impl Drop for TempDir {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_dir_all(&self.0);
+        }
+    }
+
+    fn account(index: u64) -> AccountSharedData {
+        AccountSharedData::from(Account {
+            lamports: index,
+            data: index.to_le_bytes().repeat(4),
+            ..Account::default()
+        })
+    }
+
+    #[test]
+    fn sorted_accounts_merge_spilled_runs() {
+        let dir = TempDir(std::env::temp_dir().join(format!("spill_{}", std::process::id())));
+        std::fs::create_dir_all(&dir.0).unwrap();
+        let config = SpillConfig {
+            // spills a run of every fourth account
+            memory_cap_bytes: 3 * account_size(&account(0)),
+            spill_dir: dir.0.clone(),
+        };
+        let mut buffer = SortedAccountBuffer::new("test", Some(config));
+        let count = 50;
+        for index in 0..count {
+            // pubkeys in an arbitrary order
+            let pubkey =
+                Pubkey::new_from_array(solana_program::hash::hash(&index.to_le_bytes()).to_bytes());
+            buffer.push(pubkey, account(index)).unwrap();
+        }
+        assert!(buffer.runs.len() > 1);
+        assert!(!buffer.in_memory.is_empty());
+        assert_eq!(buffer.len(), count);
+
+        let mut seen = 0;
+        let mut previous: Option<Pubkey> = None;
+        for item in buffer.into_sorted().unwrap() {
+            let (pubkey, account) = item.unwrap();
+            assert!(previous.map_or(true, |previous| previous < pubkey));
+            assert_eq!(account.data(), account.lamports().to_le_bytes().repeat(4));
+            previous = Some(pubkey);
+            seen += 1;
+        }
+        assert_eq!(seen, count);
+        // the spill files are removed with the merged accounts
+        assert_eq!(std::fs::read_dir(&dir.0).unwrap().count(), 0);
+    }
+}
@@ -0,0 +1,106 @@
+use mpl_token_metadata::accounts::Metadata;
+use snapshot_parser::program_registry::{builtin_address, TOKEN_METADATA_PROGRAM};
+use solana_program::pubkey::Pubkey;
+use solana_runtime::bank::Bank;
+use solana_sdk::account::ReadableAccount;
+use std::collections::{HashMap, HashSet};
+use tracing::{debug, info};
+use unicode_security::{skeleton, MixedScript};
+
+const METADATA_PDA_SEED: &[u8] = b"metadata";
+
+/// Flags token metadata imitating the tracked mints of the filters.
+/// Names and symbols are compared by their confusable skeleton (Unicode TS #39),
+/// so e.g. `USDC` written with Cyrillic `С` matches the tracked `USDC`.
+pub struct SpoofDetector {
+    /// skeleton of the name or symbol of a tracked mint to that mint
+    tracked_skeletons: HashMap<String, Pubkey>,
+}
+
+/// Spoof flags of one token metadata account.
+#[derive(Debug, Default)]
+pub struct SpoofFlags {
+    /// tracked mint whose name or symbol is imitated by a different mint
+    pub confusable_mint: Option<Pubkey>,
+    /// name or symbol mixes characters of several scripts
+    pub mixed_script: bool,
+}
+
+impl SpoofDetector {
+    /// Reads the metadata of the tracked mints from the bank; mints without metadata are not tracked.
+    pub fn load(bank: &Bank, tracked_mints: &HashSet<Pubkey>) -> Self {
+        let mut tracked_skeletons = HashMap::new();
+        for (mint, metadata) in load_tracked_metadata(bank, tracked_mints) {
+            for value in [&metadata.name, &metadata.symbol] {
+                if let Some(value_skeleton) = normalized_skeleton(value) {
+                    tracked_skeletons.insert(value_skeleton, mint);
+                }
+            }
+        }
+        info!(
+            "Spoof detection tracks {} names and symbols of {} mints",
+            tracked_skeletons.len(),
+            tracked_mints.len()
+        );
+        Self { tracked_skeletons }
+    }
+
+    pub fn check(&self, metadata: &Metadata) -> SpoofFlags {
+        let mint = Pubkey::from(metadata.mint.to_bytes());
+        let confusable_mint = [&metadata.name, &metadata.symbol]
+            .into_iter()
+            .filter_map(|value| normalized_skeleton(value))
+            .find_map(|value_skeleton| self.tracked_skeletons.get(&value_skeleton))
+            .filter(|tracked_mint| **tracked_mint != mint)
+            .copied();
+        let mixed_script = [&metadata.name, &metadata.symbol]
+            .into_iter()
+            .any(|value| !trim_metadata_value(value).is_single_script());
+        SpoofFlags {
+            confusable_mint,
+            mixed_script,
+        }
+    }
+}
+
+/// Reads the token metadata of the mints from the bank, mints without decodable metadata are skipped.
+pub fn load_tracked_metadata(bank: &Bank, mints: &HashSet<Pubkey>) -> Vec<(Pubkey, Metadata)> {
+    let metadata_id = builtin_address(TOKEN_METADATA_PROGRAM);
+    mints
+        .iter()
+        .filter_map(|mint| {
+            let (metadata_pubkey, _) = Pubkey::find_program_address(
+                &[METADATA_PDA_SEED, metadata_id.as_ref(), mint.as_ref()],
+                &metadata_id,
+            );
+            let Some(account) = bank.get_account(&metadata_pubkey) else {
+                debug!("No token metadata of tracked mint {}", mint);
+                return None;
+            };
+            match Metadata::safe_deserialize(&mut account.data()) {
+                Ok(metadata) => Some((*mint, metadata)),
+                Err(e) => {
+                    debug!(
+                        "Cannot decode token metadata of tracked mint {}: {}",
+                        mint, e
+                    );
+                    None
+                }
+            }
+        })
+        .collect()
+}
+
+/// Metadata strings are padded with NULs.
+pub fn trim_metadata_value(value: &str) -> &str {
+    value.trim_end_matches('\0').trim()
+}
+
+/// Case-insensitive confusable skeleton, `None` for empty values that imitate nothing.
+fn normalized_skeleton(value: &str) -> Option<String> {
+    let value = trim_metadata_value(value);
+    if value.is_empty() {
+        return None;
+    }
+    Some(skeleton(value).collect::<String>().to_lowercase())
+}
@@ -0,0 +1,177 @@
+use crate::accounts::{
+    StakePool, ValidatorList, STAKE_POOL_ACCOUNT_TYPE, VALIDATOR_LIST_ACCOUNT_TYPE,
+};
+use crate::db_message::DbMessage;
+use crate::processors::Processor;
+use crate::progress_bar::ProgressCounter;
+use crate::schema::STAKE_POOL_TABLE;
+use crate::sql_row::{create_table, insert_row, insert_single_row};
+use crate::stats::ProcessorCallback;
+use anchor_lang::AnchorDeserialize;
+use async_trait::async_trait;
+use snapshot_parser::program_registry::{builtin_address, SPL_STAKE_POOL_PROGRAM};
+use snapshot_parser_types::decoded_accounts::{StakePoolRow, StakePoolValidatorRow};
+use solana_accounts_db::accounts_index::ScanConfig;
+use solana_program::pubkey::Pubkey;
+use solana_runtime::bank::Bank;
+use solana_sdk::account::ReadableAccount;
+use std::collections::HashMap;
+use std::future::Future;
+use std::string::ToString;
+use std::sync::Arc;
+use tokio::sync::mpsc::Sender;
+use tracing::{debug, error, warn};
+
+pub struct ProcessorStakePool {
+    bank: Arc<Bank>,
+    db_sender: Sender<DbMessage>,
+    stake_pool_program: Pubkey,
+    stake_pool_counter: Arc<ProgressCounter>,
+}
+
+impl ProcessorStakePool {
+    pub async fn new(
+        bank: Arc<Bank>,
+        db_sender: Sender<DbMessage>,
+        stake_pool_progress_counter: Arc<ProgressCounter>,
+    ) -> anyhow::Result<Self> {
+        let processor = Self {
+            bank,
+            db_sender,
+            stake_pool_program: builtin_address(SPL_STAKE_POOL_PROGRAM),
+            stake_pool_counter: stake_pool_progress_counter,
+        };
+        create_table::<StakePoolRow>(&processor.db_sender).await?;
+        create_table::<StakePoolValidatorRow>(&processor.db_sender).await?;
+        Ok(processor)
+    }
+
+    pub async fn process(&mut self) -> anyhow::Result<()> {
+        debug!("Loading stake pool accounts from bank...");
+
+        let stake_pool_accounts = self.bank.get_filtered_program_accounts(
+            &self.stake_pool_program,
+            |account_data| {
+                matches!(
+                    account_data.data().first(),
+                    Some(&STAKE_POOL_ACCOUNT_TYPE) | Some(&VALIDATOR_LIST_ACCOUNT_TYPE)
+                )
+            },
+            &ScanConfig {
+                collect_all_unsorted: true,
+                ..ScanConfig::default()
+            },
+        )?;
+
+        // the validators are attributed to the pool by its validator list
+        let mut pools_by_validator_list: HashMap<Pubkey, Pubkey> = HashMap::new();
+        let mut validator_lists: Vec<(Pubkey, ValidatorList)> = Vec::new();
+        for (pubkey, account) in stake_pool_accounts {
+            match account.data()[0] {
+                STAKE_POOL_ACCOUNT_TYPE => match StakePool::deserialize(&mut account.data()) {
+                    Ok(stake_pool) => {
+                        pools_by_validator_list.insert(stake_pool.validator_list, pubkey);
+                        insert_row(
+                            &self.db_sender,
+                            &self.stake_pool_counter,
+                            &stake_pool_row(&pubkey, account.owner(), &stake_pool),
+                        )
+                        .await
+                        .unwrap_or_else(|e| {
+                            error!("Failed to insert stake pool {}: {:?}", pubkey, e);
+                            0
+                        });
+                    }
+                    Err(e) => warn!("Failed to unpack stake pool {}: {:?}", pubkey, e),
+                },
+                _ => match ValidatorList::deserialize(&mut account.data()) {
+                    Ok(validator_list) => validator_lists.push((pubkey, validator_list)),
+                    Err(e) => warn!("Failed to unpack validator list {}: {:?}", pubkey, e),
+                },
+            }
+        }
+        debug!(
+            "Stake pool processor loaded {} stake pools and {} validator lists",
+            pools_by_validator_list.len(),
+            validator_lists.len()
+        );
+
+        for (pubkey, validator_list) in validator_lists {
+            let Some(stake_pool) = pools_by_validator_list.get(&pubkey) else {
+                warn!(
+                    "Validator list {} belongs to no stake pool, skipping",
+                    pubkey
+                );
+                continue;
+            };
+            for row in stake_pool_validator_rows(stake_pool, &validator_list) {
+                insert_single_row(&self.db_sender, &row)
+                    .await
+                    .unwrap_or_else(|e| {
+                        error!(
+                            "Failed to insert stake pool validator {}: {:?}",
+                            row.validator_id, e
+                        );
+                        0
+                    });
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Processor for ProcessorStakePool {
+    fn name() -> &'static str {
+        "Stake Pool"
+    }
+    fn process(&mut self) -> impl Future<Output = anyhow::Result<()>> + Send {
+        self.process()
+    }
+}
+
+#[async_trait]
+impl ProcessorCallback for ProcessorStakePool {
+    async fn get_count(&self) -> (String, u64) {
+        (STAKE_POOL_TABLE.to_string(), self.stake_pool_counter.get())
+    }
+}
+
+pub fn stake_pool_row(pubkey: &Pubkey, program: &Pubkey, stake_pool: &StakePool) -> StakePoolRow {
+    StakePoolRow {
+        pubkey: pubkey.to_string(),
+        program: program.to_string(),
+        manager: stake_pool.manager.to_string(),
+        staker: stake_pool.staker.to_string(),
+        stake_deposit_authority: stake_pool.stake_deposit_authority.to_string(),
+        validator_list: stake_pool.validator_list.to_string(),
+        reserve_stake: stake_pool.reserve_stake.to_string(),
+        pool_mint: stake_pool.pool_mint.to_string(),
+        manager_fee_account: stake_pool.manager_fee_account.to_string(),
+        total_lamports: stake_pool.total_lamports,
+        pool_token_supply: stake_pool.pool_token_supply,
+        last_update_epoch: stake_pool.last_update_epoch,
+        last_epoch_total_lamports: stake_pool.last_epoch_total_lamports,
+        last_epoch_pool_token_supply: stake_pool.last_epoch_pool_token_supply,
+        epoch_fee_numerator: stake_pool.epoch_fee.numerator,
+        epoch_fee_denominator: stake_pool.epoch_fee.denominator,
+    }
+}
+
+pub fn stake_pool_validator_rows(
+    stake_pool: &Pubkey,
+    validator_list: &ValidatorList,
+) -> Vec<StakePoolValidatorRow> {
+    validator_list
+        .validators
+        .iter()
+        .map(|validator| StakePoolValidatorRow {
+            validator_id: format!("{}:{}", stake_pool, validator.vote_account_address),
+            stake_pool: stake_pool.to_string(),
+            vote_account: validator.vote_account_address.to_string(),
+            active_stake_lamports: validator.active_stake_lamports,
+            transient_stake_lamports: validator.transient_stake_lamports,
+            last_update_epoch: validator.last_update_epoch,
+            status: validator.status,
+        })
+        .collect()
+}
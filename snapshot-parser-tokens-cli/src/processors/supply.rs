@@ -0,0 +1,286 @@
+use crate::db_message::DbMessage;
+use crate::processors::scan_cache::ScanCache;
+use crate::processors::{
+    is_token_account_data, unpack_mint, unpack_token_account, Processor, TOKEN_PROGRAMS,
+};
+use crate::progress_bar::ProgressCounter;
+use crate::schema::SUPPLY_STATS_TABLE;
+use crate::sql_row::{create_table, insert_row};
+use crate::stats::ProcessorCallback;
+use async_trait::async_trait;
+use serde::Deserialize;
+use snapshot_parser::program_registry::{ProgramCategory, ProgramRegistry};
+use snapshot_parser::serde_serialize::pubkey_string_conversion;
+use snapshot_parser::utils::read_from_json_file;
+use snapshot_parser_types::decoded_accounts::SupplyStatsRow;
+use solana_program::pubkey::Pubkey;
+use solana_runtime::bank::Bank;
+use solana_sdk::account::ReadableAccount;
+use std::collections::{HashMap, HashSet};
+use std::future::Future;
+use std::path::Path;
+use std::str::FromStr;
+use std::sync::{Arc, Mutex};
+use tokio::sync::mpsc::Sender;
+use tracing::{debug, error};
+
+/// Categories of the programs of the registry whose token accounts are escrows, not circulating.
+const ESCROW_PROGRAM_CATEGORIES: [ProgramCategory; 3] = [
+    ProgramCategory::Staking,
+    ProgramCategory::Governance,
+    ProgramCategory::Mev,
+];
+
+/// Supply config file listing the mints whose circulating supply is computed,
+/// each with the addresses holding its non-circulating supply (e.g., treasury, team vesting).
+/// An address is either a token account or the owner of token accounts.
+///
+/// ```json
+/// {
+///   "mints": [
+///     { "mint": "<mint pubkey>", "non_circulating": ["<token account or owner pubkey>"] }
+///   ]
+/// }
+/// ```
+#[derive(Debug, Clone, Deserialize)]
+pub struct SupplyConfig {
+    pub mints: Vec<SupplyMint>,
+    /// programs of the program registry whose token accounts are escrows, set by [`SupplyConfig::load`]
+    #[serde(skip)]
+    escrow_programs: HashSet<Pubkey>,
+    /// supply stats computed by the run, for the run summary
+    #[serde(skip)]
+    stats: Arc<Mutex<Vec<SupplyStatsRow>>>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct SupplyMint {
+    #[serde(with = "pubkey_string_conversion")]
+    pub mint: Pubkey,
+    #[serde(default)]
+    pub non_circulating: Vec<String>,
+}
+
+impl SupplyConfig {
+    pub fn load(config_path: &Path, program_registry: &ProgramRegistry) -> anyhow::Result<Self> {
+        let mut config: Self = read_from_json_file(&config_path).map_err(|e| {
+            anyhow::anyhow!("Could not load supply config {:?}: {}", config_path, e)
+        })?;
+        if config.mints.is_empty() {
+            anyhow::bail!("No mints in supply config {:?}", config_path);
+        }
+        let mut mints = HashSet::new();
+        for supply_mint in config.mints.iter() {
+            if !mints.insert(supply_mint.mint) {
+                anyhow::bail!(
+                    "Supply mint {} is configured more than once",
+                    supply_mint.mint
+                );
+            }
+            supply_mint.non_circulating_addresses()?;
+        }
+        config.escrow_programs = program_registry
+            .addresses_of(&ESCROW_PROGRAM_CATEGORIES)
+            .into_iter()
+            .collect();
+        Ok(config)
+    }
+
+    /// Supply stats written by the processor, empty until it completes.
+    pub fn stats(&self) -> Vec<SupplyStatsRow> {
+        self.stats
+            .lock()
+            .expect("supply stats lock poisoned")
+            .clone()
+    }
+}
+
+impl SupplyMint {
+    fn non_circulating_addresses(&self) -> anyhow::Result<HashSet<Pubkey>> {
+        self.non_circulating
+            .iter()
+            .map(|address| {
+                Pubkey::from_str(address).map_err(|e| {
+                    anyhow::anyhow!(
+                        "Invalid non-circulating address {} of mint {}: {}",
+                        address,
+                        self.mint,
+                        e
+                    )
+                })
+            })
+            .collect()
+    }
+}
+
+/// Amounts of a mint held outside of the circulation.
+#[derive(Default)]
+struct HeldSupply {
+    non_circulating: u64,
+    escrowed: u64,
+}
+
+/// Computes the circulating supply of the configured mints into the supply_stats table, one row per mint:
+/// the total supply of the mint minus the amounts held by the non-circulating addresses and the program escrows.
+pub struct ProcessorSupply {
+    bank: Arc<Bank>,
+    db_sender: Sender<DbMessage>,
+    config: SupplyConfig,
+    counter: Arc<ProgressCounter>,
+    scan_cache: Arc<ScanCache>,
+}
+
+impl ProcessorSupply {
+    pub async fn new(
+        bank: Arc<Bank>,
+        db_sender: Sender<DbMessage>,
+        config: SupplyConfig,
+        counter: Arc<ProgressCounter>,
+        scan_cache: Arc<ScanCache>,
+    ) -> anyhow::Result<Self> {
+        let processor = Self {
+            bank,
+            db_sender,
+            config,
+            counter,
+            scan_cache,
+        };
+        create_table::<SupplyStatsRow>(&processor.db_sender).await?;
+        Ok(processor)
+    }
+
+    /// The owner holds an escrow when it is an escrow program or an account of one (e.g., a vault PDA).
+    fn is_escrow_owner(&self, owner: &Pubkey) -> bool {
+        self.config.escrow_programs.contains(owner)
+            || self
+                .bank
+                .get_account(owner)
+                .is_some_and(|account| self.config.escrow_programs.contains(account.owner()))
+    }
+
+    /// Amounts of the configured mints held by the non-circulating addresses and by the program escrows.
+    async fn held_supply(&self) -> anyhow::Result<HashMap<Pubkey, HeldSupply>> {
+        let non_circulating: Arc<HashMap<Pubkey, HashSet<Pubkey>>> = Arc::new(
+            self.config
+                .mints
+                .iter()
+                .map(|supply_mint| Ok((supply_mint.mint, supply_mint.non_circulating_addresses()?)))
+                .collect::<anyhow::Result<_>>()?,
+        );
+        let mut held: HashMap<Pubkey, HeldSupply> = HashMap::new();
+        // amounts of the circulating candidates by owner and mint, the owners are looked up once the scan is done
+        let mut owner_amounts: HashMap<(Pubkey, Pubkey), u64> = HashMap::new();
+        for token_program in TOKEN_PROGRAMS {
+            let non_circulating = non_circulating.clone();
+            let (mut amounts_receiver, scan_handle) = self
+                .scan_cache
+                .stream_decoded_program_accounts(token_program, move |pubkey, account| {
+                    if !is_token_account_data(account.data()) {
+                        return None;
+                    }
+                    let token = unpack_token_account(account.data()).ok()?;
+                    let addresses = non_circulating.get(&token.base.mint)?;
+                    let is_non_circulating =
+                        addresses.contains(pubkey) || addresses.contains(&token.base.owner);
+                    Some((
+                        token.base.owner,
+                        token.base.mint,
+                        token.base.amount,
+                        is_non_circulating,
+                    ))
+                });
+            while let Some((owner, mint, amount, is_non_circulating)) =
+                amounts_receiver.recv().await
+            {
+                if is_non_circulating {
+                    held.entry(mint).or_default().non_circulating += amount;
+                } else {
+                    *owner_amounts.entry((owner, mint)).or_default() += amount;
+                }
+            }
+            scan_handle.await??;
+        }
+
+        let mut escrow_owners: HashMap<Pubkey, bool> = HashMap::new();
+        for ((owner, mint), amount) in owner_amounts {
+            let is_escrow = *escrow_owners
+                .entry(owner)
+                .or_insert_with(|| self.is_escrow_owner(&owner));
+            if is_escrow {
+                held.entry(mint).or_default().escrowed += amount;
+            }
+        }
+        Ok(held)
+    }
+
+    pub async fn process(&mut self) -> anyhow::Result<()> {
+        debug!(
+            "Computing circulating supply of {} mints from bank...",
+            self.config.mints.len()
+        );
+        let held = self.held_supply().await?;
+        let mut rows = Vec::new();
+        for supply_mint in self.config.mints.iter() {
+            let account = self
+                .bank
+                .get_account(&supply_mint.mint)
+                .ok_or_else(|| anyhow::anyhow!("Supply mint {} not found", supply_mint.mint))?;
+            let mint = unpack_mint(account.data()).map_err(|e| {
+                anyhow::anyhow!("Failed to unpack supply mint {}: {:?}", supply_mint.mint, e)
+            })?;
+            let held = held.get(&supply_mint.mint);
+            let non_circulating = held.map_or(0, |held| held.non_circulating);
+            let escrowed = held.map_or(0, |held| held.escrowed);
+            rows.push(SupplyStatsRow {
+                mint: supply_mint.mint.to_string(),
+                slot: self.bank.slot(),
+                decimals: mint.base.decimals,
+                total_supply: mint.base.supply,
+                non_circulating,
+                escrowed,
+                circulating: mint
+                    .base
+                    .supply
+                    .saturating_sub(non_circulating)
+                    .saturating_sub(escrowed),
+            });
+        }
+
+        for row in rows.iter() {
+            insert_row(&self.db_sender, &self.counter, row)
+                .await
+                .unwrap_or_else(|e| {
+                    error!(
+                        "Failed to insert supply stats of mint {}: {:?}",
+                        row.mint, e
+                    );
+                    0
+                });
+        }
+        *self
+            .config
+            .stats
+            .lock()
+            .expect("supply stats lock poisoned") = rows;
+        Ok(())
+    }
+}
+
+impl Processor for ProcessorSupply {
+    fn name() -> &'static str {
+        "Supply"
+    }
+    fn process(&mut self) -> impl Future<Output = anyhow::Result<()>> + Send {
+        self.process()
+    }
+    fn release_scans(&self) {
+        self.scan_cache.release(TOKEN_PROGRAMS);
+    }
+}
+
+#[async_trait]
+impl ProcessorCallback for ProcessorSupply {
+    async fn get_count(&self) -> (String, u64) {
+        (SUPPLY_STATS_TABLE.to_string(), self.counter.get())
+    }
+}
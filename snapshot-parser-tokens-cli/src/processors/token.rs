@@ -1,33 +1,47 @@
-use crate::db_message::{DbMessage, OwnedSqlValue};
+use crate::checkpoint::commit_checkpoint;
+use crate::db_message::DbMessage;
 use crate::filters::Filters;
-use crate::processors::{insert_account_meta, Processor};
+use crate::processors::scan_cache::ScanCache;
+use crate::processors::spill::{SortedAccountBuffer, SpillConfig};
+use crate::processors::{account_row, Processor};
 use crate::progress_bar::ProgressCounter;
-use crate::sql_params;
+use crate::schema::TOKEN_ACCOUNT_TABLE;
+use crate::sql_row::{create_table, RowBatch};
 use crate::stats::ProcessorCallback;
 use async_trait::async_trait;
-use log::{debug, error};
-use rusqlite::ToSql;
-use solana_accounts_db::accounts_index::ScanConfig;
+use snapshot_parser_types::decoded_accounts::{AccountRow, ScanCheckpointRow, TokenRow};
+use solana_program::hash::hashv;
 use solana_program::program_error::ProgramError;
 use solana_program::program_pack::Pack;
 use solana_program::pubkey::Pubkey;
 use solana_runtime::bank::Bank;
-use solana_sdk::account::ReadableAccount;
+use solana_sdk::account::{AccountSharedData, ReadableAccount};
+use spl_token_2022::extension::transfer_fee::TransferFeeAmount;
+use spl_token_2022::extension::{AccountType, BaseStateWithExtensions, StateWithExtensions};
+use std::collections::HashSet;
 use std::future::Future;
+use std::str::FromStr;
 use std::string::ToString;
 use std::sync::Arc;
 use tokio::sync::mpsc::Sender;
-use tokio::sync::oneshot;
-
-pub const TOKEN_ACCOUNT_TABLE: &str = "token_account";
-pub const INSERT_TOKEN_ACCOUNT_QUERY: &str = "INSERT OR REPLACE INTO token_account (pubkey, mint, owner, amount, delegate, state, is_native, delegated_amount, close_authority) SELECT ?, ?, ?, ?, ?, ?, ?, ?, ?;";
+use tracing::{debug, info};
 
 pub struct ProcessorToken {
     bank: Arc<Bank>,
     db_sender: Sender<DbMessage>,
-    mints: Vec<Pubkey>,
+    mints: Arc<HashSet<Pubkey>>,
     account_owners_counter: Arc<ProgressCounter>,
     token_counter: Arc<ProgressCounter>,
+    account_batch: RowBatch<AccountRow>,
+    token_batch: RowBatch<TokenRow>,
+    checkpoint_interval: Option<usize>,
+    /// memory cap of the accounts buffered for the checkpoints
+    checkpoint_buffer: Option<SpillConfig>,
+    /// checkpoint of the resumed run, the accounts up to its last pubkey were committed already
+    resume_checkpoint: Option<ScanCheckpointRow>,
+    /// hash of the mint filter, recorded with the checkpoints, see [`mints_hash`]
+    filter_hash: String,
+    scan_cache: Arc<ScanCache>,
 }
 
 impl ProcessorToken {
@@ -37,90 +51,162 @@ impl ProcessorToken {
         filters: &Filters,
         account_owners_progress_counter: Arc<ProgressCounter>,
         token_progress_counter: Arc<ProgressCounter>,
+        checkpoint_interval: Option<usize>,
+        checkpoint_buffer: Option<SpillConfig>,
+        resume_checkpoint: Option<ScanCheckpointRow>,
+        scan_cache: Arc<ScanCache>,
     ) -> anyhow::Result<Self> {
-        let mints = filters.account_mints.clone();
+        let mints = Arc::new(filters.account_mints.clone());
         let processor = Self {
             bank,
             db_sender,
+            account_batch: RowBatch::new(account_owners_progress_counter.clone()),
+            token_batch: RowBatch::new(token_progress_counter.clone()),
             account_owners_counter: account_owners_progress_counter,
             token_counter: token_progress_counter,
+            filter_hash: mints_hash(&mints),
             mints,
+            checkpoint_interval: checkpoint_interval.filter(|interval| *interval > 0),
+            checkpoint_buffer,
+            resume_checkpoint,
+            scan_cache,
         };
-        processor.create_token_table().await?;
+        create_table::<TokenRow>(&processor.db_sender).await?;
         Ok(processor)
     }
 
-    async fn create_token_table(&self) -> anyhow::Result<usize> {
-        let (response_tx, response_rx) = oneshot::channel();
-        self.db_sender
-            .send(DbMessage::ExecuteSpecial {
-                query: "CREATE TABLE token_account (
-                    pubkey TEXT NOT NULL PRIMARY KEY,
-                    mint TEXT NOT NULL,
-                    owner TEXT NOT NULL,
-                    amount INTEGER(8) NOT NULL,
-                    delegate TEXT,
-                    state INTEGER(1) NOT NULL,
-                    is_native INTEGER(8),
-                    delegated_amount INTEGER(8) NOT NULL,
-                    close_authority TEXT
-                );"
-                .to_string(),
-                params: vec![],
-                response: response_tx,
-            })
-            .await?;
-        response_rx.await?
-    }
-
     pub async fn process(&mut self) -> anyhow::Result<()> {
         debug!(
             "Loading token accounts for {} mints from bank...",
             self.mints.len()
         );
-        let token_accounts = self.bank.get_filtered_program_accounts(
-            &spl_token::ID,
-            |account_data| match account_data.data().len() {
-                spl_token::state::Account::LEN => {
-                    match spl_token::state::Account::unpack(account_data.data()) {
-                        Ok(token) => self.mints.contains(&token.mint),
-                        Err(ProgramError::UninitializedAccount) => false,
-                        Err(e) => {
-                            debug!("Error: failed to unpack token account: {:?}", e);
-                            false
-                        }
+        // without checkpoints, the rows of the previous run were not committed and the scan starts over
+        let resume_from = match (self.checkpoint_interval, &self.resume_checkpoint) {
+            (Some(_), Some(checkpoint)) => {
+                if checkpoint.filter_hash != self.filter_hash {
+                    anyhow::bail!(
+                        "Token checkpoint was written with other mint filters; remove the temporary DB to start over"
+                    );
+                }
+                let last_pubkey = Pubkey::from_str(&checkpoint.last_pubkey).map_err(|e| {
+                    anyhow::anyhow!(
+                        "Invalid last pubkey {} of the token checkpoint: {}",
+                        checkpoint.last_pubkey,
+                        e
+                    )
+                })?;
+                info!(
+                    "Token processor resuming after pubkey {}, {} accounts committed",
+                    last_pubkey, checkpoint.processed
+                );
+                Some((last_pubkey, checkpoint.processed))
+            }
+            _ => None,
+        };
+        let mut token_accounts = SortedAccountBuffer::new("token", self.checkpoint_buffer.clone());
+        let mut loaded_accounts = 0u64;
+        for token_program in TOKEN_PROGRAMS {
+            let mints = self.mints.clone();
+            let (mut accounts_receiver, scan_handle) = self
+                .scan_cache
+                .stream_program_accounts(token_program, move |account| {
+                    is_filtered_token_account(&mints, account.data())
+                });
+            while let Some((pubkey, account)) = accounts_receiver.recv().await {
+                loaded_accounts += 1;
+                if self.checkpoint_interval.is_some() {
+                    if resume_from.is_some_and(|(last_pubkey, _)| pubkey <= last_pubkey) {
+                        continue;
                     }
+                    // checkpoints need all the accounts to be committed in a stable order
+                    token_accounts.push(pubkey, account)?;
+                } else {
+                    self.insert_token_account(&pubkey, &account).await?;
                 }
-                _ => false,
-            },
-            &ScanConfig {
-                collect_all_unsorted: true,
-                ..ScanConfig::default()
-            },
-        )?;
-
-        debug!("Token processor loaded {} accounts", token_accounts.len());
-        for (pubkey, account) in token_accounts {
-            let token_account = spl_token::state::Account::unpack(account.data())?;
-            insert_account_meta(
+            }
+            scan_handle.await??;
+        }
+
+        debug!("Token processor loaded {} accounts", loaded_accounts);
+        let Some(checkpoint_interval) = self.checkpoint_interval else {
+            return self.flush_batches().await;
+        };
+
+        // the scan streams the accounts in no stable order, so only the insertion of the sorted accounts
+        // is checkpointed: a run failing mid-scan commits no token rows and the resumed run scans again.
+        // stable order of the accounts makes the last committed pubkey a valid resume position
+        let mut processed = resume_from.map_or(0, |(_, processed)| processed);
+        let mut sorted_accounts = token_accounts.into_sorted()?;
+        loop {
+            let slice = sorted_accounts
+                .by_ref()
+                .take(checkpoint_interval)
+                .collect::<anyhow::Result<Vec<_>>>()?;
+            if slice.is_empty() {
+                break;
+            }
+            self.insert_token_accounts(&slice).await?;
+            self.flush_batches().await?;
+            processed += slice.len() as u64;
+            let (last_pubkey, _) = slice.last().expect("empty slices end the loop");
+            commit_checkpoint(
                 &self.db_sender,
-                &self.account_owners_counter,
-                &pubkey,
-                &account,
+                &ScanCheckpointRow {
+                    processor: Self::name().to_string(),
+                    last_pubkey: last_pubkey.to_string(),
+                    processed,
+                    slot: self.bank.slot(),
+                    filter_hash: self.filter_hash.clone(),
+                },
             )
             .await?;
-            insert_token(
+            info!(
+                "Token processor checkpoint: {}/{} accounts committed",
+                processed, loaded_accounts
+            );
+        }
+        Ok(())
+    }
+
+    async fn insert_token_accounts(
+        &mut self,
+        token_accounts: &[(Pubkey, AccountSharedData)],
+    ) -> anyhow::Result<()> {
+        for (pubkey, account) in token_accounts {
+            self.insert_token_account(pubkey, account).await?;
+        }
+        Ok(())
+    }
+
+    async fn insert_token_account(
+        &mut self,
+        pubkey: &Pubkey,
+        account: &AccountSharedData,
+    ) -> anyhow::Result<()> {
+        let token_account = unpack_token_account(account.data())?;
+        self.account_batch
+            .push(&self.db_sender, account_row(pubkey, account))
+            .await?;
+        self.token_batch
+            .push(
                 &self.db_sender,
-                &self.token_counter,
-                &pubkey,
-                &token_account,
+                token_row(pubkey, account.owner(), &token_account),
             )
             .await
-            .unwrap_or_else(|e| {
-                error!("Failed to insert token account {}: {:?}", pubkey, e);
-                0
-            });
-        }
+            .map_err(|e| {
+                anyhow::anyhow!("Failed to insert token accounts up to {}: {}", pubkey, e)
+            })?;
+        Ok(())
+    }
+
+    /// Inserts the rows left in the batches, before a checkpoint commits them.
+    /// A failed insert fails the processor, a checkpoint must not be committed over rows not written.
+    async fn flush_batches(&mut self) -> anyhow::Result<()> {
+        self.account_batch.flush(&self.db_sender).await?;
+        self.token_batch
+            .flush(&self.db_sender)
+            .await
+            .map_err(|e| anyhow::anyhow!("Failed to insert token accounts: {}", e))?;
         Ok(())
     }
 }
@@ -132,6 +218,9 @@ impl Processor for ProcessorToken {
     fn process(&mut self) -> impl Future<Output = anyhow::Result<()>> + Send {
         self.process()
     }
+    fn release_scans(&self) {
+        self.scan_cache.release(TOKEN_PROGRAMS);
+    }
 }
 
 #[async_trait]
@@ -141,35 +230,69 @@ impl ProcessorCallback for ProcessorToken {
     }
 }
 
-pub async fn insert_token(
-    db_sender: &Sender<DbMessage>,
-    progress_counter: &Arc<ProgressCounter>,
+/// Hash of the mints in their pubkey order, identifying the filter of the token scan.
+fn mints_hash(mints: &HashSet<Pubkey>) -> String {
+    let mut mints: Vec<&Pubkey> = mints.iter().collect();
+    mints.sort();
+    let mints: Vec<&[u8]> = mints.iter().map(|mint| mint.as_ref()).collect();
+    hashv(&mints).to_string()
+}
+
+/// Token programs whose token accounts are scanned.
+pub const TOKEN_PROGRAMS: [Pubkey; 2] = [spl_token::ID, spl_token_2022::ID];
+
+/// Token accounts have the legacy size or carry the account type of a token-2022 account with extensions,
+/// so mints and multisigs are skipped without unpacking.
+pub fn is_token_account_data(data: &[u8]) -> bool {
+    data.len() == spl_token_2022::state::Account::LEN
+        || data.get(spl_token_2022::state::Account::LEN) == Some(&(AccountType::Account as u8))
+}
+
+fn is_filtered_token_account(mints: &HashSet<Pubkey>, data: &[u8]) -> bool {
+    if !is_token_account_data(data) {
+        return false;
+    }
+    match unpack_token_account(data) {
+        Ok(token) => mints.contains(&token.base.mint),
+        Err(ProgramError::UninitializedAccount) => false,
+        Err(e) => {
+            debug!("Error: failed to unpack token account: {:?}", e);
+            false
+        }
+    }
+}
+
+/// Unpacks a token account of either token program; legacy spl-token accounts have the layout of
+/// a token-2022 account without extensions.
+/// Mints and other non-token accounts of the token programs fail to unpack.
+pub fn unpack_token_account(
+    data: &[u8],
+) -> Result<StateWithExtensions<'_, spl_token_2022::state::Account>, ProgramError> {
+    StateWithExtensions::<spl_token_2022::state::Account>::unpack(data)
+}
+
+pub fn token_row(
     pubkey: &Pubkey,
-    token_account: &spl_token::state::Account,
-) -> anyhow::Result<usize> {
-    let (response_tx, response_rx) = oneshot::channel();
-    let owned_params = sql_params![
-        pubkey.to_string(),
-        token_account.mint.to_string(),
-        token_account.owner.to_string(),
-        token_account.amount as i64,
-        token_account
-            .delegate
-            .map_or(None, |key| Some(key.to_string())),
-        token_account.state as u8,
-        Option::<u64>::from(token_account.is_native),
-        token_account.delegated_amount as i64,
-        token_account
+    token_program: &Pubkey,
+    token_account: &StateWithExtensions<spl_token_2022::state::Account>,
+) -> TokenRow {
+    let base = &token_account.base;
+    TokenRow {
+        pubkey: pubkey.to_string(),
+        token_program: token_program.to_string(),
+        mint: base.mint.to_string(),
+        owner: base.owner.to_string(),
+        amount: base.amount,
+        delegate: base.delegate.map_or(None, |key| Some(key.to_string())),
+        state: base.state as u8,
+        is_native: Option::<u64>::from(base.is_native),
+        delegated_amount: base.delegated_amount,
+        close_authority: base
             .close_authority
             .map_or(None, |key| Some(bs58::encode(key.as_ref()).into_string())),
-    ];
-    db_sender
-        .send(DbMessage::Execute {
-            query: INSERT_TOKEN_ACCOUNT_QUERY.to_string(),
-            params: owned_params,
-            response: response_tx,
-        })
-        .await?;
-    progress_counter.inc();
-    response_rx.await?
+        transfer_fee_withheld_amount: token_account
+            .get_extension::<TransferFeeAmount>()
+            .ok()
+            .map(|transfer_fee_amount| u64::from(transfer_fee_amount.withheld_amount)),
+    }
 }
@@ -1,31 +1,36 @@
-use crate::db_message::{DbMessage, OwnedSqlValue};
-use crate::processors::Processor;
+use crate::db_message::DbMessage;
+use crate::processors::scan_cache::ScanCache;
+use crate::processors::{load_tracked_metadata, trim_metadata_value, Processor, SpoofDetector};
 use crate::progress_bar::ProgressCounter;
-use crate::sql_params;
+use crate::schema::TOKEN_METADATA_ACCOUNT_TABLE;
+use crate::sql_row::{create_table, insert_row};
 use crate::stats::ProcessorCallback;
 use async_trait::async_trait;
-use log::{debug, error};
 use mpl_token_metadata::accounts::Metadata;
-use rusqlite::ToSql;
-use solana_accounts_db::accounts_index::ScanConfig;
+use snapshot_parser::program_registry::{builtin_address, TOKEN_METADATA_PROGRAM};
+use snapshot_parser_types::decoded_accounts::{SymbolCollisionRow, TokenMetadataRow};
 use solana_program::pubkey::Pubkey;
 use solana_runtime::bank::Bank;
 use solana_sdk::account::ReadableAccount;
+use std::collections::{HashMap, HashSet};
 use std::future::Future;
 use std::io::ErrorKind;
 use std::string::ToString;
 use std::sync::Arc;
 use tokio::sync::mpsc::Sender;
-use tokio::sync::oneshot;
-
-pub const TOKEN_METADATA_ACCOUNT_TABLE: &str = "token_metadata";
-pub const INSERT_TOKEN_METADATA_ACCOUNT_QUERY: &str = "INSERT OR REPLACE INTO token_metadata (pubkey, mint, update_authority, name, symbol, uri, data_length, seller_fee_basis_points, primary_sale_happened, is_mutable, edition_nonce, collection_verified, collection_key)\
-SELECT ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?;";
+use tracing::{debug, error, warn};
+use unicode_normalization::UnicodeNormalization;
 
 pub struct ProcessorTokenMetadata {
     bank: Arc<Bank>,
     db_sender: Sender<DbMessage>,
     token_metadata_counter: Arc<ProgressCounter>,
+    sanitization: MetadataSanitization,
+    spoof_detector: Option<Arc<SpoofDetector>>,
+    /// upper-cased symbol of a tracked mint to that mint
+    tracked_symbols: HashMap<String, Pubkey>,
+    symbol_collision_counter: Arc<ProgressCounter>,
+    scan_cache: Arc<ScanCache>,
 }
 
 impl ProcessorTokenMetadata {
@@ -33,96 +38,128 @@ impl ProcessorTokenMetadata {
         bank: Arc<Bank>,
         db_sender: Sender<DbMessage>,
         token_metadata_counter: Arc<ProgressCounter>,
+        sanitization: MetadataSanitization,
+        spoof_detector: Option<Arc<SpoofDetector>>,
+        tracked_mints: &HashSet<Pubkey>,
+        symbol_collision_counter: Arc<ProgressCounter>,
+        scan_cache: Arc<ScanCache>,
     ) -> anyhow::Result<Self> {
+        let tracked_symbols: HashMap<String, Pubkey> = load_tracked_metadata(&bank, tracked_mints)
+            .into_iter()
+            .filter_map(|(mint, metadata)| {
+                symbol_key(&metadata.symbol).map(|symbol| (symbol, mint))
+            })
+            .collect();
         let processor = Self {
             bank,
             db_sender,
             token_metadata_counter,
+            sanitization,
+            spoof_detector,
+            tracked_symbols,
+            symbol_collision_counter,
+            scan_cache,
         };
-        processor.create_token_table().await?;
+        create_table::<TokenMetadataRow>(&processor.db_sender).await?;
+        create_table::<SymbolCollisionRow>(&processor.db_sender).await?;
         Ok(processor)
     }
 
-    async fn create_token_table(&self) -> anyhow::Result<usize> {
-        let (response_tx, response_rx) = oneshot::channel();
-        self.db_sender
-            .send(DbMessage::ExecuteSpecial {
-                query: "CREATE TABLE token_metadata (
-                    pubkey TEXT NOT NULL PRIMARY KEY,
-                    mint TEXT NOT NULL,
-                    update_authority TEXT NOT NULL,
-                    name TEXT NOT NULL,
-                    symbol TEXT(10) NOT NULL,
-                    uri TEXT(200) NOT NULL,
-                    data_length INTEGER(8) NOT NULL,
-                    seller_fee_basis_points INTEGER(4) NOT NULL,
-                    primary_sale_happened INTEGER(1) NOT NULL,
-                    is_mutable INTEGER(1) NOT NULL,
-                    edition_nonce INTEGER(2) NULL,
-                    collection_verified INTEGER(1) NULL,
-                    collection_key TEXT NULL
-                );"
-                .to_string(),
-                params: vec![],
-                response: response_tx,
-            })
-            .await?;
-        response_rx.await?
-    }
-
     pub async fn process(&mut self) -> anyhow::Result<()> {
-        let metadata_id = Pubkey::from(mpl_token_metadata::ID.to_bytes());
+        let metadata_id = builtin_address(TOKEN_METADATA_PROGRAM);
         debug!(
             "Loading token metadata accounts for owner {} from bank...",
             metadata_id,
         );
-        let token_metadata_accounts = self.bank.get_program_accounts(
-            &metadata_id,
-            &ScanConfig {
-                collect_all_unsorted: true,
-                ..ScanConfig::default()
-            },
-        )?;
-
-        debug!(
-            "Token metadata processor loaded {} accounts",
-            token_metadata_accounts.len()
-        );
-        for (pubkey, account) in token_metadata_accounts {
-            match Metadata::safe_deserialize(&mut account.data()) {
-                Ok(metadata) => {
-                    insert_token_metadata(
-                        &self.db_sender,
-                        &self.token_metadata_counter,
-                        &pubkey,
-                        account.data().len(),
-                        &metadata,
-                    )
-                    .await
-                    .unwrap_or_else(|e| {
-                        error!(
-                            "Failed to insert token metadata account {}: {:?}",
-                            pubkey, e
-                        );
-                        0
-                    });
-                }
-                Err(e) => match e.kind() {
-                    ErrorKind::Other => {
-                        // Ignore; this is expected for non-MetadataV1 accounts
+        // the metadata is deserialized on the scan threads
+        let (mut accounts_receiver, scan_handle) =
+            self.scan_cache
+                .stream_decoded_program_accounts(metadata_id, |pubkey, account| {
+                    match Metadata::safe_deserialize(&mut account.data()) {
+                        Ok(metadata) => Some((*pubkey, account.data().len(), metadata)),
+                        Err(e) => {
+                            // ErrorKind::Other is expected for non-MetadataV1 accounts
+                            if e.kind() != ErrorKind::Other {
+                                debug!(
+                                    "Failed to deserialize token metadata account {}: {:?}",
+                                    pubkey, e
+                                );
+                            }
+                            None
+                        }
                     }
-                    _ => {
-                        debug!(
-                            "Failed to deserialize token metadata account {}: {:?}",
-                            pubkey, e
-                        );
-                    }
-                },
+                });
+        let mut loaded_accounts = 0u64;
+        let mut symbol_collisions = Vec::new();
+        while let Some((pubkey, data_len, metadata)) = accounts_receiver.recv().await {
+            loaded_accounts += 1;
+            if let Some(symbol_collision) = self.symbol_collision(&pubkey, &metadata) {
+                symbol_collisions.push(symbol_collision);
             }
+            insert_row(
+                &self.db_sender,
+                &self.token_metadata_counter,
+                &token_metadata_row(
+                    &pubkey,
+                    data_len,
+                    &metadata,
+                    &self.sanitization,
+                    self.spoof_detector.as_deref(),
+                ),
+            )
+            .await
+            .unwrap_or_else(|e| {
+                error!(
+                    "Failed to insert token metadata account {}: {:?}",
+                    pubkey, e
+                );
+                0
+            });
         }
+        scan_handle.await??;
+        debug!(
+            "Token metadata processor loaded {} accounts",
+            loaded_accounts
+        );
+        self.report_symbol_collisions(symbol_collisions).await;
 
         Ok(())
     }
+
+    /// Metadata of a different mint using the symbol of a tracked mint.
+    fn symbol_collision(&self, pubkey: &Pubkey, metadata: &Metadata) -> Option<SymbolCollisionRow> {
+        let tracked_mint = self.tracked_symbols.get(&symbol_key(&metadata.symbol)?)?;
+        let mint = Pubkey::from(metadata.mint.to_bytes());
+        (mint != *tracked_mint).then(|| SymbolCollisionRow {
+            pubkey: pubkey.to_string(),
+            mint: mint.to_string(),
+            symbol: trim_metadata_value(&metadata.symbol).to_string(),
+            tracked_mint: tracked_mint.to_string(),
+        })
+    }
+
+    /// Writes the mints colliding with the symbols of the tracked mints at the end of the scan,
+    /// the report feeds the denylist of phishing tokens.
+    async fn report_symbol_collisions(&self, symbol_collisions: Vec<SymbolCollisionRow>) {
+        if !symbol_collisions.is_empty() {
+            warn!(
+                "{} token metadata accounts use a symbol of the {} tracked mints with a different mint",
+                symbol_collisions.len(),
+                self.tracked_symbols.len()
+            );
+        }
+        for row in symbol_collisions.iter() {
+            insert_row(&self.db_sender, &self.symbol_collision_counter, row)
+                .await
+                .unwrap_or_else(|e| {
+                    error!(
+                        "Failed to insert symbol collision of mint {}: {:?}",
+                        row.mint, e
+                    );
+                    0
+                });
+        }
+    }
 }
 
 impl Processor for ProcessorTokenMetadata {
@@ -132,6 +169,10 @@ impl Processor for ProcessorTokenMetadata {
     fn process(&mut self) -> impl Future<Output = anyhow::Result<()>> + Send {
         self.process()
     }
+    fn release_scans(&self) {
+        self.scan_cache
+            .release([builtin_address(TOKEN_METADATA_PROGRAM)]);
+    }
 }
 
 #[async_trait]
@@ -144,36 +185,123 @@ impl ProcessorCallback for ProcessorTokenMetadata {
     }
 }
 
-pub async fn insert_token_metadata(
-    db_sender: &Sender<DbMessage>,
-    progress_counter: &Arc<ProgressCounter>,
+/// Symbols are compared case-insensitively, empty symbols collide with nothing.
+fn symbol_key(symbol: &str) -> Option<String> {
+    let symbol = trim_metadata_value(symbol);
+    (!symbol.is_empty()).then(|| symbol.to_uppercase())
+}
+
+/// CLI options of the token metadata processing.
+#[derive(clap::Args, Debug, Clone, Default)]
+pub struct MetadataArgs {
+    /// Trim the NUL padding of the token metadata name, symbol and uri
+    #[arg(long, env, default_value_t = false)]
+    pub metadata_trim_nul: bool,
+
+    /// Strip control characters from the token metadata name, symbol and uri
+    #[arg(long, env, default_value_t = false)]
+    pub metadata_strip_control_chars: bool,
+
+    /// Normalize the token metadata name, symbol and uri to Unicode NFC
+    #[arg(long, env, default_value_t = false)]
+    pub metadata_nfc: bool,
+
+    /// Flag token metadata whose name or symbol imitates a mint of the filters or mixes scripts
+    #[arg(long, env, default_value_t = false)]
+    pub metadata_spoof_detection: bool,
+
+    /// Maximum number of characters kept of the token metadata name, symbol and uri
+    #[arg(long, env)]
+    pub metadata_max_length: Option<usize>,
+
+    /// Keep the token metadata name, symbol and uri as stored on chain in the *_raw columns
+    #[arg(long, env, default_value_t = false)]
+    pub metadata_keep_raw: bool,
+}
+
+impl MetadataArgs {
+    pub fn sanitization(&self) -> MetadataSanitization {
+        MetadataSanitization {
+            trim_nul: self.metadata_trim_nul,
+            strip_control_chars: self.metadata_strip_control_chars,
+            nfc_normalize: self.metadata_nfc,
+            max_length: self.metadata_max_length,
+            keep_raw: self.metadata_keep_raw,
+        }
+    }
+}
+
+/// Cleanup of the metadata name, symbol and uri, which are stored padded with NULs
+/// and may contain control characters breaking the downstream consumers of the DB.
+/// The default keeps the values as they are.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct MetadataSanitization {
+    pub trim_nul: bool,
+    /// Unicode NFC normalization, so equal looking names are stored equal
+    pub nfc_normalize: bool,
+    pub strip_control_chars: bool,
+    /// maximum number of characters kept
+    pub max_length: Option<usize>,
+    /// the values as stored on chain are kept in the `*_raw` columns
+    pub keep_raw: bool,
+}
+
+impl MetadataSanitization {
+    pub fn sanitize(&self, value: &str) -> String {
+        let value = if self.trim_nul {
+            value.trim_end_matches('\0')
+        } else {
+            value
+        };
+        let value: String = if self.strip_control_chars {
+            value.chars().filter(|c| !c.is_control()).collect()
+        } else {
+            value.to_string()
+        };
+        let value: String = if self.nfc_normalize {
+            value.nfc().collect()
+        } else {
+            value
+        };
+        match self.max_length {
+            Some(max_length) => value.chars().take(max_length).collect(),
+            None => value,
+        }
+    }
+
+    fn raw(&self, value: &str) -> Option<String> {
+        self.keep_raw.then(|| value.to_string())
+    }
+}
+
+pub fn token_metadata_row(
     pubkey: &Pubkey,
     account_data_len: usize,
     metadata: &Metadata,
-) -> anyhow::Result<usize> {
-    let (response_tx, response_rx) = oneshot::channel();
-    let owned_params = sql_params![
-        pubkey.to_string(),
-        metadata.mint.to_string(),
-        metadata.update_authority.to_string(),
-        metadata.name.clone(),
-        metadata.symbol.clone(),
-        metadata.uri.clone(),
-        account_data_len as u64,
-        metadata.seller_fee_basis_points,
-        metadata.primary_sale_happened,
-        metadata.is_mutable,
-        metadata.edition_nonce,
-        metadata.collection.clone().map(|c| c.verified),
-        format!("{:?}", metadata.key),
-    ];
-    db_sender
-        .send(DbMessage::Execute {
-            query: INSERT_TOKEN_METADATA_ACCOUNT_QUERY.to_string(),
-            params: owned_params,
-            response: response_tx,
-        })
-        .await?;
-    progress_counter.inc();
-    response_rx.await?
+    sanitization: &MetadataSanitization,
+    spoof_detector: Option<&SpoofDetector>,
+) -> TokenMetadataRow {
+    let spoof_flags = spoof_detector.map(|spoof_detector| spoof_detector.check(metadata));
+    TokenMetadataRow {
+        pubkey: pubkey.to_string(),
+        mint: metadata.mint.to_string(),
+        update_authority: metadata.update_authority.to_string(),
+        name: sanitization.sanitize(&metadata.name),
+        symbol: sanitization.sanitize(&metadata.symbol),
+        uri: sanitization.sanitize(&metadata.uri),
+        data_length: account_data_len as u64,
+        seller_fee_basis_points: metadata.seller_fee_basis_points,
+        primary_sale_happened: metadata.primary_sale_happened,
+        is_mutable: metadata.is_mutable,
+        edition_nonce: metadata.edition_nonce,
+        collection_verified: metadata.collection.as_ref().map(|c| c.verified),
+        collection_key: metadata.collection.as_ref().map(|c| c.key.to_string()),
+        name_raw: sanitization.raw(&metadata.name),
+        symbol_raw: sanitization.raw(&metadata.symbol),
+        uri_raw: sanitization.raw(&metadata.uri),
+        confusable_mint: spoof_flags
+            .as_ref()
+            .and_then(|flags| flags.confusable_mint.map(|mint| mint.to_string())),
+        mixed_script: spoof_flags.as_ref().map(|flags| flags.mixed_script),
+    }
 }
@@ -1,27 +1,37 @@
-use crate::db_message::{DbMessage, OwnedSqlValue};
+use crate::db_message::DbMessage;
 use crate::filters::Filters;
+use crate::mint_authority::MintAuthorityTracker;
 use crate::processors::Processor;
 use crate::progress_bar::ProgressCounter;
-use crate::sql_params;
-use log::{error, info};
-use rusqlite::ToSql;
-use solana_program::program_pack::Pack;
+use crate::schema::MISSING_MINT_TABLE;
+use crate::sql_row::{create_table, insert_row, insert_single_row};
+use crate::stats::{NamedCounter, ProcessorCallback};
+use snapshot_parser_types::decoded_accounts::{MintAuthorityChangeRow, MintRow, MissingMintRow};
+use solana_program::clock::Epoch;
+use solana_program::program_error::ProgramError;
 use solana_program::pubkey::Pubkey;
 use solana_runtime::bank::Bank;
 use solana_sdk::account::ReadableAccount;
+use spl_token_2022::extension::interest_bearing_mint::InterestBearingConfig;
+use spl_token_2022::extension::permanent_delegate::PermanentDelegate;
+use spl_token_2022::extension::transfer_fee::TransferFeeConfig;
+use spl_token_2022::extension::{BaseStateWithExtensions, StateWithExtensions};
+use std::collections::HashSet;
 use std::future::Future;
 use std::string::ToString;
 use std::sync::Arc;
 use tokio::sync::mpsc::Sender;
-use tokio::sync::oneshot;
-
-pub const INSERT_MINT_QUERY: &str = "INSERT OR REPLACE INTO token_mint (pubkey, mint_authority, supply, decimals, is_initialized, freeze_authority) SELECT ?, ?, ?, ?, ?, ?;";
+use tracing::{error, info, warn};
 
 pub struct ProcessorMint {
     bank: Arc<Bank>,
     db_sender: Sender<DbMessage>,
-    mints: Vec<Pubkey>,
+    mints: HashSet<Pubkey>,
+    require_all_mints: bool,
+    mint_authority_tracker: Option<Arc<MintAuthorityTracker>>,
     token_counter: Arc<ProgressCounter>,
+    /// filtered mints not found in the snapshot, reported in the run summary
+    missing_mints_counter: Arc<NamedCounter>,
 }
 
 impl ProcessorMint {
@@ -30,6 +40,8 @@ impl ProcessorMint {
         db_sender: Sender<DbMessage>,
         filters: &Filters,
         token_progress_counter: Arc<ProgressCounter>,
+        require_all_mints: bool,
+        mint_authority_tracker: Option<Arc<MintAuthorityTracker>>,
     ) -> anyhow::Result<Self> {
         let mints = filters.account_mints.clone();
         let processor = Self {
@@ -37,47 +49,81 @@ impl ProcessorMint {
             db_sender,
             token_counter: token_progress_counter,
             mints,
+            require_all_mints,
+            mint_authority_tracker,
+            missing_mints_counter: Arc::new(NamedCounter::new(MISSING_MINT_TABLE.to_string())),
         };
-        processor.create_mint_table().await?;
+        create_table::<MintRow>(&processor.db_sender).await?;
+        create_table::<MissingMintRow>(&processor.db_sender).await?;
+        if processor.mint_authority_tracker.is_some() {
+            create_table::<MintAuthorityChangeRow>(&processor.db_sender).await?;
+        }
         Ok(processor)
     }
 
-    async fn create_mint_table(&self) -> anyhow::Result<usize> {
-        let (response_tx, response_rx) = oneshot::channel();
-        self.db_sender
-            .send(DbMessage::ExecuteSpecial {
-                query: "CREATE TABLE token_mint (
-                    pubkey TEXT NOT NULL PRIMARY KEY,
-                    mint_authority TEXT NULL,
-                    supply INTEGER(8) NOT NULL,
-                    decimals INTEGER(2) NOT NULL,
-                    is_initialized BOOL NOT NULL,
-                    freeze_authority TEXT NULL
-                );"
-                .to_string(),
-                params: vec![],
-                response: response_tx,
-            })
-            .await?;
-        response_rx.await?
+    /// Counter of the filtered mints not found in the snapshot, for the run summary.
+    pub fn missing_mints_counter(&self) -> Arc<dyn ProcessorCallback> {
+        self.missing_mints_counter.clone()
     }
 
     pub async fn process(&mut self) -> anyhow::Result<()> {
         info!("Loading {} mint accounts...", self.mints.len());
+        let mut missing_mints: Vec<&Pubkey> = Vec::new();
         for mint_pubkey in self.mints.iter() {
-            let account = self
-                .bank
-                .get_account(mint_pubkey)
-                .ok_or_else(|| anyhow::anyhow!("Mint account not found: {}", mint_pubkey))?;
-            let mint = spl_token::state::Mint::unpack(account.data())
+            let account = match self.bank.get_account(mint_pubkey) {
+                Some(account) => account,
+                None if self.require_all_mints => {
+                    return Err(anyhow::anyhow!("Mint account not found: {}", mint_pubkey));
+                }
+                None => {
+                    warn!("Mint account not found, skipping: {}", mint_pubkey);
+                    missing_mints.push(mint_pubkey);
+                    let row = MissingMintRow {
+                        mint: mint_pubkey.to_string(),
+                        slot: self.bank.slot(),
+                    };
+                    insert_single_row(&self.db_sender, &row).await?;
+                    self.missing_mints_counter.add(1);
+                    continue;
+                }
+            };
+            let mint = unpack_mint(account.data())
                 .map_err(|e| anyhow::anyhow!("Failed to unpack mint {}: {:?}", mint_pubkey, e))?;
-            insert_mint(&self.db_sender, &self.token_counter, mint_pubkey, &mint)
+            let row = mint_row(mint_pubkey, account.owner(), &mint, self.bank.epoch());
+            if let Some(change) = self
+                .mint_authority_tracker
+                .as_ref()
+                .and_then(|tracker| tracker.check(&row, self.bank.slot()))
+            {
+                insert_single_row(&self.db_sender, &change)
+                    .await
+                    .unwrap_or_else(|e| {
+                        error!(
+                            "Failed to insert authority change of mint {}: {:?}",
+                            mint_pubkey, e
+                        );
+                        0
+                    });
+            }
+            insert_row(&self.db_sender, &self.token_counter, &row)
                 .await
                 .unwrap_or_else(|e| {
                     error!("Failed to insert mint {}: {:?}", mint_pubkey, e);
                     0
                 });
         }
+        if !missing_mints.is_empty() {
+            warn!(
+                "{} of {} filtered mints not found in snapshot: {}",
+                missing_mints.len(),
+                self.mints.len(),
+                missing_mints
+                    .iter()
+                    .map(|m| m.to_string())
+                    .collect::<Vec<_>>()
+                    .join(",")
+            );
+        }
         Ok(())
     }
 }
@@ -91,32 +137,49 @@ impl Processor for ProcessorMint {
     }
 }
 
-pub async fn insert_mint(
-    db_sender: &Sender<DbMessage>,
-    progress_counter: &Arc<ProgressCounter>,
+/// Unpacks a mint of either token program, legacy spl-token mints have the layout of
+/// a token-2022 mint without extensions.
+pub fn unpack_mint(
+    data: &[u8],
+) -> Result<StateWithExtensions<'_, spl_token_2022::state::Mint>, ProgramError> {
+    StateWithExtensions::<spl_token_2022::state::Mint>::unpack(data)
+}
+
+/// The transfer fee of the token-2022 mint is the one in effect at the `epoch`.
+pub fn mint_row(
     pubkey: &Pubkey,
-    token_mint: &spl_token::state::Mint,
-) -> anyhow::Result<usize> {
-    let (response_tx, response_rx) = oneshot::channel();
-    let owned_params = sql_params![
-        pubkey.to_string(),
-        token_mint
+    token_program: &Pubkey,
+    token_mint: &StateWithExtensions<spl_token_2022::state::Mint>,
+    epoch: Epoch,
+) -> MintRow {
+    let base = &token_mint.base;
+    let transfer_fee = token_mint
+        .get_extension::<TransferFeeConfig>()
+        .ok()
+        .map(|transfer_fee_config| transfer_fee_config.get_epoch_fee(epoch));
+    MintRow {
+        pubkey: pubkey.to_string(),
+        token_program: token_program.to_string(),
+        mint_authority: base
             .mint_authority
             .map_or(None, |key| Some(key.to_string())),
-        token_mint.supply as i64,
-        token_mint.decimals,
-        token_mint.is_initialized,
-        token_mint
+        supply: base.supply,
+        decimals: base.decimals,
+        is_initialized: base.is_initialized,
+        freeze_authority: base
             .freeze_authority
             .map_or(None, |key| Some(key.to_string())),
-    ];
-    db_sender
-        .send(DbMessage::Execute {
-            query: INSERT_MINT_QUERY.to_string(),
-            params: owned_params,
-            response: response_tx,
-        })
-        .await?;
-    progress_counter.inc();
-    response_rx.await?
+        transfer_fee_basis_points: transfer_fee
+            .map(|transfer_fee| u16::from(transfer_fee.transfer_fee_basis_points)),
+        transfer_fee_maximum: transfer_fee.map(|transfer_fee| u64::from(transfer_fee.maximum_fee)),
+        interest_rate_bps: token_mint
+            .get_extension::<InterestBearingConfig>()
+            .ok()
+            .map(|interest_bearing_config| i16::from(interest_bearing_config.current_rate)),
+        permanent_delegate: token_mint
+            .get_extension::<PermanentDelegate>()
+            .ok()
+            .and_then(|permanent_delegate| Option::<Pubkey>::from(permanent_delegate.delegate))
+            .map(|key| key.to_string()),
+    }
 }
@@ -1,35 +1,30 @@
-use crate::accounts::{Registrar, Voter};
-use crate::db_message::{DbMessage, OwnedSqlValue};
+use crate::accounts::{LockupKind, Registrar, Voter};
+use crate::db_message::DbMessage;
 use crate::filters::Filters;
 use crate::processors::Processor;
 use crate::progress_bar::ProgressCounter;
-use crate::sql_params;
+use crate::schema::VE_MNDE_ACCOUNT_TABLE;
+use crate::sql_row::{create_table, insert_row, insert_single_row};
 use crate::stats::ProcessorCallback;
 use anchor_lang::AnchorDeserialize;
-use anyhow::anyhow;
 use async_trait::async_trait;
-use log::{debug, error, warn};
-use rusqlite::ToSql;
+use snapshot_parser_types::decoded_accounts::{VeMndeDepositRow, VeMndeRow};
 use solana_accounts_db::accounts_index::ScanConfig;
 use solana_program::pubkey::Pubkey;
 use solana_runtime::bank::Bank;
 use solana_sdk::account::ReadableAccount;
 use std::future::Future;
-use std::str::FromStr;
 use std::string::ToString;
 use std::sync::Arc;
 use tokio::sync::mpsc::Sender;
-use tokio::sync::oneshot;
+use tracing::{debug, error, warn};
 
-pub const VE_MNDE_ACCOUNT_TABLE: &str = "vemnde_accounts";
-pub const INSERT_VE_MNDE_ACCOUNT_QUERY: &str = "INSERT OR REPLACE INTO vemnde_accounts (pubkey, voter_authority, voting_power, owner) SELECT ?, ?, ?, ?;";
-const MARINADE_VSR_PROGRAM_ADDR: &str = "VoteMBhDCqGLRgYpp9o7DGyq81KNmwjXQRAHStjtJsS";
 const VOTER_ACCOUNT_LEN: usize = 2728;
 
 pub struct ProcessorVeMnde {
     bank: Arc<Bank>,
     db_sender: Sender<DbMessage>,
-    marinade_vsr_program_addr: Pubkey,
+    vsr_program: Pubkey,
     vsr_registrar: Registrar,
     vemnde_counter: Arc<ProgressCounter>,
     current_ts: i64,
@@ -43,52 +38,25 @@ impl ProcessorVeMnde {
         vemnde_progress_counter: Arc<ProgressCounter>,
         current_ts: i64,
     ) -> anyhow::Result<Self> {
-        let vsr_registrar_vec = filters.vsr_registrar_data.clone();
-        let vsr_registrar_data: &mut &[u8] = &mut vsr_registrar_vec.as_slice();
-        let vsr_registrar: Registrar = Registrar::deserialize(vsr_registrar_data)?;
+        let vsr_registrar = filters.vsr_registrar()?;
         let processor = Self {
             bank,
             db_sender,
-            marinade_vsr_program_addr: Pubkey::from_str(MARINADE_VSR_PROGRAM_ADDR).map_err(
-                |e| {
-                    anyhow!(
-                        "Cannot pars VSR program address {}: {:?}",
-                        MARINADE_VSR_PROGRAM_ADDR,
-                        e
-                    )
-                },
-            )?,
+            vsr_program: filters.vsr_program,
             vemnde_counter: vemnde_progress_counter,
             vsr_registrar,
             current_ts,
         };
-        processor.create_native_staking_table().await?;
+        create_table::<VeMndeRow>(&processor.db_sender).await?;
+        create_table::<VeMndeDepositRow>(&processor.db_sender).await?;
         Ok(processor)
     }
 
-    async fn create_native_staking_table(&self) -> anyhow::Result<usize> {
-        let (response_tx, response_rx) = oneshot::channel();
-        self.db_sender
-            .send(DbMessage::ExecuteSpecial {
-                query: "CREATE TABLE vemnde_accounts (
-                    pubkey TEXT NOT NULL PRIMARY KEY,
-                    voter_authority TEXT NOT NULL,
-                    voting_power TEXT NOT NULL,
-                    owner TEXT NOT NULL
-                );"
-                .to_string(),
-                params: vec![],
-                response: response_tx,
-            })
-            .await?;
-        response_rx.await?
-    }
-
     pub async fn process(&mut self) -> anyhow::Result<()> {
         debug!("Loading VSR registrar accounts from bank...");
 
         let vsr_voter_accounts = self.bank.get_filtered_program_accounts(
-            &self.marinade_vsr_program_addr,
+            &self.vsr_program,
             |account_data| match account_data.data().len() {
                 VOTER_ACCOUNT_LEN => true,
                 _ => false,
@@ -105,20 +73,26 @@ impl ProcessorVeMnde {
         );
         for (pubkey, account) in vsr_voter_accounts {
             if let Ok(voter_account) = Voter::deserialize(&mut account.data()) {
-                insert_vemnde(
-                    &self.db_sender,
-                    &self.vemnde_counter,
+                let result = match vemnde_row(
                     &pubkey,
-                    &account.owner(),
+                    account.owner(),
                     &self.vsr_registrar,
                     &voter_account,
                     self.current_ts,
-                )
-                .await
-                .unwrap_or_else(|e| {
+                ) {
+                    Ok(row) => insert_row(&self.db_sender, &self.vemnde_counter, &row).await,
+                    Err(e) => Err(e),
+                };
+                result.unwrap_or_else(|e| {
                     error!("Error: failed to insert voter account {}: {:?}", pubkey, e);
                     0
                 });
+                if let Err(e) = self.insert_deposits(&pubkey, &voter_account).await {
+                    error!(
+                        "Error: failed to insert deposits of voter account {}: {:?}",
+                        pubkey, e
+                    );
+                }
             } else {
                 warn!("Error: failed to unpack voter account: {:?}", pubkey);
             }
@@ -126,6 +100,13 @@ impl ProcessorVeMnde {
 
         Ok(())
     }
+
+    async fn insert_deposits(&self, pubkey: &Pubkey, voter: &Voter) -> anyhow::Result<()> {
+        for row in vemnde_deposit_rows(pubkey, &self.vsr_registrar, voter, self.current_ts)? {
+            insert_single_row(&self.db_sender, &row).await?;
+        }
+        Ok(())
+    }
 }
 
 impl Processor for ProcessorVeMnde {
@@ -144,41 +125,203 @@ impl ProcessorCallback for ProcessorVeMnde {
     }
 }
 
-pub async fn insert_vemnde(
-    db_sender: &Sender<DbMessage>,
-    progress_counter: &Arc<ProgressCounter>,
+pub fn vemnde_row(
     pubkey: &Pubkey,
     owner: &Pubkey,
     registrar: &Registrar,
     voter: &Voter,
     current_ts: i64,
-) -> anyhow::Result<usize> {
-    let (response_tx, response_rx) = oneshot::channel();
+) -> anyhow::Result<VeMndeRow> {
+    let mut row = VeMndeRow {
+        pubkey: pubkey.to_string(),
+        voter_authority: voter.voter_authority.to_string(),
+        voting_power: 0,
+        owner: owner.to_string(),
+        baseline_vote_weight: 0,
+        locked_vote_weight: 0,
+        amount_deposited_native: 0,
+        active_deposits: 0,
+        max_lockup_end_ts: None,
+    };
+    let overflow = || anyhow::anyhow!("VoterWeightOverflow");
+    for d in voter.deposits.iter().filter(|d| d.is_used) {
+        let (baseline_vote_weight, locked_vote_weight) =
+            d.vote_weights(registrar.voting_mint_config(d)?, current_ts)?;
+        row.baseline_vote_weight = row
+            .baseline_vote_weight
+            .checked_add(baseline_vote_weight)
+            .ok_or_else(overflow)?;
+        row.locked_vote_weight = row
+            .locked_vote_weight
+            .checked_add(locked_vote_weight)
+            .ok_or_else(overflow)?;
+        row.amount_deposited_native = row
+            .amount_deposited_native
+            .checked_add(d.amount_deposited_native)
+            .ok_or_else(overflow)?;
+        row.active_deposits += 1;
+        if d.lockup.kind != LockupKind::None {
+            row.max_lockup_end_ts = row.max_lockup_end_ts.max(Some(d.lockup.end_ts));
+        }
+    }
+    row.voting_power = row
+        .baseline_vote_weight
+        .checked_add(row.locked_vote_weight)
+        .ok_or_else(overflow)?;
+    Ok(row)
+}
 
-    let voting_power = voter
+/// Rows of the used deposits of the voter, each with the voting mint config it is weighted by.
+pub fn vemnde_deposit_rows(
+    pubkey: &Pubkey,
+    registrar: &Registrar,
+    voter: &Voter,
+    current_ts: i64,
+) -> anyhow::Result<Vec<VeMndeDepositRow>> {
+    voter
         .deposits
         .iter()
-        .filter(|d| d.is_used)
-        .try_fold(0u64, |sum, d| {
-            d.voting_power(
-                &registrar.voting_mints[d.voting_mint_config_idx as usize],
-                current_ts,
-            )
-            .map(|vp| sum.checked_add(vp).unwrap())
-        })?;
-    let owned_params = sql_params![
-        pubkey.to_string(),
-        voter.voter_authority.to_string(),
-        voting_power.to_string(),
-        owner.to_string(),
-    ];
-    db_sender
-        .send(DbMessage::Execute {
-            query: INSERT_VE_MNDE_ACCOUNT_QUERY.to_string(),
-            params: owned_params,
-            response: response_tx,
+        .enumerate()
+        .filter(|(_, d)| d.is_used)
+        .map(|(index, d)| {
+            let voting_mint_config = registrar.voting_mint_config(d)?;
+            Ok(VeMndeDepositRow {
+                deposit_id: format!("{}:{}", pubkey, index),
+                voter: pubkey.to_string(),
+                deposit_index: index as u8,
+                voter_authority: voter.voter_authority.to_string(),
+                voting_mint: voting_mint_config.mint.to_string(),
+                amount_deposited_native: d.amount_deposited_native,
+                amount_initially_locked_native: d.amount_initially_locked_native,
+                lockup_kind: d.lockup.kind as u8,
+                lockup_start_ts: d.lockup.start_ts,
+                lockup_end_ts: d.lockup.end_ts,
+                baseline_vote_weight_scaled_factor: voting_mint_config
+                    .baseline_vote_weight_scaled_factor,
+                max_extra_lockup_vote_weight_scaled_factor: voting_mint_config
+                    .max_extra_lockup_vote_weight_scaled_factor,
+                lockup_saturation_secs: voting_mint_config.lockup_saturation_secs,
+                digit_shift: voting_mint_config.digit_shift,
+                voting_power: d.voting_power(voting_mint_config, current_ts)?,
+            })
         })
-        .await?;
-    progress_counter.inc();
-    response_rx.await?
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::accounts::{DepositEntry, Lockup, VotingMintConfig, SECS_PER_DAY};
+
+    const DAY: i64 = SECS_PER_DAY as i64;
+
+    fn voting_mint_config(mint: Pubkey) -> VotingMintConfig {
+        VotingMintConfig {
+            mint,
+            grant_authority: Pubkey::default(),
+            baseline_vote_weight_scaled_factor: 1_000_000_000,
+            max_extra_lockup_vote_weight_scaled_factor: 1_000_000_000,
+            lockup_saturation_secs: 100 * SECS_PER_DAY,
+            digit_shift: 0,
+            reserved1: [0; 7],
+            reserved2: [0; 7],
+        }
+    }
+
+    fn registrar(mint: Pubkey) -> Registrar {
+        Registrar {
+            discriminator: [0; 8],
+            governance_program_id: Pubkey::default(),
+            realm: Pubkey::default(),
+            realm_governing_token_mint: mint,
+            realm_authority: Pubkey::default(),
+            reserved1: [0; 32],
+            voting_mints: std::array::from_fn(|_| voting_mint_config(mint)),
+            time_offset: 0,
+            bump: 0,
+            reserved2: [0; 7],
+            reserved3: [0; 11],
+        }
+    }
+
+    fn deposit(kind: LockupKind, end_ts: i64, amount: u64) -> DepositEntry {
+        DepositEntry {
+            lockup: Lockup {
+                start_ts: 0,
+                end_ts,
+                kind,
+                reserved: [0; 15],
+            },
+            amount_deposited_native: amount,
+            amount_initially_locked_native: amount,
+            is_used: true,
+            allow_clawback: false,
+            voting_mint_config_idx: 0,
+            reserved: [0; 29],
+        }
+    }
+
+    fn voter(deposits: Vec<DepositEntry>) -> Voter {
+        let mut deposits = deposits.into_iter();
+        Voter {
+            discriminator: [0; 8],
+            voter_authority: Pubkey::new_unique(),
+            registrar: Pubkey::new_unique(),
+            deposits: std::array::from_fn(|_| {
+                deposits.next().unwrap_or_else(|| DepositEntry {
+                    is_used: false,
+                    ..deposit(LockupKind::None, 0, 0)
+                })
+            }),
+            voter_bump: 0,
+            voter_weight_record_bump: 0,
+            reserved: [0; 94],
+        }
+    }
+
+    #[test]
+    fn voting_power_of_lockups() {
+        let registrar = registrar(Pubkey::new_unique());
+        let voter = voter(vec![
+            // baseline 1000, no locked weight
+            deposit(LockupKind::None, 0, 1_000),
+            // baseline 1000, locked 1000 * 50 days left / 100 days of saturation = 500
+            deposit(LockupKind::Cliff, 50 * DAY, 1_000),
+            // baseline 1000, locked 1000 saturated as the constant lockup never shortens
+            deposit(LockupKind::Constant, 200 * DAY, 1_000),
+            // baseline 1000, four daily cliffs of 250 vesting in 1, 2, 3 and 4 days:
+            // locked 250 * (1 + 2 + 3 + 4) / 100 = 25
+            deposit(LockupKind::Daily, 4 * DAY, 1_000),
+        ]);
+        let pubkey = Pubkey::new_unique();
+
+        let rows = vemnde_deposit_rows(&pubkey, &registrar, &voter, 0).unwrap();
+        let voting_powers: Vec<u64> = rows.iter().map(|row| row.voting_power).collect();
+        assert_eq!(voting_powers, vec![1_000, 1_500, 2_000, 1_025]);
+
+        let row = vemnde_row(&pubkey, &pubkey, &registrar, &voter, 0).unwrap();
+        assert_eq!(row.baseline_vote_weight, 4_000);
+        assert_eq!(row.locked_vote_weight, 1_525);
+        assert_eq!(row.voting_power, 5_525);
+        assert_eq!(row.amount_deposited_native, 4_000);
+        assert_eq!(row.active_deposits, 4);
+        assert_eq!(row.max_lockup_end_ts, Some(200 * DAY));
+
+        // 30 days later the cliff has 20 days left and the daily vesting has expired
+        let row = vemnde_row(&pubkey, &pubkey, &registrar, &voter, 30 * DAY).unwrap();
+        assert_eq!(row.locked_vote_weight, 200 + 1_000);
+    }
+
+    #[test]
+    fn unknown_voting_mint_config_is_rejected() {
+        let registrar = registrar(Pubkey::new_unique());
+        let voter = voter(vec![DepositEntry {
+            voting_mint_config_idx: registrar.voting_mints.len() as u8,
+            ..deposit(LockupKind::Cliff, 50 * DAY, 1_000)
+        }]);
+        let pubkey = Pubkey::new_unique();
+
+        assert!(vemnde_deposit_rows(&pubkey, &registrar, &voter, 0).is_err());
+        assert!(vemnde_row(&pubkey, &pubkey, &registrar, &voter, 0).is_err());
+    }
 }
@@ -0,0 +1,140 @@
+use crate::db_message::DbMessage;
+use crate::processors::Processor;
+use crate::progress_bar::ProgressCounter;
+use crate::schema::VOTE_ACCOUNT_TABLE;
+use crate::sql_row::{create_table, insert_row, insert_single_row};
+use crate::stats::ProcessorCallback;
+use async_trait::async_trait;
+use snapshot_parser_types::decoded_accounts::{VoteAccountEpochCreditsRow, VoteAccountRow};
+use solana_program::pubkey::Pubkey;
+use solana_program::vote::state::VoteState;
+use solana_runtime::bank::Bank;
+use solana_sdk::account::ReadableAccount;
+use std::future::Future;
+use std::string::ToString;
+use std::sync::Arc;
+use tokio::sync::mpsc::Sender;
+use tracing::{debug, error, warn};
+
+pub struct ProcessorVoteAccounts {
+    bank: Arc<Bank>,
+    db_sender: Sender<DbMessage>,
+    vote_account_counter: Arc<ProgressCounter>,
+}
+
+impl ProcessorVoteAccounts {
+    pub async fn new(
+        bank: Arc<Bank>,
+        db_sender: Sender<DbMessage>,
+        vote_account_progress_counter: Arc<ProgressCounter>,
+    ) -> anyhow::Result<Self> {
+        let processor = Self {
+            bank,
+            db_sender,
+            vote_account_counter: vote_account_progress_counter,
+        };
+        create_table::<VoteAccountRow>(&processor.db_sender).await?;
+        create_table::<VoteAccountEpochCreditsRow>(&processor.db_sender).await?;
+        Ok(processor)
+    }
+
+    pub async fn process(&mut self) -> anyhow::Result<()> {
+        debug!("Loading vote accounts from bank...");
+
+        let mut rows = Vec::new();
+        for (pubkey, (stake, vote_account)) in self.bank.vote_accounts().iter() {
+            match vote_account.vote_state() {
+                Ok(vote_state) => rows.push((
+                    vote_account_row(pubkey, vote_account.lamports(), *stake, vote_state),
+                    vote_account_epoch_credits_rows(pubkey, vote_state),
+                )),
+                Err(e) => warn!("Failed to get the vote state of {}: {}", pubkey, e),
+            }
+        }
+        debug!("Vote account processor loaded {} vote accounts", rows.len());
+
+        for (vote_account_row, epoch_credits_rows) in rows {
+            insert_row(
+                &self.db_sender,
+                &self.vote_account_counter,
+                &vote_account_row,
+            )
+            .await
+            .unwrap_or_else(|e| {
+                error!(
+                    "Failed to insert vote account {}: {:?}",
+                    vote_account_row.vote_account, e
+                );
+                0
+            });
+            for row in epoch_credits_rows {
+                insert_single_row(&self.db_sender, &row)
+                    .await
+                    .unwrap_or_else(|e| {
+                        error!("Failed to insert epoch credits {}: {:?}", row.id, e);
+                        0
+                    });
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Processor for ProcessorVoteAccounts {
+    fn name() -> &'static str {
+        "Vote Accounts"
+    }
+    fn process(&mut self) -> impl Future<Output = anyhow::Result<()>> + Send {
+        self.process()
+    }
+}
+
+#[async_trait]
+impl ProcessorCallback for ProcessorVoteAccounts {
+    async fn get_count(&self) -> (String, u64) {
+        (
+            VOTE_ACCOUNT_TABLE.to_string(),
+            self.vote_account_counter.get(),
+        )
+    }
+}
+
+pub fn vote_account_row(
+    pubkey: &Pubkey,
+    lamports: u64,
+    stake: u64,
+    vote_state: &VoteState,
+) -> VoteAccountRow {
+    VoteAccountRow {
+        vote_account: pubkey.to_string(),
+        node_pubkey: vote_state.node_pubkey.to_string(),
+        authorized_withdrawer: vote_state.authorized_withdrawer.to_string(),
+        commission: vote_state.commission,
+        lamports,
+        stake,
+        last_vote: vote_state.last_voted_slot(),
+        root_slot: vote_state.root_slot,
+        credits: vote_state.credits(),
+        last_timestamp_slot: vote_state.last_timestamp.slot,
+        last_timestamp: vote_state.last_timestamp.timestamp,
+    }
+}
+
+pub fn vote_account_epoch_credits_rows(
+    pubkey: &Pubkey,
+    vote_state: &VoteState,
+) -> Vec<VoteAccountEpochCreditsRow> {
+    vote_state
+        .epoch_credits
+        .iter()
+        .map(
+            |(epoch, credits, prev_credits)| VoteAccountEpochCreditsRow {
+                id: format!("{}:{}", pubkey, epoch),
+                vote_account: pubkey.to_string(),
+                epoch: *epoch,
+                credits: *credits,
+                prev_credits: *prev_credits,
+            },
+        )
+        .collect()
+}
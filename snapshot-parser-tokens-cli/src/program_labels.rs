@@ -0,0 +1,26 @@
+use crate::db_message::DbMessage;
+use crate::sql_row::{create_table, insert_single_row};
+use snapshot_parser::program_registry::ProgramRegistry;
+use snapshot_parser_types::decoded_accounts::ProgramLabelRow;
+use tokio::sync::mpsc::Sender;
+
+/// Records the programs of the registry into the `program_label` table,
+/// so the owners of the parsed accounts can be labeled and classified by a join.
+pub async fn write_program_labels(
+    db_sender: &Sender<DbMessage>,
+    registry: &ProgramRegistry,
+) -> anyhow::Result<()> {
+    create_table::<ProgramLabelRow>(db_sender).await?;
+    for program in registry.programs.iter() {
+        insert_single_row(
+            db_sender,
+            &ProgramLabelRow {
+                address: program.address.to_string(),
+                name: program.name.clone(),
+                category: program.category.as_str().to_string(),
+            },
+        )
+        .await?;
+    }
+    Ok(())
+}
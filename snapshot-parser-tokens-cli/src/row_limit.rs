@@ -0,0 +1,71 @@
+// Sampling of the output tables: the rows of the processors over the per-table limit are dropped
+// before they reach the output writer, so a small DB is produced for schema validation and integration tests.
+use crate::db_message::DbMessage;
+use crate::schema::{PROCESSOR_COMPLETION_TABLE, SCAN_CHECKPOINT_TABLE, SCHEMA_VERSION_TABLE};
+use std::collections::HashMap;
+use tokio::sync::mpsc::{self, Sender};
+use tokio::task::JoinHandle;
+use tracing::info;
+
+/// Bookkeeping tables whose rows are always written.
+const UNLIMITED_TABLES: [&str; 3] = [
+    SCHEMA_VERSION_TABLE,
+    SCAN_CHECKPOINT_TABLE,
+    PROCESSOR_COMPLETION_TABLE,
+];
+
+/// Forwards the messages to `sink`, inserting the first `limit` rows of every table only;
+/// the inserts over the limit are answered without being written, as if the row was not changed,
+/// and the batches are cut to the rows under the limit.
+/// The returned sender is used in place of `sink`, the task ends when all its clones are dropped.
+pub fn spawn_row_limiter(
+    limit: u64,
+    sink: Sender<DbMessage>,
+) -> (Sender<DbMessage>, JoinHandle<()>) {
+    let (sender, mut receiver) = mpsc::channel(sink.max_capacity());
+    let handle = tokio::spawn(async move {
+        let mut table_rows: HashMap<&'static str, u64> = HashMap::new();
+        while let Some(mut msg) = receiver.recv().await {
+            if let DbMessage::InsertBatch { rows, .. } = &mut msg {
+                let table = rows.table();
+                if !UNLIMITED_TABLES.contains(&table) {
+                    let written = table_rows.entry(table).or_default();
+                    let remaining = limit.saturating_sub(*written);
+                    if (rows.len() as u64) > remaining {
+                        rows.truncate(remaining as usize);
+                    }
+                    if rows.is_empty() {
+                        if let DbMessage::InsertBatch { response, .. } = msg {
+                            let _ = response.send(Ok(0));
+                        }
+                        continue;
+                    }
+                    *written += rows.len() as u64;
+                    if *written == limit {
+                        info!("Table {} reached the limit of {} rows", table, limit);
+                    }
+                }
+            }
+            if let DbMessage::Insert { row, .. } = &msg {
+                let table = row.table();
+                if !UNLIMITED_TABLES.contains(&table) {
+                    let rows = table_rows.entry(table).or_default();
+                    if *rows >= limit {
+                        if let DbMessage::Insert { response, .. } = msg {
+                            let _ = response.send(Ok(0));
+                        }
+                        continue;
+                    }
+                    *rows += 1;
+                    if *rows == limit {
+                        info!("Table {} reached the limit of {} rows", table, limit);
+                    }
+                }
+            }
+            if sink.send(msg).await.is_err() {
+                break;
+            }
+        }
+    });
+    (sender, handle)
+}
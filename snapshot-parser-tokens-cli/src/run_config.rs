@@ -0,0 +1,78 @@
+use crate::db_message::DbMessage;
+use crate::filters::Filters;
+use crate::sql_row::{create_table, insert_single_row};
+use clap::parser::ValueSource;
+use clap::{ArgAction, ArgMatches, Command};
+use serde_json::{Map, Value};
+use snapshot_parser::program_registry::ProgramRegistry;
+use snapshot_parser_types::decoded_accounts::RunConfigRow;
+use tokio::sync::mpsc::Sender;
+
+/// Value recorded instead of the options carrying credentials.
+const REDACTED_VALUE: &str = "<redacted>";
+
+/// CLI options by their long name and the environment variables they were set from,
+/// collected from the clap matches so no option is forgotten when new ones are added.
+pub struct RunArgs {
+    pub args: Map<String, Value>,
+    pub env_overrides: Map<String, Value>,
+}
+
+impl RunArgs {
+    /// Values of the `redacted` options (by their id) are replaced, e.g. for URLs with passwords.
+    pub fn from_matches(command: &Command, matches: &ArgMatches, redacted: &[&str]) -> Self {
+        let mut args = Map::new();
+        let mut env_overrides = Map::new();
+        for arg in command.get_arguments() {
+            let id = arg.get_id().as_str();
+            let (Some(source), Some(raw_values)) = (matches.value_source(id), matches.get_raw(id))
+            else {
+                continue;
+            };
+            let value = if redacted.contains(&id) {
+                Value::String(REDACTED_VALUE.to_string())
+            } else {
+                let values: Vec<Value> = raw_values
+                    .map(|raw| Value::String(raw.to_string_lossy().to_string()))
+                    .collect();
+                match arg.get_action() {
+                    ArgAction::Append => Value::Array(values),
+                    _ => values.into_iter().next().unwrap_or(Value::Null),
+                }
+            };
+            if let (ValueSource::EnvVariable, Some(env)) = (source, arg.get_env()) {
+                env_overrides.insert(env.to_string_lossy().to_string(), value.clone());
+            }
+            let name = arg.get_long().unwrap_or(id);
+            args.insert(name.to_string(), value);
+        }
+        Self {
+            args,
+            env_overrides,
+        }
+    }
+}
+
+/// Records the effective configuration of the run into the `run_config` table next to the `snapshot_info`,
+/// so any output can be reproduced later with the same options, filters and program registry.
+pub async fn write_run_config(
+    db_sender: &Sender<DbMessage>,
+    slot: u64,
+    run_args: &RunArgs,
+    filters: &Filters,
+    registry: &ProgramRegistry,
+) -> anyhow::Result<()> {
+    create_table::<RunConfigRow>(db_sender).await?;
+    insert_single_row(
+        db_sender,
+        &RunConfigRow {
+            slot,
+            filters_hash: filters.content_hash(),
+            args: serde_json::to_string(&run_args.args)?,
+            env_overrides: serde_json::to_string(&run_args.env_overrides)?,
+            program_registry: serde_json::to_string(registry)?,
+        },
+    )
+    .await?;
+    Ok(())
+}
@@ -0,0 +1,125 @@
+// Seeded sampling of the scanned program accounts: an account is kept when the hash of the seed and its pubkey
+// falls under the sampling rate, so the same seed selects the same accounts regardless of the scan order or threads.
+use sha2::{Digest, Sha256};
+use solana_program::pubkey::Pubkey;
+use std::collections::HashSet;
+
+#[derive(Clone, Debug)]
+pub struct Sampling {
+    rate: f64,
+    seed: u64,
+    /// accounts with the hash under the threshold are kept
+    threshold: u64,
+    /// accounts kept regardless of the rate, e.g., the `account_pubkeys` filter
+    always_included: HashSet<Pubkey>,
+}
+
+impl Sampling {
+    pub fn new(rate: f64, seed: u64, always_included: HashSet<Pubkey>) -> anyhow::Result<Self> {
+        if rate.is_nan() || rate <= 0.0 || rate > 1.0 {
+            anyhow::bail!("Sampling rate must be in (0, 1], got {}", rate);
+        }
+        Ok(Self {
+            rate,
+            seed,
+            threshold: (rate * u64::MAX as f64) as u64,
+            always_included,
+        })
+    }
+
+    pub fn rate(&self) -> f64 {
+        self.rate
+    }
+
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    /// Whether the account is in the sample; the same for every run with the same seed.
+    pub fn includes(&self, pubkey: &Pubkey) -> bool {
+        if self.rate >= 1.0 || self.always_included.contains(pubkey) {
+            return true;
+        }
+        let digest = Sha256::new()
+            .chain_update(self.seed.to_le_bytes())
+            .chain_update(pubkey.as_ref())
+            .finalize();
+        let hash = u64::from_le_bytes(digest[..8].try_into().expect("sha256 has 32 bytes"));
+        hash < self.threshold
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pubkeys(count: u64) -> Vec<Pubkey> {
+        (0..count)
+            .map(|index| {
+                Pubkey::new_from_array(solana_program::hash::hash(&index.to_le_bytes()).to_bytes())
+            })
+            .collect()
+    }
+
+    fn sample(sampling: &Sampling, pubkeys: &[Pubkey]) -> Vec<Pubkey> {
+        pubkeys
+            .iter()
+            .filter(|pubkey| sampling.includes(pubkey))
+            .copied()
+            .collect()
+    }
+
+    #[test]
+    fn same_seed_selects_same_accounts() {
+        let pubkeys = pubkeys(10_000);
+        let sampling = Sampling::new(0.1, 42, HashSet::new()).unwrap();
+        let selected = sample(&sampling, &pubkeys);
+        assert_eq!(
+            selected,
+            sample(&Sampling::new(0.1, 42, HashSet::new()).unwrap(), &pubkeys)
+        );
+        // the scan order does not matter
+        let mut reversed = pubkeys.clone();
+        reversed.reverse();
+        let mut selected_reversed = sample(&sampling, &reversed);
+        selected_reversed.reverse();
+        assert_eq!(selected, selected_reversed);
+        assert_ne!(
+            selected,
+            sample(&Sampling::new(0.1, 43, HashSet::new()).unwrap(), &pubkeys)
+        );
+    }
+
+    #[test]
+    fn sample_respects_rate() {
+        let pubkeys = pubkeys(10_000);
+        for (rate, min, max) in [(0.01, 50, 150), (0.1, 850, 1_150), (0.5, 4_700, 5_300)] {
+            let selected = sample(&Sampling::new(rate, 7, HashSet::new()).unwrap(), &pubkeys).len();
+            assert!(
+                (min..=max).contains(&selected),
+                "rate {} selected {} of {}",
+                rate,
+                selected,
+                pubkeys.len()
+            );
+        }
+        assert_eq!(
+            sample(&Sampling::new(1.0, 7, HashSet::new()).unwrap(), &pubkeys).len(),
+            pubkeys.len()
+        );
+    }
+
+    #[test]
+    fn always_included_accounts_are_kept() {
+        let pubkeys = pubkeys(1_000);
+        let sampling = Sampling::new(0.001, 7, pubkeys.iter().copied().collect()).unwrap();
+        assert_eq!(sample(&sampling, &pubkeys).len(), pubkeys.len());
+    }
+
+    #[test]
+    fn invalid_rate_is_rejected() {
+        for rate in [0.0, -0.5, 1.5, f64::NAN] {
+            assert!(Sampling::new(rate, 7, HashSet::new()).is_err());
+        }
+    }
+}
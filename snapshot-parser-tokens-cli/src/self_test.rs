@@ -0,0 +1,67 @@
+use crate::extract::verify_query_templates;
+use crate::processors::processor_registry;
+use rusqlite::Connection;
+use std::io::Write;
+use std::path::Path;
+use tracing::info;
+
+/// Size of the probe file written to verify there is enough writable space for the output DB.
+pub const SELF_TEST_SPACE_PROBE_BYTES: u64 = 64 * 1024 * 1024;
+
+/// Verifies that `bytes` can be written to and removed from the directory.
+pub fn check_writable_space(dir: &Path, bytes: u64) -> anyhow::Result<()> {
+    let probe_path = dir.join(format!(
+        "_snapshot-parser-self-test-{}.probe",
+        std::process::id()
+    ));
+    let result = (|| -> std::io::Result<()> {
+        let mut probe = std::fs::File::create(&probe_path)?;
+        let chunk = vec![0u8; 1024 * 1024];
+        let mut written = 0u64;
+        while written < bytes {
+            let len = chunk.len().min((bytes - written) as usize);
+            probe.write_all(&chunk[..len])?;
+            written += len as u64;
+        }
+        probe.sync_all()
+    })();
+    let _ = std::fs::remove_file(&probe_path);
+    result.map_err(|e| {
+        anyhow::anyhow!(
+            "Could not write {} bytes to directory {:?}: {}",
+            bytes,
+            dir,
+            e
+        )
+    })?;
+    info!("Self-test: {} bytes writable in {:?}", bytes, dir);
+    Ok(())
+}
+
+/// Verifies that the output DB contains all tables of the registered processors and that the query templates
+/// of the `extract` subcommand compile against them; returns the number of rows of every table.
+pub fn verify_output_tables(db_path: &Path) -> anyhow::Result<Vec<(&'static str, u64)>> {
+    let db = Connection::open(db_path)
+        .map_err(|e| anyhow::anyhow!("Could not open output DB {:?}: {}", db_path, e))?;
+    let mut table_rows = vec![];
+    for processor in processor_registry().processors {
+        for table in processor.tables {
+            let rows: u64 = db
+                .query_row(&format!("SELECT COUNT(*) FROM {}", table.name), [], |row| {
+                    row.get(0)
+                })
+                .map_err(|e| {
+                    anyhow::anyhow!(
+                        "Table {} of processor {} is not readable: {}",
+                        table.name,
+                        processor.name,
+                        e
+                    )
+                })?;
+            info!("Self-test: table {} has {} rows", table.name, rows);
+            table_rows.push((table.name, rows));
+        }
+    }
+    verify_query_templates(db_path)?;
+    Ok(table_rows)
+}
@@ -0,0 +1,86 @@
+use crate::db_message::DbMessage;
+use crate::schema::{SCHEMA_VERSION, SNAPSHOT_INFO_TABLE};
+use crate::sql_row::{create_table, insert_single_row};
+use rusqlite::{params, Connection, OpenFlags, OptionalExtension};
+use snapshot_parser::accounts_db_stats::accounts_db_stats;
+use snapshot_parser_types::decoded_accounts::SnapshotInfoRow;
+use solana_runtime::bank::Bank;
+use std::path::Path;
+use tokio::sync::mpsc::Sender;
+
+pub fn snapshot_info_row(bank: &Bank, parsed_at: i64) -> SnapshotInfoRow {
+    let stats = accounts_db_stats(bank);
+    SnapshotInfoRow {
+        slot: bank.slot(),
+        epoch: bank.epoch(),
+        bank_hash: bank.hash().to_string(),
+        capitalization: bank.capitalization(),
+        parsed_at,
+        parser_version: env!("CARGO_PKG_VERSION").to_string(),
+        schema_version: SCHEMA_VERSION,
+        total_accounts: stats.total_accounts,
+        storage_entries: stats.storage_entries,
+        alive_bytes: stats.alive_bytes,
+        dead_bytes: stats.dead_bytes,
+        oldest_slot: stats.oldest_slot,
+        partial: false,
+    }
+}
+
+/// Records the bank, the parser run and the accounts-db storage statistics into the `snapshot_info` table,
+/// so consumers of the DB know which snapshot it was parsed from. Returns the written row.
+pub async fn write_snapshot_info(
+    db_sender: &Sender<DbMessage>,
+    bank: &Bank,
+    parsed_at: i64,
+) -> anyhow::Result<SnapshotInfoRow> {
+    create_table::<SnapshotInfoRow>(db_sender).await?;
+    let row = snapshot_info_row(bank, parsed_at);
+    insert_single_row(db_sender, &row).await?;
+    Ok(row)
+}
+
+/// Marks the `snapshot_info` row of an interrupted run as partial.
+pub async fn write_partial_snapshot_info(
+    db_sender: &Sender<DbMessage>,
+    row: &SnapshotInfoRow,
+) -> anyhow::Result<()> {
+    let row = SnapshotInfoRow {
+        partial: true,
+        ..row.clone()
+    };
+    insert_single_row(db_sender, &row).await?;
+    Ok(())
+}
+
+/// Whether the DB at `db_path` was already written from the bank of the slot and bank hash,
+/// i.e., its `snapshot_info` table records them. A missing DB or table means not processed.
+pub fn is_snapshot_processed(db_path: &Path, slot: u64, bank_hash: &str) -> anyhow::Result<bool> {
+    if !db_path.exists() {
+        return Ok(false);
+    }
+    let db = Connection::open_with_flags(db_path, OpenFlags::SQLITE_OPEN_READ_ONLY)?;
+    let has_table = db
+        .query_row(
+            "SELECT 1 FROM sqlite_master WHERE type = 'table' AND name = ?;",
+            params![SNAPSHOT_INFO_TABLE],
+            |_| Ok(()),
+        )
+        .optional()?
+        .is_some();
+    if !has_table {
+        return Ok(false);
+    }
+    let processed = db
+        .query_row(
+            &format!(
+                "SELECT 1 FROM {} WHERE slot = ? AND bank_hash = ?;",
+                SNAPSHOT_INFO_TABLE
+            ),
+            params![slot as i64, bank_hash],
+            |_| Ok(()),
+        )
+        .optional()?
+        .is_some();
+    Ok(processed)
+}
@@ -0,0 +1,77 @@
+use crate::filters::Filters;
+use crate::processors::{unpack_mint, TOKEN_PROGRAMS};
+use solana_client::nonblocking::rpc_client::RpcClient;
+use solana_program::pubkey::Pubkey;
+use tracing::{info, warn};
+
+/// Maximum number of accounts fetched by one `getMultipleAccounts` RPC call.
+const RPC_MULTIPLE_ACCOUNTS_LIMIT: usize = 100;
+
+/// Checks the filters without loading a bank, so bad filters fail before the expensive bank load.
+/// Pubkeys and the overlaps of owners and mints are checked while loading the filters;
+/// this verifies the VSR registrar decodes and, when the RPC URL is set, that all mints exist on chain.
+pub async fn validate_filters(filters: &Filters, rpc_url: Option<&str>) -> anyhow::Result<()> {
+    let registrar = filters.vsr_registrar()?;
+    info!(
+        "VSR registrar of realm {} decoded, governing token mint {}",
+        registrar.realm, registrar.realm_governing_token_mint
+    );
+    info!(
+        "Native stake authority {}, VSR program {}",
+        filters.native_stake_authority, filters.vsr_program
+    );
+    info!(
+        "Filters contain {} account owners, {} mints and {} account pubkeys",
+        filters.account_owners.len(),
+        filters.account_mints.len(),
+        filters.account_pubkeys.len()
+    );
+
+    let Some(rpc_url) = rpc_url else {
+        return Ok(());
+    };
+    info!(
+        "Checking {} mints against RPC {}",
+        filters.account_mints.len(),
+        rpc_url
+    );
+    let rpc_client = RpcClient::new(rpc_url.to_string());
+    let mints: Vec<Pubkey> = filters.account_mints.iter().copied().collect();
+    let mut invalid_mints = Vec::new();
+    for mints_chunk in mints.chunks(RPC_MULTIPLE_ACCOUNTS_LIMIT) {
+        let accounts = rpc_client
+            .get_multiple_accounts(mints_chunk)
+            .await
+            .map_err(|e| anyhow::anyhow!("Could not fetch mints from RPC: {}", e))?;
+        for (mint, account) in mints_chunk.iter().zip(accounts) {
+            match account {
+                None => {
+                    warn!("Mint {} does not exist", mint);
+                    invalid_mints.push(*mint);
+                }
+                Some(account)
+                    if !TOKEN_PROGRAMS.contains(&account.owner)
+                        || unpack_mint(&account.data).is_err() =>
+                {
+                    warn!("Account {} is not a mint of a token program", mint);
+                    invalid_mints.push(*mint);
+                }
+                Some(_) => {}
+            }
+        }
+    }
+    if !invalid_mints.is_empty() {
+        anyhow::bail!(
+            "{} of {} filtered mints are not valid mints: {}",
+            invalid_mints.len(),
+            mints.len(),
+            invalid_mints
+                .iter()
+                .map(|mint| mint.to_string())
+                .collect::<Vec<_>>()
+                .join(",")
+        );
+    }
+    info!("All {} mints exist", mints.len());
+    Ok(())
+}
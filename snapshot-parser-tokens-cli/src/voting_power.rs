@@ -0,0 +1,148 @@
+use crate::accounts::{DepositEntry, Lockup, LockupKind, VotingMintConfig};
+use crate::schema::VE_MNDE_DEPOSIT_TABLE;
+use rusqlite::types::Type;
+use rusqlite::{Connection, OpenFlags};
+use serde::{Deserialize, Serialize};
+use snapshot_parser_types::decoded_accounts::VeMndeDepositRow;
+use solana_program::pubkey::Pubkey;
+use std::collections::BTreeMap;
+use std::path::Path;
+use std::str::FromStr;
+use tracing::info;
+
+#[derive(Clone, Deserialize, Serialize, Debug, Eq, PartialEq)]
+pub struct VoterVotingPower {
+    /// voter account
+    pub pubkey: String,
+    pub voter_authority: String,
+    pub voting_power: u64,
+}
+
+/// Voting power of the voters recomputed at a timestamp other than the one the DB was parsed at,
+/// e.g., at the creation of a governance proposal.
+#[derive(Clone, Deserialize, Serialize, Debug, Eq, PartialEq)]
+pub struct VotingPowerAt {
+    pub timestamp: i64,
+    pub total_voting_power: u64,
+    /// voters with the voting power above zero, ordered by the voter account
+    pub voters: Vec<VoterVotingPower>,
+}
+
+/// Reads the per-deposit breakdown written by the VeMnde processor from an output SQLite DB.
+pub fn read_vemnde_deposits(db_path: &Path) -> anyhow::Result<Vec<VeMndeDepositRow>> {
+    let db = Connection::open_with_flags(db_path, OpenFlags::SQLITE_OPEN_READ_ONLY)?;
+    let mut stmt = db
+        .prepare(&format!(
+            "SELECT deposit_id, voter, deposit_index, voter_authority, voting_mint, \
+             amount_deposited_native, amount_initially_locked_native, lockup_kind, lockup_start_ts, lockup_end_ts, \
+             baseline_vote_weight_scaled_factor, max_extra_lockup_vote_weight_scaled_factor, lockup_saturation_secs, \
+             digit_shift, voting_power FROM {};",
+            VE_MNDE_DEPOSIT_TABLE
+        ))
+        .map_err(|e| {
+            anyhow::anyhow!(
+                "Could not read {} from {:?}, was it written by a parser with the per-deposit breakdown? {}",
+                VE_MNDE_DEPOSIT_TABLE,
+                db_path,
+                e
+            )
+        })?;
+    let rows = stmt.query_map([], |row| {
+        Ok(VeMndeDepositRow {
+            deposit_id: row.get(0)?,
+            voter: row.get(1)?,
+            deposit_index: row.get(2)?,
+            voter_authority: row.get(3)?,
+            voting_mint: row.get(4)?,
+            amount_deposited_native: row.get::<_, i64>(5)? as u64,
+            amount_initially_locked_native: row.get::<_, i64>(6)? as u64,
+            lockup_kind: row.get(7)?,
+            lockup_start_ts: row.get(8)?,
+            lockup_end_ts: row.get(9)?,
+            baseline_vote_weight_scaled_factor: row.get::<_, i64>(10)? as u64,
+            max_extra_lockup_vote_weight_scaled_factor: row.get::<_, i64>(11)? as u64,
+            lockup_saturation_secs: row.get::<_, i64>(12)? as u64,
+            digit_shift: row.get(13)?,
+            voting_power: row.get::<_, String>(14)?.parse().map_err(|e| {
+                rusqlite::Error::FromSqlConversionFailure(14, Type::Text, Box::new(e))
+            })?,
+        })
+    })?;
+    let deposits = rows.collect::<Result<Vec<_>, _>>()?;
+    info!(
+        "Loaded {} vemnde deposits from {:?}",
+        deposits.len(),
+        db_path
+    );
+    Ok(deposits)
+}
+
+/// Voting power of the deposit at the timestamp, computed the same way as by the VSR program.
+pub fn deposit_voting_power(deposit: &VeMndeDepositRow, timestamp: i64) -> anyhow::Result<u64> {
+    let voting_mint_config = VotingMintConfig {
+        mint: Pubkey::from_str(&deposit.voting_mint)?,
+        grant_authority: Pubkey::default(),
+        baseline_vote_weight_scaled_factor: deposit.baseline_vote_weight_scaled_factor,
+        max_extra_lockup_vote_weight_scaled_factor: deposit
+            .max_extra_lockup_vote_weight_scaled_factor,
+        lockup_saturation_secs: deposit.lockup_saturation_secs,
+        digit_shift: deposit.digit_shift,
+        reserved1: [0; 7],
+        reserved2: [0; 7],
+    };
+    let deposit_entry = DepositEntry {
+        lockup: Lockup {
+            start_ts: deposit.lockup_start_ts,
+            end_ts: deposit.lockup_end_ts,
+            kind: LockupKind::try_from(deposit.lockup_kind)?,
+            reserved: [0; 15],
+        },
+        amount_deposited_native: deposit.amount_deposited_native,
+        amount_initially_locked_native: deposit.amount_initially_locked_native,
+        is_used: true,
+        allow_clawback: false,
+        voting_mint_config_idx: 0,
+        reserved: [0; 29],
+    };
+    deposit_entry.voting_power(&voting_mint_config, timestamp)
+}
+
+/// Sums the voting power of the deposits at the timestamp per voter.
+pub fn recompute_voting_power(
+    deposits: &[VeMndeDepositRow],
+    timestamp: i64,
+) -> anyhow::Result<VotingPowerAt> {
+    let mut voters: BTreeMap<&str, VoterVotingPower> = BTreeMap::new();
+    for deposit in deposits {
+        let voting_power = deposit_voting_power(deposit, timestamp).map_err(|e| {
+            anyhow::anyhow!(
+                "Failed to compute voting power of deposit {}: {}",
+                deposit.deposit_id,
+                e
+            )
+        })?;
+        let voter = voters
+            .entry(&deposit.voter)
+            .or_insert_with(|| VoterVotingPower {
+                pubkey: deposit.voter.clone(),
+                voter_authority: deposit.voter_authority.clone(),
+                voting_power: 0,
+            });
+        voter.voting_power = voter
+            .voting_power
+            .checked_add(voting_power)
+            .ok_or_else(|| anyhow::anyhow!("VoterWeightOverflow"))?;
+    }
+    let voters: Vec<VoterVotingPower> = voters
+        .into_values()
+        .filter(|voter| voter.voting_power > 0)
+        .collect();
+    Ok(VotingPowerAt {
+        timestamp,
+        total_voting_power: voters
+            .iter()
+            .try_fold(0u64, |sum, voter| sum.checked_add(voter.voting_power))
+            .ok_or_else(|| anyhow::anyhow!("VoterWeightOverflow"))?,
+        voters,
+    })
+}
@@ -0,0 +1,31 @@
+use {
+    serde::{Deserialize, Serialize},
+    solana_program::clock::Epoch,
+};
+
+/// Inflation rates of the bank at its slot, as yearly fractions of the capitalization.
+#[derive(Clone, Deserialize, Serialize, Debug, Default, PartialEq)]
+pub struct InflationRates {
+    pub total: f64,
+    /// part of the inflation paid to the validators and their stakers
+    pub validator: f64,
+    /// part of the inflation paid to the foundation
+    pub foundation: f64,
+}
+
+/// Economics of the bank, the small counterpart of the validator and stake meta collections
+/// for the jobs that need no more than the rewards of the epoch.
+#[derive(Clone, Deserialize, Serialize, Debug, Default, PartialEq)]
+pub struct BankInfo {
+    pub epoch: Epoch,
+    pub slot: u64,
+    /// hash of the bank, to match it with the other artifacts of the snapshot
+    pub bank_hash: String,
+    pub capitalization: u64,
+    /// active stake delegated to all vote accounts
+    pub total_stake: u64,
+    pub epoch_duration_in_years: f64,
+    pub inflation: InflationRates,
+    /// validator inflation rewards of the epoch, as in the validator meta collection
+    pub validator_rewards: u64,
+}
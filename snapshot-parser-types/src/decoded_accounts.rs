@@ -0,0 +1,505 @@
+// Decoded account rows produced by the tokens CLI processors.
+// Pubkeys are kept as base58 strings so the rows do not depend on a particular `solana-program` version.
+
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Deserialize, Serialize, Debug, Eq, PartialEq)]
+pub struct AccountRow {
+    pub pubkey: String,
+    pub data_len: u64,
+    pub owner: String,
+    pub lamports: u64,
+    pub executable: bool,
+    pub rent_epoch: u64,
+}
+
+#[derive(Clone, Deserialize, Serialize, Debug, Eq, PartialEq)]
+pub struct TokenRow {
+    pub pubkey: String,
+    /// owner program of the token account, spl-token or spl-token-2022
+    pub token_program: String,
+    pub mint: String,
+    pub owner: String,
+    pub amount: u64,
+    pub delegate: Option<String>,
+    /// spl-token `AccountState` as a number (0 uninitialized, 1 initialized, 2 frozen)
+    pub state: u8,
+    pub is_native: Option<u64>,
+    pub delegated_amount: u64,
+    pub close_authority: Option<String>,
+    /// token-2022 transfer fee withheld in the account, `None` without the transfer fee extension
+    pub transfer_fee_withheld_amount: Option<u64>,
+}
+
+#[derive(Clone, Deserialize, Serialize, Debug, Eq, PartialEq)]
+pub struct MintRow {
+    pub pubkey: String,
+    /// owner program of the mint, spl-token or spl-token-2022
+    pub token_program: String,
+    pub mint_authority: Option<String>,
+    pub supply: u64,
+    pub decimals: u8,
+    pub is_initialized: bool,
+    pub freeze_authority: Option<String>,
+    /// token-2022 transfer fee in effect at the snapshot epoch
+    pub transfer_fee_basis_points: Option<u16>,
+    pub transfer_fee_maximum: Option<u64>,
+    /// token-2022 current interest rate of an interest-bearing mint
+    pub interest_rate_bps: Option<i16>,
+    /// token-2022 permanent delegate
+    pub permanent_delegate: Option<String>,
+}
+
+#[derive(Clone, Deserialize, Serialize, Debug, Eq, PartialEq)]
+pub struct VeMndeRow {
+    pub pubkey: String,
+    pub voter_authority: String,
+    pub voting_power: u64,
+    /// owner program of the voter account
+    pub owner: String,
+    /// part of the voting power given by the deposited amounts, summed over the used deposits
+    pub baseline_vote_weight: u64,
+    /// part of the voting power given by the remaining lockups, summed over the used deposits
+    pub locked_vote_weight: u64,
+    pub amount_deposited_native: u64,
+    /// number of the used deposit entries
+    pub active_deposits: u8,
+    /// latest end of the lockups of the used deposits, none without any lockup
+    pub max_lockup_end_ts: Option<i64>,
+}
+
+/// Used deposit entry of a VSR voter account together with its lockup and the voting mint config of the registrar,
+/// enough to recompute the voting power at any timestamp without the snapshot.
+#[derive(Clone, Deserialize, Serialize, Debug, Eq, PartialEq)]
+pub struct VeMndeDepositRow {
+    /// `<voter>:<deposit_index>`
+    pub deposit_id: String,
+    /// voter account, the `pubkey` of the `vemnde_accounts` row
+    pub voter: String,
+    pub deposit_index: u8,
+    pub voter_authority: String,
+    pub voting_mint: String,
+    pub amount_deposited_native: u64,
+    pub amount_initially_locked_native: u64,
+    /// 0 none, 1 daily, 2 monthly, 3 cliff, 4 constant
+    pub lockup_kind: u8,
+    pub lockup_start_ts: i64,
+    pub lockup_end_ts: i64,
+    pub baseline_vote_weight_scaled_factor: u64,
+    pub max_extra_lockup_vote_weight_scaled_factor: u64,
+    pub lockup_saturation_secs: u64,
+    pub digit_shift: i8,
+    /// voting power of the deposit at the parse timestamp
+    pub voting_power: u64,
+}
+
+#[derive(Clone, Deserialize, Serialize, Debug, Eq, PartialEq)]
+pub struct NativeStakeRow {
+    pub pubkey: String,
+    pub withdraw_authority: String,
+    /// active delegation in lamports
+    pub amount: u64,
+}
+
+/// SPL stake pool with its authorities and the pool-level stake and token supply.
+#[derive(Clone, Deserialize, Serialize, Debug, Eq, PartialEq)]
+pub struct StakePoolRow {
+    pub pubkey: String,
+    /// owner program of the stake pool account, there are several deployments of the stake pool program
+    pub program: String,
+    pub manager: String,
+    pub staker: String,
+    pub stake_deposit_authority: String,
+    pub validator_list: String,
+    pub reserve_stake: String,
+    pub pool_mint: String,
+    pub manager_fee_account: String,
+    /// total stake under management in lamports, as of the `last_update_epoch`
+    pub total_lamports: u64,
+    pub pool_token_supply: u64,
+    pub last_update_epoch: u64,
+    pub last_epoch_total_lamports: u64,
+    pub last_epoch_pool_token_supply: u64,
+    pub epoch_fee_numerator: u64,
+    pub epoch_fee_denominator: u64,
+}
+
+/// Validator of the validator list of an SPL stake pool.
+#[derive(Clone, Deserialize, Serialize, Debug, Eq, PartialEq)]
+pub struct StakePoolValidatorRow {
+    /// `<stake_pool>:<vote_account>`
+    pub validator_id: String,
+    pub stake_pool: String,
+    pub vote_account: String,
+    pub active_stake_lamports: u64,
+    pub transient_stake_lamports: u64,
+    pub last_update_epoch: u64,
+    /// 0 active, 1 deactivating transient, 2 ready for removal, 3 deactivating validator, 4 deactivating all
+    pub status: u8,
+}
+
+/// State of the Marinade liquid staking program.
+#[derive(Clone, Deserialize, Serialize, Debug, Eq, PartialEq)]
+pub struct MsolStateRow {
+    pub pubkey: String,
+    pub msol_mint: String,
+    pub admin_authority: String,
+    pub validator_manager_authority: String,
+    pub operational_sol_account: String,
+    pub treasury_msol_account: String,
+    pub validator_list: String,
+    pub stake_list: String,
+    pub reward_fee_bps: u32,
+    pub available_reserve_balance: u64,
+    pub msol_supply: u64,
+    /// mSOL price in SOL shifted by 2^32, for reference only
+    pub msol_price: u64,
+    pub circulating_ticket_count: u64,
+    pub circulating_ticket_balance: u64,
+    pub lent_from_reserve: u64,
+    pub staking_sol_cap: u64,
+    pub min_stake: u64,
+    /// sum of the active balances of the validators
+    pub total_active_balance: u64,
+    pub total_validator_score: u32,
+    pub validator_count: u32,
+    pub stake_count: u32,
+    pub liq_pool_lp_supply: u64,
+    pub liq_pool_lent_from_sol_leg: u64,
+    pub paused: bool,
+}
+
+/// Validator of the Marinade liquid staking validator list.
+#[derive(Clone, Deserialize, Serialize, Debug, Eq, PartialEq)]
+pub struct MsolValidatorRow {
+    pub vote_account: String,
+    /// Marinade state account
+    pub state: String,
+    /// position in the validator list
+    pub list_index: u32,
+    pub active_balance: u64,
+    pub score: u32,
+    pub last_stake_delta_epoch: u64,
+}
+
+/// Stake account of the Marinade liquid staking stake list.
+#[derive(Clone, Deserialize, Serialize, Debug, Eq, PartialEq)]
+pub struct MsolStakeRow {
+    pub stake_account: String,
+    /// Marinade state account
+    pub state: String,
+    /// position in the stake list
+    pub list_index: u32,
+    pub last_update_delegated_lamports: u64,
+    pub last_update_epoch: u64,
+    pub is_emergency_unstaking: bool,
+}
+
+/// Vote account with its vote state, the epoch credits are in [`VoteAccountEpochCreditsRow`].
+#[derive(Clone, Deserialize, Serialize, Debug, Eq, PartialEq)]
+pub struct VoteAccountRow {
+    pub vote_account: String,
+    pub node_pubkey: String,
+    pub authorized_withdrawer: String,
+    pub commission: u8,
+    pub lamports: u64,
+    /// stake delegated to the vote account in the snapshot epoch
+    pub stake: u64,
+    pub last_vote: Option<u64>,
+    pub root_slot: Option<u64>,
+    /// credits earned over the whole history of the vote account
+    pub credits: u64,
+    pub last_timestamp_slot: u64,
+    pub last_timestamp: i64,
+}
+
+/// Credits of a vote account in one epoch of the history kept by the vote state.
+#[derive(Clone, Deserialize, Serialize, Debug, Eq, PartialEq)]
+pub struct VoteAccountEpochCreditsRow {
+    /// `<vote_account>:<epoch>`
+    pub id: String,
+    pub vote_account: String,
+    pub epoch: u64,
+    /// credits at the end of the epoch
+    pub credits: u64,
+    /// credits at the end of the previous epoch
+    pub prev_credits: u64,
+}
+
+/// Balance of a protocol-owned account (e.g., reserve, treasury) in one asset.
+#[derive(Clone, Deserialize, Serialize, Debug, Eq, PartialEq)]
+pub struct ProtocolBalanceRow {
+    /// `<account>:<mint>`, `<account>:lamports` for the lamports of the account
+    pub id: String,
+    /// label of the account in the protocol accounts config
+    pub label: String,
+    pub account: String,
+    /// none for the lamports of the account
+    pub mint: Option<String>,
+    /// lamports, or the amount of the mint summed over the token accounts owned by the account
+    pub amount: u64,
+    /// token accounts of the mint owned by the account, 0 for the lamports
+    pub token_accounts: u64,
+}
+
+/// Circulating supply of a mint at the snapshot slot.
+#[derive(Clone, Deserialize, Serialize, Debug, Eq, PartialEq)]
+pub struct SupplyStatsRow {
+    pub mint: String,
+    pub slot: u64,
+    pub decimals: u8,
+    pub total_supply: u64,
+    /// held by the non-circulating addresses of the supply config
+    pub non_circulating: u64,
+    /// held by the program escrows, the token accounts owned by the staking, governance and MEV programs
+    /// of the program registry or by the accounts of these programs
+    pub escrowed: u64,
+    /// total supply minus the non-circulating and the escrowed amounts
+    pub circulating: u64,
+}
+
+/// Token metadata of a mint using the symbol of a mint tracked by the filters.
+#[derive(Clone, Deserialize, Serialize, Debug, Eq, PartialEq)]
+pub struct SymbolCollisionRow {
+    /// token metadata account
+    pub pubkey: String,
+    pub mint: String,
+    pub symbol: String,
+    pub tracked_mint: String,
+}
+
+/// Any stake account of the snapshot, see `snapshot_parser::stake_meta::StakeMeta`.
+#[derive(Clone, Deserialize, Serialize, Debug, Eq, PartialEq)]
+pub struct StakeAccountRow {
+    pub pubkey: String,
+    pub balance_lamports: u64,
+    pub active_delegation_lamports: u64,
+    pub activating_delegation_lamports: u64,
+    pub deactivating_delegation_lamports: u64,
+    pub activation_epoch: Option<u64>,
+    pub deactivation_epoch: Option<u64>,
+    /// vote account the stake is delegated to
+    pub validator: Option<String>,
+    pub stake_authority: String,
+    pub withdraw_authority: String,
+}
+
+#[derive(Clone, Deserialize, Serialize, Debug, Eq, PartialEq)]
+pub struct TokenMetadataRow {
+    pub pubkey: String,
+    pub mint: String,
+    pub update_authority: String,
+    pub name: String,
+    pub symbol: String,
+    pub uri: String,
+    pub data_length: u64,
+    pub seller_fee_basis_points: u16,
+    pub primary_sale_happened: bool,
+    pub is_mutable: bool,
+    pub edition_nonce: Option<u8>,
+    pub collection_verified: Option<bool>,
+    pub collection_key: Option<String>,
+    /// name, symbol and uri as stored on chain, present when kept next to the sanitized values
+    pub name_raw: Option<String>,
+    pub symbol_raw: Option<String>,
+    pub uri_raw: Option<String>,
+    /// tracked mint imitated by the name or symbol, set only with the spoof detection
+    pub confusable_mint: Option<String>,
+    /// name or symbol mixes several scripts, set only with the spoof detection
+    pub mixed_script: Option<bool>,
+}
+
+/// Program or authority known to the parsers, see `snapshot_parser::program_registry`.
+#[derive(Clone, Deserialize, Serialize, Debug, Eq, PartialEq)]
+pub struct ProgramLabelRow {
+    pub address: String,
+    pub name: String,
+    pub category: String,
+}
+
+/// Token owner passing the rules of a distribution campaign, see the eligibility rules of the tokens CLI.
+#[derive(Clone, Deserialize, Serialize, Debug, Eq, PartialEq)]
+pub struct EligibleOwnerRow {
+    pub owner: String,
+    pub mint: String,
+    /// balance of all token accounts of the owner in the snapshot
+    pub amount: u64,
+    /// lowest balance over the snapshot and the earlier held-through ones
+    pub held_amount: u64,
+    /// slot of the earliest snapshot the balance was held through
+    pub held_since_slot: u64,
+    /// category of the program owning the owner account, `off_curve` for an unlabeled PDA
+    pub owner_type: Option<String>,
+}
+
+/// Token owner with a balance of the campaign mint in the previous snapshot and none in this one,
+/// carried forward by the eligibility rules from the latest held-through output DB.
+#[derive(Clone, Deserialize, Serialize, Debug, Eq, PartialEq)]
+pub struct ClosedOwnerRow {
+    pub owner: String,
+    pub mint: String,
+    /// balance of all token accounts of the owner in the previous snapshot
+    pub previous_amount: u64,
+    pub previous_slot: u64,
+    /// the owner has no token account of the mint left, otherwise its accounts are empty
+    pub accounts_closed: bool,
+}
+
+/// Effective configuration of the parser run, the JSON columns are enough to reproduce the run.
+#[derive(Clone, Deserialize, Serialize, Debug, Eq, PartialEq)]
+pub struct RunConfigRow {
+    pub slot: u64,
+    /// sha256 of the effective filters, including the pubkeys of the referenced files and the overridden addresses
+    pub filters_hash: String,
+    /// JSON object of the CLI options by their long name, including the default values
+    pub args: String,
+    /// JSON object of the environment variables the options were set from
+    pub env_overrides: String,
+    /// JSON of the program registry the run used
+    pub program_registry: String,
+}
+
+/// Bank and accounts-db storage statistics of the parsed snapshot.
+#[derive(Clone, Deserialize, Serialize, Debug, Eq, PartialEq)]
+pub struct SnapshotInfoRow {
+    pub slot: u64,
+    pub epoch: u64,
+    pub bank_hash: String,
+    pub capitalization: u64,
+    /// unix timestamp the parser started at
+    pub parsed_at: i64,
+    pub parser_version: String,
+    /// version of the output DB layout
+    pub schema_version: u32,
+    pub total_accounts: u64,
+    pub storage_entries: u64,
+    pub alive_bytes: u64,
+    pub dead_bytes: u64,
+    pub oldest_slot: Option<u64>,
+    /// the run was interrupted, the DB holds the rows written until then
+    #[serde(default)]
+    pub partial: bool,
+}
+
+/// Version of an output table, recorded whenever the table is created.
+#[derive(Clone, Deserialize, Serialize, Debug, Eq, PartialEq)]
+pub struct SchemaVersionRow {
+    pub table_name: String,
+    /// version of the table layout, bumped with any change of its columns
+    pub table_version: u32,
+    /// version of the whole output DB layout the table was written with
+    pub schema_version: u32,
+}
+
+/// Bank-level values of the validator meta collection written by the validator CLI.
+#[derive(Clone, Deserialize, Serialize, Debug, Eq, PartialEq)]
+pub struct ValidatorMetaCollectionRow {
+    pub slot: u64,
+    pub epoch: u64,
+    pub bank_hash: String,
+    /// `current_epoch` or `cumulative`, the window of the validator credits
+    pub credits_window: String,
+    pub capitalization: u64,
+    pub validator_rewards: u64,
+    /// epoch the jito tip distribution accounts were matched for
+    pub jito_epoch: u64,
+    pub total_jito_tips: u64,
+    pub total_priority_fees_transferred: u64,
+}
+
+/// Validator meta of a vote account written by the validator CLI.
+#[derive(Clone, Deserialize, Serialize, Debug, Eq, PartialEq)]
+pub struct ValidatorMetaRow {
+    pub vote_account: String,
+    pub commission: u8,
+    pub mev_commission: Option<u16>,
+    pub priority_fee_commission: Option<u16>,
+    pub priority_fee_lamports_transferred: Option<u64>,
+    pub stake: u64,
+    pub credits: u64,
+    pub identity: String,
+    pub identity_lamports: u64,
+    pub delinquent: bool,
+}
+
+/// Stake meta of a stake account written by the validator CLI.
+#[derive(Clone, Deserialize, Serialize, Debug, Eq, PartialEq)]
+pub struct StakeMetaRow {
+    pub pubkey: String,
+    pub balance_lamports: u64,
+    pub active_delegation_lamports: u64,
+    pub activating_delegation_lamports: u64,
+    pub deactivating_delegation_lamports: u64,
+    pub activation_epoch: Option<u64>,
+    pub deactivation_epoch: Option<u64>,
+    /// vote account the stake is delegated to
+    pub validator: Option<String>,
+    pub stake_authority: String,
+    pub withdraw_authority: String,
+    pub delegated_lamports: Option<u64>,
+    pub credits_observed: Option<u64>,
+    pub rent_exempt_reserve: Option<u64>,
+}
+
+/// Epoch and slot of the stake meta collection written by the validator CLI.
+#[derive(Clone, Deserialize, Serialize, Debug, Eq, PartialEq)]
+pub struct StakeMetaCollectionRow {
+    pub slot: u64,
+    pub epoch: u64,
+}
+
+/// Progress of a long running scan, committed together with the rows inserted so far.
+/// Rows are processed in the order of their pubkeys, so a resumed scan skips pubkeys up to `last_pubkey`.
+#[derive(Clone, Deserialize, Serialize, Debug, Eq, PartialEq)]
+pub struct ScanCheckpointRow {
+    pub processor: String,
+    pub last_pubkey: String,
+    pub processed: u64,
+    pub slot: u64,
+    /// hash of the filters the scan selects the rows by, a scan resumed with other filters is rejected
+    pub filter_hash: String,
+}
+
+/// Processor whose rows were all committed, a resumed run of the same slot skips it.
+#[derive(Clone, Deserialize, Serialize, Debug, Eq, PartialEq)]
+pub struct ProcessorCompletionRow {
+    pub processor: String,
+    pub slot: u64,
+    /// unix timestamp the processor completed at
+    pub completed_at: i64,
+}
+
+/// Change of the mint or freeze authority of a filtered mint since the previous run into the same output DB.
+#[derive(Clone, Deserialize, Serialize, Debug, Eq, PartialEq)]
+pub struct MintAuthorityChangeRow {
+    /// `<mint>:<slot>`
+    pub id: String,
+    pub mint: String,
+    /// slot of the snapshot the change was found in
+    pub slot: u64,
+    pub previous_mint_authority: Option<String>,
+    pub mint_authority: Option<String>,
+    pub previous_freeze_authority: Option<String>,
+    pub freeze_authority: Option<String>,
+}
+
+/// Filtered mint not found in the snapshot, skipped by the mint processor unless all mints are required.
+#[derive(Clone, Deserialize, Serialize, Debug, Eq, PartialEq)]
+pub struct MissingMintRow {
+    pub mint: String,
+    /// slot of the snapshot the mint was missing in
+    pub slot: u64,
+}
+
+/// Mint referenced by an allowlisted token account that is not written into the `token_mint` table,
+/// so the amounts of its token accounts cannot be decimal-adjusted from the output alone.
+#[derive(Clone, Deserialize, Serialize, Debug, Eq, PartialEq)]
+pub struct UnknownMintRow {
+    pub mint: String,
+    /// decimals of the `--mint-decimals` override, none when unknown
+    pub decimals: Option<u8>,
+    /// `not_fetched` with the `unknown` policy, `not_found` when the mint is missing in the snapshot
+    pub reason: String,
+    /// allowlisted token accounts of the mint
+    pub token_accounts: u64,
+}
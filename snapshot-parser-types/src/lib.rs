@@ -1,4 +1,8 @@
+pub mod bank_info;
+pub mod decoded_accounts;
 pub mod jito_mev;
 pub mod serde_serialize_solana_17;
+pub mod stake_delta;
 pub mod stake_meta;
 pub mod validator_meta;
+pub mod vote_account_state;
@@ -0,0 +1,171 @@
+use {
+    crate::{
+        serde_serialize_solana_17::pubkey_string_conversion,
+        stake_meta::{StakeMeta, StakeMetaCollection},
+    },
+    serde::{Deserialize, Serialize},
+    solana_program::{clock::Epoch, pubkey::Pubkey},
+    std::collections::{BTreeMap, HashMap, HashSet},
+};
+
+/// Change of the active delegated stake of a validator between two epochs.
+#[derive(Clone, Deserialize, Serialize, Debug, Eq, PartialEq)]
+pub struct ValidatorStakeDelta {
+    #[serde(with = "pubkey_string_conversion")]
+    pub vote_account: Pubkey,
+    pub previous_stake: u64,
+    pub stake: u64,
+    /// stake added to the stake accounts delegated to the validator and stake of the accounts newly delegated to it
+    pub inflow: u64,
+    /// stake removed from the stake accounts delegated to the validator and stake of the accounts delegated away
+    pub outflow: u64,
+}
+
+/// Stake of one stake authority moved into and out of one validator between two epochs.
+#[derive(Clone, Deserialize, Serialize, Debug, Eq, PartialEq)]
+pub struct AuthorityStakeMovement {
+    #[serde(with = "pubkey_string_conversion")]
+    pub stake_authority: Pubkey,
+    #[serde(with = "pubkey_string_conversion")]
+    pub vote_account: Pubkey,
+    pub inflow: u64,
+    pub outflow: u64,
+}
+
+#[derive(Clone, Deserialize, Serialize, Debug, Default, Eq, PartialEq)]
+pub struct StakeDeltaCollection {
+    pub previous_epoch: Epoch,
+    pub epoch: Epoch,
+    /// validators with any stake flowing in or out, ordered by the vote account
+    pub validator_deltas: Vec<ValidatorStakeDelta>,
+    /// ordered by the stake authority and the vote account
+    pub authority_movements: Vec<AuthorityStakeMovement>,
+}
+
+#[derive(Default)]
+struct Flow {
+    inflow: u64,
+    outflow: u64,
+}
+
+/// Validator and active delegated stake of a stake account, `None` when it is not delegated.
+fn delegation(stake_meta: &StakeMeta) -> Option<(Pubkey, u64)> {
+    stake_meta
+        .validator
+        .map(|validator| (validator, stake_meta.active_delegation_lamports))
+}
+
+impl StakeDeltaCollection {
+    /// Attributes the change of the active delegated stake between the collections of two epochs
+    /// to the validators and to the stake authorities, matching the stake accounts by their pubkey.
+    ///
+    /// A stake account delegated to the same validator in both epochs contributes the difference of its stake,
+    /// a stake account delegated to another validator (or created, or closed) the whole stake
+    /// as an outflow of the previous validator and an inflow of the current one.
+    /// The movement is attributed to the current stake authority of the account,
+    /// or to the previous one for a stake account closed since.
+    pub fn between(previous: &StakeMetaCollection, current: &StakeMetaCollection) -> Self {
+        let previous_stake_metas: HashMap<Pubkey, &StakeMeta> = previous
+            .stake_metas
+            .iter()
+            .map(|stake_meta| (stake_meta.pubkey, stake_meta))
+            .collect();
+        let mut validator_flows: BTreeMap<Pubkey, Flow> = BTreeMap::new();
+        let mut authority_flows: BTreeMap<(Pubkey, Pubkey), Flow> = BTreeMap::new();
+        let mut add_flow =
+            |stake_authority: Pubkey, validator: Pubkey, inflow: u64, outflow: u64| {
+                if inflow == 0 && outflow == 0 {
+                    return;
+                }
+                for flow in [
+                    validator_flows.entry(validator).or_default(),
+                    authority_flows
+                        .entry((stake_authority, validator))
+                        .or_default(),
+                ] {
+                    flow.inflow += inflow;
+                    flow.outflow += outflow;
+                }
+            };
+
+        let mut matched = HashSet::with_capacity(previous_stake_metas.len());
+        for stake_meta in current.stake_metas.iter() {
+            let previous_stake_meta = previous_stake_metas.get(&stake_meta.pubkey).copied();
+            if previous_stake_meta.is_some() {
+                matched.insert(stake_meta.pubkey);
+            }
+            let stake_authority = stake_meta.stake_authority;
+            match (
+                previous_stake_meta.and_then(delegation),
+                delegation(stake_meta),
+            ) {
+                (Some((previous_validator, previous_stake)), Some((validator, stake)))
+                    if previous_validator == validator =>
+                {
+                    add_flow(
+                        stake_authority,
+                        validator,
+                        stake.saturating_sub(previous_stake),
+                        previous_stake.saturating_sub(stake),
+                    );
+                }
+                (previous_delegation, current_delegation) => {
+                    if let Some((previous_validator, previous_stake)) = previous_delegation {
+                        add_flow(stake_authority, previous_validator, 0, previous_stake);
+                    }
+                    if let Some((validator, stake)) = current_delegation {
+                        add_flow(stake_authority, validator, stake, 0);
+                    }
+                }
+            }
+        }
+        for stake_meta in previous.stake_metas.iter() {
+            if matched.contains(&stake_meta.pubkey) {
+                continue;
+            }
+            if let Some((previous_validator, previous_stake)) = delegation(stake_meta) {
+                add_flow(
+                    stake_meta.stake_authority,
+                    previous_validator,
+                    0,
+                    previous_stake,
+                );
+            }
+        }
+
+        let stake_per_validator = |collection: &StakeMetaCollection| {
+            let mut stakes: HashMap<Pubkey, u64> = HashMap::new();
+            for (validator, stake) in collection.stake_metas.iter().filter_map(delegation) {
+                *stakes.entry(validator).or_default() += stake;
+            }
+            stakes
+        };
+        let (previous_stakes, stakes) =
+            (stake_per_validator(previous), stake_per_validator(current));
+        Self {
+            previous_epoch: previous.epoch,
+            epoch: current.epoch,
+            validator_deltas: validator_flows
+                .into_iter()
+                .map(|(vote_account, flow)| ValidatorStakeDelta {
+                    vote_account,
+                    previous_stake: previous_stakes.get(&vote_account).copied().unwrap_or(0),
+                    stake: stakes.get(&vote_account).copied().unwrap_or(0),
+                    inflow: flow.inflow,
+                    outflow: flow.outflow,
+                })
+                .collect(),
+            authority_movements: authority_flows
+                .into_iter()
+                .map(
+                    |((stake_authority, vote_account), flow)| AuthorityStakeMovement {
+                        stake_authority,
+                        vote_account,
+                        inflow: flow.inflow,
+                        outflow: flow.outflow,
+                    },
+                )
+                .collect(),
+        }
+    }
+}
@@ -12,12 +12,28 @@ pub struct StakeMeta {
     pub active_delegation_lamports: u64,
     pub activating_delegation_lamports: u64,
     pub deactivating_delegation_lamports: u64,
+    /// epoch the delegation was activated at, `None` when not delegated or in older collections
+    #[serde(default)]
+    pub activation_epoch: Option<Epoch>,
+    /// epoch the delegation was deactivated at, `None` when not delegated, not deactivated or in older collections
+    #[serde(default)]
+    pub deactivation_epoch: Option<Epoch>,
     #[serde(with = "option_pubkey_string_conversion")]
     pub validator: Option<Pubkey>,
     #[serde(with = "pubkey_string_conversion")]
     pub stake_authority: Pubkey,
     #[serde(with = "pubkey_string_conversion")]
     pub withdraw_authority: Pubkey,
+    /// lamports of the delegation the activation and deactivation is computed for,
+    /// `None` when not delegated or in older collections
+    #[serde(default)]
+    pub delegated_lamports: Option<u64>,
+    /// vote credits of the validator the stake rewards were last paid for, `None` when not delegated or in older collections
+    #[serde(default)]
+    pub credits_observed: Option<u64>,
+    /// lamports of the balance reserved for the rent exemption, `None` when not initialized or in older collections
+    #[serde(default)]
+    pub rent_exempt_reserve: Option<u64>,
 }
 
 impl Ord for StakeMeta {
@@ -32,9 +48,21 @@ impl PartialOrd<Self> for StakeMeta {
     }
 }
 
-#[derive(Clone, Deserialize, Serialize, Debug)]
+/// Cluster stake of an epoch of the stake history sysvar, the delegations are activated and deactivated by it.
+#[derive(Clone, Deserialize, Serialize, Debug, Eq, PartialEq)]
+pub struct StakeHistoryEpoch {
+    pub epoch: Epoch,
+    pub effective: u64,
+    pub activating: u64,
+    pub deactivating: u64,
+}
+
+#[derive(Clone, Deserialize, Serialize, Debug, PartialEq)]
 pub struct StakeMetaCollection {
     pub epoch: Epoch,
     pub slot: u64,
     pub stake_metas: Vec<StakeMeta>,
+    /// stake history sysvar of the bank, newest epoch first; empty in older collections
+    #[serde(default)]
+    pub stake_history: Vec<StakeHistoryEpoch>,
 }
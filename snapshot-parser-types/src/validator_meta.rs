@@ -1,7 +1,9 @@
 use {
-    crate::serde_serialize_solana_17::pubkey_string_conversion,
+    crate::serde_serialize_solana_17::{option_pubkey_string_conversion, pubkey_string_conversion},
+    crate::vote_account_state::EpochCredits,
     serde::{Deserialize, Serialize},
     solana_program::{clock::Epoch, pubkey::Pubkey},
+    std::collections::BTreeMap,
 };
 
 #[derive(Clone, Deserialize, Serialize, Debug, Eq, PartialEq)]
@@ -11,8 +13,29 @@ pub struct ValidatorMeta {
     pub commission: u8,
     /// jito-tip-distribution // TipDistributionAccount // validator_commission_bps
     pub mev_commission: Option<u16>,
+    /// jito-priority-fee-distribution // PriorityFeeDistributionAccount // validator_commission_bps
+    #[serde(default)]
+    pub priority_fee_commission: Option<u16>,
+    /// jito-priority-fee-distribution // PriorityFeeDistributionAccount // total_lamports_transferred
+    #[serde(default)]
+    pub priority_fee_lamports_transferred: Option<u64>,
     pub stake: u64,
+    /// active delegated stake split by labels of the stake authorities (e.g., marinade_native, other)
+    #[serde(default)]
+    pub stake_by_authority: BTreeMap<String, u64>,
     pub credits: u64,
+    /// node identity of the vote account, not present in older collections
+    #[serde(default, with = "option_pubkey_string_conversion")]
+    pub identity: Option<Pubkey>,
+    /// lamports of the node identity account at the snapshot slot, not present in older collections
+    #[serde(default)]
+    pub identity_lamports: Option<u64>,
+    /// credits of the last epochs of the vote account, oldest first
+    #[serde(default)]
+    pub epoch_credits: Vec<EpochCredits>,
+    /// the last vote of the vote account is too old at the snapshot slot, as reported by the `getVoteAccounts` RPC
+    #[serde(default)]
+    pub delinquent: bool,
 }
 
 impl Ord for ValidatorMeta {
@@ -27,14 +50,74 @@ impl PartialOrd<Self> for ValidatorMeta {
     }
 }
 
-#[derive(Clone, Deserialize, Serialize, Debug, Default)]
+/// Which vote credits are reported in [`ValidatorMeta::credits`].
+#[derive(Clone, Copy, Deserialize, Serialize, Debug, Default, Eq, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum CreditsWindow {
+    /// credits earned in the epoch of the snapshot only
+    #[default]
+    CurrentEpoch,
+    /// all credits earned by the vote account since its creation
+    Cumulative,
+}
+
+/// Normalized performance of a validator, see [`ValidatorMetaCollection::validator_scores`].
+#[derive(Clone, Debug, PartialEq)]
+pub struct ValidatorScore {
+    pub vote_account: Pubkey,
+    /// credits relative to the stake-weighted cluster average (1.0 = average)
+    pub credits_score: f64,
+    /// credits score scaled by the share of rewards left to stakers after the inflation commission
+    pub commission_adjusted_score: f64,
+}
+
+fn score(validator_meta: &ValidatorMeta, average_credits: f64) -> ValidatorScore {
+    let credits_score = if average_credits > 0.0 {
+        validator_meta.credits as f64 / average_credits
+    } else {
+        0.0
+    };
+    ValidatorScore {
+        vote_account: validator_meta.vote_account,
+        credits_score,
+        commission_adjusted_score: credits_score
+            * (100.0 - validator_meta.commission.min(100) as f64)
+            / 100.0,
+    }
+}
+
+#[derive(Clone, Deserialize, Serialize, Debug, Default, PartialEq)]
 pub struct ValidatorMetaCollection {
     pub epoch: Epoch,
     pub slot: u64,
+    /// hash of the bank the collection was generated from, not present in older collections
+    #[serde(default)]
+    pub bank_hash: Option<String>,
+    /// collections generated before the window was configurable contain current epoch credits
+    #[serde(default)]
+    pub credits_window: CreditsWindow,
     pub capitalization: u64,
     pub epoch_duration_in_years: f64,
     pub validator_rate: f64,
     pub validator_rewards: u64,
+    /// epoch the jito tip distribution accounts were matched for, not present in older collections
+    #[serde(default)]
+    pub jito_epoch: Option<Epoch>,
+    /// sum of tips collected by the jito tip distribution accounts of the jito epoch
+    #[serde(default)]
+    pub total_jito_tips: u64,
+    /// number of validators with a jito tip distribution account for the jito epoch
+    #[serde(default)]
+    pub jito_enabled_validators: u64,
+    /// number of duplicate jito tip distribution accounts found for the same validator
+    #[serde(default)]
+    pub jito_duplicate_distribution_accounts: u64,
+    /// sum of priority fees transferred to the jito priority fee distribution accounts of the jito epoch
+    #[serde(default)]
+    pub total_priority_fees_transferred: u64,
+    /// number of validators with a jito priority fee distribution account for the jito epoch
+    #[serde(default)]
+    pub jito_priority_fee_enabled_validators: u64,
     pub validator_metas: Vec<ValidatorMeta>,
 }
 
@@ -63,4 +146,31 @@ impl ValidatorMetaCollection {
 
         move |commission: u8| expected_epr * (100.0 - commission as f64) / 100.0
     }
+
+    /// stake-weighted average of credits over all validators, 0 when there is no stake
+    pub fn stake_weighted_average_credits(&self) -> f64 {
+        let total_stake = self.total_stake();
+        if total_stake == 0 {
+            return 0.0;
+        }
+        self.total_stake_weighted_credits() as f64 / total_stake as f64
+    }
+
+    /// scores of all validators by vote account; when the cluster average is 0 all credits scores are 0
+    pub fn validator_scores(&self) -> BTreeMap<Pubkey, ValidatorScore> {
+        let average_credits = self.stake_weighted_average_credits();
+        self.validator_metas
+            .iter()
+            .map(|v| (v.vote_account, score(v, average_credits)))
+            .collect()
+    }
+
+    /// score of one validator, use [`Self::validator_scores`] to score them all
+    pub fn validator_score(&self, vote_account: &Pubkey) -> Option<ValidatorScore> {
+        let validator_meta = self
+            .validator_metas
+            .iter()
+            .find(|v| v.vote_account == *vote_account)?;
+        Some(score(validator_meta, self.stake_weighted_average_credits()))
+    }
 }
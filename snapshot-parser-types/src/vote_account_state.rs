@@ -0,0 +1,84 @@
+use {
+    crate::serde_serialize_solana_17::pubkey_string_conversion,
+    serde::{Deserialize, Serialize},
+    solana_program::{
+        clock::{Epoch, UnixTimestamp},
+        pubkey::Pubkey,
+    },
+};
+
+/// Lockout of a vote not yet rooted.
+#[derive(Clone, Deserialize, Serialize, Debug, Eq, PartialEq)]
+pub struct VoteLockout {
+    pub slot: u64,
+    pub confirmation_count: u32,
+    /// slots between the voted slot and the slot the vote landed in, 0 for votes cast by older validators
+    pub latency: u8,
+}
+
+#[derive(Clone, Deserialize, Serialize, Debug, Eq, PartialEq)]
+pub struct AuthorizedVoter {
+    pub epoch: Epoch,
+    #[serde(with = "pubkey_string_conversion")]
+    pub authorized_voter: Pubkey,
+}
+
+/// Authorized voter replaced in the past, valid from the start epoch (inclusive) to the end epoch (exclusive).
+#[derive(Clone, Deserialize, Serialize, Debug, Eq, PartialEq)]
+pub struct PriorVoter {
+    #[serde(with = "pubkey_string_conversion")]
+    pub authorized_voter: Pubkey,
+    pub start_epoch: Epoch,
+    pub end_epoch: Epoch,
+}
+
+#[derive(Clone, Deserialize, Serialize, Debug, Eq, PartialEq)]
+pub struct EpochCredits {
+    pub epoch: Epoch,
+    /// cumulative credits at the end of the epoch
+    pub credits: u64,
+    /// cumulative credits at the start of the epoch
+    pub prev_credits: u64,
+}
+
+/// Decoded vote state of a vote account.
+#[derive(Clone, Deserialize, Serialize, Debug, Eq, PartialEq)]
+pub struct VoteAccountState {
+    #[serde(with = "pubkey_string_conversion")]
+    pub vote_account: Pubkey,
+    pub lamports: u64,
+    /// stake delegated to the vote account in the snapshot epoch
+    pub stake: u64,
+    #[serde(with = "pubkey_string_conversion")]
+    pub node_pubkey: Pubkey,
+    #[serde(with = "pubkey_string_conversion")]
+    pub authorized_withdrawer: Pubkey,
+    pub commission: u8,
+    pub root_slot: Option<u64>,
+    pub votes: Vec<VoteLockout>,
+    pub authorized_voters: Vec<AuthorizedVoter>,
+    /// at most the last 32 replaced authorized voters, sorted by the start epoch
+    pub prior_voters: Vec<PriorVoter>,
+    pub epoch_credits: Vec<EpochCredits>,
+    pub last_timestamp_slot: u64,
+    pub last_timestamp: UnixTimestamp,
+}
+
+impl Ord for VoteAccountState {
+    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+        self.vote_account.cmp(&other.vote_account)
+    }
+}
+
+impl PartialOrd<Self> for VoteAccountState {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+#[derive(Clone, Deserialize, Serialize, Debug, Default, PartialEq, Eq)]
+pub struct VoteAccountStateCollection {
+    pub epoch: Epoch,
+    pub slot: u64,
+    pub vote_accounts: Vec<VoteAccountState>,
+}
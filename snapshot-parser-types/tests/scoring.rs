@@ -0,0 +1,79 @@
+use {
+    snapshot_parser_types::validator_meta::{ValidatorMeta, ValidatorMetaCollection},
+    solana_program::pubkey::Pubkey,
+    std::collections::BTreeMap,
+};
+
+fn validator(credits: u64, stake: u64, commission: u8) -> ValidatorMeta {
+    ValidatorMeta {
+        vote_account: Pubkey::new_unique(),
+        commission,
+        mev_commission: None,
+        priority_fee_commission: None,
+        priority_fee_lamports_transferred: None,
+        stake,
+        stake_by_authority: BTreeMap::new(),
+        credits,
+        identity: None,
+        identity_lamports: None,
+        epoch_credits: vec![],
+        delinquent: false,
+    }
+}
+
+fn assert_close(left: f64, right: f64) {
+    assert!((left - right).abs() < 1e-12, "{} != {}", left, right);
+}
+
+fn collection(validator_metas: Vec<ValidatorMeta>) -> ValidatorMetaCollection {
+    ValidatorMetaCollection {
+        validator_metas,
+        ..ValidatorMetaCollection::default()
+    }
+}
+
+#[test]
+fn stake_weighted_average_credits() {
+    let collection = collection(vec![validator(100, 3, 0), validator(200, 1, 0)]);
+    assert_eq!(collection.stake_weighted_average_credits(), 125.0);
+}
+
+#[test]
+fn stake_weighted_average_credits_without_stake() {
+    let collection = collection(vec![validator(100, 0, 0)]);
+    let vote_account = collection.validator_metas[0].vote_account;
+    assert_eq!(collection.stake_weighted_average_credits(), 0.0);
+    assert_eq!(
+        collection.validator_scores()[&vote_account].credits_score,
+        0.0
+    );
+}
+
+#[test]
+fn validator_scores_are_commission_adjusted() {
+    let collection = collection(vec![
+        validator(100, 1, 0),
+        validator(100, 1, 10),
+        validator(50, 2, 100),
+    ]);
+    let scores = collection.validator_scores();
+    let scores: Vec<_> = collection
+        .validator_metas
+        .iter()
+        .map(|v| scores[&v.vote_account].clone())
+        .collect();
+
+    assert_close(scores[0].credits_score, 100.0 / 75.0);
+    assert_close(scores[0].commission_adjusted_score, 100.0 / 75.0);
+    assert_close(scores[1].credits_score, 100.0 / 75.0);
+    assert_close(scores[1].commission_adjusted_score, 0.9 * 100.0 / 75.0);
+    assert_close(scores[2].credits_score, 50.0 / 75.0);
+    assert_close(scores[2].commission_adjusted_score, 0.0);
+
+    let vote_account = collection.validator_metas[1].vote_account;
+    assert_eq!(
+        collection.validator_score(&vote_account),
+        Some(scores[1].clone())
+    );
+    assert_eq!(collection.validator_score(&Pubkey::new_unique()), None);
+}
@@ -0,0 +1,449 @@
+use {
+    snapshot_parser_types::{
+        bank_info::{BankInfo, InflationRates},
+        stake_meta::{StakeHistoryEpoch, StakeMeta, StakeMetaCollection},
+        validator_meta::{CreditsWindow, ValidatorMeta, ValidatorMetaCollection},
+        vote_account_state::{
+            AuthorizedVoter, EpochCredits, PriorVoter, VoteAccountState,
+            VoteAccountStateCollection, VoteLockout,
+        },
+    },
+    solana_program::pubkey::Pubkey,
+    std::{collections::BTreeMap, str::FromStr},
+};
+
+fn pubkey(s: &str) -> Pubkey {
+    Pubkey::from_str(s).unwrap()
+}
+
+fn sample_validator_meta_collection() -> ValidatorMetaCollection {
+    ValidatorMetaCollection {
+        epoch: 650,
+        slot: 280_800_123,
+        bank_hash: Some("5Ns1pCn9ctEgS2hAzHsc6ZQ5JjzkmGf3nqJDtSqpuRMS".to_string()),
+        credits_window: CreditsWindow::CurrentEpoch,
+        capitalization: 584_000_000_000_000_000,
+        epoch_duration_in_years: 0.005_5,
+        validator_rate: 0.05,
+        validator_rewards: 160_600_000_000_000,
+        jito_epoch: Some(649),
+        total_jito_tips: 1_234_000_000_000,
+        jito_enabled_validators: 1,
+        jito_duplicate_distribution_accounts: 1,
+        total_priority_fees_transferred: 12_000_000_000,
+        jito_priority_fee_enabled_validators: 1,
+        validator_metas: vec![
+            ValidatorMeta {
+                vote_account: pubkey("Vote111111111111111111111111111111111111111"),
+                commission: 5,
+                mev_commission: Some(800),
+                priority_fee_commission: Some(5_000),
+                priority_fee_lamports_transferred: Some(12_000_000_000),
+                stake: 1_000_000_000,
+                stake_by_authority: BTreeMap::from([
+                    ("marinade_native".to_string(), 400_000_000),
+                    ("other".to_string(), 600_000_000),
+                ]),
+                credits: 432_000,
+                identity: Some(pubkey("Stake11111111111111111111111111111111111111")),
+                identity_lamports: Some(2_500_000_000),
+                epoch_credits: vec![
+                    EpochCredits {
+                        epoch: 649,
+                        credits: 1_000_000,
+                        prev_credits: 580_000,
+                    },
+                    EpochCredits {
+                        epoch: 650,
+                        credits: 1_432_000,
+                        prev_credits: 1_000_000,
+                    },
+                ],
+                delinquent: false,
+            },
+            ValidatorMeta {
+                vote_account: pubkey("Stake11111111111111111111111111111111111111"),
+                commission: 100,
+                mev_commission: None,
+                priority_fee_commission: None,
+                priority_fee_lamports_transferred: None,
+                stake: 0,
+                stake_by_authority: BTreeMap::new(),
+                credits: 0,
+                identity: None,
+                identity_lamports: None,
+                epoch_credits: vec![],
+                delinquent: true,
+            },
+        ],
+    }
+}
+
+fn sample_stake_meta_collection() -> StakeMetaCollection {
+    StakeMetaCollection {
+        epoch: 650,
+        slot: 280_800_123,
+        stake_metas: vec![
+            StakeMeta {
+                pubkey: pubkey("Stake11111111111111111111111111111111111111"),
+                balance_lamports: 2_282_880,
+                active_delegation_lamports: 1_000_000_000,
+                activating_delegation_lamports: 1,
+                deactivating_delegation_lamports: 2,
+                activation_epoch: Some(600),
+                deactivation_epoch: Some(650),
+                validator: Some(pubkey("Vote111111111111111111111111111111111111111")),
+                stake_authority: pubkey("stWirqFCf2Uts1JBL1Jsd3r6VBWhgnpdPxCTe1MFjrq"),
+                withdraw_authority: pubkey("11111111111111111111111111111111"),
+                delegated_lamports: Some(1_000_000_003),
+                credits_observed: Some(1_432_000),
+                rent_exempt_reserve: Some(2_282_880),
+            },
+            StakeMeta {
+                pubkey: pubkey("Vote111111111111111111111111111111111111111"),
+                balance_lamports: 0,
+                active_delegation_lamports: 0,
+                activating_delegation_lamports: 0,
+                deactivating_delegation_lamports: 0,
+                activation_epoch: None,
+                deactivation_epoch: None,
+                validator: None,
+                stake_authority: pubkey("11111111111111111111111111111111"),
+                withdraw_authority: pubkey("11111111111111111111111111111111"),
+                delegated_lamports: None,
+                credits_observed: None,
+                rent_exempt_reserve: None,
+            },
+        ],
+        stake_history: vec![
+            StakeHistoryEpoch {
+                epoch: 649,
+                effective: 390_000_000_000_000_000,
+                activating: 1_200_000_000_000_000,
+                deactivating: 900_000_000_000_000,
+            },
+            StakeHistoryEpoch {
+                epoch: 648,
+                effective: 389_700_000_000_000_000,
+                activating: 1_000_000_000_000_000,
+                deactivating: 700_000_000_000_000,
+            },
+        ],
+    }
+}
+
+fn sample_vote_account_state_collection() -> VoteAccountStateCollection {
+    VoteAccountStateCollection {
+        epoch: 650,
+        slot: 280_800_123,
+        vote_accounts: vec![VoteAccountState {
+            vote_account: pubkey("Vote111111111111111111111111111111111111111"),
+            lamports: 27_074_400,
+            stake: 1_000_000_000,
+            node_pubkey: pubkey("Stake11111111111111111111111111111111111111"),
+            authorized_withdrawer: pubkey("11111111111111111111111111111111"),
+            commission: 5,
+            root_slot: Some(280_800_090),
+            votes: vec![
+                VoteLockout {
+                    slot: 280_800_091,
+                    confirmation_count: 31,
+                    latency: 1,
+                },
+                VoteLockout {
+                    slot: 280_800_121,
+                    confirmation_count: 1,
+                    latency: 2,
+                },
+            ],
+            authorized_voters: vec![AuthorizedVoter {
+                epoch: 650,
+                authorized_voter: pubkey("Stake11111111111111111111111111111111111111"),
+            }],
+            prior_voters: vec![PriorVoter {
+                authorized_voter: pubkey("11111111111111111111111111111111"),
+                start_epoch: 600,
+                end_epoch: 620,
+            }],
+            epoch_credits: vec![
+                EpochCredits {
+                    epoch: 649,
+                    credits: 1_000_000,
+                    prev_credits: 568_000,
+                },
+                EpochCredits {
+                    epoch: 650,
+                    credits: 1_432_000,
+                    prev_credits: 1_000_000,
+                },
+            ],
+            last_timestamp_slot: 280_800_121,
+            last_timestamp: 1_726_000_000,
+        }],
+    }
+}
+
+/// Deterministic pseudo-random generator so the round-trip cases are reproducible.
+struct Lcg(u64);
+
+impl Lcg {
+    fn next(&mut self) -> u64 {
+        self.0 = self
+            .0
+            .wrapping_mul(6_364_136_223_846_793_005)
+            .wrapping_add(1_442_695_040_888_963_407);
+        self.0
+    }
+
+    fn pubkey(&mut self) -> Pubkey {
+        let mut bytes = [0u8; 32];
+        for chunk in bytes.chunks_mut(8) {
+            chunk.copy_from_slice(&self.next().to_le_bytes());
+        }
+        Pubkey::new_from_array(bytes)
+    }
+}
+
+fn sample_bank_info() -> BankInfo {
+    BankInfo {
+        epoch: 650,
+        slot: 280_800_123,
+        bank_hash: "5Ns1pCn9ctEgS2hAzHsc6ZQ5JjzkmGf3nqJDtSqpuRMS".to_string(),
+        capitalization: 584_000_000_000_000_000,
+        total_stake: 390_000_000_000_000_000,
+        epoch_duration_in_years: 0.005_5,
+        inflation: InflationRates {
+            total: 0.05,
+            validator: 0.05,
+            foundation: 0.0,
+        },
+        validator_rewards: 160_600_000_000_000,
+    }
+}
+
+#[test]
+fn bank_info_json_matches_golden_file() {
+    let json = serde_json::to_string_pretty(&sample_bank_info()).unwrap();
+    assert_eq!(json, include_str!("golden/bank_info.json").trim_end());
+    assert_eq!(
+        serde_json::from_str::<BankInfo>(&json).unwrap(),
+        sample_bank_info()
+    );
+}
+
+#[test]
+fn validator_meta_collection_json_matches_golden_file() {
+    let json = serde_json::to_string_pretty(&sample_validator_meta_collection()).unwrap();
+    assert_eq!(
+        json,
+        include_str!("golden/validator_meta_collection.json").trim_end()
+    );
+}
+
+#[test]
+fn stake_meta_collection_json_matches_golden_file() {
+    let json = serde_json::to_string_pretty(&sample_stake_meta_collection()).unwrap();
+    assert_eq!(
+        json,
+        include_str!("golden/stake_meta_collection.json").trim_end()
+    );
+}
+
+#[test]
+fn vote_account_state_collection_json_matches_golden_file() {
+    let json = serde_json::to_string_pretty(&sample_vote_account_state_collection()).unwrap();
+    assert_eq!(
+        json,
+        include_str!("golden/vote_account_state_collection.json").trim_end()
+    );
+}
+
+#[test]
+fn validator_meta_collection_round_trip() {
+    let mut rng = Lcg(42);
+    for _ in 0..100 {
+        let collection = ValidatorMetaCollection {
+            epoch: rng.next(),
+            slot: rng.next(),
+            bank_hash: (rng.next() & 1 == 1).then(|| rng.pubkey().to_string()),
+            credits_window: if rng.next() & 1 == 1 {
+                CreditsWindow::Cumulative
+            } else {
+                CreditsWindow::CurrentEpoch
+            },
+            capitalization: rng.next(),
+            epoch_duration_in_years: (rng.next() % 1_000_000) as f64 / 1_000_000.0,
+            validator_rate: (rng.next() % 1_000) as f64 / 1_000.0,
+            validator_rewards: rng.next(),
+            jito_epoch: (rng.next() & 1 == 1).then(|| rng.next()),
+            total_jito_tips: rng.next(),
+            jito_enabled_validators: rng.next() % 5,
+            jito_duplicate_distribution_accounts: rng.next() % 3,
+            total_priority_fees_transferred: rng.next(),
+            jito_priority_fee_enabled_validators: rng.next() % 5,
+            validator_metas: (0..rng.next() % 5)
+                .map(|_| ValidatorMeta {
+                    vote_account: rng.pubkey(),
+                    commission: rng.next() as u8,
+                    mev_commission: (rng.next() & 1 == 1).then(|| rng.next() as u16),
+                    priority_fee_commission: (rng.next() & 1 == 1).then(|| rng.next() as u16),
+                    priority_fee_lamports_transferred: (rng.next() & 1 == 1).then(|| rng.next()),
+                    stake: rng.next(),
+                    stake_by_authority: (0..rng.next() % 3)
+                        .map(|i| (format!("label_{}", i), rng.next()))
+                        .collect(),
+                    credits: rng.next(),
+                    identity: (rng.next() & 1 == 1).then(|| rng.pubkey()),
+                    identity_lamports: (rng.next() & 1 == 1).then(|| rng.next()),
+                    epoch_credits: (0..rng.next() % 3)
+                        .map(|_| EpochCredits {
+                            epoch: rng.next(),
+                            credits: rng.next(),
+                            prev_credits: rng.next(),
+                        })
+                        .collect(),
+                    delinquent: rng.next() & 1 == 1,
+                })
+                .collect(),
+        };
+
+        let json = serde_json::to_string(&collection).unwrap();
+        assert_eq!(
+            serde_json::from_str::<ValidatorMetaCollection>(&json).unwrap(),
+            collection
+        );
+
+        let bytes = bincode::serialize(&collection).unwrap();
+        assert_eq!(
+            bincode::deserialize::<ValidatorMetaCollection>(&bytes).unwrap(),
+            collection
+        );
+    }
+}
+
+#[test]
+fn validator_meta_without_identity_and_epoch_credits_deserializes() {
+    let json = r#"{
+        "vote_account": "Vote111111111111111111111111111111111111111",
+        "commission": 5,
+        "mev_commission": null,
+        "stake": 1000000000,
+        "credits": 432000
+    }"#;
+    let validator_meta = serde_json::from_str::<ValidatorMeta>(json).unwrap();
+    assert_eq!(validator_meta.identity, None);
+    assert_eq!(validator_meta.identity_lamports, None);
+    assert!(validator_meta.epoch_credits.is_empty());
+    assert!(!validator_meta.delinquent);
+}
+
+#[test]
+fn stake_meta_collection_round_trip() {
+    let mut rng = Lcg(7);
+    for _ in 0..100 {
+        let collection = StakeMetaCollection {
+            epoch: rng.next(),
+            slot: rng.next(),
+            stake_metas: (0..rng.next() % 5)
+                .map(|_| StakeMeta {
+                    pubkey: rng.pubkey(),
+                    balance_lamports: rng.next(),
+                    active_delegation_lamports: rng.next(),
+                    activating_delegation_lamports: rng.next(),
+                    deactivating_delegation_lamports: rng.next(),
+                    activation_epoch: (rng.next() & 1 == 1).then(|| rng.next()),
+                    deactivation_epoch: (rng.next() & 1 == 1).then(|| rng.next()),
+                    validator: (rng.next() & 1 == 1).then(|| rng.pubkey()),
+                    stake_authority: rng.pubkey(),
+                    withdraw_authority: rng.pubkey(),
+                    delegated_lamports: (rng.next() & 1 == 1).then(|| rng.next()),
+                    credits_observed: (rng.next() & 1 == 1).then(|| rng.next()),
+                    rent_exempt_reserve: (rng.next() & 1 == 1).then(|| rng.next()),
+                })
+                .collect(),
+            stake_history: (0..rng.next() % 4)
+                .map(|_| StakeHistoryEpoch {
+                    epoch: rng.next(),
+                    effective: rng.next(),
+                    activating: rng.next(),
+                    deactivating: rng.next(),
+                })
+                .collect(),
+        };
+
+        let json = serde_json::to_string(&collection).unwrap();
+        assert_eq!(
+            serde_json::from_str::<StakeMetaCollection>(&json).unwrap(),
+            collection
+        );
+
+        let bytes = bincode::serialize(&collection).unwrap();
+        assert_eq!(
+            bincode::deserialize::<StakeMetaCollection>(&bytes).unwrap(),
+            collection
+        );
+    }
+}
+
+#[test]
+fn vote_account_state_collection_round_trip() {
+    let mut rng = Lcg(11);
+    for _ in 0..100 {
+        let collection = VoteAccountStateCollection {
+            epoch: rng.next(),
+            slot: rng.next(),
+            vote_accounts: (0..rng.next() % 5)
+                .map(|_| VoteAccountState {
+                    vote_account: rng.pubkey(),
+                    lamports: rng.next(),
+                    stake: rng.next(),
+                    node_pubkey: rng.pubkey(),
+                    authorized_withdrawer: rng.pubkey(),
+                    commission: rng.next() as u8,
+                    root_slot: (rng.next() & 1 == 1).then(|| rng.next()),
+                    votes: (0..rng.next() % 4)
+                        .map(|_| VoteLockout {
+                            slot: rng.next(),
+                            confirmation_count: rng.next() as u32,
+                            latency: rng.next() as u8,
+                        })
+                        .collect(),
+                    authorized_voters: (0..rng.next() % 3)
+                        .map(|_| AuthorizedVoter {
+                            epoch: rng.next(),
+                            authorized_voter: rng.pubkey(),
+                        })
+                        .collect(),
+                    prior_voters: (0..rng.next() % 3)
+                        .map(|_| PriorVoter {
+                            authorized_voter: rng.pubkey(),
+                            start_epoch: rng.next(),
+                            end_epoch: rng.next(),
+                        })
+                        .collect(),
+                    epoch_credits: (0..rng.next() % 4)
+                        .map(|_| EpochCredits {
+                            epoch: rng.next(),
+                            credits: rng.next(),
+                            prev_credits: rng.next(),
+                        })
+                        .collect(),
+                    last_timestamp_slot: rng.next(),
+                    last_timestamp: rng.next() as i64,
+                })
+                .collect(),
+        };
+
+        let json = serde_json::to_string(&collection).unwrap();
+        assert_eq!(
+            serde_json::from_str::<VoteAccountStateCollection>(&json).unwrap(),
+            collection
+        );
+
+        let bytes = bincode::serialize(&collection).unwrap();
+        assert_eq!(
+            bincode::deserialize::<VoteAccountStateCollection>(&bytes).unwrap(),
+            collection
+        );
+    }
+}
@@ -0,0 +1,154 @@
+use {
+    snapshot_parser_types::{
+        stake_delta::{AuthorityStakeMovement, StakeDeltaCollection, ValidatorStakeDelta},
+        stake_meta::{StakeMeta, StakeMetaCollection},
+    },
+    solana_program::pubkey::Pubkey,
+};
+
+fn stake_meta(
+    pubkey: Pubkey,
+    validator: Option<Pubkey>,
+    active_delegation_lamports: u64,
+    stake_authority: Pubkey,
+) -> StakeMeta {
+    StakeMeta {
+        pubkey,
+        balance_lamports: active_delegation_lamports,
+        active_delegation_lamports,
+        activating_delegation_lamports: 0,
+        deactivating_delegation_lamports: 0,
+        activation_epoch: None,
+        deactivation_epoch: None,
+        validator,
+        stake_authority,
+        withdraw_authority: stake_authority,
+        delegated_lamports: None,
+        credits_observed: None,
+        rent_exempt_reserve: None,
+    }
+}
+
+fn collection(epoch: u64, stake_metas: Vec<StakeMeta>) -> StakeMetaCollection {
+    StakeMetaCollection {
+        epoch,
+        slot: epoch * 432_000,
+        stake_metas,
+        stake_history: vec![],
+    }
+}
+
+#[test]
+fn stake_delta_between_epochs() {
+    let (validator_a, validator_b) = (Pubkey::new_unique(), Pubkey::new_unique());
+    let (authority_x, authority_y) = (Pubkey::new_unique(), Pubkey::new_unique());
+    let (grown, redelegated, closed, created) = (
+        Pubkey::new_unique(),
+        Pubkey::new_unique(),
+        Pubkey::new_unique(),
+        Pubkey::new_unique(),
+    );
+    let previous = collection(
+        600,
+        vec![
+            stake_meta(grown, Some(validator_a), 100, authority_x),
+            stake_meta(redelegated, Some(validator_a), 50, authority_x),
+            stake_meta(closed, Some(validator_b), 30, authority_y),
+        ],
+    );
+    let current = collection(
+        601,
+        vec![
+            stake_meta(grown, Some(validator_a), 120, authority_x),
+            stake_meta(redelegated, Some(validator_b), 50, authority_x),
+            stake_meta(created, Some(validator_b), 10, authority_y),
+        ],
+    );
+
+    let delta = StakeDeltaCollection::between(&previous, &current);
+    assert_eq!((delta.previous_epoch, delta.epoch), (600, 601));
+    let mut expected_validator_deltas = vec![
+        ValidatorStakeDelta {
+            vote_account: validator_a,
+            previous_stake: 150,
+            stake: 120,
+            inflow: 20,
+            outflow: 50,
+        },
+        ValidatorStakeDelta {
+            vote_account: validator_b,
+            previous_stake: 30,
+            stake: 60,
+            inflow: 60,
+            outflow: 30,
+        },
+    ];
+    expected_validator_deltas.sort_by_key(|delta| delta.vote_account);
+    assert_eq!(delta.validator_deltas, expected_validator_deltas);
+    for validator_delta in delta.validator_deltas.iter() {
+        assert_eq!(
+            validator_delta.previous_stake + validator_delta.inflow - validator_delta.outflow,
+            validator_delta.stake
+        );
+    }
+
+    let mut expected_movements = vec![
+        AuthorityStakeMovement {
+            stake_authority: authority_x,
+            vote_account: validator_a,
+            inflow: 20,
+            outflow: 50,
+        },
+        AuthorityStakeMovement {
+            stake_authority: authority_x,
+            vote_account: validator_b,
+            inflow: 50,
+            outflow: 0,
+        },
+        AuthorityStakeMovement {
+            stake_authority: authority_y,
+            vote_account: validator_b,
+            inflow: 10,
+            outflow: 30,
+        },
+    ];
+    expected_movements.sort_by_key(|movement| (movement.stake_authority, movement.vote_account));
+    assert_eq!(delta.authority_movements, expected_movements);
+}
+
+#[test]
+fn stake_delta_ignores_unchanged_and_undelegated_stake() {
+    let validator = Pubkey::new_unique();
+    let authority = Pubkey::new_unique();
+    let (unchanged, deactivated) = (Pubkey::new_unique(), Pubkey::new_unique());
+    let previous = collection(
+        600,
+        vec![
+            stake_meta(unchanged, Some(validator), 100, authority),
+            stake_meta(deactivated, Some(validator), 40, authority),
+        ],
+    );
+    let current = collection(
+        601,
+        vec![
+            stake_meta(unchanged, Some(validator), 100, authority),
+            stake_meta(deactivated, None, 0, authority),
+        ],
+    );
+
+    let delta = StakeDeltaCollection::between(&previous, &current);
+    assert_eq!(
+        delta.validator_deltas,
+        vec![ValidatorStakeDelta {
+            vote_account: validator,
+            previous_stake: 140,
+            stake: 100,
+            inflow: 0,
+            outflow: 40,
+        }]
+    );
+    assert_eq!(delta.authority_movements.len(), 1);
+    assert!(StakeDeltaCollection::between(&current, &current)
+        .validator_deltas
+        .is_empty());
+}
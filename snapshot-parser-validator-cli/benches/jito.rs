@@ -0,0 +1,67 @@
+// Offset parsing of the jito tip distribution accounts, see the Benchmarks section of the README.
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use snapshot_parser_validator_cli::jito_mev::{
+    update_jito_mev_metas, TIP_DISTRIBUTION_ACCOUNT_DISCRIMINATOR,
+};
+use solana_program::pubkey::Pubkey;
+use solana_sdk::account::Account;
+
+const EPOCH: u64 = 700;
+const RENT_EXEMPT_MINIMUM: u64 = 2_000_000;
+// discriminator, validator_vote_account, merkle_root_upload_authority, Option<MerkleRoot>,
+// epoch_created_at, validator_commission_bps, expires_at, bump
+const TIP_DISTRIBUTION_ACCOUNT_LEN: usize = 8 + 32 + 32 + 1 + 64 + 8 + 2 + 8 + 1;
+
+/// Tip distribution account created in the [`EPOCH`], with the merkle root uploaded or not.
+fn tip_distribution_account(with_merkle_root: bool) -> Account {
+    let mut data = Vec::with_capacity(TIP_DISTRIBUTION_ACCOUNT_LEN);
+    data.extend_from_slice(&TIP_DISTRIBUTION_ACCOUNT_DISCRIMINATOR);
+    data.extend_from_slice(Pubkey::new_unique().as_ref()); // validator_vote_account
+    data.extend_from_slice(Pubkey::new_unique().as_ref()); // merkle_root_upload_authority
+    if with_merkle_root {
+        data.push(1);
+        data.extend_from_slice(&[7; 32]); // root
+        data.extend_from_slice(&123_456_789_000u64.to_le_bytes()); // max_total_claim
+        data.extend_from_slice(&[0; 24]); // max_num_nodes, total_funds_claimed, num_nodes_claimed
+    } else {
+        data.push(0);
+    }
+    data.extend_from_slice(&EPOCH.to_le_bytes()); // epoch_created_at
+    data.extend_from_slice(&800u16.to_le_bytes()); // validator_commission_bps
+    data.extend_from_slice(&(EPOCH + 3).to_le_bytes()); // expires_at
+    data.push(255); // bump
+    data.resize(TIP_DISTRIBUTION_ACCOUNT_LEN, 0);
+    Account {
+        lamports: RENT_EXEMPT_MINIMUM + 98_765_432_100,
+        data,
+        owner: Pubkey::new_unique(),
+        executable: false,
+        rent_epoch: 0,
+    }
+}
+
+fn jito(c: &mut Criterion) {
+    let pubkey = Pubkey::new_unique();
+    let mut group = c.benchmark_group("jito");
+    for (name, with_merkle_root) in [("no_merkle_root", false), ("merkle_root", true)] {
+        let account = tip_distribution_account(with_merkle_root);
+        group.bench_function(format!("tip_distribution/{}", name), |b| {
+            b.iter(|| {
+                let mut metas = Vec::with_capacity(1);
+                update_jito_mev_metas(
+                    &mut metas,
+                    black_box(&account),
+                    pubkey,
+                    EPOCH,
+                    RENT_EXEMPT_MINIMUM,
+                )
+                .unwrap();
+                metas
+            })
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, jito);
+criterion_main!(benches);
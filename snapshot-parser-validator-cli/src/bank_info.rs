@@ -0,0 +1,49 @@
+use {
+    snapshot_parser::utils::write_to_json_file,
+    snapshot_parser_types::bank_info::{BankInfo, InflationRates},
+    solana_runtime::bank::Bank,
+    tracing::info,
+};
+
+/// Economics of the bank: the inflation at its slot and the validator rewards of its epoch,
+/// computed the same way as in the validator meta collection.
+pub fn generate_bank_info(bank: &Bank) -> BankInfo {
+    assert!(bank.is_frozen());
+
+    let epoch = bank.epoch();
+    let inflation = bank.inflation();
+    let slot_in_year = bank.slot_in_year_for_inflation();
+    let inflation = InflationRates {
+        total: inflation.total(slot_in_year),
+        validator: inflation.validator(slot_in_year),
+        foundation: inflation.foundation(slot_in_year),
+    };
+    let capitalization = bank.capitalization();
+    let epoch_duration_in_years = bank.epoch_duration_in_years(epoch);
+    let validator_rewards =
+        (inflation.validator * capitalization as f64 * epoch_duration_in_years) as u64;
+    let total_stake = bank.vote_accounts().values().map(|(stake, _)| *stake).sum();
+
+    BankInfo {
+        epoch,
+        slot: bank.slot(),
+        bank_hash: bank.hash().to_string(),
+        capitalization,
+        total_stake,
+        epoch_duration_in_years,
+        inflation,
+        validator_rewards,
+    }
+}
+
+pub fn write_bank_info(bank: &Bank, output_path: &str) -> anyhow::Result<()> {
+    let bank_info = generate_bank_info(bank);
+    info!(
+        "Bank info of epoch {}: capitalization {}, total stake {}, validator rewards {}",
+        bank_info.epoch,
+        bank_info.capitalization,
+        bank_info.total_stake,
+        bank_info.validator_rewards
+    );
+    write_to_json_file(&bank_info, output_path)
+}
@@ -1,12 +1,41 @@
-use env_logger::{Builder, Env};
-use log::LevelFilter;
+use indicatif::MultiProgress;
+use snapshot_parser::bank_loader::BankLoaderConfig;
+use snapshot_parser::metrics::{spawn_metrics_reporter, spawn_metrics_server, MetricsState};
+use snapshot_parser::program_registry::{
+    ProgramRegistry, JITO_PRIORITY_FEE_DISTRIBUTION_PROGRAM, JITO_TIP_DISTRIBUTION_PROGRAM,
+};
+use snapshot_parser::progress_bar::{create_bank_with_progress, define_counter};
+use snapshot_parser::runner::{run_blocking_tasks, spawn_interrupt_handler, BlockingTask};
+use snapshot_parser::snapshot_archive::log_snapshot_archives;
+use snapshot_parser::snapshot_fetch::{fetch_snapshot, SnapshotFetchArgs};
 use snapshot_parser::stake_meta;
-use snapshot_parser::utils::write_to_json_file;
+use snapshot_parser::stake_meta::StakeMetaFilter;
+use snapshot_parser::stats::Stats;
+use snapshot_parser::telemetry::{init_telemetry, TelemetryArgs};
+use snapshot_parser::utils::{write_to_json_file, JsonCompression};
+use snapshot_parser_validator_cli::bank_info::write_bank_info;
+use snapshot_parser_validator_cli::jito_mev::{parse_jito_epoch_selection, JitoEpochSelection};
+use snapshot_parser_validator_cli::sqlite_output::{
+    write_collections_to_sqlite, write_stake_metas_to_sqlite,
+};
+use snapshot_parser_validator_cli::stake_delta::write_stake_delta;
+use snapshot_parser_validator_cli::stake_meta_partition::write_stake_metas_per_validator;
 use snapshot_parser_validator_cli::validator_meta;
-use std::thread::spawn;
+use snapshot_parser_validator_cli::validator_meta::{
+    fill_stake_by_authority, is_validator_meta_collection_written, parse_stake_authority_label,
+    CreditsWindow,
+};
+use snapshot_parser_validator_cli::vote_account_state::generate_vote_account_state_collection;
+use solana_program::pubkey::Pubkey;
+use std::net::SocketAddr;
+use std::sync::mpsc::sync_channel;
+use std::sync::Arc;
+use std::time::Instant;
 use {
-    clap::Parser, log::info, snapshot_parser::bank_loader::create_bank_from_ledger,
-    snapshot_parser::cli::path_parser, std::path::PathBuf,
+    clap::Parser,
+    snapshot_parser::cli::path_parser,
+    std::path::PathBuf,
+    tracing::{info, info_span, Instrument},
 };
 
 #[derive(Parser, Debug)]
@@ -17,72 +46,353 @@ struct Args {
     ledger_path: PathBuf,
 
     /// Path to write JSON file to for the validator metas (e.g., validators.json)
+    #[arg(long, env, required_unless_present = "output_bank_info")]
+    output_validator_meta_collection: Option<String>,
+
+    /// Path to write JSON file to for the economics of the bank (e.g., bank_info.json): capitalization, total stake,
+    /// epoch duration, inflation and validator rewards; the validator and stake meta outputs are optional with it
     #[arg(long, env)]
-    output_validator_meta_collection: String,
+    output_bank_info: Option<String>,
+
+    /// Process the snapshot even when the validator meta collection was already written from the same slot and bank hash;
+    /// without it, such a run exits successfully without loading the bank
+    #[arg(long, env)]
+    force: bool,
 
     /// Path to write JSON file to for the stake metas (e.g., stakes.json)
+    #[arg(
+        long,
+        env,
+        required_unless_present_any = ["output_stake_meta_sqlite", "output_stake_meta_dir", "output_sqlite", "output_bank_info"]
+    )]
+    output_stake_meta_collection: Option<String>,
+
+    /// Path to SQLite DB to write the stake metas to (e.g., stakes.db)
     #[arg(long, env)]
-    output_stake_meta_collection: String,
+    output_stake_meta_sqlite: Option<PathBuf>,
+
+    /// Path to SQLite DB to write the validator metas and the stake metas to (e.g., validators.db),
+    /// written the same way as the output DB of the tokens CLI
+    #[arg(long, env)]
+    output_sqlite: Option<PathBuf>,
+
+    /// Path to directory to write one stake metas JSON file per validator vote account to
+    #[arg(long, env)]
+    output_stake_meta_dir: Option<PathBuf>,
+
+    /// Path to the stake meta collection JSON file of a previous epoch to compute the stake delta since
+    #[arg(long, env, value_parser = path_parser, requires = "output_stake_delta")]
+    previous_stake_meta_collection: Option<PathBuf>,
+
+    /// Path to write JSON file to for the stake inflow and outflow per validator and per stake authority
+    /// since the previous stake meta collection (e.g., stake-delta.json)
+    #[arg(long, env, requires = "previous_stake_meta_collection")]
+    output_stake_delta: Option<String>,
+
+    /// Stake or withdraw authority whose stake accounts are written into the stake meta outputs
+    /// and the stake delta, repeatable; all stake accounts are written when neither this nor --vote-account is set
+    #[arg(long, env, value_delimiter = ',')]
+    stake_authority: Vec<Pubkey>,
+
+    /// Vote account whose delegated stake accounts are written into the stake meta outputs and the stake delta,
+    /// repeatable; combined with --stake-authority, only the stake accounts matching both are written
+    #[arg(long, env, value_delimiter = ',')]
+    vote_account: Vec<Pubkey>,
+
+    /// Path to write JSON file to for the decoded vote states of all vote accounts (e.g., vote-accounts.json)
+    #[arg(long, env)]
+    output_vote_accounts: Option<String>,
+
+    /// Which vote credits are reported per validator: earned in the snapshot epoch or cumulative
+    #[arg(long, env, value_enum, default_value_t = CreditsWindow::CurrentEpoch)]
+    credits_window: CreditsWindow,
+
+    /// Number of the last epochs whose vote credits are reported per validator, the vote state keeps at most 64
+    #[arg(long, env, default_value_t = 10)]
+    epoch_credits_history: usize,
+
+    /// Epoch to match the jito tip distribution accounts for: `current` (snapshot epoch), `previous` or an epoch number
+    #[arg(long, env, value_parser = parse_jito_epoch_selection, default_value = "current")]
+    jito_epoch: JitoEpochSelection,
+
+    /// Labels of stake authorities to split the validator active stake by, in format <label>=<stake authority pubkey>;
+    /// the stake authorities of the program registry are used when not set
+    #[arg(
+        long,
+        env,
+        value_delimiter = ',',
+        value_parser = parse_stake_authority_label
+    )]
+    stake_authority_labels: Vec<(String, Pubkey)>,
+
+    /// Path to JSON config extending and overriding the built-in program registry (e.g., per environment)
+    #[arg(long, env, value_parser = path_parser)]
+    program_registry: Option<PathBuf>,
+
+    /// Compression of the JSON outputs, the extension of the compression is appended to their paths
+    /// (e.g., stakes.json.zst); outputs named with the .zst or .gz extension are compressed without it
+    #[arg(long, env, value_enum, default_value_t = JsonCompression::None)]
+    compress: JsonCompression,
+
+    /// Address to serve Prometheus metrics on at /metrics (e.g., 0.0.0.0:9090), disabled when not set
+    #[arg(long, env)]
+    metrics_addr: Option<SocketAddr>,
+
+    #[command(flatten)]
+    snapshot_fetch: SnapshotFetchArgs,
+
+    #[command(flatten)]
+    bank_loader: BankLoaderConfig,
+
+    #[command(flatten)]
+    telemetry: TelemetryArgs,
 }
 
-fn main() -> anyhow::Result<()> {
-    let mut builder = Builder::from_env(Env::default().default_filter_or("info"));
-    builder.filter_module("solana_metrics::metrics", LevelFilter::Error);
-    builder.init();
+#[tokio::main]
+async fn main() -> anyhow::Result<()> {
+    let mut args: Args = Args::parse();
+    // the JSON outputs are compressed by their extension
+    let compression = args.compress;
+    let compress = |output_path: Option<String>| {
+        output_path.map(|output_path| compression.output_path(&output_path))
+    };
+    args.output_validator_meta_collection = compress(args.output_validator_meta_collection.take());
+    args.output_bank_info = compress(args.output_bank_info.take());
+    args.output_stake_meta_collection = compress(args.output_stake_meta_collection.take());
+    args.output_stake_delta = compress(args.output_stake_delta.take());
+    args.output_vote_accounts = compress(args.output_vote_accounts.take());
+    let mut telemetry = init_telemetry("snapshot-parser-validator-cli", &args.telemetry)?;
+    spawn_interrupt_handler();
 
     info!("Starting snapshot parser...");
-    let args: Args = Args::parse();
 
+    let program_registry = ProgramRegistry::load(args.program_registry.as_deref())?;
+    let jito_program = program_registry.address(JITO_TIP_DISTRIBUTION_PROGRAM)?;
+    let jito_priority_fee_program =
+        program_registry.address(JITO_PRIORITY_FEE_DISTRIBUTION_PROGRAM)?;
+    let stake_authority_labels = if args.stake_authority_labels.is_empty() {
+        program_registry.stake_authority_labels()
+    } else {
+        args.stake_authority_labels.clone()
+    };
+
+    let metrics = match args.metrics_addr {
+        Some(metrics_addr) => {
+            let metrics = Arc::new(MetricsState::new());
+            spawn_metrics_server(metrics_addr, metrics.clone())?;
+            Some(metrics)
+        }
+        None => None,
+    };
+
+    if args.snapshot_fetch.is_set() {
+        fetch_snapshot(&args.ledger_path, &args.snapshot_fetch).await?;
+    }
+    let stats = Arc::new(Stats::new());
+    let multi_progress = MultiProgress::new();
+    let snapshot_manifest = log_snapshot_archives(&args.ledger_path);
+    if let (Some(manifest), Some(output_path)) =
+        (&snapshot_manifest, &args.output_validator_meta_collection)
+    {
+        if !args.force
+            && is_validator_meta_collection_written(output_path, manifest.slot, &manifest.bank_hash)
+        {
+            info!(
+                "Validator meta collection {} was already written from slot {} (bank hash {}), nothing to do; use --force to process again",
+                output_path, manifest.slot, manifest.bank_hash
+            );
+            return Ok(());
+        }
+    }
+    // the spans of the run down from the bank load are the children of the snapshot span
+    let snapshot_span = telemetry.snapshot_span(snapshot_manifest.as_ref())?;
     info!("Creating bank from ledger path: {:?}", &args.ledger_path);
-    let bank = create_bank_from_ledger(&args.ledger_path)?;
+    let bank_load_started = Instant::now();
+    let bank = snapshot_span.in_scope(|| {
+        create_bank_with_progress(
+            &args.ledger_path,
+            &args.bank_loader,
+            &multi_progress,
+            &stats,
+        )
+    })?;
+    let metrics_reporter_handle = metrics.map(|metrics| {
+        metrics.set_bank_load_duration(bank_load_started.elapsed());
+        spawn_metrics_reporter(metrics, stats.clone(), None)
+    });
 
-    let validator_meta_collection_handle = {
-        let bank = bank.clone();
-        spawn(move || {
-            info!("Creating validator meta collection...");
+    // written first, so it is available even when the collections fail
+    if let Some(output_path) = &args.output_bank_info {
+        snapshot_span.in_scope(|| write_bank_info(&bank, output_path))?;
+    }
 
-            let call = || -> anyhow::Result<()> {
-                let validator_meta_collection =
-                    validator_meta::generate_validator_collection(&bank)?;
-                write_to_json_file(
-                    &validator_meta_collection,
-                    &args.output_validator_meta_collection,
-                )?;
-                info!("Validator meta collection finished.");
-                Ok(())
-            };
+    let vote_account_counter = define_counter("vote_accounts", &multi_progress, &stats).await;
+    let stake_account_counter = define_counter("stake_accounts", &multi_progress, &stats).await;
+
+    // the stake metas are needed to split the validator stake by stake authorities
+    let (stake_meta_tx, stake_meta_rx) = sync_channel(1);
+    // the finished collections are passed back for the SQLite outputs
+    let (validator_meta_output_tx, validator_meta_output_rx) = sync_channel(1);
+    let (stake_meta_output_tx, stake_meta_output_rx) = sync_channel(1);
 
-            call()
+    let validator_meta_collection_task: BlockingTask = {
+        let bank = bank.clone();
+        let vote_account_counter = vote_account_counter.clone();
+        let (
+            credits_window,
+            epoch_credits_history,
+            jito_epoch,
+            stake_authority_labels,
+            output_path,
+        ) = (
+            args.credits_window,
+            args.epoch_credits_history,
+            args.jito_epoch,
+            stake_authority_labels,
+            args.output_validator_meta_collection.clone(),
+        );
+        Box::new(move || {
+            info!("Creating validator meta collection...");
+            let mut validator_meta_collection = validator_meta::generate_validator_collection(
+                &bank,
+                credits_window,
+                epoch_credits_history,
+                jito_epoch,
+                &jito_program,
+                &jito_priority_fee_program,
+                &vote_account_counter,
+            )?;
+            let stake_meta_collection: Arc<_> = stake_meta_rx
+                .recv()
+                .map_err(|_| anyhow::anyhow!("Stake meta collection not available"))?;
+            fill_stake_by_authority(
+                &mut validator_meta_collection,
+                &stake_meta_collection,
+                &stake_authority_labels,
+            );
+            if let Some(output_path) = &output_path {
+                write_to_json_file(&validator_meta_collection, output_path)?;
+            }
+            let _ = validator_meta_output_tx.send(validator_meta_collection);
+            info!("Validator meta collection finished.");
+            Ok(())
         })
     };
 
-    let stake_meta_collection_handle = {
+    let stake_meta_collection_task: BlockingTask = {
         let bank = bank.clone();
-        spawn(move || {
+        let stake_account_counter = stake_account_counter.clone();
+        let (output_json, output_dir) = (
+            args.output_stake_meta_collection.clone(),
+            args.output_stake_meta_dir.clone(),
+        );
+        let (previous_collection, output_delta) = (
+            args.previous_stake_meta_collection.clone(),
+            args.output_stake_delta.clone(),
+        );
+        let stake_meta_filter = StakeMetaFilter {
+            stake_authorities: args.stake_authority.iter().copied().collect(),
+            vote_accounts: args.vote_account.iter().copied().collect(),
+        };
+        Box::new(move || {
             info!("Creating stake meta collection...");
-
-            let call = || -> anyhow::Result<()> {
-                let stake_meta_collection = stake_meta::generate_stake_meta_collection(&bank)?;
-                write_to_json_file(&stake_meta_collection, &args.output_stake_meta_collection)?;
-                info!("Stake meta collection finished.");
-                Ok(())
+            let stake_meta_collection =
+                Arc::new(stake_meta::generate_stake_meta_collection_with_progress(
+                    &bank,
+                    Some(&stake_account_counter),
+                )?);
+            // the validator metas split the stake of all stake accounts by the stake authorities
+            let _ = stake_meta_tx.send(stake_meta_collection.clone());
+            let stake_meta_collection = if stake_meta_filter.is_empty() {
+                stake_meta_collection
+            } else {
+                let filtered_collection =
+                    Arc::new(stake_meta_collection.filtered(&stake_meta_filter));
+                info!(
+                    "Writing {} of {} stake metas matching the stake authorities and the vote accounts",
+                    filtered_collection.stake_metas.len(),
+                    stake_meta_collection.stake_metas.len()
+                );
+                filtered_collection
             };
-
-            call()
+            let _ = stake_meta_output_tx.send(stake_meta_collection.clone());
+            if let Some(output_path) = &output_json {
+                write_to_json_file(stake_meta_collection.as_ref(), output_path)?;
+            }
+            if let Some(output_dir) = &output_dir {
+                write_stake_metas_per_validator(&stake_meta_collection, output_dir, compression)?;
+            }
+            if let (Some(previous_collection), Some(output_path)) =
+                (&previous_collection, &output_delta)
+            {
+                write_stake_delta(&stake_meta_collection, previous_collection, output_path)?;
+            }
+            info!("Stake meta collection finished.");
+            Ok(())
         })
     };
 
-    for handle in vec![
-        validator_meta_collection_handle,
-        stake_meta_collection_handle,
-    ] {
-        match handle.join() {
-            Ok(Ok(())) => info!("Thread completed successfully."),
-            Ok(Err(err)) => anyhow::bail!("Error in thread: {err:?}"),
-            Err(err) => anyhow::bail!("Thread panicked: {err:?}"),
+    // the validator metas need the stake metas, the collections are skipped when only the bank info is written
+    let write_validator_metas =
+        args.output_validator_meta_collection.is_some() || args.output_sqlite.is_some();
+    let write_stake_metas = write_validator_metas
+        || args.output_stake_meta_collection.is_some()
+        || args.output_stake_meta_sqlite.is_some()
+        || args.output_stake_meta_dir.is_some()
+        || args.output_stake_delta.is_some();
+    let mut tasks = Vec::new();
+    if write_validator_metas {
+        tasks.push(("validator_meta_collection", validator_meta_collection_task));
+    }
+    if write_stake_metas {
+        tasks.push(("stake_meta_collection", stake_meta_collection_task));
+    }
+    if let Some(output_path) = args.output_vote_accounts.clone() {
+        let bank = bank.clone();
+        let vote_state_counter = define_counter("vote_states", &multi_progress, &stats).await;
+        let vote_account_state_collection_task: BlockingTask = Box::new(move || {
+            info!("Creating vote account state collection...");
+            let vote_account_state_collection =
+                generate_vote_account_state_collection(&bank, &vote_state_counter)?;
+            write_to_json_file(&vote_account_state_collection, &output_path)?;
+            info!("Vote account state collection finished.");
+            Ok(())
+        });
+        tasks.push((
+            "vote_account_state_collection",
+            vote_account_state_collection_task,
+        ));
+    }
+    run_blocking_tasks(tasks)
+        .instrument(snapshot_span.clone())
+        .await?;
+
+    if args.output_stake_meta_sqlite.is_some() || args.output_sqlite.is_some() {
+        let db_counter = define_counter("db_execute", &multi_progress, &stats).await;
+        let stake_meta_collection = stake_meta_output_rx.try_recv()?;
+        if let Some(output_path) = args.output_stake_meta_sqlite.clone() {
+            write_stake_metas_to_sqlite(output_path, &stake_meta_collection, db_counter.clone())
+                .instrument(info_span!(parent: &snapshot_span, "output"))
+                .await?;
+        }
+        if let Some(output_path) = args.output_sqlite.clone() {
+            let validator_meta_collection = validator_meta_output_rx.try_recv()?;
+            write_collections_to_sqlite(
+                output_path,
+                &validator_meta_collection,
+                &stake_meta_collection,
+                db_counter,
+            )
+            .instrument(info_span!(parent: &snapshot_span, "output"))
+            .await?;
         }
     }
+    drop(multi_progress);
+    if let Some(metrics_reporter_handle) = metrics_reporter_handle {
+        metrics_reporter_handle.abort();
+    }
 
+    stats.print_info().await;
     info!("Finished.");
     Ok(())
 }
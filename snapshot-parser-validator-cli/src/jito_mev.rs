@@ -1,20 +1,75 @@
 use solana_accounts_db::accounts_index::ScanConfig;
 use solana_program::pubkey::Pubkey;
 use solana_sdk::account::{Account, AccountSharedData};
-use {log::info, solana_program::stake_history::Epoch, solana_runtime::bank::Bank, std::sync::Arc};
+use std::collections::BTreeMap;
+use {
+    solana_program::stake_history::Epoch,
+    solana_runtime::bank::Bank,
+    std::sync::Arc,
+    tracing::{info, warn},
+};
 
 pub struct JitoMevMeta {
     pub vote_account: Pubkey,
     pub mev_commission: u16,
+    pub tip_distribution_account: Pubkey,
+    /// tips collected by the tip distribution account
+    pub tips_lamports: u64,
+}
+
+/// Epoch the jito tip distribution accounts are matched for.
+/// Depending on the snapshot timing, the accounts of the snapshot epoch may not be created yet.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum JitoEpochSelection {
+    /// epoch of the snapshot bank
+    #[default]
+    Current,
+    /// epoch before the epoch of the snapshot bank
+    Previous,
+    Explicit(Epoch),
+}
+
+impl JitoEpochSelection {
+    pub fn resolve(&self, bank_epoch: Epoch) -> Epoch {
+        match self {
+            JitoEpochSelection::Current => bank_epoch,
+            JitoEpochSelection::Previous => bank_epoch.saturating_sub(1),
+            JitoEpochSelection::Explicit(epoch) => *epoch,
+        }
+    }
+}
+
+/// Parses the jito epoch selection from `current`, `previous` or an epoch number.
+pub fn parse_jito_epoch_selection(value: &str) -> Result<JitoEpochSelection, String> {
+    match value.trim() {
+        "current" => Ok(JitoEpochSelection::Current),
+        "previous" => Ok(JitoEpochSelection::Previous),
+        epoch => epoch
+            .parse()
+            .map(JitoEpochSelection::Explicit)
+            .map_err(|e| {
+                format!(
+                    "Expected 'current', 'previous' or epoch number, got '{}': {}",
+                    value, e
+                )
+            }),
+    }
+}
+
+pub struct JitoMevMetas {
+    /// one meta per validator vote account
+    pub metas: Vec<JitoMevMeta>,
+    /// sum of tips collected by all tip distribution accounts of the epoch
+    pub total_tips_lamports: u64,
+    /// number of tip distribution accounts merged away as duplicates of an already found validator account
+    pub duplicate_accounts: u64,
 }
 
 // https://github.com/jito-foundation/jito-programs/blob/v0.1.5/mev-programs/programs/tip-distribution/src/state.rs#L32
 // only one TipDistribution account per epoch
-// https://github.com/jito-foundation/jito-programs/blob/v0.1.5/mev-programs/programs/tip-distribution/src/lib.rs#L385
-const JITO_PROGRAM: &str = "4R3gSG8BpU4t19KYj8CfnbtRpnT8gtk4dvTHxVRwc2r7";
-const TIP_DISTRIBUTION_ACCOUNT_DISCRIMINATOR: [u8; 8] = [85, 64, 113, 198, 234, 94, 120, 123];
-const VALIDATOR_VOTE_ACCOUNT_BYTE_INDEX: usize = 8; // anchor header
-const MERKLE_ROOT_OPTION_BYTE_INDEX: usize = 8 + // anchor header
+pub const TIP_DISTRIBUTION_ACCOUNT_DISCRIMINATOR: [u8; 8] = [85, 64, 113, 198, 234, 94, 120, 123];
+pub(crate) const VALIDATOR_VOTE_ACCOUNT_BYTE_INDEX: usize = 8; // anchor header
+pub(crate) const MERKLE_ROOT_OPTION_BYTE_INDEX: usize = 8 + // anchor header
     // TipDistributionAccount "prefix" data
     64;
 // epoch at byte index 73
@@ -27,12 +82,17 @@ const EPOCH_CREATED_AT_WITH_MERKLE_ROOT_BYTE_INDEX: usize =
     EPOCH_CREATED_AT_NO_MERKLE_ROOT_BYTE_INDEX +
     // MerkleRoot
     64;
-const VALIDATOR_COMMISSION_BPS_BYTE_OFFSET: usize = 8;
+pub(crate) const VALIDATOR_COMMISSION_BPS_BYTE_OFFSET: usize = 8;
+// MerkleRoot { root: [u8; 32], max_total_claim: u64, .. } follows the Option tag
+const MAX_TOTAL_CLAIM_BYTE_INDEX: usize = MERKLE_ROOT_OPTION_BYTE_INDEX + 1 + 32;
 
-pub fn fetch_jito_mev_metas(bank: &Arc<Bank>, epoch: Epoch) -> anyhow::Result<Vec<JitoMevMeta>> {
-    let jito_program: Pubkey = JITO_PROGRAM.try_into()?;
+pub fn fetch_jito_mev_metas(
+    bank: &Arc<Bank>,
+    jito_program: &Pubkey,
+    epoch: Epoch,
+) -> anyhow::Result<JitoMevMetas> {
     let jito_accounts_raw = bank.get_program_accounts(
-        &jito_program,
+        jito_program,
         &ScanConfig {
             collect_all_unsorted: true,
             ..ScanConfig::default()
@@ -40,7 +100,7 @@ pub fn fetch_jito_mev_metas(bank: &Arc<Bank>, epoch: Epoch) -> anyhow::Result<Ve
     )?;
     info!(
         "jito program {} `raw` processors loaded: {}",
-        JITO_PROGRAM,
+        jito_program,
         jito_accounts_raw.len()
     );
 
@@ -49,7 +109,15 @@ pub fn fetch_jito_mev_metas(bank: &Arc<Bank>, epoch: Epoch) -> anyhow::Result<Ve
     for (pubkey, shared_account) in jito_accounts_raw {
         let account = <AccountSharedData as Into<Account>>::into(shared_account);
         if account.data[0..8] == TIP_DISTRIBUTION_ACCOUNT_DISCRIMINATOR {
-            update_jito_mev_metas(&mut jito_mev_metas, &account, pubkey, epoch)?;
+            let rent_exempt_minimum =
+                bank.get_minimum_balance_for_rent_exemption(account.data.len());
+            update_jito_mev_metas(
+                &mut jito_mev_metas,
+                &account,
+                pubkey,
+                epoch,
+                rent_exempt_minimum,
+            )?;
         }
     }
 
@@ -64,24 +132,76 @@ pub fn fetch_jito_mev_metas(bank: &Arc<Bank>, epoch: Epoch) -> anyhow::Result<Ve
         epoch,
         jito_mev_metas.len()
     );
-    Ok(jito_mev_metas)
+    Ok(dedup_jito_mev_metas(jito_mev_metas))
 }
 
-fn update_jito_mev_metas(
+/// Only one tip distribution account per validator and epoch is expected.
+/// Duplicates are merged into the one with the highest commission (the least favourable for stakers) and counted,
+/// the tips of the duplicates are summed.
+fn dedup_jito_mev_metas(jito_mev_metas: Vec<JitoMevMeta>) -> JitoMevMetas {
+    let mut by_vote_account: BTreeMap<Pubkey, JitoMevMeta> = BTreeMap::new();
+    let mut duplicate_accounts = 0u64;
+    for jito_mev_meta in jito_mev_metas {
+        match by_vote_account.get_mut(&jito_mev_meta.vote_account) {
+            None => {
+                by_vote_account.insert(jito_mev_meta.vote_account, jito_mev_meta);
+            }
+            Some(existing) => {
+                duplicate_accounts += 1;
+                warn!(
+                    "Duplicate jito tip distribution accounts {} (commission {} bps) and {} (commission {} bps) for vote account {}",
+                    existing.tip_distribution_account,
+                    existing.mev_commission,
+                    jito_mev_meta.tip_distribution_account,
+                    jito_mev_meta.mev_commission,
+                    jito_mev_meta.vote_account
+                );
+                let tips_lamports = existing.tips_lamports + jito_mev_meta.tips_lamports;
+                if jito_mev_meta.mev_commission > existing.mev_commission {
+                    *existing = jito_mev_meta;
+                }
+                existing.tips_lamports = tips_lamports;
+            }
+        }
+    }
+    if duplicate_accounts > 0 {
+        warn!(
+            "Merged {} duplicate jito tip distribution accounts",
+            duplicate_accounts
+        );
+    }
+    let metas: Vec<JitoMevMeta> = by_vote_account.into_values().collect();
+    JitoMevMetas {
+        total_tips_lamports: metas.iter().map(|meta| meta.tips_lamports).sum(),
+        metas,
+        duplicate_accounts,
+    }
+}
+
+/// Pushes the meta of the tip distribution account when the account was created in the `epoch`.
+pub fn update_jito_mev_metas(
     jito_mev_metas: &mut Vec<JitoMevMeta>,
     account: &Account,
     pubkey: Pubkey,
     epoch: Epoch,
+    rent_exempt_minimum: u64,
 ) -> anyhow::Result<()> {
     let (epoch_created_at, epoch_byte_index) = get_epoch_created_at(account)?;
     if epoch_created_at == epoch {
-        update_mev_commission(jito_mev_metas, account, pubkey, epoch_byte_index, epoch)?;
+        update_mev_commission(
+            jito_mev_metas,
+            account,
+            pubkey,
+            epoch_byte_index,
+            epoch,
+            rent_exempt_minimum,
+        )?;
     }
     Ok(())
 }
 
 /// Returns the epoch and the byte index where the epoch was found at.
-fn get_epoch_created_at(account: &Account) -> anyhow::Result<(u64, usize)> {
+pub(crate) fn get_epoch_created_at(account: &Account) -> anyhow::Result<(u64, usize)> {
     // epoch_created_at_*_byte_index -1 contains info about Option is None (0) or Some (1)
     if u8::from_le_bytes([account.data[MERKLE_ROOT_OPTION_BYTE_INDEX]]) == 0 {
         Ok((
@@ -114,17 +234,44 @@ fn update_mev_commission(
     account_pubkey: Pubkey,
     epoch_byte_index: usize,
     epoch: Epoch,
+    rent_exempt_minimum: u64,
 ) -> anyhow::Result<()> {
     let (vote_account, jito_commission, epoch_parsed) =
         read_jito_mev_commission(account_pubkey, account, epoch_byte_index)?;
     assert_eq!(epoch, epoch_parsed);
+    let tips_lamports = read_jito_tips(account_pubkey, account, rent_exempt_minimum)?;
     jito_mev_metas.push(JitoMevMeta {
         vote_account,
         mev_commission: jito_commission,
+        tip_distribution_account: account_pubkey,
+        tips_lamports,
     });
     Ok(())
 }
 
+/// Tips are the lamports above the rent exempt minimum until the merkle root is uploaded.
+/// Afterwards the claims drain the account, so the tips are taken from the merkle root `max_total_claim`.
+fn read_jito_tips(
+    account_pubkey: Pubkey,
+    account: &Account,
+    rent_exempt_minimum: u64,
+) -> anyhow::Result<u64> {
+    if account.data[MERKLE_ROOT_OPTION_BYTE_INDEX] == 0 {
+        return Ok(account.lamports.saturating_sub(rent_exempt_minimum));
+    }
+    Ok(u64::from_le_bytes(
+        account.data[MAX_TOTAL_CLAIM_BYTE_INDEX..MAX_TOTAL_CLAIM_BYTE_INDEX + 8]
+            .try_into()
+            .map_err(|e| {
+                anyhow::anyhow!(
+                    "Failed to parse max_total_claim for account {}: {:?}",
+                    account_pubkey,
+                    e
+                )
+            })?,
+    ))
+}
+
 fn read_jito_mev_commission(
     account_pubkey: Pubkey,
     account: &Account,
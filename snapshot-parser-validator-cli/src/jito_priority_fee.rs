@@ -0,0 +1,176 @@
+use crate::jito_mev::{
+    get_epoch_created_at, VALIDATOR_COMMISSION_BPS_BYTE_OFFSET, VALIDATOR_VOTE_ACCOUNT_BYTE_INDEX,
+};
+use solana_accounts_db::accounts_index::ScanConfig;
+use solana_program::pubkey::Pubkey;
+use solana_sdk::account::{Account, AccountSharedData};
+use std::collections::BTreeMap;
+use {
+    solana_program::stake_history::Epoch,
+    solana_runtime::bank::Bank,
+    std::sync::Arc,
+    tracing::{info, warn},
+};
+
+pub struct JitoPriorityFeeMeta {
+    pub vote_account: Pubkey,
+    /// validator_commission_bps of the priority fee distribution account
+    pub priority_fee_commission: u16,
+    pub priority_fee_distribution_account: Pubkey,
+    /// priority fees transferred by the validator to the priority fee distribution account
+    pub total_lamports_transferred: u64,
+}
+
+pub struct JitoPriorityFeeMetas {
+    /// one meta per validator vote account
+    pub metas: Vec<JitoPriorityFeeMeta>,
+    /// sum of priority fees transferred to all priority fee distribution accounts of the epoch
+    pub total_lamports_transferred: u64,
+    /// number of priority fee distribution accounts merged away as duplicates of an already found validator account
+    pub duplicate_accounts: u64,
+}
+
+// https://github.com/jito-foundation/jito-programs/blob/master/mev-programs/programs/priority-fee-distribution/src/state.rs
+// PriorityFeeDistributionAccount shares the layout of TipDistributionAccount up to validator_commission_bps,
+// followed by expires_at: u64 and total_lamports_transferred: u64
+const PRIORITY_FEE_DISTRIBUTION_ACCOUNT_DISCRIMINATOR: [u8; 8] =
+    [163, 183, 254, 12, 121, 137, 235, 27];
+const TOTAL_LAMPORTS_TRANSFERRED_BYTE_OFFSET: usize = VALIDATOR_COMMISSION_BPS_BYTE_OFFSET +
+    // validator_commission_bps
+    2 +
+    // expires_at
+    8;
+
+/// Priority fee distribution accounts of the epoch; unlike the tip distribution accounts,
+/// validators are not expected to run the priority fee distribution, so none found is not an error.
+pub fn fetch_jito_priority_fee_metas(
+    bank: &Arc<Bank>,
+    priority_fee_program: &Pubkey,
+    epoch: Epoch,
+) -> anyhow::Result<JitoPriorityFeeMetas> {
+    let accounts_raw = bank.get_program_accounts(
+        priority_fee_program,
+        &ScanConfig {
+            collect_all_unsorted: true,
+            ..ScanConfig::default()
+        },
+    )?;
+    info!(
+        "jito priority fee program {} `raw` accounts loaded: {}",
+        priority_fee_program,
+        accounts_raw.len()
+    );
+
+    let mut metas: Vec<JitoPriorityFeeMeta> = Vec::new();
+    for (pubkey, shared_account) in accounts_raw {
+        let account = <AccountSharedData as Into<Account>>::into(shared_account);
+        if account.data.len() < 8
+            || account.data[0..8] != PRIORITY_FEE_DISTRIBUTION_ACCOUNT_DISCRIMINATOR
+        {
+            continue;
+        }
+        let (epoch_created_at, epoch_byte_index) = get_epoch_created_at(&account)?;
+        if epoch_created_at == epoch {
+            metas.push(read_jito_priority_fee_meta(
+                pubkey,
+                &account,
+                epoch_byte_index,
+            )?);
+        }
+    }
+
+    if metas.is_empty() {
+        warn!(
+            "No jito priority fee distribution accounts found for epoch {}",
+            epoch
+        );
+    }
+    info!(
+        "jito priority fee distribution accounts for epoch {}: {}",
+        epoch,
+        metas.len()
+    );
+    Ok(dedup_jito_priority_fee_metas(metas))
+}
+
+/// Duplicates are merged the same way as the tip distribution accounts, see [`crate::jito_mev::JitoMevMetas`].
+fn dedup_jito_priority_fee_metas(metas: Vec<JitoPriorityFeeMeta>) -> JitoPriorityFeeMetas {
+    let mut by_vote_account: BTreeMap<Pubkey, JitoPriorityFeeMeta> = BTreeMap::new();
+    let mut duplicate_accounts = 0u64;
+    for meta in metas {
+        match by_vote_account.get_mut(&meta.vote_account) {
+            None => {
+                by_vote_account.insert(meta.vote_account, meta);
+            }
+            Some(existing) => {
+                duplicate_accounts += 1;
+                warn!(
+                    "Duplicate jito priority fee distribution accounts {} and {} for vote account {}",
+                    existing.priority_fee_distribution_account,
+                    meta.priority_fee_distribution_account,
+                    meta.vote_account
+                );
+                let total_lamports_transferred =
+                    existing.total_lamports_transferred + meta.total_lamports_transferred;
+                if meta.priority_fee_commission > existing.priority_fee_commission {
+                    *existing = meta;
+                }
+                existing.total_lamports_transferred = total_lamports_transferred;
+            }
+        }
+    }
+    let metas: Vec<JitoPriorityFeeMeta> = by_vote_account.into_values().collect();
+    JitoPriorityFeeMetas {
+        total_lamports_transferred: metas
+            .iter()
+            .map(|meta| meta.total_lamports_transferred)
+            .sum(),
+        metas,
+        duplicate_accounts,
+    }
+}
+
+fn read_jito_priority_fee_meta(
+    account_pubkey: Pubkey,
+    account: &Account,
+    epoch_byte_index: usize,
+) -> anyhow::Result<JitoPriorityFeeMeta> {
+    let parse_error = |field: &str, e: std::array::TryFromSliceError| {
+        anyhow::anyhow!(
+            "Failed to parse {} for priority fee distribution account {}: {:?}",
+            field,
+            account_pubkey,
+            e
+        )
+    };
+    let vote_account: Pubkey = account.data
+        [VALIDATOR_VOTE_ACCOUNT_BYTE_INDEX..VALIDATOR_VOTE_ACCOUNT_BYTE_INDEX + 32]
+        .try_into()
+        .map_err(|e| parse_error("validator_vote_account", e))?;
+    let commission_byte_index = epoch_byte_index + VALIDATOR_COMMISSION_BPS_BYTE_OFFSET;
+    let priority_fee_commission = u16::from_le_bytes(
+        account.data[commission_byte_index..commission_byte_index + 2]
+            .try_into()
+            .map_err(|e| parse_error("validator_commission_bps", e))?,
+    );
+    let transferred_byte_index = epoch_byte_index + TOTAL_LAMPORTS_TRANSFERRED_BYTE_OFFSET;
+    let total_lamports_transferred = u64::from_le_bytes(
+        account
+            .data
+            .get(transferred_byte_index..transferred_byte_index + 8)
+            .ok_or_else(|| {
+                anyhow::anyhow!(
+                    "Priority fee distribution account {} is too short",
+                    account_pubkey
+                )
+            })?
+            .try_into()
+            .map_err(|e| parse_error("total_lamports_transferred", e))?,
+    );
+    Ok(JitoPriorityFeeMeta {
+        vote_account,
+        priority_fee_commission,
+        priority_fee_distribution_account: account_pubkey,
+        total_lamports_transferred,
+    })
+}
@@ -1,2 +1,8 @@
+pub mod bank_info;
 pub mod jito_mev;
+pub mod jito_priority_fee;
+pub mod sqlite_output;
+pub mod stake_delta;
+pub mod stake_meta_partition;
 pub mod validator_meta;
+pub mod vote_account_state;
@@ -0,0 +1,184 @@
+use {
+    crate::validator_meta::{CreditsWindow, ValidatorMetaCollection},
+    snapshot_parser::{
+        db_connection::SQLiteExecutor,
+        db_message::DbMessage,
+        progress_bar::ProgressCounter,
+        schema::finalize_output,
+        sql_row::{create_table, insert_single_row},
+        stake_meta::{StakeMeta, StakeMetaCollection},
+    },
+    snapshot_parser_types::decoded_accounts::{
+        StakeMetaCollectionRow, StakeMetaRow, ValidatorMetaCollectionRow, ValidatorMetaRow,
+    },
+    std::{path::PathBuf, sync::Arc},
+    tokio::{
+        sync::{
+            mpsc::{self, Sender},
+            oneshot,
+        },
+        task::JoinHandle,
+    },
+    tracing::info,
+};
+
+/// Number of inserts committed in one transaction.
+const SQLITE_TX_BULK: u16 = 10_000;
+const CHANNEL_SIZE: usize = 1000;
+
+fn validator_meta_collection_row(
+    collection: &ValidatorMetaCollection,
+) -> ValidatorMetaCollectionRow {
+    ValidatorMetaCollectionRow {
+        slot: collection.slot,
+        epoch: collection.epoch,
+        bank_hash: collection.bank_hash.clone(),
+        credits_window: match collection.credits_window {
+            CreditsWindow::CurrentEpoch => "current_epoch",
+            CreditsWindow::Cumulative => "cumulative",
+        }
+        .to_string(),
+        capitalization: collection.capitalization,
+        validator_rewards: collection.validator_rewards,
+        jito_epoch: collection.jito_epoch,
+        total_jito_tips: collection.total_jito_tips,
+        total_priority_fees_transferred: collection.total_priority_fees_transferred,
+    }
+}
+
+fn stake_meta_row(stake_meta: &StakeMeta) -> StakeMetaRow {
+    StakeMetaRow {
+        pubkey: stake_meta.pubkey.to_string(),
+        balance_lamports: stake_meta.balance_lamports,
+        active_delegation_lamports: stake_meta.active_delegation_lamports,
+        activating_delegation_lamports: stake_meta.activating_delegation_lamports,
+        deactivating_delegation_lamports: stake_meta.deactivating_delegation_lamports,
+        activation_epoch: stake_meta.activation_epoch,
+        deactivation_epoch: stake_meta.deactivation_epoch,
+        validator: stake_meta.validator.map(|validator| validator.to_string()),
+        stake_authority: stake_meta.stake_authority.to_string(),
+        withdraw_authority: stake_meta.withdraw_authority.to_string(),
+        delegated_lamports: stake_meta.delegated_lamports,
+        credits_observed: stake_meta.credits_observed,
+        rent_exempt_reserve: stake_meta.rent_exempt_reserve,
+    }
+}
+
+/// Starts the [`SQLiteExecutor`] writing a new SQLite DB at `db_path`, the same way as the output DB
+/// of the tokens CLI, so it carries the same schema versioning and it is promoted from a temporary file on success.
+fn spawn_sqlite_executor(
+    db_path: PathBuf,
+    db_execute_counter: Arc<ProgressCounter>,
+) -> anyhow::Result<(Sender<DbMessage>, JoinHandle<()>)> {
+    let (db_sender, receiver) = mpsc::channel::<DbMessage>(CHANNEL_SIZE);
+    let db = SQLiteExecutor::new(
+        db_path,
+        None,
+        None,
+        Some(SQLITE_TX_BULK),
+        false,
+        false,
+        false,
+        db_execute_counter,
+        receiver,
+    )?;
+    Ok((db_sender, tokio::spawn(db.start())))
+}
+
+/// Indexes the written tables and promotes the DB once the executor is shut down.
+async fn shutdown_sqlite_executor(
+    db_sender: Sender<DbMessage>,
+    db_handle: JoinHandle<()>,
+) -> anyhow::Result<()> {
+    finalize_output(&db_sender).await?;
+    let (response_tx, response_rx) = oneshot::channel();
+    db_sender
+        .send(DbMessage::Shutdown {
+            response: response_tx,
+        })
+        .await?;
+    response_rx.await??;
+    drop(db_sender);
+    db_handle.await?;
+    Ok(())
+}
+
+/// Writes the stake metas into the `stake_metas` table (with the epoch and slot in `stake_meta_collection`)
+/// of a new SQLite DB at `db_path`.
+pub async fn write_stake_metas_to_sqlite(
+    db_path: PathBuf,
+    stake_meta_collection: &StakeMetaCollection,
+    db_execute_counter: Arc<ProgressCounter>,
+) -> anyhow::Result<()> {
+    let (db_sender, db_handle) = spawn_sqlite_executor(db_path.clone(), db_execute_counter)?;
+
+    create_table::<StakeMetaCollectionRow>(&db_sender).await?;
+    create_table::<StakeMetaRow>(&db_sender).await?;
+    insert_single_row(
+        &db_sender,
+        &StakeMetaCollectionRow {
+            slot: stake_meta_collection.slot,
+            epoch: stake_meta_collection.epoch,
+        },
+    )
+    .await?;
+    for stake_meta in stake_meta_collection.stake_metas.iter() {
+        insert_single_row(&db_sender, &stake_meta_row(stake_meta)).await?;
+    }
+    info!(
+        "Inserted {} stake metas into SQLite",
+        stake_meta_collection.stake_metas.len()
+    );
+
+    shutdown_sqlite_executor(db_sender, db_handle).await?;
+    info!("Stake metas SQLite DB written to: {:?}", db_path);
+    Ok(())
+}
+
+/// Writes the validator metas and the stake metas into the `validator_metas` and `stake_metas` tables
+/// (with the bank-level values in `validator_meta_collection`) of a new SQLite DB at `db_path`.
+pub async fn write_collections_to_sqlite(
+    db_path: PathBuf,
+    validator_meta_collection: &ValidatorMetaCollection,
+    stake_meta_collection: &StakeMetaCollection,
+    db_execute_counter: Arc<ProgressCounter>,
+) -> anyhow::Result<()> {
+    let (db_sender, db_handle) = spawn_sqlite_executor(db_path.clone(), db_execute_counter)?;
+
+    create_table::<ValidatorMetaCollectionRow>(&db_sender).await?;
+    create_table::<ValidatorMetaRow>(&db_sender).await?;
+    create_table::<StakeMetaRow>(&db_sender).await?;
+
+    insert_single_row(
+        &db_sender,
+        &validator_meta_collection_row(validator_meta_collection),
+    )
+    .await?;
+    for validator_meta in validator_meta_collection.validator_metas.iter() {
+        let row = ValidatorMetaRow {
+            vote_account: validator_meta.vote_account.to_string(),
+            commission: validator_meta.commission,
+            mev_commission: validator_meta.mev_commission,
+            priority_fee_commission: validator_meta.priority_fee_commission,
+            priority_fee_lamports_transferred: validator_meta.priority_fee_lamports_transferred,
+            stake: validator_meta.stake,
+            credits: validator_meta.credits,
+            identity: validator_meta.identity.to_string(),
+            identity_lamports: validator_meta.identity_lamports,
+            delinquent: validator_meta.delinquent,
+        };
+        insert_single_row(&db_sender, &row).await?;
+    }
+    for stake_meta in stake_meta_collection.stake_metas.iter() {
+        insert_single_row(&db_sender, &stake_meta_row(stake_meta)).await?;
+    }
+    info!(
+        "Inserted {} validator metas and {} stake metas into SQLite",
+        validator_meta_collection.validator_metas.len(),
+        stake_meta_collection.stake_metas.len()
+    );
+
+    shutdown_sqlite_executor(db_sender, db_handle).await?;
+    info!("Validator CLI SQLite DB written to: {:?}", db_path);
+    Ok(())
+}
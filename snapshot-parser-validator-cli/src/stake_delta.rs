@@ -0,0 +1,92 @@
+use {
+    snapshot_parser::{
+        stake_meta::{StakeMeta, StakeMetaCollection},
+        utils::{read_from_json_file, write_to_json_file},
+    },
+    snapshot_parser_types::{stake_delta::StakeDeltaCollection, stake_meta as artifact},
+    solana_program::pubkey::Pubkey,
+    std::path::Path,
+    tracing::{info, warn},
+};
+
+/// The artifact types are built against an older solana-program, the pubkeys are converted by their bytes.
+fn artifact_pubkey<P: From<[u8; 32]>>(pubkey: &Pubkey) -> P {
+    P::from(pubkey.to_bytes())
+}
+
+fn artifact_stake_meta(stake_meta: &StakeMeta) -> artifact::StakeMeta {
+    artifact::StakeMeta {
+        pubkey: artifact_pubkey(&stake_meta.pubkey),
+        balance_lamports: stake_meta.balance_lamports,
+        active_delegation_lamports: stake_meta.active_delegation_lamports,
+        activating_delegation_lamports: stake_meta.activating_delegation_lamports,
+        deactivating_delegation_lamports: stake_meta.deactivating_delegation_lamports,
+        activation_epoch: stake_meta.activation_epoch,
+        deactivation_epoch: stake_meta.deactivation_epoch,
+        validator: stake_meta.validator.as_ref().map(artifact_pubkey),
+        stake_authority: artifact_pubkey(&stake_meta.stake_authority),
+        withdraw_authority: artifact_pubkey(&stake_meta.withdraw_authority),
+        delegated_lamports: stake_meta.delegated_lamports,
+        credits_observed: stake_meta.credits_observed,
+        rent_exempt_reserve: stake_meta.rent_exempt_reserve,
+    }
+}
+
+/// Writes the stake inflow and outflow per validator and per stake authority
+/// since the stake meta collection of a previous epoch, see [`StakeDeltaCollection::between`].
+pub fn write_stake_delta(
+    stake_meta_collection: &StakeMetaCollection,
+    previous_collection_path: &Path,
+    output_path: &str,
+) -> anyhow::Result<()> {
+    let previous: artifact::StakeMetaCollection = read_from_json_file(&previous_collection_path)
+        .map_err(|e| {
+            anyhow::anyhow!(
+                "Could not load previous stake meta collection {:?}: {}",
+                previous_collection_path,
+                e
+            )
+        })?;
+    if previous.epoch >= stake_meta_collection.epoch {
+        anyhow::bail!(
+            "Previous stake meta collection of epoch {} is not older than the snapshot epoch {}",
+            previous.epoch,
+            stake_meta_collection.epoch
+        );
+    }
+    if previous.epoch + 1 != stake_meta_collection.epoch {
+        warn!(
+            "Previous stake meta collection is of epoch {}, the stake delta spans {} epochs",
+            previous.epoch,
+            stake_meta_collection.epoch - previous.epoch
+        );
+    }
+    let current = artifact::StakeMetaCollection {
+        epoch: stake_meta_collection.epoch,
+        slot: stake_meta_collection.slot,
+        stake_metas: stake_meta_collection
+            .stake_metas
+            .iter()
+            .map(artifact_stake_meta)
+            .collect(),
+        stake_history: stake_meta_collection
+            .stake_history
+            .iter()
+            .map(|entry| artifact::StakeHistoryEpoch {
+                epoch: entry.epoch,
+                effective: entry.effective,
+                activating: entry.activating,
+                deactivating: entry.deactivating,
+            })
+            .collect(),
+    };
+    let stake_delta = StakeDeltaCollection::between(&previous, &current);
+    info!(
+        "Stake delta since epoch {}: {} validators, {} authority movements",
+        previous.epoch,
+        stake_delta.validator_deltas.len(),
+        stake_delta.authority_movements.len()
+    );
+    write_to_json_file(&stake_delta, output_path)?;
+    Ok(())
+}
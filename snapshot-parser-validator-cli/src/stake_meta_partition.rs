@@ -0,0 +1,55 @@
+use {
+    snapshot_parser::{
+        stake_meta::{StakeMeta, StakeMetaCollection},
+        utils::{write_to_json_file, JsonCompression},
+    },
+    solana_program::pubkey::Pubkey,
+    std::{collections::BTreeMap, fs, path::Path},
+    tracing::info,
+};
+
+/// File name for stake accounts that are not delegated to any validator.
+const UNDELEGATED_FILE_NAME: &str = "undelegated.json";
+
+/// Writes one stake meta collection per validator vote account into `output_dir`
+/// as `<vote_account>.json`; stake accounts without delegation go to `undelegated.json`.
+/// The file names get the extension of the `compression`, e.g., `<vote_account>.json.zst`.
+pub fn write_stake_metas_per_validator(
+    stake_meta_collection: &StakeMetaCollection,
+    output_dir: &Path,
+    compression: JsonCompression,
+) -> anyhow::Result<()> {
+    fs::create_dir_all(output_dir)?;
+
+    let mut partitions: BTreeMap<Option<Pubkey>, Vec<StakeMeta>> = BTreeMap::new();
+    for stake_meta in stake_meta_collection.stake_metas.iter() {
+        partitions
+            .entry(stake_meta.validator)
+            .or_default()
+            .push(stake_meta.clone());
+    }
+
+    let partitions_count = partitions.len();
+    for (validator, stake_metas) in partitions {
+        let file_name = match validator {
+            Some(vote_account) => format!("{}.json", vote_account),
+            None => UNDELEGATED_FILE_NAME.to_string(),
+        };
+        let partition = StakeMetaCollection {
+            epoch: stake_meta_collection.epoch,
+            slot: stake_meta_collection.slot,
+            stake_metas,
+            stake_history: stake_meta_collection.stake_history.clone(),
+        };
+        let output_path = output_dir.join(file_name);
+        write_to_json_file(
+            &partition,
+            &compression.output_path(&output_path.to_string_lossy()),
+        )?;
+    }
+    info!(
+        "Written {} stake meta partition files to: {:?}",
+        partitions_count, output_dir
+    );
+    Ok(())
+}
@@ -1,15 +1,34 @@
 use {
-    crate::jito_mev::fetch_jito_mev_metas,
-    log::{error, info, warn},
+    crate::bank_info::generate_bank_info,
+    crate::jito_mev::{fetch_jito_mev_metas, JitoEpochSelection},
+    crate::jito_priority_fee::fetch_jito_priority_fee_metas,
+    crate::vote_account_state::EpochCredits,
     serde::{Deserialize, Serialize},
-    snapshot_parser::serde_serialize::pubkey_string_conversion,
+    snapshot_parser::{
+        progress_bar::ProgressCounter, serde_serialize::pubkey_string_conversion,
+        stake_meta::StakeMetaCollection, utils::read_from_json_file,
+    },
     solana_program::pubkey::Pubkey,
     solana_program::stake_history::Epoch,
     solana_runtime::bank::Bank,
-    solana_sdk::epoch_info::EpochInfo,
-    std::{fmt::Debug, sync::Arc},
+    solana_sdk::{account::ReadableAccount, epoch_info::EpochInfo},
+    std::{
+        collections::{BTreeMap, HashMap},
+        fmt::Debug,
+        path::Path,
+        str::FromStr,
+        sync::Arc,
+    },
+    tracing::{error, info, warn},
 };
 
+/// Label of the active stake whose stake authority has no configured label.
+pub const OTHER_STAKE_AUTHORITY_LABEL: &str = "other";
+
+/// Slots the last vote of a vote account may be behind the snapshot slot before the validator is delinquent,
+/// as in the `getVoteAccounts` RPC.
+pub const DELINQUENT_SLOT_DISTANCE: u64 = 128;
+
 #[derive(Clone, Deserialize, Serialize, Debug, Eq, PartialEq)]
 pub struct ValidatorMeta {
     #[serde(with = "pubkey_string_conversion")]
@@ -17,8 +36,23 @@ pub struct ValidatorMeta {
     pub commission: u8,
     /// jito-tip-distribution // TipDistributionAccount // validator_commission_bps
     pub mev_commission: Option<u16>,
+    /// jito-priority-fee-distribution // PriorityFeeDistributionAccount // validator_commission_bps
+    pub priority_fee_commission: Option<u16>,
+    /// jito-priority-fee-distribution // PriorityFeeDistributionAccount // total_lamports_transferred
+    pub priority_fee_lamports_transferred: Option<u64>,
     pub stake: u64,
+    /// active delegated stake split by labels of the stake authorities, by default the stake authorities of the program registry
+    pub stake_by_authority: BTreeMap<String, u64>,
     pub credits: u64,
+    /// node identity of the vote account
+    #[serde(with = "pubkey_string_conversion")]
+    pub identity: Pubkey,
+    /// lamports of the node identity account at the snapshot slot, paying the vote transaction fees
+    pub identity_lamports: u64,
+    /// credits of the last epochs of the vote account, oldest first
+    pub epoch_credits: Vec<EpochCredits>,
+    /// the last vote of the vote account is more than [`DELINQUENT_SLOT_DISTANCE`] slots behind the snapshot slot
+    pub delinquent: bool,
 }
 
 impl Ord for ValidatorMeta {
@@ -33,14 +67,40 @@ impl PartialOrd<Self> for ValidatorMeta {
     }
 }
 
+/// Which vote credits are reported in [`ValidatorMeta::credits`].
+#[derive(Clone, Copy, Deserialize, Serialize, Debug, Default, Eq, PartialEq, clap::ValueEnum)]
+#[serde(rename_all = "snake_case")]
+pub enum CreditsWindow {
+    /// credits earned in the epoch of the snapshot only
+    #[default]
+    CurrentEpoch,
+    /// all credits earned by the vote account since its creation
+    Cumulative,
+}
+
 #[derive(Clone, Deserialize, Serialize, Debug, Default)]
 pub struct ValidatorMetaCollection {
     pub epoch: Epoch,
     pub slot: u64,
+    /// hash of the bank the collection was generated from, to match it with the other artifacts of the snapshot
+    pub bank_hash: String,
+    pub credits_window: CreditsWindow,
     pub capitalization: u64,
     pub epoch_duration_in_years: f64,
     pub validator_rate: f64,
     pub validator_rewards: u64,
+    /// epoch the jito tip distribution accounts were matched for
+    pub jito_epoch: Epoch,
+    /// sum of tips collected by the jito tip distribution accounts of the jito epoch
+    pub total_jito_tips: u64,
+    /// number of validators with a jito tip distribution account for the jito epoch
+    pub jito_enabled_validators: u64,
+    /// number of duplicate jito tip distribution accounts found for the same validator, see [`crate::jito_mev::JitoMevMetas`]
+    pub jito_duplicate_distribution_accounts: u64,
+    /// sum of priority fees transferred to the jito priority fee distribution accounts of the jito epoch
+    pub total_priority_fees_transferred: u64,
+    /// number of validators with a jito priority fee distribution account for the jito epoch
+    pub jito_priority_fee_enabled_validators: u64,
     pub validator_metas: Vec<ValidatorMeta>,
 }
 
@@ -73,34 +133,69 @@ impl ValidatorMetaCollection {
 
 struct VoteAccountMeta {
     vote_account: Pubkey,
+    identity: Pubkey,
+    identity_lamports: u64,
     commission: u8,
     stake: u64,
     credits: u64,
+    epoch_credits: Vec<EpochCredits>,
+    delinquent: bool,
 }
 
-fn fetch_vote_account_metas(bank: &Arc<Bank>, epoch: Epoch) -> Vec<VoteAccountMeta> {
+fn fetch_vote_account_metas(
+    bank: &Arc<Bank>,
+    epoch: Epoch,
+    slot: u64,
+    credits_window: CreditsWindow,
+    epoch_credits_history: usize,
+    progress_counter: &ProgressCounter,
+) -> Vec<VoteAccountMeta> {
     bank.vote_accounts()
         .iter()
+        .inspect(|_| progress_counter.inc())
         .filter_map(
             |(pubkey, (stake, vote_account))| match vote_account.vote_state() {
                 Ok(vote_state) => {
-                    let credits = vote_state
+                    let credits = match credits_window {
+                        CreditsWindow::CurrentEpoch => vote_state
+                            .epoch_credits
+                            .iter()
+                            .find_map(|(credits_epoch, _, prev_credits)| {
+                                if *credits_epoch == epoch {
+                                    Some(vote_state.credits() - *prev_credits)
+                                } else {
+                                    None
+                                }
+                            })
+                            .unwrap_or(0),
+                        CreditsWindow::Cumulative => vote_state.credits(),
+                    };
+
+                    let epoch_credits = vote_state.epoch_credits[vote_state
                         .epoch_credits
+                        .len()
+                        .saturating_sub(epoch_credits_history)..]
                         .iter()
-                        .find_map(|(credits_epoch, _, prev_credits)| {
-                            if *credits_epoch == epoch {
-                                Some(vote_state.credits() - *prev_credits)
-                            } else {
-                                None
-                            }
+                        .map(|(epoch, credits, prev_credits)| EpochCredits {
+                            epoch: *epoch,
+                            credits: *credits,
+                            prev_credits: *prev_credits,
                         })
-                        .unwrap_or(0);
+                        .collect();
+                    let delinquent = vote_state.last_voted_slot().unwrap_or_default()
+                        <= slot.saturating_sub(DELINQUENT_SLOT_DISTANCE);
 
                     Some(VoteAccountMeta {
                         vote_account: *pubkey,
+                        identity: vote_state.node_pubkey,
+                        identity_lamports: bank
+                            .get_account(&vote_state.node_pubkey)
+                            .map_or(0, |identity_account| identity_account.lamports()),
                         commission: vote_state.commission,
                         stake: *stake,
                         credits,
+                        epoch_credits,
+                        delinquent,
                     })
                 }
                 Err(err) => {
@@ -112,7 +207,15 @@ fn fetch_vote_account_metas(bank: &Arc<Bank>, epoch: Epoch) -> Vec<VoteAccountMe
         .collect()
 }
 
-pub fn generate_validator_collection(bank: &Arc<Bank>) -> anyhow::Result<ValidatorMetaCollection> {
+pub fn generate_validator_collection(
+    bank: &Arc<Bank>,
+    credits_window: CreditsWindow,
+    epoch_credits_history: usize,
+    jito_epoch_selection: JitoEpochSelection,
+    jito_program: &Pubkey,
+    jito_priority_fee_program: &Pubkey,
+    progress_counter: &ProgressCounter,
+) -> anyhow::Result<ValidatorMetaCollection> {
     assert!(bank.is_frozen());
 
     let EpochInfo {
@@ -121,35 +224,63 @@ pub fn generate_validator_collection(bank: &Arc<Bank>) -> anyhow::Result<Validat
         ..
     } = bank.get_epoch_info();
 
-    let validator_rate = bank
-        .inflation()
-        .validator(bank.slot_in_year_for_inflation());
-    let capitalization = bank.capitalization();
-    let epoch_duration_in_years = bank.epoch_duration_in_years(epoch);
-    let validator_rewards =
-        (validator_rate * capitalization as f64 * epoch_duration_in_years) as u64;
+    let bank_info = generate_bank_info(bank);
 
-    let vote_account_metas = fetch_vote_account_metas(bank, epoch);
-    let jito_mev_metas = fetch_jito_mev_metas(bank, epoch)?;
+    info!("Collecting vote credits for window: {:?}", credits_window);
+    let vote_account_metas = fetch_vote_account_metas(
+        bank,
+        epoch,
+        absolute_slot,
+        credits_window,
+        epoch_credits_history,
+        progress_counter,
+    );
+    let jito_epoch = jito_epoch_selection.resolve(epoch);
+    info!(
+        "Matching jito tip distribution accounts for epoch {} ({:?})",
+        jito_epoch, jito_epoch_selection
+    );
+    let jito_mev_metas = fetch_jito_mev_metas(bank, jito_program, jito_epoch)?;
+    let jito_enabled_validators = jito_mev_metas.metas.len() as u64;
+    let jito_priority_fee_metas =
+        fetch_jito_priority_fee_metas(bank, jito_priority_fee_program, jito_epoch)?;
+    let jito_priority_fee_enabled_validators = jito_priority_fee_metas.metas.len() as u64;
 
     let mut validator_metas = vote_account_metas
         .into_iter()
-        .map(|vote_account_meta| ValidatorMeta {
-            vote_account: vote_account_meta.vote_account,
-            commission: vote_account_meta.commission,
-            mev_commission: jito_mev_metas
+        .map(|vote_account_meta| {
+            let priority_fee_meta = jito_priority_fee_metas
+                .metas
                 .iter()
-                .find(|jito_mev_meta| jito_mev_meta.vote_account == vote_account_meta.vote_account)
-                .map(|jito_mev_meta| Some(jito_mev_meta.mev_commission))
-                .unwrap_or_else(|| {
-                    warn!(
-                        "No Jito MEV commission found for vote account: {}",
-                        vote_account_meta.vote_account
-                    );
-                    None
-                }),
-            stake: vote_account_meta.stake,
-            credits: vote_account_meta.credits,
+                .find(|meta| meta.vote_account == vote_account_meta.vote_account);
+            ValidatorMeta {
+                vote_account: vote_account_meta.vote_account,
+                commission: vote_account_meta.commission,
+                mev_commission: jito_mev_metas
+                    .metas
+                    .iter()
+                    .find(|jito_mev_meta| {
+                        jito_mev_meta.vote_account == vote_account_meta.vote_account
+                    })
+                    .map(|jito_mev_meta| Some(jito_mev_meta.mev_commission))
+                    .unwrap_or_else(|| {
+                        warn!(
+                            "No Jito MEV commission found for vote account: {}",
+                            vote_account_meta.vote_account
+                        );
+                        None
+                    }),
+                priority_fee_commission: priority_fee_meta.map(|meta| meta.priority_fee_commission),
+                priority_fee_lamports_transferred: priority_fee_meta
+                    .map(|meta| meta.total_lamports_transferred),
+                stake: vote_account_meta.stake,
+                stake_by_authority: BTreeMap::new(),
+                credits: vote_account_meta.credits,
+                identity: vote_account_meta.identity,
+                identity_lamports: vote_account_meta.identity_lamports,
+                epoch_credits: vote_account_meta.epoch_credits,
+                delinquent: vote_account_meta.delinquent,
+            }
         })
         .collect::<Vec<_>>();
 
@@ -161,6 +292,11 @@ pub fn generate_validator_collection(bank: &Arc<Bank>) -> anyhow::Result<Validat
         "Vote processors with some credits earned: {}",
         validator_metas.iter().filter(|v| v.credits > 0).count()
     );
+    info!(
+        "Delinquent vote accounts at slot {}: {}",
+        absolute_slot,
+        validator_metas.iter().filter(|v| v.delinquent).count()
+    );
 
     validator_metas.sort();
     info!("Sorted vote account metas");
@@ -168,10 +304,98 @@ pub fn generate_validator_collection(bank: &Arc<Bank>) -> anyhow::Result<Validat
     Ok(ValidatorMetaCollection {
         epoch,
         slot: absolute_slot,
-        capitalization,
-        epoch_duration_in_years,
-        validator_rate,
-        validator_rewards,
+        bank_hash: bank.hash().to_string(),
+        credits_window,
+        capitalization: bank_info.capitalization,
+        epoch_duration_in_years: bank_info.epoch_duration_in_years,
+        validator_rate: bank_info.inflation.validator,
+        validator_rewards: bank_info.validator_rewards,
+        jito_epoch,
+        total_jito_tips: jito_mev_metas.total_tips_lamports,
+        jito_enabled_validators,
+        jito_duplicate_distribution_accounts: jito_mev_metas.duplicate_accounts,
+        total_priority_fees_transferred: jito_priority_fee_metas.total_lamports_transferred,
+        jito_priority_fee_enabled_validators,
         validator_metas,
     })
 }
+
+/// Parses the stake authority label in format `<label>=<stake authority pubkey>`.
+pub fn parse_stake_authority_label(value: &str) -> Result<(String, Pubkey), String> {
+    let (label, pubkey) = value
+        .split_once('=')
+        .ok_or_else(|| format!("Expected <label>=<pubkey>, got '{}'", value))?;
+    let pubkey = Pubkey::from_str(pubkey.trim())
+        .map_err(|e| format!("Invalid stake authority pubkey '{}': {}", pubkey, e))?;
+    Ok((label.trim().to_string(), pubkey))
+}
+
+/// Fills [`ValidatorMeta::stake_by_authority`] with the active delegations of the stake accounts
+/// summed by the label of their stake authority. Unlabeled authorities are summed as [`OTHER_STAKE_AUTHORITY_LABEL`].
+pub fn fill_stake_by_authority(
+    validator_meta_collection: &mut ValidatorMetaCollection,
+    stake_meta_collection: &StakeMetaCollection,
+    stake_authority_labels: &[(String, Pubkey)],
+) {
+    let labels: HashMap<Pubkey, &str> = stake_authority_labels
+        .iter()
+        .map(|(label, pubkey)| (*pubkey, label.as_str()))
+        .collect();
+    let empty_split: BTreeMap<String, u64> = stake_authority_labels
+        .iter()
+        .map(|(label, _)| label.as_str())
+        .chain([OTHER_STAKE_AUTHORITY_LABEL])
+        .map(|label| (label.to_string(), 0))
+        .collect();
+
+    let mut stake_by_validator: HashMap<Pubkey, BTreeMap<String, u64>> = HashMap::new();
+    for stake_meta in stake_meta_collection.stake_metas.iter() {
+        let Some(validator) = stake_meta.validator else {
+            continue;
+        };
+        let label = labels
+            .get(&stake_meta.stake_authority)
+            .copied()
+            .unwrap_or(OTHER_STAKE_AUTHORITY_LABEL);
+        *stake_by_validator
+            .entry(validator)
+            .or_insert_with(|| empty_split.clone())
+            .entry(label.to_string())
+            .or_default() += stake_meta.active_delegation_lamports;
+    }
+
+    for validator_meta in validator_meta_collection.validator_metas.iter_mut() {
+        validator_meta.stake_by_authority = stake_by_validator
+            .remove(&validator_meta.vote_account)
+            .unwrap_or_else(|| empty_split.clone());
+    }
+    info!(
+        "Split validator stake by {} stake authority labels",
+        stake_authority_labels.len()
+    );
+}
+
+/// Whether the validator meta collection at `path` was generated from the bank of the slot and bank hash.
+/// A missing or unreadable file means not written.
+pub fn is_validator_meta_collection_written(path: &str, slot: u64, bank_hash: &str) -> bool {
+    #[derive(Deserialize)]
+    struct CollectionBank {
+        slot: u64,
+        bank_hash: Option<String>,
+    }
+    if !Path::new(path).exists() {
+        return false;
+    }
+    match read_from_json_file::<_, CollectionBank>(&path) {
+        Ok(collection) => {
+            collection.slot == slot && collection.bank_hash.as_deref() == Some(bank_hash)
+        }
+        Err(e) => {
+            warn!(
+                "Cannot read the existing validator meta collection {}: {}",
+                path, e
+            );
+            false
+        }
+    }
+}
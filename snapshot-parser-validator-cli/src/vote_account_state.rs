@@ -0,0 +1,179 @@
+use {
+    serde::{Deserialize, Serialize},
+    snapshot_parser::{progress_bar::ProgressCounter, serde_serialize::pubkey_string_conversion},
+    solana_program::{clock::UnixTimestamp, pubkey::Pubkey, stake_history::Epoch},
+    solana_runtime::bank::Bank,
+    solana_sdk::{account::ReadableAccount, epoch_info::EpochInfo},
+    std::sync::Arc,
+    tracing::{error, info},
+};
+
+/// Lockout of a vote not yet rooted, see `LandedVote`.
+#[derive(Clone, Deserialize, Serialize, Debug, Eq, PartialEq)]
+pub struct VoteLockout {
+    pub slot: u64,
+    pub confirmation_count: u32,
+    /// slots between the voted slot and the slot the vote landed in, 0 for votes cast by older validators
+    pub latency: u8,
+}
+
+#[derive(Clone, Deserialize, Serialize, Debug, Eq, PartialEq)]
+pub struct AuthorizedVoter {
+    pub epoch: Epoch,
+    #[serde(with = "pubkey_string_conversion")]
+    pub authorized_voter: Pubkey,
+}
+
+/// Authorized voter replaced in the past, valid from the start epoch (inclusive) to the end epoch (exclusive).
+#[derive(Clone, Deserialize, Serialize, Debug, Eq, PartialEq)]
+pub struct PriorVoter {
+    #[serde(with = "pubkey_string_conversion")]
+    pub authorized_voter: Pubkey,
+    pub start_epoch: Epoch,
+    pub end_epoch: Epoch,
+}
+
+#[derive(Clone, Deserialize, Serialize, Debug, Eq, PartialEq)]
+pub struct EpochCredits {
+    pub epoch: Epoch,
+    /// cumulative credits at the end of the epoch
+    pub credits: u64,
+    /// cumulative credits at the start of the epoch
+    pub prev_credits: u64,
+}
+
+/// Decoded vote state of a vote account.
+#[derive(Clone, Deserialize, Serialize, Debug, Eq, PartialEq)]
+pub struct VoteAccountState {
+    #[serde(with = "pubkey_string_conversion")]
+    pub vote_account: Pubkey,
+    pub lamports: u64,
+    /// stake delegated to the vote account in the snapshot epoch
+    pub stake: u64,
+    #[serde(with = "pubkey_string_conversion")]
+    pub node_pubkey: Pubkey,
+    #[serde(with = "pubkey_string_conversion")]
+    pub authorized_withdrawer: Pubkey,
+    pub commission: u8,
+    pub root_slot: Option<u64>,
+    pub votes: Vec<VoteLockout>,
+    pub authorized_voters: Vec<AuthorizedVoter>,
+    /// at most the last 32 replaced authorized voters, sorted by the start epoch
+    pub prior_voters: Vec<PriorVoter>,
+    pub epoch_credits: Vec<EpochCredits>,
+    pub last_timestamp_slot: u64,
+    pub last_timestamp: UnixTimestamp,
+}
+
+impl Ord for VoteAccountState {
+    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+        self.vote_account.cmp(&other.vote_account)
+    }
+}
+
+impl PartialOrd<Self> for VoteAccountState {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+#[derive(Clone, Deserialize, Serialize, Debug, Default)]
+pub struct VoteAccountStateCollection {
+    pub epoch: Epoch,
+    pub slot: u64,
+    pub vote_accounts: Vec<VoteAccountState>,
+}
+
+/// Decodes the vote states of all vote accounts of the bank, accounts whose vote state cannot be decoded are skipped.
+pub fn generate_vote_account_state_collection(
+    bank: &Arc<Bank>,
+    progress_counter: &ProgressCounter,
+) -> anyhow::Result<VoteAccountStateCollection> {
+    assert!(bank.is_frozen());
+
+    let EpochInfo {
+        epoch,
+        absolute_slot,
+        ..
+    } = bank.get_epoch_info();
+
+    let mut vote_accounts = bank
+        .vote_accounts()
+        .iter()
+        .inspect(|_| progress_counter.inc())
+        .filter_map(
+            |(pubkey, (stake, vote_account))| match vote_account.vote_state() {
+                Ok(vote_state) => {
+                    // the prior voters are readable through a mutable accessor only
+                    let mut vote_state = vote_state.clone();
+                    let mut prior_voters = vote_state
+                        .prior_voters()
+                        .buf()
+                        .iter()
+                        .filter(|(voter, _, _)| *voter != Pubkey::default())
+                        .map(|(voter, start_epoch, end_epoch)| PriorVoter {
+                            authorized_voter: *voter,
+                            start_epoch: *start_epoch,
+                            end_epoch: *end_epoch,
+                        })
+                        .collect::<Vec<_>>();
+                    prior_voters.sort_by_key(|prior_voter| prior_voter.start_epoch);
+
+                    Some(VoteAccountState {
+                        vote_account: *pubkey,
+                        lamports: vote_account.lamports(),
+                        stake: *stake,
+                        node_pubkey: vote_state.node_pubkey,
+                        authorized_withdrawer: vote_state.authorized_withdrawer,
+                        commission: vote_state.commission,
+                        root_slot: vote_state.root_slot,
+                        votes: vote_state
+                            .votes
+                            .iter()
+                            .map(|vote| VoteLockout {
+                                slot: vote.slot(),
+                                confirmation_count: vote.confirmation_count(),
+                                latency: vote.latency,
+                            })
+                            .collect(),
+                        authorized_voters: vote_state
+                            .authorized_voters()
+                            .iter()
+                            .map(|(epoch, voter)| AuthorizedVoter {
+                                epoch: *epoch,
+                                authorized_voter: *voter,
+                            })
+                            .collect(),
+                        prior_voters,
+                        epoch_credits: vote_state
+                            .epoch_credits
+                            .iter()
+                            .map(|(epoch, credits, prev_credits)| EpochCredits {
+                                epoch: *epoch,
+                                credits: *credits,
+                                prev_credits: *prev_credits,
+                            })
+                            .collect(),
+                        last_timestamp_slot: vote_state.last_timestamp.slot,
+                        last_timestamp: vote_state.last_timestamp.timestamp,
+                    })
+                }
+                Err(err) => {
+                    error!("Failed to get the vote state for: {}: {}", pubkey, err);
+                    None
+                }
+            },
+        )
+        .collect::<Vec<_>>();
+    vote_accounts.sort();
+    info!(
+        "Decoded vote states of {} vote accounts",
+        vote_accounts.len()
+    );
+
+    Ok(VoteAccountStateCollection {
+        epoch,
+        slot: absolute_slot,
+        vote_accounts,
+    })
+}
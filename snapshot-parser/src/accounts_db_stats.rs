@@ -0,0 +1,44 @@
+use {
+    serde::{Deserialize, Serialize},
+    solana_runtime::bank::Bank,
+    solana_sdk::clock::Slot,
+    tracing::info,
+};
+
+/// Accounts-db storage statistics of the loaded snapshot.
+#[derive(Clone, Deserialize, Serialize, Debug, Default, Eq, PartialEq)]
+pub struct AccountsDbStats {
+    /// accounts alive in the storages
+    pub total_accounts: u64,
+    pub storage_entries: u64,
+    pub alive_bytes: u64,
+    /// bytes written to the storages that belong to accounts updated in later slots
+    pub dead_bytes: u64,
+    pub oldest_slot: Option<Slot>,
+}
+
+pub fn accounts_db_stats(bank: &Bank) -> AccountsDbStats {
+    let (storages, _) = bank
+        .rc
+        .accounts
+        .accounts_db
+        .get_snapshot_storages(..=bank.slot());
+
+    let stats = storages
+        .iter()
+        .fold(AccountsDbStats::default(), |mut stats, storage| {
+            let alive_bytes = storage.alive_bytes() as u64;
+            stats.total_accounts += storage.count() as u64;
+            stats.storage_entries += 1;
+            stats.alive_bytes += alive_bytes;
+            stats.dead_bytes += storage.written_bytes().saturating_sub(alive_bytes);
+            stats.oldest_slot = Some(
+                stats
+                    .oldest_slot
+                    .map_or(storage.slot(), |slot| slot.min(storage.slot())),
+            );
+            stats
+        });
+    info!("Accounts-db stats: {:?}", stats);
+    stats
+}
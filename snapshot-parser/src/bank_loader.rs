@@ -1,8 +1,7 @@
 use {
-    log::info,
     solana_accounts_db::{
         accounts_db::AccountsDbConfig,
-        accounts_index::AccountsIndexConfig,
+        accounts_index::{AccountsIndexConfig, IndexLimitMb},
         hardened_unpack::{open_genesis_config, MAX_GENESIS_ARCHIVE_UNPACKED_SIZE},
     },
     solana_ledger::{
@@ -19,11 +18,229 @@ use {
     std::{
         fs,
         path::{Path, PathBuf},
-        sync::{atomic::AtomicBool, Arc},
+        sync::{
+            atomic::{AtomicBool, Ordering},
+            Arc, Mutex,
+        },
+        thread::{sleep, spawn},
+        time::{Duration, Instant},
     },
+    tracing::{debug, info, warn},
 };
 
+/// Files of the ledger are sampled this often while the bank loads.
+const SAMPLE_INTERVAL: Duration = Duration::from_secs(10);
+/// Every this many samples the progress is logged, so the loading is not silent without a progress bar.
+const SAMPLES_PER_LOG: u32 = 6;
+
+/// Phases of the bank loading.
+///
+/// The snapshot phases run inside `load_bank_forks` without any hooks, so they are inferred from the files written:
+/// the account storages are unpacked into the account path, then the accounts index is generated into the index drive,
+/// then the bank is verified with no files written.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum BankLoadPhase {
+    /// genesis config and blockstore
+    OpenLedger,
+    /// untar of the snapshot archive and load of the account storages
+    UnpackSnapshot,
+    GenerateIndex,
+    /// accounts hash verification and the rest of the bank setup
+    VerifyBank,
+}
+
+/// Progress of the bank loading, passed to the callback of [`create_bank_from_ledger_with_progress`].
+#[derive(Clone, Copy, Debug)]
+pub struct BankLoadSample {
+    pub phase: BankLoadPhase,
+    /// since the bank loading started
+    pub elapsed: Duration,
+    /// size of the unpacked account storages
+    pub unpacked_bytes: u64,
+    /// size of the accounts index on the index drive
+    pub index_bytes: u64,
+}
+
+struct PhaseTracker {
+    phase: BankLoadPhase,
+    phase_started: Instant,
+    durations: Vec<(BankLoadPhase, Duration)>,
+}
+
+impl PhaseTracker {
+    fn new() -> Self {
+        info!("Bank loading phase {:?} started", BankLoadPhase::OpenLedger);
+        Self {
+            phase: BankLoadPhase::OpenLedger,
+            phase_started: Instant::now(),
+            durations: Vec::new(),
+        }
+    }
+
+    fn finish_phase(&mut self) {
+        let duration = self.phase_started.elapsed();
+        info!("Bank loading phase {:?} took {:?}", self.phase, duration);
+        self.durations.push((self.phase, duration));
+        self.phase_started = Instant::now();
+    }
+
+    fn enter(&mut self, phase: BankLoadPhase) {
+        if phase != self.phase {
+            self.finish_phase();
+            info!("Bank loading phase {:?} started", phase);
+            self.phase = phase;
+        }
+    }
+}
+
+/// Total size of the files under the directory, skipping the `snapshot` dirs of hard links to the run files.
+fn dir_size(dir: &Path) -> u64 {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return 0;
+    };
+    entries
+        .flatten()
+        .map(|entry| match entry.metadata() {
+            Ok(metadata) if metadata.is_dir() => {
+                if entry.file_name() == "snapshot" {
+                    0
+                } else {
+                    dir_size(&entry.path())
+                }
+            }
+            Ok(metadata) => metadata.len(),
+            // the files come and go while unpacking
+            Err(_) => 0,
+        })
+        .sum()
+}
+
+/// Samples the files written by `load_bank_forks` until `done` is set, moving the tracker through the phases.
+fn monitor_bank_load<F: Fn(&BankLoadSample)>(
+    accounts_dir: &Path,
+    index_dir: &Path,
+    tracker: &Mutex<PhaseTracker>,
+    done: &AtomicBool,
+    on_sample: F,
+) {
+    let started = Instant::now();
+    let (mut last_unpacked_bytes, mut last_index_bytes) = (0, 0);
+    let mut samples = 0u32;
+    while !done.load(Ordering::Relaxed) {
+        sleep(Duration::from_secs(1));
+        if started.elapsed().as_secs() < (samples as u64 + 1) * SAMPLE_INTERVAL.as_secs() {
+            continue;
+        }
+        samples += 1;
+        let (unpacked_bytes, index_bytes) = (dir_size(accounts_dir), dir_size(index_dir));
+        let mut tracker = tracker.lock().unwrap();
+        let next_phase = match tracker.phase {
+            // the unpacked storages stopped growing or the index is being written
+            BankLoadPhase::UnpackSnapshot
+                if (unpacked_bytes > 0 && unpacked_bytes == last_unpacked_bytes)
+                    || index_bytes > last_index_bytes =>
+            {
+                BankLoadPhase::GenerateIndex
+            }
+            BankLoadPhase::GenerateIndex
+                if index_bytes > 0
+                    && index_bytes == last_index_bytes
+                    && unpacked_bytes == last_unpacked_bytes =>
+            {
+                BankLoadPhase::VerifyBank
+            }
+            phase => phase,
+        };
+        tracker.enter(next_phase);
+        let sample = BankLoadSample {
+            phase: tracker.phase,
+            elapsed: started.elapsed(),
+            unpacked_bytes,
+            index_bytes,
+        };
+        drop(tracker);
+        if samples % SAMPLES_PER_LOG == 0 {
+            info!(
+                "Loading bank: phase {:?}, {:?} elapsed, {} MB of account storages unpacked, {} MB of accounts index",
+                sample.phase,
+                sample.elapsed,
+                unpacked_bytes / 1024 / 1024,
+                index_bytes / 1024 / 1024
+            );
+        } else {
+            debug!("Loading bank: {:?}", sample);
+        }
+        on_sample(&sample);
+        (last_unpacked_bytes, last_index_bytes) = (unpacked_bytes, index_bytes);
+    }
+}
+
+/// Tuning of the accounts-db the bank is loaded into, to trade the loading time for memory and safety.
+/// The defaults keep the accounts index on disk in the ledger path with the accounts-db default bins and cache.
+#[derive(Debug, Clone, Default)]
+#[cfg_attr(feature = "cli", derive(clap::Args))]
+pub struct BankLoaderConfig {
+    /// Skips the accounts hash verification and the shrink of the account storages while loading the bank;
+    /// faster, but a corrupted snapshot is not detected, use only for trusted snapshots
+    #[cfg_attr(feature = "cli", arg(long, env))]
+    pub skip_verification: bool,
+
+    /// Number of bins of the accounts index, a power of two; more bins mean smaller in-memory chunks of the index
+    #[cfg_attr(feature = "cli", arg(long, env))]
+    pub accounts_index_bins: Option<usize>,
+
+    /// Memory in MB the in-memory part of the accounts index is kept under, the rest stays in the disk index;
+    /// lets the parser run on machines with less memory than the full index needs, at the cost of speed
+    #[cfg_attr(
+        feature = "cli",
+        arg(long, env, conflicts_with = "accounts_index_in_memory")
+    )]
+    pub accounts_index_memory_limit_mb: Option<usize>,
+
+    /// Keeps the whole accounts index in memory without the disk index; fastest, needs the most memory
+    #[cfg_attr(feature = "cli", arg(long, env))]
+    pub accounts_index_in_memory: bool,
+
+    /// Directory of the disk accounts index (e.g., on a fast local disk), `<ledger path>/drive1` when not set
+    #[cfg_attr(feature = "cli", arg(long, env))]
+    pub accounts_index_path: Option<PathBuf>,
+}
+
+impl BankLoaderConfig {
+    fn index_limit_mb(&self) -> IndexLimitMb {
+        if self.accounts_index_in_memory {
+            IndexLimitMb::InMemOnly
+        } else if let Some(limit_mb) = self.accounts_index_memory_limit_mb {
+            IndexLimitMb::Limit(limit_mb)
+        } else {
+            IndexLimitMb::Unspecified
+        }
+    }
+}
+
 pub fn create_bank_from_ledger(ledger_path: &Path) -> anyhow::Result<Arc<Bank>> {
+    let (bank, _) =
+        create_bank_from_ledger_with_progress(ledger_path, &BankLoaderConfig::default(), |_| {})?;
+    Ok(bank)
+}
+
+/// Loads the bank like [`create_bank_from_ledger`] with the accounts-db tuned by the config,
+/// calling `on_sample` with the progress every few seconds from a monitor thread.
+/// Returns the bank with the durations of the loading phases, see [`BankLoadPhase`].
+pub fn create_bank_from_ledger_with_progress<F>(
+    ledger_path: &Path,
+    config: &BankLoaderConfig,
+    on_sample: F,
+) -> anyhow::Result<(Arc<Bank>, Vec<(BankLoadPhase, Duration)>)>
+where
+    F: Fn(&BankLoadSample) + Send + 'static,
+{
+    if let Some(bins) = config.accounts_index_bins {
+        if !bins.is_power_of_two() {
+            anyhow::bail!("Accounts index bins must be a power of two, got {}", bins);
+        }
+    }
+    let tracker = Arc::new(Mutex::new(PhaseTracker::new()));
     let genesis_config = open_genesis_config(ledger_path, MAX_GENESIS_ARCHIVE_UNPACKED_SIZE)?;
     let snapshot_config = SnapshotConfig {
         usage: SnapshotUsage::LoadOnly,
@@ -45,13 +262,34 @@ pub fn create_bank_from_ledger(ledger_path: &Path) -> anyhow::Result<Arc<Bank>>
     )?;
     info!("Blockstore loaded.");
 
-    let drive_dir = PathBuf::from(ledger_path).join("drive1");
-    fs::create_dir_all(&drive_dir).unwrap();
+    let drive_dir = config
+        .accounts_index_path
+        .clone()
+        .unwrap_or_else(|| PathBuf::from(ledger_path).join("drive1"));
+    fs::create_dir_all(&drive_dir)?;
+    let accounts_dir = PathBuf::from(ledger_path).join(Path::new("stake-meta.processors"));
 
-    let (bank_forks, ..) = bank_forks_utils::load_bank_forks(
+    tracker.lock().unwrap().enter(BankLoadPhase::UnpackSnapshot);
+    if config.skip_verification {
+        warn!("Bank verification is skipped, the snapshot is trusted as it is");
+    }
+    info!(
+        "Accounts index: {:?}, bins {:?}, disk index in {:?}",
+        config.index_limit_mb(),
+        config.accounts_index_bins,
+        drive_dir
+    );
+    let done = Arc::new(AtomicBool::new(false));
+    let monitor_handle = {
+        let (accounts_dir, index_dir) = (accounts_dir.clone(), drive_dir.clone());
+        let (tracker, done) = (tracker.clone(), done.clone());
+        spawn(move || monitor_bank_load(&accounts_dir, &index_dir, &tracker, &done, on_sample))
+    };
+
+    let bank_forks_result = bank_forks_utils::load_bank_forks(
         &genesis_config,
         &blockstore,
-        vec![PathBuf::from(ledger_path).join(Path::new("stake-meta.processors"))],
+        vec![accounts_dir],
         Some(&snapshot_config),
         &ProcessOptions {
             slot_callback: Some(Arc::new(|bank| info!("Slot callback: {}", bank.slot()))),
@@ -62,22 +300,31 @@ pub fn create_bank_from_ledger(ledger_path: &Path) -> anyhow::Result<Arc<Bank>>
             accounts_db_config: Some(AccountsDbConfig {
                 index: Some(AccountsIndexConfig {
                     drives: Some(vec![drive_dir]),
+                    bins: config.accounts_index_bins,
+                    index_limit_mb: config.index_limit_mb(),
                     ..AccountsIndexConfig::default()
                 }),
                 base_working_path: Some(PathBuf::from(ledger_path)),
+                skip_initial_hash_calc: config.skip_verification,
                 ..AccountsDbConfig::default()
             }),
+            accounts_db_skip_shrink: config.skip_verification,
             ..ProcessOptions::default()
         },
         None,
         None,
         None,
         Arc::new(AtomicBool::new(false)),
-    )?;
+    );
+    done.store(true, Ordering::Relaxed);
+    let _ = monitor_handle.join();
+    let (bank_forks, ..) = bank_forks_result?;
     info!("Bank forks loaded.");
 
     let working_bank = bank_forks.read().unwrap().working_bank();
     info!("Bank slot: {}", working_bank.slot());
 
-    Ok(working_bank)
+    let mut tracker = tracker.lock().unwrap();
+    tracker.finish_phase();
+    Ok((working_bank, std::mem::take(&mut tracker.durations)))
 }
@@ -0,0 +1,502 @@
+use crate::db_message::{DbMessage, OwnedSqlValue, TableColumns};
+use crate::progress_bar::{LocalCounter, ProgressCounter};
+use crate::schema::{create_index_query, SCHEMA_VERSION, TABLE_INDEXES};
+use crate::sql_row::{create_table_query, insert_query, insert_rows_query, SqlRow};
+use crate::temp_file::TempFileGuard;
+use rusqlite::{params_from_iter, Connection, Params};
+use snapshot_parser_types::decoded_accounts::{ProcessorCompletionRow, ScanCheckpointRow};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Instant;
+use tokio::sync::mpsc::Receiver;
+use tracing::{debug, error, info, info_span};
+
+/// Path of the temporary DB file written next to `db_path` and promoted to it on success.
+pub fn temp_db_path(db_path: &Path) -> PathBuf {
+    db_path.with_file_name(format!(
+        "_{}.tmp",
+        db_path.file_name().unwrap().to_string_lossy()
+    ))
+}
+
+/// Path the DB of an interrupted run is promoted to, e.g., `tokens.partial.db` for `tokens.db`.
+pub fn partial_db_path(db_path: &Path) -> PathBuf {
+    let stem = db_path.file_stem().unwrap().to_string_lossy();
+    let extension = db_path
+        .extension()
+        .map_or("db".into(), |extension| extension.to_string_lossy());
+    db_path.with_file_name(format!("{}.partial.{}", stem, extension))
+}
+
+/// Upsert of the [`ScanCheckpointRow`] keeping the checkpoint of a newer slot.
+fn checkpoint_upsert_query() -> String {
+    let columns = ScanCheckpointRow::column_names();
+    format!(
+        "INSERT INTO {table} ({columns}) VALUES ({values}) \
+         ON CONFLICT(processor) DO UPDATE SET {updates} WHERE excluded.slot >= {table}.slot;",
+        table = ScanCheckpointRow::TABLE,
+        columns = columns.join(", "),
+        values = vec!["?"; columns.len()].join(", "),
+        updates = columns
+            .iter()
+            .map(|column| format!("{column} = excluded.{column}"))
+            .collect::<Vec<_>>()
+            .join(", "),
+    )
+}
+
+/// Host parameters of a statement of the bundled SQLite at most (`SQLITE_MAX_VARIABLE_NUMBER`).
+const SQLITE_MAX_VARIABLES: usize = 32_766;
+
+/// Rows of a multi-row INSERT statement at most, longer batches are split into several statements.
+const MAX_STATEMENT_ROWS: usize = 256;
+
+/// Prepared statements kept by the connection, enough for the single-row and multi-row INSERTs of all tables.
+const STATEMENT_CACHE_CAPACITY: usize = 256;
+
+pub struct SQLiteExecutor {
+    db: Connection,
+    db_path: PathBuf,
+    db_temp_guard: TempFileGuard,
+    /// the temporary DB of an interrupted run is kept to resume from instead of being promoted as partial
+    resume: bool,
+
+    tx_bulk: Option<u16>,
+    /// statements of the open transaction bulk, counted with `tx_bulk` only
+    transaction_batch_counter: u16,
+
+    db_execute_counter: LocalCounter,
+
+    receiver: Receiver<DbMessage>,
+    shut_down: bool,
+}
+
+impl SQLiteExecutor {
+    /// This is a SQLite DB connection wrapper that provides a temporary file for the DB.
+    /// This connection requires exclusive locking unless `shared_reads` is set and has got no journaling set up.
+    /// With `reprocess` set the existing DB at `db_path` is updated instead of being replaced.
+    /// With `resume` set the temporary DB of a failed run is reused and it is kept when this run fails too.
+    pub fn new(
+        db_path: PathBuf,
+        cache_size: Option<i64>,
+        mmap_size: Option<u16>,
+        tx_bulk: Option<u16>,
+        shared_reads: bool,
+        reprocess: bool,
+        resume: bool,
+        db_execute_counter: Arc<ProgressCounter>,
+        receiver: Receiver<DbMessage>,
+    ) -> anyhow::Result<Self> {
+        // Create temporary DB file, which gets promoted on success.
+        let db_temp_path = temp_db_path(&db_path);
+        let resumed = resume && db_temp_path.exists();
+        let db_temp_guard = if resume {
+            TempFileGuard::persistent(db_temp_path.clone())
+        } else {
+            TempFileGuard::new(db_temp_path.clone())
+        };
+        if resumed {
+            info!("Resuming into temporary DB {:?}", db_temp_path);
+        } else {
+            let _ = std::fs::remove_file(&db_temp_path);
+        }
+        // Re-running into an existing DB starts from its copy, the rows are upserted by their primary keys.
+        let reprocessed = reprocess && !resumed && db_path.exists();
+        if reprocessed {
+            info!("Reprocessing into existing DB {:?}", db_path);
+            std::fs::copy(&db_path, &db_temp_path)?;
+        }
+        // Create and configure the DB as file-backed
+        let db = Self::connect_db(&db_temp_path, cache_size, mmap_size, shared_reads)
+            .map_err(|e| SQLiteExecutor::convert_sqlite_error("new", e))?;
+        // An existing DB of another layout cannot be updated, its tables may miss or differ in columns.
+        if resumed || reprocessed {
+            let db_schema_version: u32 = db
+                .pragma_query_value(None, "user_version", |row| row.get(0))
+                .map_err(|e| SQLiteExecutor::convert_sqlite_error("new", e))?;
+            if db_schema_version != SCHEMA_VERSION {
+                anyhow::bail!(
+                    "DB {:?} was written with schema version {}, the parser writes version {}; re-run without --reprocess and --resume",
+                    db_path,
+                    db_schema_version,
+                    SCHEMA_VERSION
+                );
+            }
+        }
+        db.pragma_update(None, "user_version", SCHEMA_VERSION)
+            .map_err(|e| SQLiteExecutor::convert_sqlite_error("new", e))?;
+
+        Ok(Self {
+            db,
+            db_path,
+            db_temp_guard,
+            resume,
+            tx_bulk,
+            transaction_batch_counter: 0,
+            db_execute_counter: db_execute_counter.local(),
+            receiver,
+            shut_down: false,
+        })
+    }
+
+    /// Execute data insertion into the DB within transaction processing.
+    pub async fn execute<P: Params>(&mut self, sql: &str, params: P) -> anyhow::Result<usize> {
+        if self.tx_bulk.is_some() {
+            // we explicitly start transaction bulk here, otherwise every insert will be a separate transaction that fsync to disk
+            self.begin_db("execute")?;
+        }
+
+        // the statement is parsed once per query, then taken from the cache of the connection
+        let mut stmt = self
+            .db
+            .prepare_cached(sql)
+            .map_err(|e| SQLiteExecutor::convert_sqlite_error("execute:prepare", e))?;
+
+        let result = stmt
+            .execute(params)
+            .map_err(|e| SQLiteExecutor::convert_sqlite_error("execute:statement", e))?;
+        self.db_execute_counter.inc();
+        drop(stmt);
+
+        if let Some(bulk_size) = self.tx_bulk {
+            self.transaction_batch_counter = self.transaction_batch_counter.saturating_add(1);
+            if self.transaction_batch_counter >= bulk_size {
+                self.commit_db("execute")?;
+            }
+        }
+        Ok(result)
+    }
+
+    /// Upserts the rows by multi-row INSERT statements, prepared once per table and number of rows.
+    /// Without the transaction bulk, all the rows are inserted in one transaction.
+    pub async fn execute_rows(
+        &mut self,
+        table: &str,
+        columns: TableColumns,
+        rows: &[Vec<OwnedSqlValue>],
+    ) -> anyhow::Result<usize> {
+        if rows.is_empty() {
+            return Ok(0);
+        }
+        self.begin_db("execute_rows")?;
+
+        let column_names: Vec<&str> = columns.iter().map(|(name, _)| *name).collect();
+        let statement_rows =
+            (SQLITE_MAX_VARIABLES / column_names.len().max(1)).min(MAX_STATEMENT_ROWS);
+        let mut result = 0;
+        for chunk in rows.chunks(statement_rows) {
+            let mut stmt = self
+                .db
+                .prepare_cached(&insert_rows_query(table, &column_names, chunk.len()))
+                .map_err(|e| SQLiteExecutor::convert_sqlite_error("execute_rows:prepare", e))?;
+            result += stmt
+                .execute(params_from_iter(chunk.iter().flatten()))
+                .map_err(|e| SQLiteExecutor::convert_sqlite_error("execute_rows:statement", e))?;
+            self.db_execute_counter.add(chunk.len() as u64);
+        }
+
+        match self.tx_bulk {
+            Some(bulk_size) => {
+                let rows_count = u16::try_from(rows.len()).unwrap_or(u16::MAX);
+                self.transaction_batch_counter =
+                    self.transaction_batch_counter.saturating_add(rows_count);
+                if self.transaction_batch_counter >= bulk_size {
+                    self.commit_db("execute_rows")?;
+                }
+            }
+            None => self.commit_db("execute_rows")?,
+        }
+        Ok(result)
+    }
+
+    /// Usable for special cases when quiting transaction is required.
+    /// Use only for really special cases that are un-usual like creating tables and similar.
+    pub async fn execute_special<P: Params>(
+        &mut self,
+        sql: &str,
+        params: P,
+    ) -> anyhow::Result<usize> {
+        // closing any open transaction
+        self.commit_db("execute_special")?;
+
+        debug!("Executing special out-of-transaction SQL: {}", sql);
+        let result = self
+            .db
+            .execute(sql, params)
+            .map_err(|e| SQLiteExecutor::convert_sqlite_error("execute_special:execute", e))?;
+
+        Ok(result)
+    }
+
+    fn connect_db(
+        path: &Path,
+        cache_size_mb: Option<i64>,
+        mmap_size_mb: Option<u16>,
+        shared_reads: bool,
+    ) -> rusqlite::Result<Connection> {
+        let db = Connection::open(&path)?;
+        db.pragma_update(None, "synchronous", false)?;
+        db.pragma_update(None, "journal_mode", "off")?;
+        // exclusive locking keeps the file locked till the connection is closed,
+        // the shared reads allow to query the committed rows of the temporary DB mid-run
+        if !shared_reads {
+            db.pragma_update(None, "locking_mode", "exclusive")?;
+        }
+        db.pragma_update(None, "temp_store", "memory")?;
+        db.set_prepared_statement_cache_capacity(STATEMENT_CACHE_CAPACITY);
+        if let Some(size_mib) = cache_size_mb {
+            let size = size_mib * 1024;
+            db.pragma_update(None, "cache_size", -size)?;
+        }
+        if let Some(size_mib) = mmap_size_mb {
+            let size_kb = size_mib * 1024;
+            db.pragma_update(None, "mmap_size", size_kb)?;
+        }
+        Ok(db)
+    }
+
+    pub async fn start(mut self) {
+        if self.shut_down {
+            error!("SQLiteExecutor already shut down");
+            return;
+        }
+
+        info!("SQLiteExecutor receiver started to listen for SQL insertion messages");
+        while let Some(msg) = self.receiver.recv().await {
+            match msg {
+                DbMessage::CreateTable {
+                    table,
+                    columns,
+                    response,
+                } => {
+                    let result = self
+                        .execute_special(&create_table_query(table, columns), [])
+                        .await;
+                    let _ = response.send(result);
+                }
+                DbMessage::Insert { row, response } => {
+                    let column_names: Vec<&str> =
+                        row.columns().iter().map(|(name, _)| *name).collect();
+                    let query = insert_query(row.table(), &column_names);
+                    let values = row.into_values();
+                    let result = self.execute(&query, params_from_iter(values.iter())).await;
+                    let _ = response.send(result);
+                }
+                DbMessage::InsertBatch { rows, response } => {
+                    let (table, columns) = (rows.table(), rows.columns());
+                    let result = self.execute_rows(table, columns, &rows.into_values()).await;
+                    let _ = response.send(result);
+                }
+                DbMessage::CommitCheckpoint {
+                    checkpoint,
+                    response,
+                } => {
+                    let result = self
+                        .execute_special(
+                            &checkpoint_upsert_query(),
+                            params_from_iter(checkpoint.to_values().iter()),
+                        )
+                        .await;
+                    let _ = response.send(result);
+                }
+                DbMessage::RecordCompletion {
+                    completion,
+                    response,
+                } => {
+                    let result = self
+                        .execute_special(
+                            &ProcessorCompletionRow::insert_query(),
+                            params_from_iter(completion.to_values().iter()),
+                        )
+                        .await;
+                    let _ = response.send(result);
+                }
+                DbMessage::Finalize { response } => {
+                    let _ = response.send(self.optimize());
+                }
+                DbMessage::Shutdown { response } => {
+                    let result = self.finalize().await;
+                    if result.is_ok() {
+                        self.shut_down = true;
+                    }
+                    let _ = response.send(result);
+                }
+                DbMessage::Interrupt { response } => {
+                    let result = self.interrupt();
+                    self.shut_down = true;
+                    let _ = response.send(result);
+                    // the receiver is dropped, so the processors still running fail to send
+                    break;
+                }
+            }
+        }
+    }
+
+    /// Indexes the written tables, updates the statistics of the query planner and vacuums the DB,
+    /// so the lookups by owner or mint do not scan the whole table.
+    pub fn optimize(&mut self) -> anyhow::Result<()> {
+        self.commit_db("optimize")?;
+        let _span = info_span!("db_optimize").entered();
+        for (table, column) in TABLE_INDEXES {
+            if !self.table_exists(table)? {
+                continue;
+            }
+            let started = Instant::now();
+            self.db
+                .execute_batch(&create_index_query(table, column))
+                .map_err(|e| SQLiteExecutor::convert_sqlite_error("optimize:index", e))?;
+            info!(
+                "Index of table {} on {} created in {:?}",
+                table,
+                column,
+                started.elapsed()
+            );
+        }
+        let started = Instant::now();
+        self.db
+            .execute_batch("ANALYZE;")
+            .map_err(|e| SQLiteExecutor::convert_sqlite_error("optimize:analyze", e))?;
+        self.db
+            .execute_batch("VACUUM;")
+            .map_err(|e| SQLiteExecutor::convert_sqlite_error("optimize:vacuum", e))?;
+        info!("SQLite DB analyzed and vacuumed in {:?}", started.elapsed());
+        Ok(())
+    }
+
+    fn table_exists(&self, table: &str) -> anyhow::Result<bool> {
+        self.db
+            .query_row(
+                "SELECT EXISTS(SELECT 1 FROM sqlite_master WHERE type = 'table' AND name = ?1)",
+                [table],
+                |row| row.get(0),
+            )
+            .map_err(|e| SQLiteExecutor::convert_sqlite_error("table_exists", e))
+    }
+
+    pub async fn finalize(&mut self) -> anyhow::Result<()> {
+        // first, commit transactions if there is some started
+        self.commit_db("finalize")?;
+
+        // second, promote the DB file as finished
+        let db_path = self.db_path.clone();
+        self.db_temp_guard.promote(db_path)?;
+        info!(
+            "SQLite DB file promoted to: {:?} and finalized",
+            &self.db_path
+        );
+        Ok(())
+    }
+
+    /// Commits the rows written so far and promotes the DB as partial, see [`partial_db_path`].
+    /// With `resume` the temporary DB is kept instead, the next run resumes from it.
+    pub fn interrupt(&mut self) -> anyhow::Result<()> {
+        self.commit_db("interrupt")?;
+        if self.resume {
+            info!("Interrupted, keeping the temporary DB to resume from");
+            return Ok(());
+        }
+        let partial_path = partial_db_path(&self.db_path);
+        self.db_temp_guard.promote(&partial_path)?;
+        info!(
+            "Interrupted, partial SQLite DB promoted to: {:?}",
+            partial_path
+        );
+        Ok(())
+    }
+
+    /// Opens a transaction unless one is open already.
+    fn begin_db(&mut self, method_name: &str) -> anyhow::Result<()> {
+        if self.db.is_autocommit() {
+            self.db.execute_batch("BEGIN;").map_err(|e| {
+                SQLiteExecutor::convert_sqlite_error(format!("{}:begin", method_name).as_str(), e)
+            })?;
+        }
+        Ok(())
+    }
+
+    /// Commits the open transaction, if there is one.
+    fn commit_db(&mut self, method_name: &str) -> anyhow::Result<()> {
+        if !self.db.is_autocommit() {
+            let _span =
+                info_span!("db_commit", statements = self.transaction_batch_counter).entered();
+            self.db.execute_batch("COMMIT;").map_err(|e| {
+                SQLiteExecutor::convert_sqlite_error(format!("{}:commit", method_name).as_str(), e)
+            })?;
+        }
+        self.transaction_batch_counter = 0;
+        self.db_execute_counter.flush();
+        Ok(())
+    }
+
+    fn convert_sqlite_error(method: &str, err: rusqlite::Error) -> anyhow::Error {
+        let msg = format!("SQLite error at {}: {}", method, err);
+        error!("Sqlite error: {}", msg);
+        anyhow::Error::msg(msg)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use indicatif::MultiProgress;
+
+    const COLUMNS: TableColumns = &[
+        ("pubkey", "TEXT NOT NULL PRIMARY KEY"),
+        ("amount", "INTEGER(8) NOT NULL"),
+    ];
+
+    fn executor(name: &str, tx_bulk: Option<u16>) -> SQLiteExecutor {
+        let db_path =
+            std::env::temp_dir().join(format!("db_connection_{}_{}.db", name, std::process::id()));
+        let (_sender, receiver) = tokio::sync::mpsc::channel(1);
+        let counter = Arc::new(ProgressCounter::new(&MultiProgress::new(), "db_execute"));
+        SQLiteExecutor::new(
+            db_path, None, None, tx_bulk, false, false, false, counter, receiver,
+        )
+        .unwrap()
+    }
+
+    fn row(pubkey: &str, amount: i64) -> Vec<OwnedSqlValue> {
+        vec![pubkey.into(), amount.into()]
+    }
+
+    async fn insert_then_insert_batch(mut executor: SQLiteExecutor) {
+        executor
+            .execute_special(&create_table_query("holders", COLUMNS), [])
+            .await
+            .unwrap();
+        let query = insert_query("holders", &["pubkey", "amount"]);
+        executor
+            .execute(&query, params_from_iter(row("a", 1).iter()))
+            .await
+            .unwrap();
+        executor
+            .execute_rows("holders", COLUMNS, &[row("b", 2), row("c", 3)])
+            .await
+            .unwrap();
+        executor.commit_db("test").unwrap();
+        assert!(executor.db.is_autocommit());
+        let count: i64 = executor
+            .db
+            .query_row("SELECT COUNT(*) FROM holders", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(count, 3);
+    }
+
+    #[tokio::test]
+    async fn insert_then_insert_batch_without_tx_bulk() {
+        insert_then_insert_batch(executor("no_tx_bulk", None)).await;
+    }
+
+    #[tokio::test]
+    async fn insert_then_insert_batch_with_tx_bulk() {
+        insert_then_insert_batch(executor("tx_bulk", Some(2))).await;
+    }
+
+    #[tokio::test]
+    async fn commit_without_transaction() {
+        let mut executor = executor("commit", None);
+        executor.commit_db("test").unwrap();
+        executor.commit_db("test").unwrap();
+    }
+}
@@ -1,23 +1,62 @@
+use crate::schema::{TableRow, TableRows};
 use rusqlite::ToSql;
+use serde::{Deserialize, Serialize};
+use snapshot_parser_types::decoded_accounts::{ProcessorCompletionRow, ScanCheckpointRow};
 use tokio::sync::oneshot;
 
+/// Table columns as pairs of column name and its SQL type definition.
+pub type TableColumns = &'static [(&'static str, &'static str)];
+
+/// Messages processed by the output writer task, see [`crate::db_connection::SQLiteExecutor`]
+/// and the Parquet, PostgreSQL and JSON Lines executors of the tokens CLI.
+/// The processors send the typed rows of the tables only, each backend decides how to store them.
 pub enum DbMessage {
-    Execute {
-        query: String,
-        params: Vec<Box<dyn ToSql + Send + Sync>>,
+    /// Creates the table if it does not exist yet.
+    CreateTable {
+        table: &'static str,
+        columns: TableColumns,
+        response: oneshot::Sender<anyhow::Result<usize>>,
+    },
+    /// Upserts the row into its table, the backend converts it to the values of the table columns.
+    Insert {
+        row: Box<TableRow>,
+        response: oneshot::Sender<anyhow::Result<usize>>,
+    },
+    /// Upserts the rows of a table at once, the same as an [`DbMessage::Insert`] of each of them;
+    /// the SQLite backend inserts them by multi-row INSERT statements. Responds with the number of changed rows.
+    InsertBatch {
+        rows: TableRows,
+        response: oneshot::Sender<anyhow::Result<usize>>,
+    },
+    /// Commits the rows inserted so far and upserts the checkpoint of the scan, unless the DB holds
+    /// a checkpoint of a newer slot; supported by the SQLite backend only.
+    CommitCheckpoint {
+        checkpoint: ScanCheckpointRow,
         response: oneshot::Sender<anyhow::Result<usize>>,
     },
-    ExecuteSpecial {
-        query: String,
-        params: Vec<Box<dyn ToSql + Send + Sync>>,
+    /// Commits the rows inserted so far and records the processor as completed,
+    /// supported by the SQLite backend only.
+    RecordCompletion {
+        completion: ProcessorCompletionRow,
         response: oneshot::Sender<anyhow::Result<usize>>,
     },
+    /// Sent once all rows are inserted, before the [`DbMessage::Shutdown`]. Creates the indexes
+    /// of [`crate::schema::TABLE_INDEXES`] on the written tables and updates the statistics of the query planner;
+    /// the SQLite backend also vacuums the DB. The file backends have nothing to finalize.
+    Finalize {
+        response: oneshot::Sender<anyhow::Result<()>>,
+    },
     Shutdown {
         response: oneshot::Sender<anyhow::Result<()>>,
     },
+    /// Stops the writer of an interrupted run. The SQLite backend commits the written rows and keeps them
+    /// as a partial output, the other backends discard the output.
+    Interrupt {
+        response: oneshot::Sender<anyhow::Result<()>>,
+    },
 }
 
-#[derive(Clone)]
+#[derive(Clone, Serialize, Deserialize)]
 pub enum OwnedSqlValue {
     Text(Option<String>),
     Integer(Option<i64>),
@@ -45,6 +84,20 @@ impl OwnedSqlValue {
     pub fn boxed<T: Into<OwnedSqlValue>>(value: T) -> Box<dyn ToSql + Send + Sync> {
         Box::new(value.into())
     }
+
+    /// Numeric value of the non-text values, booleans are 0 or 1.
+    pub fn as_i64(&self) -> anyhow::Result<Option<i64>> {
+        Ok(match self {
+            OwnedSqlValue::Integer(value) => *value,
+            OwnedSqlValue::UnsignedInteger(value) => value.map(i64::try_from).transpose()?,
+            OwnedSqlValue::UnsignedU16(value) => value.map(i64::from),
+            OwnedSqlValue::Boolean(value) => value.map(i64::from),
+            OwnedSqlValue::U8(value) => value.map(i64::from),
+            OwnedSqlValue::Text(_) => {
+                anyhow::bail!("Text value cannot be stored in a numeric column")
+            }
+        })
+    }
 }
 
 impl From<String> for OwnedSqlValue {
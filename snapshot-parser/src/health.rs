@@ -0,0 +1,118 @@
+use {
+    std::{
+        io::{BufRead, BufReader, Write},
+        net::{TcpListener, TcpStream},
+        sync::{
+            atomic::{AtomicU64, AtomicU8, Ordering},
+            Arc, Mutex,
+        },
+        thread::{spawn, JoinHandle},
+        time::{Duration, Instant},
+    },
+    tracing::{debug, error, info},
+};
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+#[repr(u8)]
+pub enum HealthPhase {
+    LoadingBank = 0,
+    Processing = 1,
+    Finished = 2,
+}
+
+impl HealthPhase {
+    fn from_u8(value: u8) -> Self {
+        match value {
+            0 => HealthPhase::LoadingBank,
+            1 => HealthPhase::Processing,
+            _ => HealthPhase::Finished,
+        }
+    }
+}
+
+/// Shared state reported by the `/healthz` (liveness) and `/readyz` (readiness) endpoints.
+/// The parser is considered hung when it is processing and the progress has not moved
+/// for longer than the stall timeout. Bank loading reports no progress, so it is always alive.
+pub struct HealthState {
+    phase: AtomicU8,
+    progress: AtomicU64,
+    last_progress_at: Mutex<Instant>,
+    stall_timeout: Duration,
+}
+
+impl HealthState {
+    pub fn new(stall_timeout: Duration) -> Self {
+        Self {
+            phase: AtomicU8::new(HealthPhase::LoadingBank as u8),
+            progress: AtomicU64::new(0),
+            last_progress_at: Mutex::new(Instant::now()),
+            stall_timeout,
+        }
+    }
+
+    pub fn phase(&self) -> HealthPhase {
+        HealthPhase::from_u8(self.phase.load(Ordering::Relaxed))
+    }
+
+    pub fn set_phase(&self, phase: HealthPhase) {
+        info!("Health phase: {:?}", phase);
+        self.phase.store(phase as u8, Ordering::Relaxed);
+        *self.last_progress_at.lock().unwrap() = Instant::now();
+    }
+
+    /// Reports the current value of a monotonic progress counter (e.g., number of processed rows).
+    pub fn report_progress(&self, progress: u64) {
+        if self.progress.swap(progress, Ordering::Relaxed) != progress {
+            *self.last_progress_at.lock().unwrap() = Instant::now();
+        }
+    }
+
+    pub fn is_alive(&self) -> bool {
+        self.phase() != HealthPhase::Processing
+            || self.last_progress_at.lock().unwrap().elapsed() <= self.stall_timeout
+    }
+
+    pub fn is_ready(&self) -> bool {
+        self.phase() != HealthPhase::LoadingBank
+    }
+}
+
+/// Serves `/healthz` and `/readyz` on the given port in a background thread.
+pub fn spawn_health_server(port: u16, state: Arc<HealthState>) -> anyhow::Result<JoinHandle<()>> {
+    let listener = TcpListener::bind(("0.0.0.0", port))?;
+    info!("Health endpoints listening on port {}", port);
+    Ok(spawn(move || {
+        for stream in listener.incoming() {
+            match stream {
+                Ok(stream) => {
+                    if let Err(err) = handle_health_request(stream, &state) {
+                        debug!("Failed to serve health request: {}", err);
+                    }
+                }
+                Err(err) => error!("Health endpoint connection failed: {}", err),
+            }
+        }
+    }))
+}
+
+fn handle_health_request(mut stream: TcpStream, state: &HealthState) -> std::io::Result<()> {
+    let mut request_line = String::new();
+    BufReader::new(&stream).read_line(&mut request_line)?;
+    let path = request_line.split_whitespace().nth(1).unwrap_or("");
+
+    let (status, body) = match path {
+        "/healthz" if state.is_alive() => ("200 OK", "alive"),
+        "/healthz" => ("503 Service Unavailable", "stalled"),
+        "/readyz" if state.is_ready() => ("200 OK", "ready"),
+        "/readyz" => ("503 Service Unavailable", "loading bank"),
+        _ => ("404 Not Found", "not found"),
+    };
+    write!(
+        stream,
+        "HTTP/1.1 {}\r\nContent-Type: text/plain\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        status,
+        body.len(),
+        body
+    )?;
+    stream.flush()
+}
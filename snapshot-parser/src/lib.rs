@@ -1,5 +1,29 @@
+pub mod accounts_db_stats;
 pub mod bank_loader;
+#[cfg(feature = "cli")]
 pub mod cli;
+#[cfg(feature = "sqlite")]
+pub mod db_connection;
+#[cfg(feature = "sqlite")]
+pub mod db_message;
+pub mod health;
+pub mod metrics;
+pub mod program_registry;
+#[cfg(feature = "cli")]
+pub mod progress_bar;
+pub mod runner;
+#[cfg(feature = "sqlite")]
+pub mod schema;
 pub mod serde_serialize;
+pub mod snapshot_archive;
+#[cfg(feature = "snapshot-fetch")]
+pub mod snapshot_fetch;
+#[cfg(feature = "sqlite")]
+pub mod sql_row;
 pub mod stake_meta;
+pub mod stats;
+pub mod storage_report;
+#[cfg(feature = "cli")]
+pub mod telemetry;
+pub mod temp_file;
 pub mod utils;
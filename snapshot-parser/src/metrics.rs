@@ -0,0 +1,219 @@
+use {
+    crate::stats::Stats,
+    std::{
+        fmt::Write as _,
+        io::{BufRead, BufReader, Write},
+        net::{SocketAddr, TcpListener, TcpStream},
+        sync::{Arc, Mutex},
+        thread::{spawn, JoinHandle},
+        time::{Duration, Instant},
+    },
+    tracing::{debug, error, info},
+};
+
+/// Name of the stats counter of the executed DB statements, reported as the inserts and not as the rows of a table.
+pub const DB_EXECUTE_COUNTER: &str = "db_execute";
+
+/// How often [`spawn_metrics_reporter`] samples the stats counters.
+pub const METRICS_REPORT_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Depth and capacity of the channel the processors send the rows through.
+pub type ChannelDepth = Box<dyn Fn() -> Option<(usize, usize)> + Send>;
+
+#[derive(Default)]
+struct InsertSample {
+    total: u64,
+    per_second: f64,
+    sampled_at: Option<Instant>,
+}
+
+/// Values exposed in the Prometheus text format by the `/metrics` endpoint.
+/// The counters are sampled periodically (see [`spawn_metrics_reporter`]), the runtime is computed on every scrape.
+pub struct MetricsState {
+    started_at: Instant,
+    bank_load: Mutex<Option<Duration>>,
+    rows: Mutex<Vec<(String, u64)>>,
+    inserts: Mutex<InsertSample>,
+    channel_depth: Mutex<Option<(usize, usize)>>,
+}
+
+impl MetricsState {
+    pub fn new() -> Self {
+        Self {
+            started_at: Instant::now(),
+            bank_load: Mutex::new(None),
+            rows: Mutex::new(Vec::new()),
+            inserts: Mutex::new(InsertSample::default()),
+            channel_depth: Mutex::new(None),
+        }
+    }
+
+    pub fn set_bank_load_duration(&self, duration: Duration) {
+        *self.bank_load.lock().unwrap() = Some(duration);
+    }
+
+    /// Reports the current values of the stats counters, the [`DB_EXECUTE_COUNTER`] one as the inserts
+    /// with the insert rate since the previous report.
+    pub fn report_counters(&self, counters: Vec<(String, u64)>) {
+        let mut rows = Vec::with_capacity(counters.len());
+        for (name, value) in counters {
+            if name == DB_EXECUTE_COUNTER {
+                self.report_inserts(value);
+            } else {
+                rows.push((name, value));
+            }
+        }
+        *self.rows.lock().unwrap() = rows;
+    }
+
+    fn report_inserts(&self, total: u64) {
+        let mut inserts = self.inserts.lock().unwrap();
+        let now = Instant::now();
+        if let Some(sampled_at) = inserts.sampled_at {
+            let elapsed = now.duration_since(sampled_at).as_secs_f64();
+            if elapsed > 0.0 {
+                inserts.per_second = total.saturating_sub(inserts.total) as f64 / elapsed;
+            }
+        }
+        inserts.total = total;
+        inserts.sampled_at = Some(now);
+    }
+
+    pub fn report_channel_depth(&self, depth: usize, capacity: usize) {
+        *self.channel_depth.lock().unwrap() = Some((depth, capacity));
+    }
+
+    /// Metrics in the Prometheus text exposition format.
+    pub fn render(&self) -> String {
+        let mut out = String::new();
+        let mut metric = |name: &str, kind: &str, help: &str, samples: Vec<(String, String)>| {
+            let _ = writeln!(out, "# HELP {} {}", name, help);
+            let _ = writeln!(out, "# TYPE {} {}", name, kind);
+            for (labels, value) in samples {
+                let _ = writeln!(out, "{}{} {}", name, labels, value);
+            }
+        };
+        metric(
+            "snapshot_parser_runtime_seconds",
+            "gauge",
+            "Seconds since the parser started",
+            vec![(
+                String::new(),
+                self.started_at.elapsed().as_secs_f64().to_string(),
+            )],
+        );
+        if let Some(bank_load) = *self.bank_load.lock().unwrap() {
+            metric(
+                "snapshot_parser_bank_load_seconds",
+                "gauge",
+                "Seconds the bank took to load from the snapshot",
+                vec![(String::new(), bank_load.as_secs_f64().to_string())],
+            );
+        }
+        metric(
+            "snapshot_parser_rows_total",
+            "counter",
+            "Accounts processed into the table",
+            self.rows
+                .lock()
+                .unwrap()
+                .iter()
+                .map(|(table, value)| (format!("{{table=\"{}\"}}", table), value.to_string()))
+                .collect(),
+        );
+        let inserts = self.inserts.lock().unwrap();
+        metric(
+            "snapshot_parser_db_inserts_total",
+            "counter",
+            "Statements executed by the output writer",
+            vec![(String::new(), inserts.total.to_string())],
+        );
+        metric(
+            "snapshot_parser_db_inserts_per_second",
+            "gauge",
+            "Statements executed by the output writer per second since the previous sample",
+            vec![(String::new(), inserts.per_second.to_string())],
+        );
+        if let Some((depth, capacity)) = *self.channel_depth.lock().unwrap() {
+            metric(
+                "snapshot_parser_channel_depth",
+                "gauge",
+                "Rows queued in the channel to the output writer",
+                vec![(String::new(), depth.to_string())],
+            );
+            metric(
+                "snapshot_parser_channel_capacity",
+                "gauge",
+                "Capacity of the channel to the output writer",
+                vec![(String::new(), capacity.to_string())],
+            );
+        }
+        out
+    }
+}
+
+impl Default for MetricsState {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Serves `/metrics` on the given address in a background thread.
+pub fn spawn_metrics_server(
+    addr: SocketAddr,
+    state: Arc<MetricsState>,
+) -> anyhow::Result<JoinHandle<()>> {
+    let listener = TcpListener::bind(addr)?;
+    info!("Metrics endpoint listening on {}", addr);
+    Ok(spawn(move || {
+        for stream in listener.incoming() {
+            match stream {
+                Ok(stream) => {
+                    if let Err(err) = handle_metrics_request(stream, &state) {
+                        debug!("Failed to serve metrics request: {}", err);
+                    }
+                }
+                Err(err) => error!("Metrics endpoint connection failed: {}", err),
+            }
+        }
+    }))
+}
+
+/// Samples the stats counters and the channel depth into the metrics every [`METRICS_REPORT_INTERVAL`]
+/// until the task is aborted.
+pub fn spawn_metrics_reporter(
+    state: Arc<MetricsState>,
+    stats: Arc<Stats>,
+    channel_depth: Option<ChannelDepth>,
+) -> tokio::task::JoinHandle<()> {
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(METRICS_REPORT_INTERVAL);
+        loop {
+            interval.tick().await;
+            state.report_counters(stats.counts().await);
+            if let Some((depth, capacity)) = channel_depth.as_ref().and_then(|depth| depth()) {
+                state.report_channel_depth(depth, capacity);
+            }
+        }
+    })
+}
+
+fn handle_metrics_request(mut stream: TcpStream, state: &MetricsState) -> std::io::Result<()> {
+    let mut request_line = String::new();
+    BufReader::new(&stream).read_line(&mut request_line)?;
+    let path = request_line.split_whitespace().nth(1).unwrap_or("");
+
+    let (status, content_type, body) = match path {
+        "/metrics" => ("200 OK", "text/plain; version=0.0.4", state.render()),
+        _ => ("404 Not Found", "text/plain", "not found".to_string()),
+    };
+    write!(
+        stream,
+        "HTTP/1.1 {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        status,
+        content_type,
+        body.len(),
+        body
+    )?;
+    stream.flush()
+}
@@ -0,0 +1,266 @@
+use crate::serde_serialize::pubkey_string_conversion;
+use crate::utils::read_from_json_file;
+use serde::{Deserialize, Serialize};
+use solana_program::pubkey;
+use solana_program::pubkey::Pubkey;
+use std::collections::HashSet;
+use std::path::Path;
+use tracing::info;
+
+pub const SYSTEM_PROGRAM: &str = "system";
+pub const STAKE_PROGRAM: &str = "stake";
+pub const VOTE_PROGRAM: &str = "vote";
+pub const SPL_TOKEN_PROGRAM: &str = "spl_token";
+pub const SPL_TOKEN_2022_PROGRAM: &str = "spl_token_2022";
+pub const TOKEN_METADATA_PROGRAM: &str = "token_metadata";
+pub const MARINADE_LIQUID_STAKING_PROGRAM: &str = "marinade_liquid_staking";
+pub const MARINADE_LIQUID_STAKE_AUTHORITY: &str = "marinade_liquid_stake_authority";
+pub const MARINADE_NATIVE_STAKE_AUTHORITY: &str = "marinade_native_stake_authority";
+pub const MARINADE_VSR_PROGRAM: &str = "marinade_vsr";
+pub const SPL_STAKE_POOL_PROGRAM: &str = "spl_stake_pool";
+pub const JITO_TIP_DISTRIBUTION_PROGRAM: &str = "jito_tip_distribution";
+pub const JITO_PRIORITY_FEE_DISTRIBUTION_PROGRAM: &str = "jito_priority_fee_distribution";
+pub const JITO_STAKE_AUTHORITY: &str = "jito_stake_authority";
+
+/// Suffix of the stake authority names stripped to get the label the validator stake is split by.
+const STAKE_AUTHORITY_NAME_SUFFIX: &str = "_stake_authority";
+
+/// What kind of accounts an address owns, used to classify the owners of the parsed accounts.
+#[derive(Debug, Clone, Copy, Deserialize, Serialize, Eq, PartialEq, Hash)]
+#[serde(rename_all = "snake_case")]
+pub enum ProgramCategory {
+    Native,
+    Token,
+    TokenMetadata,
+    Staking,
+    /// authority owning stake accounts of a staking product, not a program
+    StakeAuthority,
+    Governance,
+    Mev,
+    Other,
+}
+
+impl ProgramCategory {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ProgramCategory::Native => "native",
+            ProgramCategory::Token => "token",
+            ProgramCategory::TokenMetadata => "token_metadata",
+            ProgramCategory::Staking => "staking",
+            ProgramCategory::StakeAuthority => "stake_authority",
+            ProgramCategory::Governance => "governance",
+            ProgramCategory::Mev => "mev",
+            ProgramCategory::Other => "other",
+        }
+    }
+}
+
+const BUILTIN_PROGRAMS: [(&str, Pubkey, ProgramCategory); 14] = [
+    (
+        SYSTEM_PROGRAM,
+        pubkey!("11111111111111111111111111111111"),
+        ProgramCategory::Native,
+    ),
+    (
+        STAKE_PROGRAM,
+        pubkey!("Stake11111111111111111111111111111111111111"),
+        ProgramCategory::Native,
+    ),
+    (
+        VOTE_PROGRAM,
+        pubkey!("Vote111111111111111111111111111111111111111"),
+        ProgramCategory::Native,
+    ),
+    (
+        SPL_TOKEN_PROGRAM,
+        pubkey!("TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA"),
+        ProgramCategory::Token,
+    ),
+    (
+        SPL_TOKEN_2022_PROGRAM,
+        pubkey!("TokenzQdBNbLqP5VEhdkAS6EPFLC1PHnBqCXEpPxuEb"),
+        ProgramCategory::Token,
+    ),
+    (
+        TOKEN_METADATA_PROGRAM,
+        pubkey!("metaqbxxUerdq28cj1RbAWkYQm3ybzjb6a8bt518x1s"),
+        ProgramCategory::TokenMetadata,
+    ),
+    (
+        MARINADE_LIQUID_STAKING_PROGRAM,
+        pubkey!("MarBmsSgKXdrN1egZf5sqe1TMai9K1rChYNDJgjq7aD"),
+        ProgramCategory::Staking,
+    ),
+    (
+        MARINADE_LIQUID_STAKE_AUTHORITY,
+        pubkey!("4bZ6o3eUUNXhKuqjdCnCoPAoLgWiuLYixKaxoa8PpiKk"),
+        ProgramCategory::StakeAuthority,
+    ),
+    (
+        MARINADE_NATIVE_STAKE_AUTHORITY,
+        pubkey!("stWirqFCf2Uts1JBL1Jsd3r6VBWhgnpdPxCTe1MFjrq"),
+        ProgramCategory::StakeAuthority,
+    ),
+    (
+        MARINADE_VSR_PROGRAM,
+        pubkey!("VoteMBhDCqGLRgYpp9o7DGyq81KNmwjXQRAHStjtJsS"),
+        ProgramCategory::Governance,
+    ),
+    (
+        SPL_STAKE_POOL_PROGRAM,
+        pubkey!("SPoo1Ku8WFXoNDMHPsrGSTSG1Y47rzgn41SLUNakuHy"),
+        ProgramCategory::Staking,
+    ),
+    // https://github.com/jito-foundation/jito-programs/blob/v0.1.5/mev-programs/programs/tip-distribution/src/lib.rs#L385
+    (
+        JITO_TIP_DISTRIBUTION_PROGRAM,
+        pubkey!("4R3gSG8BpU4t19KYj8CfnbtRpnT8gtk4dvTHxVRwc2r7"),
+        ProgramCategory::Mev,
+    ),
+    (
+        JITO_PRIORITY_FEE_DISTRIBUTION_PROGRAM,
+        pubkey!("Priority6weCZ5HwDn29NxLFpb7TDp2iLZ6XKc5e8d3"),
+        ProgramCategory::Mev,
+    ),
+    (
+        JITO_STAKE_AUTHORITY,
+        pubkey!("6iQKfEyhr3bZMotVkW6beNZz5CPAkiwvgV2CTje9pVSS"),
+        ProgramCategory::StakeAuthority,
+    ),
+];
+
+/// Address of a built-in program, see [`BUILTIN_PROGRAMS`].
+pub fn builtin_address(name: &str) -> Pubkey {
+    BUILTIN_PROGRAMS
+        .iter()
+        .find(|(builtin_name, _, _)| *builtin_name == name)
+        .map(|(_, address, _)| *address)
+        .unwrap_or_else(|| panic!("{} is not a built-in program", name))
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize, Eq, PartialEq)]
+pub struct KnownProgram {
+    #[serde(with = "pubkey_string_conversion")]
+    pub address: Pubkey,
+    /// unique human readable name, e.g. `marinade_vsr`
+    pub name: String,
+    pub category: ProgramCategory,
+}
+
+/// Programs and authorities known to the parsers by their name and category, shared by the processors,
+/// the `program_label` table and the classification of account owners.
+///
+/// The built-in programs can be extended by a JSON config file, e.g. one per environment.
+/// An entry with the name of a built-in program replaces it,
+/// so staging deployments of the programs are parsed by the same binary:
+/// ```json
+/// { "programs": [{ "address": "<pubkey>", "name": "marinade_vsr", "category": "governance" }] }
+/// ```
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct ProgramRegistry {
+    pub programs: Vec<KnownProgram>,
+}
+
+impl ProgramRegistry {
+    pub fn builtin() -> Self {
+        Self {
+            programs: BUILTIN_PROGRAMS
+                .iter()
+                .map(|(name, address, category)| KnownProgram {
+                    address: *address,
+                    name: name.to_string(),
+                    category: *category,
+                })
+                .collect(),
+        }
+    }
+
+    /// Built-in programs extended and overridden by the programs of the config file.
+    pub fn load(config_path: Option<&Path>) -> anyhow::Result<Self> {
+        let mut registry = Self::builtin();
+        if let Some(config_path) = config_path {
+            let extension: Self = read_from_json_file(&config_path).map_err(|e| {
+                anyhow::anyhow!("Could not load program registry {:?}: {}", config_path, e)
+            })?;
+            registry.extend(extension.programs)?;
+        }
+        Ok(registry)
+    }
+
+    fn extend(&mut self, programs: Vec<KnownProgram>) -> anyhow::Result<()> {
+        let mut config_names = HashSet::new();
+        for program in programs {
+            if !config_names.insert(program.name.clone()) {
+                anyhow::bail!("Program name {} is configured more than once", program.name);
+            }
+            if let Some(colliding) = self
+                .programs
+                .iter()
+                .find(|p| p.address == program.address && p.name != program.name)
+            {
+                anyhow::bail!(
+                    "Program address {} of {} is already registered as {}",
+                    program.address,
+                    program.name,
+                    colliding.name
+                );
+            }
+            match self.programs.iter_mut().find(|p| p.name == program.name) {
+                Some(registered) => {
+                    info!(
+                        "Program {} overridden: {} -> {}",
+                        program.name, registered.address, program.address
+                    );
+                    *registered = program;
+                }
+                None => self.programs.push(program),
+            }
+        }
+        Ok(())
+    }
+
+    pub fn get(&self, address: &Pubkey) -> Option<&KnownProgram> {
+        self.programs
+            .iter()
+            .find(|program| program.address == *address)
+    }
+
+    pub fn address(&self, name: &str) -> anyhow::Result<Pubkey> {
+        self.programs
+            .iter()
+            .find(|program| program.name == name)
+            .map(|program| program.address)
+            .ok_or_else(|| anyhow::anyhow!("Program {} is not registered", name))
+    }
+
+    /// Category of the account owner, [`ProgramCategory::Other`] for unknown owners.
+    pub fn owner_category(&self, owner: &Pubkey) -> ProgramCategory {
+        self.get(owner)
+            .map_or(ProgramCategory::Other, |program| program.category)
+    }
+
+    /// Addresses of the programs of the categories.
+    pub fn addresses_of(&self, categories: &[ProgramCategory]) -> Vec<Pubkey> {
+        self.programs
+            .iter()
+            .filter(|program| categories.contains(&program.category))
+            .map(|program| program.address)
+            .collect()
+    }
+
+    /// Labels of the stake authorities to split the validator stake by,
+    /// the names of [`ProgramCategory::StakeAuthority`] programs without the `_stake_authority` suffix.
+    pub fn stake_authority_labels(&self) -> Vec<(String, Pubkey)> {
+        self.programs
+            .iter()
+            .filter(|program| program.category == ProgramCategory::StakeAuthority)
+            .map(|program| {
+                let label = program
+                    .name
+                    .strip_suffix(STAKE_AUTHORITY_NAME_SUFFIX)
+                    .unwrap_or(&program.name);
+                (label.to_string(), program.address)
+            })
+            .collect()
+    }
+}
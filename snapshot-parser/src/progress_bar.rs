@@ -0,0 +1,171 @@
+use crate::bank_loader::{create_bank_from_ledger_with_progress, BankLoaderConfig};
+use crate::stats::{ProcessorCallback, Stats};
+use async_trait::async_trait;
+use indicatif::{MultiProgress, ProgressBar, ProgressStyle};
+use solana_runtime::bank::Bank;
+use std::path::Path;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use tracing::info_span;
+
+pub fn create_spinner_progress_bar(name: String) -> ProgressBar {
+    let spinner_style = ProgressStyle::with_template(
+        "{prefix:>20.bold.dim} {spinner} rate={per_sec:>13} total={human_pos:>11}",
+    )
+    .unwrap();
+    ProgressBar::new_spinner()
+        .with_style(spinner_style)
+        .with_prefix(name)
+}
+
+pub fn create_finalization_progress_bar(total_number_of_tables: u64) -> ProgressBar {
+    let progress_bar_style =
+        ProgressStyle::with_template("{prefix:>20.bold.dim} [{bar:30}] {pos:>1}/{len:>1}")
+            .unwrap()
+            .progress_chars("#>-");
+    ProgressBar::new(total_number_of_tables)
+        .with_style(progress_bar_style)
+        .with_prefix("finalization")
+}
+
+/// Creates a progress counter shown in the multi progress and reported in the final stats.
+pub async fn define_counter(
+    name: &str,
+    multi_progress: &MultiProgress,
+    stats: &Stats,
+) -> Arc<ProgressCounter> {
+    let progress_counter = Arc::new(ProgressCounter::new(multi_progress, name));
+    stats.add_callback(progress_counter.clone()).await;
+    progress_counter
+}
+
+/// Creates the bank from the ledger, showing the unpacked account storages and the accounts index (in MB)
+/// in the multi progress while it loads. The durations of the loading phases are added to the stats.
+/// The loading runs in a `bank_load` span.
+pub fn create_bank_with_progress(
+    ledger_path: &Path,
+    config: &BankLoaderConfig,
+    multi_progress: &MultiProgress,
+    stats: &Stats,
+) -> anyhow::Result<Arc<Bank>> {
+    let _span = info_span!("bank_load", ledger_path = %ledger_path.display()).entered();
+    let unpacked_counter = ProgressCounter::new(multi_progress, "bank_unpacked_mb");
+    let index_counter = ProgressCounter::new(multi_progress, "bank_index_mb");
+    let (bank, phases) =
+        create_bank_from_ledger_with_progress(ledger_path, config, move |sample| {
+            unpacked_counter.set(sample.unpacked_bytes / 1024 / 1024);
+            index_counter.set(sample.index_bytes / 1024 / 1024);
+        })?;
+    for (phase, duration) in phases {
+        stats.add_phase(&format!("bank loading {:?}", phase), duration);
+    }
+    Ok(bank)
+}
+
+/// Increments after which the progress bar position is updated, and a [`LocalCounter`] is flushed.
+pub const PROGRESS_UPDATE_INTERVAL: u64 = 1024;
+
+/// Counter shared by the tasks, shown in the multi progress.
+/// The progress bar position is updated without locking, rate-limited by the progress bar itself.
+pub struct ProgressCounter {
+    name: String,
+    progress_bar: ProgressBar,
+    counter: AtomicU64,
+}
+
+impl ProgressCounter {
+    pub fn new(multi_progress: &MultiProgress, name: &str) -> ProgressCounter {
+        let name_string = name.to_string();
+        let progress_bar = create_spinner_progress_bar(name_string.clone());
+        let multi_progress_bar = multi_progress.add(progress_bar);
+        Self {
+            name: name_string,
+            progress_bar: multi_progress_bar,
+            counter: AtomicU64::new(0),
+        }
+    }
+
+    pub fn get(&self) -> u64 {
+        self.counter.load(Ordering::Relaxed)
+    }
+
+    pub fn inc(&self) {
+        self.add(1);
+    }
+
+    /// Adds a bulk of increments at once, e.g., the rows of a batch insert.
+    pub fn add(&self, value: u64) {
+        if value == 0 {
+            return;
+        }
+        let previous = self.counter.fetch_add(value, Ordering::Relaxed);
+        let count = previous + value;
+        if previous / PROGRESS_UPDATE_INTERVAL != count / PROGRESS_UPDATE_INTERVAL {
+            self.progress_bar.set_position(count)
+        }
+    }
+
+    /// Local counter of a single task, adding its increments to this counter in bulks.
+    pub fn local(self: &Arc<Self>) -> LocalCounter {
+        LocalCounter {
+            counter: self.clone(),
+            pending: 0,
+        }
+    }
+
+    /// Sets the value of a counter sampled from elsewhere, e.g., a size on disk.
+    pub fn set(&self, value: u64) {
+        self.counter.store(value, Ordering::Relaxed);
+        self.progress_bar.set_position(value)
+    }
+}
+
+impl Into<u64> for ProgressCounter {
+    fn into(self) -> u64 {
+        self.get()
+    }
+}
+
+#[async_trait]
+impl ProcessorCallback for ProgressCounter {
+    async fn get_count(&self) -> (String, u64) {
+        (self.name.clone(), self.get())
+    }
+}
+
+impl Drop for ProgressCounter {
+    fn drop(&mut self) {
+        self.progress_bar.set_position(self.get());
+        self.progress_bar.finish();
+    }
+}
+
+/// Increments of a single task, added to the shared [`ProgressCounter`] every [`PROGRESS_UPDATE_INTERVAL`]
+/// increments, on [`LocalCounter::flush`] and when dropped, so the hot path does not touch the shared counter.
+pub struct LocalCounter {
+    counter: Arc<ProgressCounter>,
+    pending: u64,
+}
+
+impl LocalCounter {
+    pub fn inc(&mut self) {
+        self.add(1);
+    }
+
+    pub fn add(&mut self, value: u64) {
+        self.pending += value;
+        if self.pending >= PROGRESS_UPDATE_INTERVAL {
+            self.flush();
+        }
+    }
+
+    pub fn flush(&mut self) {
+        self.counter.add(std::mem::take(&mut self.pending));
+    }
+}
+
+impl Drop for LocalCounter {
+    fn drop(&mut self) {
+        self.flush();
+    }
+}
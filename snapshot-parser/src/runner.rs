@@ -0,0 +1,119 @@
+use {
+    std::sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    tokio::{sync::watch, task::JoinSet},
+    tracing::{error, info, info_span},
+};
+
+/// Exit code of a process interrupted by SIGINT or SIGTERM.
+pub const INTERRUPTED_EXIT_CODE: i32 = 130;
+
+pub type BlockingTask = Box<dyn FnOnce() -> anyhow::Result<()> + Send + 'static>;
+
+/// Runs the CPU heavy tasks on the tokio blocking pool and waits for all of them.
+/// Fails with the first task error or panic. Every task runs in a `task` span of its name.
+pub async fn run_blocking_tasks(tasks: Vec<(&'static str, BlockingTask)>) -> anyhow::Result<()> {
+    let mut join_set = JoinSet::new();
+    for (name, task) in tasks {
+        let span = info_span!("task", name);
+        join_set.spawn_blocking(move || {
+            let _entered = span.enter();
+            info!("Task {} started", name);
+            let result = task();
+            (name, result)
+        });
+    }
+
+    while let Some(joined) = join_set.join_next().await {
+        match joined {
+            Ok((name, Ok(()))) => info!("Task {} completed successfully.", name),
+            Ok((name, Err(err))) => anyhow::bail!("Error in task {}: {err:?}", name),
+            Err(err) => anyhow::bail!("Task panicked: {err:?}"),
+        }
+    }
+    Ok(())
+}
+
+/// Resolves on SIGINT (Ctrl-C) or SIGTERM (e.g., sent by the cluster scheduler).
+pub async fn interrupt_signal() -> std::io::Result<()> {
+    #[cfg(unix)]
+    {
+        let mut terminate =
+            tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate())?;
+        tokio::select! {
+            result = tokio::signal::ctrl_c() => result,
+            _ = terminate.recv() => Ok(()),
+        }
+    }
+    #[cfg(not(unix))]
+    tokio::signal::ctrl_c().await
+}
+
+/// Exits the process on SIGINT or SIGTERM. The blocking tasks cannot be cancelled
+/// and waiting for them could take tens of minutes, so the process exits right away.
+/// The outputs are written through temporary files, so an interrupted run leaves no truncated output.
+pub fn spawn_interrupt_handler() {
+    tokio::spawn(async {
+        match interrupt_signal().await {
+            Ok(()) => {
+                error!("Interrupted, exiting...");
+                std::process::exit(INTERRUPTED_EXIT_CODE);
+            }
+            Err(err) => error!("Failed to listen for the interrupt signal: {}", err),
+        }
+    });
+}
+
+/// Cancellation of a run by SIGINT or SIGTERM, see [`spawn_cancellation_handler`].
+pub struct Cancellation {
+    armed: AtomicBool,
+    cancelled: watch::Sender<bool>,
+}
+
+impl Cancellation {
+    /// From now on, the signal cancels the run instead of exiting the process,
+    /// e.g., once there is a partial output worth keeping.
+    pub fn arm(&self) {
+        self.armed.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        *self.cancelled.borrow()
+    }
+
+    /// Resolves once the run is cancelled.
+    pub async fn cancelled(&self) {
+        let mut cancelled = self.cancelled.subscribe();
+        let _ = cancelled.wait_for(|cancelled| *cancelled).await;
+    }
+}
+
+/// Handles SIGINT and SIGTERM as [`spawn_interrupt_handler`] until the returned cancellation is armed.
+/// Afterwards the first signal only cancels the run, so it can stop gracefully and keep its partial output;
+/// the next signal exits the process right away.
+pub fn spawn_cancellation_handler() -> Arc<Cancellation> {
+    let (cancelled, _) = watch::channel(false);
+    let cancellation = Arc::new(Cancellation {
+        armed: AtomicBool::new(false),
+        cancelled,
+    });
+    let handler = cancellation.clone();
+    tokio::spawn(async move {
+        loop {
+            if let Err(err) = interrupt_signal().await {
+                error!("Failed to listen for the interrupt signal: {}", err);
+                return;
+            }
+            if handler.armed.load(Ordering::Relaxed) && !handler.is_cancelled() {
+                error!("Interrupted, stopping gracefully; interrupt again to exit right away...");
+                handler.cancelled.send_replace(true);
+            } else {
+                error!("Interrupted, exiting...");
+                std::process::exit(INTERRUPTED_EXIT_CODE);
+            }
+        }
+    });
+    cancellation
+}
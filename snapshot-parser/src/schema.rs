@@ -0,0 +1,570 @@
+// All tables written by the tokens CLI and the validator CLI into their output SQLite DBs.
+// Each table is defined once with its named columns, SQL types and the bound row values;
+// both CREATE TABLE and INSERT statements are generated from these definitions.
+// Every table is keyed by the account pubkey and rows are upserted, so the result does not depend
+// on the order of the processors (e.g., `account` is written by several of them) and a re-run is idempotent.
+// Every table carries its own version, recorded into the `schema_version` table when the table is created,
+// and the SQLite DB carries the SCHEMA_VERSION as its `user_version` pragma.
+use crate::db_message::DbMessage;
+use crate::sql_row::{create_table_with_columns, insert_single_row, SqlRow};
+use crate::{sql_row, table_rows};
+use snapshot_parser_types::decoded_accounts::{
+    AccountRow, ClosedOwnerRow, EligibleOwnerRow, MintAuthorityChangeRow, MintRow, MissingMintRow,
+    MsolStakeRow, MsolStateRow, MsolValidatorRow, NativeStakeRow, ProcessorCompletionRow,
+    ProgramLabelRow, ProtocolBalanceRow, RunConfigRow, ScanCheckpointRow, SchemaVersionRow,
+    SnapshotInfoRow, StakeAccountRow, StakeMetaCollectionRow, StakeMetaRow, StakePoolRow,
+    StakePoolValidatorRow, SupplyStatsRow, SymbolCollisionRow, TokenMetadataRow, TokenRow,
+    UnknownMintRow, ValidatorMetaCollectionRow, ValidatorMetaRow, VeMndeDepositRow, VeMndeRow,
+    VoteAccountEpochCreditsRow, VoteAccountRow,
+};
+use tokio::sync::mpsc::Sender;
+use tokio::sync::oneshot;
+
+/// Version of the output DB layout, to be bumped on any change of the tables below
+/// together with the version of the changed table.
+pub const SCHEMA_VERSION: u32 = 31;
+
+pub const META_ACCOUNT_TABLE: &str = "account";
+pub const TOKEN_ACCOUNT_TABLE: &str = "token_account";
+pub const TOKEN_MINT_TABLE: &str = "token_mint";
+pub const MINT_AUTHORITY_CHANGE_TABLE: &str = "token_mint_authority_change";
+pub const UNKNOWN_MINT_TABLE: &str = "unknown_mint";
+pub const MISSING_MINT_TABLE: &str = "missing_mints";
+pub const TOKEN_METADATA_ACCOUNT_TABLE: &str = "token_metadata";
+pub const VE_MNDE_ACCOUNT_TABLE: &str = "vemnde_accounts";
+pub const VE_MNDE_DEPOSIT_TABLE: &str = "vemnde_deposits";
+pub const NATIVE_STAKE_ACCOUNT_TABLE: &str = "native_stake_accounts";
+pub const STAKE_ACCOUNT_TABLE: &str = "stake_accounts";
+pub const STAKE_POOL_TABLE: &str = "stake_pools";
+pub const STAKE_POOL_VALIDATOR_TABLE: &str = "stake_pool_validators";
+pub const MSOL_STATE_TABLE: &str = "msol_state";
+pub const MSOL_VALIDATOR_TABLE: &str = "msol_validators";
+pub const MSOL_STAKE_TABLE: &str = "msol_stakes";
+pub const VOTE_ACCOUNT_TABLE: &str = "vote_accounts";
+pub const VOTE_ACCOUNT_EPOCH_CREDITS_TABLE: &str = "vote_account_epoch_credits";
+pub const PROTOCOL_BALANCES_TABLE: &str = "protocol_balances";
+pub const SUPPLY_STATS_TABLE: &str = "supply_stats";
+pub const SYMBOL_COLLISION_TABLE: &str = "symbol_collision";
+pub const SCAN_CHECKPOINT_TABLE: &str = "scan_checkpoint";
+pub const PROCESSOR_COMPLETION_TABLE: &str = "processor_completion";
+pub const SNAPSHOT_INFO_TABLE: &str = "snapshot_info";
+pub const PROGRAM_LABEL_TABLE: &str = "program_label";
+pub const RUN_CONFIG_TABLE: &str = "run_config";
+pub const ELIGIBLE_OWNERS_TABLE: &str = "eligible_owners";
+pub const CLOSED_OWNERS_TABLE: &str = "closed_owners";
+pub const SCHEMA_VERSION_TABLE: &str = "schema_version";
+// tables of the validator CLI SQLite output
+pub const VALIDATOR_META_COLLECTION_TABLE: &str = "validator_meta_collection";
+pub const VALIDATOR_META_TABLE: &str = "validator_metas";
+pub const STAKE_META_TABLE: &str = "stake_metas";
+pub const STAKE_META_COLLECTION_TABLE: &str = "stake_meta_collection";
+
+sql_row!(AccountRow, META_ACCOUNT_TABLE, version = 1, |row| {
+    pubkey: "TEXT NOT NULL PRIMARY KEY" => row.pubkey.clone(),
+    data_len: "INTEGER(8) NOT NULL" => row.data_len as i64,
+    owner: "TEXT NOT NULL" => row.owner.clone(),
+    lamports: "INTEGER(8) NOT NULL" => row.lamports as i64,
+    executable: "INTEGER(1) NOT NULL" => row.executable,
+    rent_epoch: "INTEGER(8) NOT NULL" => row.rent_epoch as i64,
+});
+
+sql_row!(TokenRow, TOKEN_ACCOUNT_TABLE, version = 2, |row| {
+    pubkey: "TEXT NOT NULL PRIMARY KEY" => row.pubkey.clone(),
+    token_program: "TEXT NOT NULL" => row.token_program.clone(),
+    mint: "TEXT NOT NULL" => row.mint.clone(),
+    owner: "TEXT NOT NULL" => row.owner.clone(),
+    amount: "INTEGER(8) NOT NULL" => row.amount as i64,
+    delegate: "TEXT" => row.delegate.clone(),
+    state: "INTEGER(1) NOT NULL" => row.state,
+    is_native: "INTEGER(8)" => row.is_native,
+    delegated_amount: "INTEGER(8) NOT NULL" => row.delegated_amount as i64,
+    close_authority: "TEXT" => row.close_authority.clone(),
+    transfer_fee_withheld_amount: "INTEGER(8) NULL" => row.transfer_fee_withheld_amount.map(|amount| amount as i64),
+});
+
+sql_row!(MintRow, TOKEN_MINT_TABLE, version = 2, |row| {
+    pubkey: "TEXT NOT NULL PRIMARY KEY" => row.pubkey.clone(),
+    token_program: "TEXT NOT NULL" => row.token_program.clone(),
+    mint_authority: "TEXT NULL" => row.mint_authority.clone(),
+    supply: "INTEGER(8) NOT NULL" => row.supply as i64,
+    decimals: "INTEGER(2) NOT NULL" => row.decimals,
+    is_initialized: "BOOL NOT NULL" => row.is_initialized,
+    freeze_authority: "TEXT NULL" => row.freeze_authority.clone(),
+    transfer_fee_basis_points: "INTEGER(2) NULL" => row.transfer_fee_basis_points,
+    transfer_fee_maximum: "INTEGER(8) NULL" => row.transfer_fee_maximum.map(|maximum| maximum as i64),
+    interest_rate_bps: "INTEGER(2) NULL" => row.interest_rate_bps.map(i64::from),
+    permanent_delegate: "TEXT NULL" => row.permanent_delegate.clone(),
+});
+
+sql_row!(MintAuthorityChangeRow, MINT_AUTHORITY_CHANGE_TABLE, version = 1, |row| {
+    id: "TEXT NOT NULL PRIMARY KEY" => row.id.clone(),
+    mint: "TEXT NOT NULL" => row.mint.clone(),
+    slot: "INTEGER(8) NOT NULL" => row.slot as i64,
+    previous_mint_authority: "TEXT NULL" => row.previous_mint_authority.clone(),
+    mint_authority: "TEXT NULL" => row.mint_authority.clone(),
+    previous_freeze_authority: "TEXT NULL" => row.previous_freeze_authority.clone(),
+    freeze_authority: "TEXT NULL" => row.freeze_authority.clone(),
+});
+
+sql_row!(UnknownMintRow, UNKNOWN_MINT_TABLE, version = 1, |row| {
+    mint: "TEXT NOT NULL PRIMARY KEY" => row.mint.clone(),
+    decimals: "INTEGER(2) NULL" => row.decimals,
+    reason: "TEXT NOT NULL" => row.reason.clone(),
+    token_accounts: "INTEGER(8) NOT NULL" => row.token_accounts as i64,
+});
+
+sql_row!(MissingMintRow, MISSING_MINT_TABLE, version = 1, |row| {
+    mint: "TEXT NOT NULL PRIMARY KEY" => row.mint.clone(),
+    slot: "INTEGER(8) NOT NULL" => row.slot as i64,
+});
+
+sql_row!(TokenMetadataRow, TOKEN_METADATA_ACCOUNT_TABLE, version = 2, |row| {
+    pubkey: "TEXT NOT NULL PRIMARY KEY" => row.pubkey.clone(),
+    mint: "TEXT NOT NULL" => row.mint.clone(),
+    update_authority: "TEXT NOT NULL" => row.update_authority.clone(),
+    name: "TEXT NOT NULL" => row.name.clone(),
+    symbol: "TEXT(10) NOT NULL" => row.symbol.clone(),
+    uri: "TEXT(200) NOT NULL" => row.uri.clone(),
+    data_length: "INTEGER(8) NOT NULL" => row.data_length,
+    seller_fee_basis_points: "INTEGER(4) NOT NULL" => row.seller_fee_basis_points,
+    primary_sale_happened: "INTEGER(1) NOT NULL" => row.primary_sale_happened,
+    is_mutable: "INTEGER(1) NOT NULL" => row.is_mutable,
+    edition_nonce: "INTEGER(2) NULL" => row.edition_nonce,
+    collection_verified: "INTEGER(1) NULL" => row.collection_verified,
+    collection_key: "TEXT NULL" => row.collection_key.clone(),
+    name_raw: "TEXT NULL" => row.name_raw.clone(),
+    symbol_raw: "TEXT NULL" => row.symbol_raw.clone(),
+    uri_raw: "TEXT NULL" => row.uri_raw.clone(),
+    confusable_mint: "TEXT NULL" => row.confusable_mint.clone(),
+    mixed_script: "INTEGER(1) NULL" => row.mixed_script,
+});
+
+sql_row!(SymbolCollisionRow, SYMBOL_COLLISION_TABLE, version = 1, |row| {
+    pubkey: "TEXT NOT NULL PRIMARY KEY" => row.pubkey.clone(),
+    mint: "TEXT NOT NULL" => row.mint.clone(),
+    symbol: "TEXT NOT NULL" => row.symbol.clone(),
+    tracked_mint: "TEXT NOT NULL" => row.tracked_mint.clone(),
+});
+
+sql_row!(VeMndeRow, VE_MNDE_ACCOUNT_TABLE, version = 2, |row| {
+    pubkey: "TEXT NOT NULL PRIMARY KEY" => row.pubkey.clone(),
+    voter_authority: "TEXT NOT NULL" => row.voter_authority.clone(),
+    voting_power: "TEXT NOT NULL" => row.voting_power.to_string(),
+    owner: "TEXT NOT NULL" => row.owner.clone(),
+    baseline_vote_weight: "TEXT NOT NULL" => row.baseline_vote_weight.to_string(),
+    locked_vote_weight: "TEXT NOT NULL" => row.locked_vote_weight.to_string(),
+    amount_deposited_native: "INTEGER(8) NOT NULL" => row.amount_deposited_native as i64,
+    active_deposits: "INTEGER(1) NOT NULL" => row.active_deposits,
+    max_lockup_end_ts: "INTEGER(8) NULL" => row.max_lockup_end_ts,
+});
+
+sql_row!(VeMndeDepositRow, VE_MNDE_DEPOSIT_TABLE, version = 1, |row| {
+    deposit_id: "TEXT NOT NULL PRIMARY KEY" => row.deposit_id.clone(),
+    voter: "TEXT NOT NULL" => row.voter.clone(),
+    deposit_index: "INTEGER(1) NOT NULL" => row.deposit_index,
+    voter_authority: "TEXT NOT NULL" => row.voter_authority.clone(),
+    voting_mint: "TEXT NOT NULL" => row.voting_mint.clone(),
+    amount_deposited_native: "INTEGER(8) NOT NULL" => row.amount_deposited_native as i64,
+    amount_initially_locked_native: "INTEGER(8) NOT NULL" => row.amount_initially_locked_native as i64,
+    lockup_kind: "INTEGER(1) NOT NULL" => row.lockup_kind,
+    lockup_start_ts: "INTEGER(8) NOT NULL" => row.lockup_start_ts,
+    lockup_end_ts: "INTEGER(8) NOT NULL" => row.lockup_end_ts,
+    baseline_vote_weight_scaled_factor: "INTEGER(8) NOT NULL" => row.baseline_vote_weight_scaled_factor as i64,
+    max_extra_lockup_vote_weight_scaled_factor: "INTEGER(8) NOT NULL" => row.max_extra_lockup_vote_weight_scaled_factor as i64,
+    lockup_saturation_secs: "INTEGER(8) NOT NULL" => row.lockup_saturation_secs as i64,
+    digit_shift: "INTEGER(1) NOT NULL" => row.digit_shift as i64,
+    voting_power: "TEXT NOT NULL" => row.voting_power.to_string(),
+});
+
+sql_row!(NativeStakeRow, NATIVE_STAKE_ACCOUNT_TABLE, version = 1, |row| {
+    pubkey: "TEXT NOT NULL PRIMARY KEY" => row.pubkey.clone(),
+    withdraw_authority: "TEXT NOT NULL" => row.withdraw_authority.clone(),
+    amount: "TEXT NOT NULL" => row.amount.to_string(),
+});
+
+sql_row!(StakeAccountRow, STAKE_ACCOUNT_TABLE, version = 1, |row| {
+    pubkey: "TEXT NOT NULL PRIMARY KEY" => row.pubkey.clone(),
+    balance_lamports: "INTEGER(8) NOT NULL" => row.balance_lamports as i64,
+    active_delegation_lamports: "INTEGER(8) NOT NULL" => row.active_delegation_lamports as i64,
+    activating_delegation_lamports: "INTEGER(8) NOT NULL" => row.activating_delegation_lamports as i64,
+    deactivating_delegation_lamports: "INTEGER(8) NOT NULL" => row.deactivating_delegation_lamports as i64,
+    activation_epoch: "INTEGER(8) NULL" => row.activation_epoch.map(|epoch| epoch as i64),
+    deactivation_epoch: "INTEGER(8) NULL" => row.deactivation_epoch.map(|epoch| epoch as i64),
+    validator: "TEXT NULL" => row.validator.clone(),
+    stake_authority: "TEXT NOT NULL" => row.stake_authority.clone(),
+    withdraw_authority: "TEXT NOT NULL" => row.withdraw_authority.clone(),
+});
+
+sql_row!(ScanCheckpointRow, SCAN_CHECKPOINT_TABLE, version = 2, |row| {
+    processor: "TEXT NOT NULL PRIMARY KEY" => row.processor.clone(),
+    last_pubkey: "TEXT NOT NULL" => row.last_pubkey.clone(),
+    processed: "INTEGER(8) NOT NULL" => row.processed as i64,
+    slot: "INTEGER(8) NOT NULL" => row.slot as i64,
+    filter_hash: "TEXT NOT NULL" => row.filter_hash.clone(),
+});
+
+sql_row!(ProcessorCompletionRow, PROCESSOR_COMPLETION_TABLE, version = 1, |row| {
+    processor: "TEXT NOT NULL PRIMARY KEY" => row.processor.clone(),
+    slot: "INTEGER(8) NOT NULL" => row.slot as i64,
+    completed_at: "INTEGER(8) NOT NULL" => row.completed_at,
+});
+
+sql_row!(StakePoolRow, STAKE_POOL_TABLE, version = 1, |row| {
+    pubkey: "TEXT NOT NULL PRIMARY KEY" => row.pubkey.clone(),
+    program: "TEXT NOT NULL" => row.program.clone(),
+    manager: "TEXT NOT NULL" => row.manager.clone(),
+    staker: "TEXT NOT NULL" => row.staker.clone(),
+    stake_deposit_authority: "TEXT NOT NULL" => row.stake_deposit_authority.clone(),
+    validator_list: "TEXT NOT NULL" => row.validator_list.clone(),
+    reserve_stake: "TEXT NOT NULL" => row.reserve_stake.clone(),
+    pool_mint: "TEXT NOT NULL" => row.pool_mint.clone(),
+    manager_fee_account: "TEXT NOT NULL" => row.manager_fee_account.clone(),
+    total_lamports: "INTEGER(8) NOT NULL" => row.total_lamports as i64,
+    pool_token_supply: "INTEGER(8) NOT NULL" => row.pool_token_supply as i64,
+    last_update_epoch: "INTEGER(8) NOT NULL" => row.last_update_epoch as i64,
+    last_epoch_total_lamports: "INTEGER(8) NOT NULL" => row.last_epoch_total_lamports as i64,
+    last_epoch_pool_token_supply: "INTEGER(8) NOT NULL" => row.last_epoch_pool_token_supply as i64,
+    epoch_fee_numerator: "INTEGER(8) NOT NULL" => row.epoch_fee_numerator as i64,
+    epoch_fee_denominator: "INTEGER(8) NOT NULL" => row.epoch_fee_denominator as i64,
+});
+
+sql_row!(StakePoolValidatorRow, STAKE_POOL_VALIDATOR_TABLE, version = 1, |row| {
+    validator_id: "TEXT NOT NULL PRIMARY KEY" => row.validator_id.clone(),
+    stake_pool: "TEXT NOT NULL" => row.stake_pool.clone(),
+    vote_account: "TEXT NOT NULL" => row.vote_account.clone(),
+    active_stake_lamports: "INTEGER(8) NOT NULL" => row.active_stake_lamports as i64,
+    transient_stake_lamports: "INTEGER(8) NOT NULL" => row.transient_stake_lamports as i64,
+    last_update_epoch: "INTEGER(8) NOT NULL" => row.last_update_epoch as i64,
+    status: "INTEGER(1) NOT NULL" => row.status,
+});
+
+sql_row!(MsolStateRow, MSOL_STATE_TABLE, version = 1, |row| {
+    pubkey: "TEXT NOT NULL PRIMARY KEY" => row.pubkey.clone(),
+    msol_mint: "TEXT NOT NULL" => row.msol_mint.clone(),
+    admin_authority: "TEXT NOT NULL" => row.admin_authority.clone(),
+    validator_manager_authority: "TEXT NOT NULL" => row.validator_manager_authority.clone(),
+    operational_sol_account: "TEXT NOT NULL" => row.operational_sol_account.clone(),
+    treasury_msol_account: "TEXT NOT NULL" => row.treasury_msol_account.clone(),
+    validator_list: "TEXT NOT NULL" => row.validator_list.clone(),
+    stake_list: "TEXT NOT NULL" => row.stake_list.clone(),
+    reward_fee_bps: "INTEGER(4) NOT NULL" => row.reward_fee_bps as i64,
+    available_reserve_balance: "INTEGER(8) NOT NULL" => row.available_reserve_balance as i64,
+    msol_supply: "INTEGER(8) NOT NULL" => row.msol_supply as i64,
+    msol_price: "INTEGER(8) NOT NULL" => row.msol_price as i64,
+    circulating_ticket_count: "INTEGER(8) NOT NULL" => row.circulating_ticket_count as i64,
+    circulating_ticket_balance: "INTEGER(8) NOT NULL" => row.circulating_ticket_balance as i64,
+    lent_from_reserve: "INTEGER(8) NOT NULL" => row.lent_from_reserve as i64,
+    staking_sol_cap: "INTEGER(8) NOT NULL" => row.staking_sol_cap as i64,
+    min_stake: "INTEGER(8) NOT NULL" => row.min_stake as i64,
+    total_active_balance: "INTEGER(8) NOT NULL" => row.total_active_balance as i64,
+    total_validator_score: "INTEGER(4) NOT NULL" => row.total_validator_score as i64,
+    validator_count: "INTEGER(4) NOT NULL" => row.validator_count as i64,
+    stake_count: "INTEGER(4) NOT NULL" => row.stake_count as i64,
+    liq_pool_lp_supply: "INTEGER(8) NOT NULL" => row.liq_pool_lp_supply as i64,
+    liq_pool_lent_from_sol_leg: "INTEGER(8) NOT NULL" => row.liq_pool_lent_from_sol_leg as i64,
+    paused: "BOOL NOT NULL" => row.paused,
+});
+
+sql_row!(MsolValidatorRow, MSOL_VALIDATOR_TABLE, version = 1, |row| {
+    vote_account: "TEXT NOT NULL PRIMARY KEY" => row.vote_account.clone(),
+    state: "TEXT NOT NULL" => row.state.clone(),
+    list_index: "INTEGER(4) NOT NULL" => row.list_index as i64,
+    active_balance: "INTEGER(8) NOT NULL" => row.active_balance as i64,
+    score: "INTEGER(4) NOT NULL" => row.score as i64,
+    last_stake_delta_epoch: "INTEGER(8) NOT NULL" => row.last_stake_delta_epoch as i64,
+});
+
+sql_row!(MsolStakeRow, MSOL_STAKE_TABLE, version = 1, |row| {
+    stake_account: "TEXT NOT NULL PRIMARY KEY" => row.stake_account.clone(),
+    state: "TEXT NOT NULL" => row.state.clone(),
+    list_index: "INTEGER(4) NOT NULL" => row.list_index as i64,
+    last_update_delegated_lamports: "INTEGER(8) NOT NULL" => row.last_update_delegated_lamports as i64,
+    last_update_epoch: "INTEGER(8) NOT NULL" => row.last_update_epoch as i64,
+    is_emergency_unstaking: "BOOL NOT NULL" => row.is_emergency_unstaking,
+});
+
+sql_row!(VoteAccountRow, VOTE_ACCOUNT_TABLE, version = 1, |row| {
+    vote_account: "TEXT NOT NULL PRIMARY KEY" => row.vote_account.clone(),
+    node_pubkey: "TEXT NOT NULL" => row.node_pubkey.clone(),
+    authorized_withdrawer: "TEXT NOT NULL" => row.authorized_withdrawer.clone(),
+    commission: "INTEGER(1) NOT NULL" => row.commission,
+    lamports: "INTEGER(8) NOT NULL" => row.lamports as i64,
+    stake: "INTEGER(8) NOT NULL" => row.stake as i64,
+    last_vote: "INTEGER(8) NULL" => row.last_vote.map(|slot| slot as i64),
+    root_slot: "INTEGER(8) NULL" => row.root_slot.map(|slot| slot as i64),
+    credits: "INTEGER(8) NOT NULL" => row.credits as i64,
+    last_timestamp_slot: "INTEGER(8) NOT NULL" => row.last_timestamp_slot as i64,
+    last_timestamp: "INTEGER(8) NOT NULL" => row.last_timestamp,
+});
+
+sql_row!(VoteAccountEpochCreditsRow, VOTE_ACCOUNT_EPOCH_CREDITS_TABLE, version = 1, |row| {
+    id: "TEXT NOT NULL PRIMARY KEY" => row.id.clone(),
+    vote_account: "TEXT NOT NULL" => row.vote_account.clone(),
+    epoch: "INTEGER(8) NOT NULL" => row.epoch as i64,
+    credits: "INTEGER(8) NOT NULL" => row.credits as i64,
+    prev_credits: "INTEGER(8) NOT NULL" => row.prev_credits as i64,
+});
+
+sql_row!(ProtocolBalanceRow, PROTOCOL_BALANCES_TABLE, version = 1, |row| {
+    id: "TEXT NOT NULL PRIMARY KEY" => row.id.clone(),
+    label: "TEXT NOT NULL" => row.label.clone(),
+    account: "TEXT NOT NULL" => row.account.clone(),
+    mint: "TEXT NULL" => row.mint.clone(),
+    amount: "INTEGER(8) NOT NULL" => row.amount as i64,
+    token_accounts: "INTEGER(8) NOT NULL" => row.token_accounts as i64,
+});
+
+sql_row!(SupplyStatsRow, SUPPLY_STATS_TABLE, version = 1, |row| {
+    mint: "TEXT NOT NULL PRIMARY KEY" => row.mint.clone(),
+    slot: "INTEGER(8) NOT NULL" => row.slot as i64,
+    decimals: "INTEGER(2) NOT NULL" => row.decimals,
+    total_supply: "INTEGER(8) NOT NULL" => row.total_supply as i64,
+    non_circulating: "INTEGER(8) NOT NULL" => row.non_circulating as i64,
+    escrowed: "INTEGER(8) NOT NULL" => row.escrowed as i64,
+    circulating: "INTEGER(8) NOT NULL" => row.circulating as i64,
+});
+
+sql_row!(SnapshotInfoRow, SNAPSHOT_INFO_TABLE, version = 2, |row| {
+    slot: "INTEGER(8) NOT NULL PRIMARY KEY" => row.slot as i64,
+    epoch: "INTEGER(8) NOT NULL" => row.epoch as i64,
+    bank_hash: "TEXT NOT NULL" => row.bank_hash.clone(),
+    capitalization: "INTEGER(8) NOT NULL" => row.capitalization as i64,
+    parsed_at: "INTEGER(8) NOT NULL" => row.parsed_at,
+    parser_version: "TEXT NOT NULL" => row.parser_version.clone(),
+    schema_version: "INTEGER(4) NOT NULL" => row.schema_version as i64,
+    total_accounts: "INTEGER(8) NOT NULL" => row.total_accounts as i64,
+    storage_entries: "INTEGER(8) NOT NULL" => row.storage_entries as i64,
+    alive_bytes: "INTEGER(8) NOT NULL" => row.alive_bytes as i64,
+    dead_bytes: "INTEGER(8) NOT NULL" => row.dead_bytes as i64,
+    oldest_slot: "INTEGER(8) NULL" => row.oldest_slot.map(|slot| slot as i64),
+    partial: "BOOL NOT NULL" => row.partial,
+});
+
+sql_row!(ProgramLabelRow, PROGRAM_LABEL_TABLE, version = 1, |row| {
+    address: "TEXT NOT NULL PRIMARY KEY" => row.address.clone(),
+    name: "TEXT NOT NULL" => row.name.clone(),
+    category: "TEXT NOT NULL" => row.category.clone(),
+});
+
+sql_row!(RunConfigRow, RUN_CONFIG_TABLE, version = 1, |row| {
+    slot: "INTEGER(8) NOT NULL PRIMARY KEY" => row.slot as i64,
+    filters_hash: "TEXT NOT NULL" => row.filters_hash.clone(),
+    args: "TEXT NOT NULL" => row.args.clone(),
+    env_overrides: "TEXT NOT NULL" => row.env_overrides.clone(),
+    program_registry: "TEXT NOT NULL" => row.program_registry.clone(),
+});
+
+sql_row!(EligibleOwnerRow, ELIGIBLE_OWNERS_TABLE, version = 1, |row| {
+    owner: "TEXT NOT NULL PRIMARY KEY" => row.owner.clone(),
+    mint: "TEXT NOT NULL" => row.mint.clone(),
+    amount: "INTEGER(8) NOT NULL" => row.amount as i64,
+    held_amount: "INTEGER(8) NOT NULL" => row.held_amount as i64,
+    held_since_slot: "INTEGER(8) NOT NULL" => row.held_since_slot as i64,
+    owner_type: "TEXT NULL" => row.owner_type.clone(),
+});
+
+sql_row!(ClosedOwnerRow, CLOSED_OWNERS_TABLE, version = 1, |row| {
+    owner: "TEXT NOT NULL PRIMARY KEY" => row.owner.clone(),
+    mint: "TEXT NOT NULL" => row.mint.clone(),
+    previous_amount: "INTEGER(8) NOT NULL" => row.previous_amount as i64,
+    previous_slot: "INTEGER(8) NOT NULL" => row.previous_slot as i64,
+    accounts_closed: "BOOL NOT NULL" => row.accounts_closed,
+});
+
+sql_row!(SchemaVersionRow, SCHEMA_VERSION_TABLE, version = 1, |row| {
+    table_name: "TEXT NOT NULL PRIMARY KEY" => row.table_name.clone(),
+    table_version: "INTEGER(4) NOT NULL" => row.table_version as i64,
+    schema_version: "INTEGER(4) NOT NULL" => row.schema_version as i64,
+});
+
+sql_row!(ValidatorMetaCollectionRow, VALIDATOR_META_COLLECTION_TABLE, version = 1, |row| {
+    slot: "INTEGER(8) NOT NULL PRIMARY KEY" => row.slot as i64,
+    epoch: "INTEGER(8) NOT NULL" => row.epoch as i64,
+    bank_hash: "TEXT NOT NULL" => row.bank_hash.clone(),
+    credits_window: "TEXT NOT NULL" => row.credits_window.clone(),
+    capitalization: "INTEGER(8) NOT NULL" => row.capitalization as i64,
+    validator_rewards: "INTEGER(8) NOT NULL" => row.validator_rewards as i64,
+    jito_epoch: "INTEGER(8) NOT NULL" => row.jito_epoch as i64,
+    total_jito_tips: "INTEGER(8) NOT NULL" => row.total_jito_tips as i64,
+    total_priority_fees_transferred: "INTEGER(8) NOT NULL" => row.total_priority_fees_transferred as i64,
+});
+
+sql_row!(ValidatorMetaRow, VALIDATOR_META_TABLE, version = 3, |row| {
+    vote_account: "TEXT NOT NULL PRIMARY KEY" => row.vote_account.clone(),
+    commission: "INTEGER(1) NOT NULL" => row.commission,
+    mev_commission: "INTEGER(2) NULL" => row.mev_commission,
+    priority_fee_commission: "INTEGER(2) NULL" => row.priority_fee_commission,
+    priority_fee_lamports_transferred: "INTEGER(8) NULL" => row.priority_fee_lamports_transferred.map(|lamports| lamports as i64),
+    stake: "INTEGER(8) NOT NULL" => row.stake as i64,
+    credits: "INTEGER(8) NOT NULL" => row.credits as i64,
+    identity: "TEXT NOT NULL" => row.identity.clone(),
+    identity_lamports: "INTEGER(8) NOT NULL" => row.identity_lamports as i64,
+    delinquent: "BOOL NOT NULL" => row.delinquent,
+});
+
+sql_row!(StakeMetaRow, STAKE_META_TABLE, version = 2, |row| {
+    pubkey: "TEXT NOT NULL PRIMARY KEY" => row.pubkey.clone(),
+    balance_lamports: "INTEGER(8) NOT NULL" => row.balance_lamports as i64,
+    active_delegation_lamports: "INTEGER(8) NOT NULL" => row.active_delegation_lamports as i64,
+    activating_delegation_lamports: "INTEGER(8) NOT NULL" => row.activating_delegation_lamports as i64,
+    deactivating_delegation_lamports: "INTEGER(8) NOT NULL" => row.deactivating_delegation_lamports as i64,
+    activation_epoch: "INTEGER(8) NULL" => row.activation_epoch.map(|epoch| epoch as i64),
+    deactivation_epoch: "INTEGER(8) NULL" => row.deactivation_epoch.map(|epoch| epoch as i64),
+    validator: "TEXT NULL" => row.validator.clone(),
+    stake_authority: "TEXT NOT NULL" => row.stake_authority.clone(),
+    withdraw_authority: "TEXT NOT NULL" => row.withdraw_authority.clone(),
+    delegated_lamports: "INTEGER(8) NULL" => row.delegated_lamports.map(|lamports| lamports as i64),
+    credits_observed: "INTEGER(8) NULL" => row.credits_observed.map(|credits| credits as i64),
+    rent_exempt_reserve: "INTEGER(8) NULL" => row.rent_exempt_reserve.map(|lamports| lamports as i64),
+});
+
+sql_row!(StakeMetaCollectionRow, STAKE_META_COLLECTION_TABLE, version = 1, |row| {
+    slot: "INTEGER(8) NOT NULL PRIMARY KEY" => row.slot as i64,
+    epoch: "INTEGER(8) NOT NULL" => row.epoch as i64,
+});
+
+table_rows! {
+    Account(AccountRow),
+    Token(TokenRow),
+    Mint(MintRow),
+    MintAuthorityChange(MintAuthorityChangeRow),
+    UnknownMint(UnknownMintRow),
+    MissingMint(MissingMintRow),
+    TokenMetadata(TokenMetadataRow),
+    SymbolCollision(SymbolCollisionRow),
+    VeMnde(VeMndeRow),
+    VeMndeDeposit(VeMndeDepositRow),
+    NativeStake(NativeStakeRow),
+    StakeAccount(StakeAccountRow),
+    ScanCheckpoint(ScanCheckpointRow),
+    ProcessorCompletion(ProcessorCompletionRow),
+    StakePool(StakePoolRow),
+    StakePoolValidator(StakePoolValidatorRow),
+    MsolState(MsolStateRow),
+    MsolValidator(MsolValidatorRow),
+    MsolStake(MsolStakeRow),
+    VoteAccount(VoteAccountRow),
+    VoteAccountEpochCredits(VoteAccountEpochCreditsRow),
+    ProtocolBalance(ProtocolBalanceRow),
+    SupplyStats(SupplyStatsRow),
+    SnapshotInfo(SnapshotInfoRow),
+    ProgramLabel(ProgramLabelRow),
+    RunConfig(RunConfigRow),
+    EligibleOwner(EligibleOwnerRow),
+    ClosedOwner(ClosedOwnerRow),
+    SchemaVersion(SchemaVersionRow),
+    ValidatorMetaCollection(ValidatorMetaCollectionRow),
+    ValidatorMeta(ValidatorMetaRow),
+    StakeMeta(StakeMetaRow),
+    StakeMetaCollection(StakeMetaCollectionRow),
+}
+
+/// Row of the `schema_version` table recording the version of the table of the row type.
+pub fn table_version_row<R: SqlRow>() -> SchemaVersionRow {
+    SchemaVersionRow {
+        table_name: R::TABLE.to_string(),
+        table_version: R::VERSION,
+        schema_version: SCHEMA_VERSION,
+    }
+}
+
+/// Records the version of the table into the `schema_version` table, see [`crate::sql_row::create_table`].
+pub async fn record_table_version<R: SqlRow>(db_sender: &Sender<DbMessage>) -> anyhow::Result<()> {
+    create_table_with_columns(
+        db_sender,
+        SchemaVersionRow::TABLE,
+        SchemaVersionRow::COLUMNS,
+    )
+    .await?;
+    insert_single_row(db_sender, &table_version_row::<R>()).await?;
+    Ok(())
+}
+
+/// Table, version and CREATE TABLE statement of the row type.
+fn table_definition<R: SqlRow>() -> (&'static str, u32, String) {
+    (R::TABLE, R::VERSION, R::create_table_query())
+}
+
+/// Columns the downstream queries look the rows up by (e.g., the token accounts of an owner),
+/// indexed once all rows are written, see [`DbMessage::Finalize`].
+pub const TABLE_INDEXES: &[(&str, &str)] = &[
+    (TOKEN_ACCOUNT_TABLE, "owner"),
+    (TOKEN_ACCOUNT_TABLE, "mint"),
+    (TOKEN_METADATA_ACCOUNT_TABLE, "mint"),
+    (VE_MNDE_ACCOUNT_TABLE, "voter_authority"),
+    (NATIVE_STAKE_ACCOUNT_TABLE, "withdraw_authority"),
+    (STAKE_META_TABLE, "validator"),
+    (STAKE_META_TABLE, "stake_authority"),
+    (STAKE_META_TABLE, "withdraw_authority"),
+];
+
+pub fn create_index_query(table: &str, column: &str) -> String {
+    format!(
+        "CREATE INDEX IF NOT EXISTS {}_{}_idx ON {} ({})",
+        table, column, table, column
+    )
+}
+
+/// Indexes the written tables and optimizes the output, sent once all rows are inserted.
+pub async fn finalize_output(db_sender: &Sender<DbMessage>) -> anyhow::Result<()> {
+    let (response_tx, response_rx) = oneshot::channel();
+    db_sender
+        .send(DbMessage::Finalize {
+            response: response_tx,
+        })
+        .await?;
+    response_rx.await?
+}
+
+/// CREATE TABLE statements of all tables with a fixed layout, each preceded by its version,
+/// followed by the indexes created by the finalization of the output;
+/// the tables of the processors config are defined at runtime and are not listed.
+pub fn schema_dump() -> String {
+    let definitions = [
+        table_definition::<SchemaVersionRow>(),
+        table_definition::<SnapshotInfoRow>(),
+        table_definition::<RunConfigRow>(),
+        table_definition::<ProgramLabelRow>(),
+        table_definition::<ScanCheckpointRow>(),
+        table_definition::<ProcessorCompletionRow>(),
+        table_definition::<AccountRow>(),
+        table_definition::<TokenRow>(),
+        table_definition::<MintRow>(),
+        table_definition::<MintAuthorityChangeRow>(),
+        table_definition::<UnknownMintRow>(),
+        table_definition::<MissingMintRow>(),
+        table_definition::<TokenMetadataRow>(),
+        table_definition::<SymbolCollisionRow>(),
+        table_definition::<VeMndeRow>(),
+        table_definition::<VeMndeDepositRow>(),
+        table_definition::<NativeStakeRow>(),
+        table_definition::<StakeAccountRow>(),
+        table_definition::<StakePoolRow>(),
+        table_definition::<StakePoolValidatorRow>(),
+        table_definition::<MsolStateRow>(),
+        table_definition::<MsolValidatorRow>(),
+        table_definition::<MsolStakeRow>(),
+        table_definition::<VoteAccountRow>(),
+        table_definition::<VoteAccountEpochCreditsRow>(),
+        table_definition::<ProtocolBalanceRow>(),
+        table_definition::<SupplyStatsRow>(),
+        table_definition::<EligibleOwnerRow>(),
+        table_definition::<ClosedOwnerRow>(),
+        table_definition::<ValidatorMetaCollectionRow>(),
+        table_definition::<ValidatorMetaRow>(),
+        table_definition::<StakeMetaRow>(),
+        table_definition::<StakeMetaCollectionRow>(),
+    ];
+    let mut dump = format!(
+        "-- schema version {}\nPRAGMA user_version = {};\n",
+        SCHEMA_VERSION, SCHEMA_VERSION
+    );
+    for (table, version, query) in definitions {
+        dump.push_str(&format!("\n-- {} version {}\n{}\n", table, version, query));
+    }
+    dump.push_str("\n-- indexes\n");
+    for (table, column) in TABLE_INDEXES {
+        dump.push_str(&format!("{};\n", create_index_query(table, column)));
+    }
+    dump
+}
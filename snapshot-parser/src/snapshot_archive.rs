@@ -0,0 +1,223 @@
+use {
+    serde::Deserialize,
+    solana_accounts_db::blockhash_queue::BlockhashQueue,
+    solana_runtime::snapshot_utils::{
+        parse_full_snapshot_archive_filename, parse_incremental_snapshot_archive_filename,
+    },
+    solana_sdk::{
+        clock::{Epoch, Slot, UnixTimestamp},
+        hard_forks::HardForks,
+        hash::Hash,
+    },
+    std::{
+        collections::HashMap,
+        fs::File,
+        io::{BufReader, Read},
+        path::{Path, PathBuf},
+    },
+    tracing::{debug, info},
+};
+
+/// Snapshot archive of the ledger path as named by its file, see [`find_snapshot_archives`].
+#[derive(Clone, Debug)]
+pub struct SnapshotArchiveInfo {
+    pub path: PathBuf,
+    pub slot: Slot,
+    /// hash of the accounts the archive name carries, it is not the bank hash
+    pub hash: String,
+}
+
+/// Full snapshot archive of the ledger path and the incremental one built on top of it, if any.
+/// The bank is loaded at the slot of the incremental archive when there is one.
+#[derive(Clone, Debug)]
+pub struct SnapshotArchives {
+    pub full: SnapshotArchiveInfo,
+    pub incremental: Option<SnapshotArchiveInfo>,
+}
+
+impl SnapshotArchives {
+    /// Slot the bank is loaded at.
+    pub fn slot(&self) -> Slot {
+        self.incremental
+            .as_ref()
+            .map_or(self.full.slot, |incremental| incremental.slot)
+    }
+}
+
+/// Leading fields of the bank serialized in the snapshot manifest (`snapshots/<slot>/<slot>` of the archive),
+/// in the order of the versioned bank of the snapshot format 1.2.0; the rest of the manifest is not read.
+#[derive(Deserialize)]
+#[allow(dead_code)]
+struct ManifestBankFields {
+    blockhash_queue: BlockhashQueue,
+    ancestors: HashMap<Slot, usize>,
+    hash: Hash,
+    parent_hash: Hash,
+    parent_slot: Slot,
+    hard_forks: HardForks,
+    transaction_count: u64,
+    tick_height: u64,
+    signature_count: u64,
+    capitalization: u64,
+    max_tick_height: u64,
+    hashes_per_tick: Option<u64>,
+    ticks_per_slot: u64,
+    ns_per_slot: u128,
+    genesis_creation_time: UnixTimestamp,
+    slots_per_year: f64,
+    accounts_data_len: u64,
+    slot: Slot,
+    epoch: Epoch,
+    block_height: u64,
+}
+
+/// Bank values read from the manifest of a snapshot archive, see [`read_snapshot_manifest`].
+#[derive(Clone, Debug)]
+pub struct SnapshotManifestInfo {
+    /// snapshot format version of the archive (the `version` file)
+    pub version: String,
+    pub slot: Slot,
+    pub epoch: Epoch,
+    pub block_height: u64,
+    pub bank_hash: String,
+    pub parent_slot: Slot,
+    pub capitalization: u64,
+}
+
+/// Finds the full snapshot archive of the highest slot in the ledger path
+/// and the incremental archive of the highest slot based on it.
+pub fn find_snapshot_archives(ledger_path: &Path) -> anyhow::Result<Option<SnapshotArchives>> {
+    let mut full_archives = Vec::new();
+    let mut incremental_archives = Vec::new();
+    for entry in std::fs::read_dir(ledger_path)? {
+        let path = entry?.path();
+        let Some(name) = path.file_name().and_then(|name| name.to_str()) else {
+            continue;
+        };
+        if let Ok((slot, hash, _)) = parse_full_snapshot_archive_filename(name) {
+            full_archives.push(SnapshotArchiveInfo {
+                slot,
+                hash: hash.0.to_string(),
+                path,
+            });
+        } else if let Ok((base_slot, slot, hash, _)) =
+            parse_incremental_snapshot_archive_filename(name)
+        {
+            incremental_archives.push((
+                base_slot,
+                SnapshotArchiveInfo {
+                    slot,
+                    hash: hash.0.to_string(),
+                    path,
+                },
+            ));
+        }
+    }
+    let Some(full) = full_archives.into_iter().max_by_key(|archive| archive.slot) else {
+        return Ok(None);
+    };
+    let incremental = incremental_archives
+        .into_iter()
+        .filter(|(base_slot, _)| *base_slot == full.slot)
+        .map(|(_, archive)| archive)
+        .max_by_key(|archive| archive.slot);
+    Ok(Some(SnapshotArchives { full, incremental }))
+}
+
+/// Reads the bank values from the manifest of the snapshot archive without unpacking the account storages.
+/// The manifest is stored at the start of the archive, so only its first entries are decompressed.
+/// Supports the `.tar.zst` and `.tar` archives.
+pub fn read_snapshot_manifest(
+    archive: &SnapshotArchiveInfo,
+) -> anyhow::Result<SnapshotManifestInfo> {
+    let file = BufReader::new(File::open(&archive.path)?);
+    let name = archive.path.to_string_lossy();
+    let reader: Box<dyn Read> = if name.ends_with(".tar.zst") {
+        Box::new(zstd::stream::read::Decoder::new(file)?)
+    } else if name.ends_with(".tar") {
+        Box::new(file)
+    } else {
+        anyhow::bail!(
+            "Cannot read the manifest of {:?}, only .tar.zst and .tar archives are supported",
+            archive.path
+        );
+    };
+    let manifest_path = format!("snapshots/{}/{}", archive.slot, archive.slot);
+    let mut version = None;
+    let mut bank_fields: Option<ManifestBankFields> = None;
+    let mut tar = tar::Archive::new(reader);
+    for entry in tar.entries()? {
+        let mut entry = entry?;
+        let entry_path = entry.path()?.to_string_lossy().to_string();
+        if entry_path == "version" {
+            let mut content = String::new();
+            entry.read_to_string(&mut content)?;
+            version = Some(content.trim().to_string());
+        } else if entry_path == manifest_path {
+            bank_fields = Some(bincode::deserialize_from(&mut entry)?);
+        } else if entry_path.starts_with("accounts") {
+            // the account storages follow the manifest, nothing more to read
+            break;
+        }
+        if version.is_some() && bank_fields.is_some() {
+            break;
+        }
+    }
+    debug!("Read manifest {} of {:?}", manifest_path, archive.path);
+    let bank_fields = bank_fields
+        .ok_or_else(|| anyhow::anyhow!("No manifest {} in {:?}", manifest_path, archive.path))?;
+    Ok(SnapshotManifestInfo {
+        version: version.unwrap_or_default(),
+        slot: bank_fields.slot,
+        epoch: bank_fields.epoch,
+        block_height: bank_fields.block_height,
+        bank_hash: bank_fields.hash.to_string(),
+        parent_slot: bank_fields.parent_slot,
+        capitalization: bank_fields.capitalization,
+    })
+}
+
+/// Logs the snapshot archives of the ledger path and the manifest of the one the bank is loaded at,
+/// returning the manifest when it could be read.
+pub fn log_snapshot_archives(ledger_path: &Path) -> Option<SnapshotManifestInfo> {
+    let archives = match find_snapshot_archives(ledger_path) {
+        Ok(Some(archives)) => archives,
+        Ok(None) => {
+            info!("No snapshot archive found in {:?}", ledger_path);
+            return None;
+        }
+        Err(e) => {
+            info!("Cannot list snapshot archives of {:?}: {}", ledger_path, e);
+            return None;
+        }
+    };
+    info!(
+        "Full snapshot archive of slot {} (hash {}): {:?}",
+        archives.full.slot, archives.full.hash, archives.full.path
+    );
+    if let Some(incremental) = &archives.incremental {
+        info!(
+            "Incremental snapshot archive of slot {} (hash {}): {:?}",
+            incremental.slot, incremental.hash, incremental.path
+        );
+    }
+    let archive = archives.incremental.as_ref().unwrap_or(&archives.full);
+    match read_snapshot_manifest(archive) {
+        Ok(manifest) => {
+            info!(
+                "Snapshot manifest: version {}, epoch {}, slot {}, block height {}, bank hash {}, capitalization {}",
+                manifest.version,
+                manifest.epoch,
+                manifest.slot,
+                manifest.block_height,
+                manifest.bank_hash,
+                manifest.capitalization
+            );
+            Some(manifest)
+        }
+        Err(e) => {
+            info!("Cannot read the snapshot manifest: {}", e);
+            None
+        }
+    }
+}
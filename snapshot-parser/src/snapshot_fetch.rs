@@ -0,0 +1,213 @@
+use crate::snapshot_archive::find_snapshot_archives;
+use crate::temp_file::TempFileGuard;
+use sha2::{Digest, Sha256};
+use solana_runtime::snapshot_utils::parse_full_snapshot_archive_filename;
+use std::path::{Path, PathBuf};
+use tokio::fs::File;
+use tokio::io::AsyncWriteExt;
+use tracing::{info, warn};
+
+/// Endpoint of the RPC nodes redirecting to their latest full snapshot archive.
+const RPC_SNAPSHOT_PATH: &str = "snapshot.tar.bz2";
+/// Genesis archive unpacked by `open_genesis_config` when `genesis.bin` is missing.
+const GENESIS_ARCHIVE_NAME: &str = "genesis.tar.bz2";
+const GENESIS_BIN_NAME: &str = "genesis.bin";
+/// Download progress is logged every this many bytes.
+const PROGRESS_LOG_BYTES: u64 = 1024 * 1024 * 1024;
+
+/// Where the full snapshot archive is downloaded from when the ledger directory does not contain one.
+#[derive(Debug, Clone, Default)]
+#[cfg_attr(feature = "cli", derive(clap::Args))]
+pub struct SnapshotFetchArgs {
+    /// URLs of full snapshot archives (snapshot-<slot>-<hash>.tar.zst), tried in order
+    #[cfg_attr(feature = "cli", arg(long, env, value_delimiter = ','))]
+    pub snapshot_url: Vec<String>,
+
+    /// Base URLs of RPC nodes or snapshot mirrors serving /snapshot.tar.bz2 and /genesis.tar.bz2,
+    /// tried in order after the snapshot URLs
+    #[cfg_attr(feature = "cli", arg(long, env, value_delimiter = ','))]
+    pub snapshot_source: Vec<String>,
+
+    /// Expected SHA-256 (hex) of the downloaded snapshot archive
+    #[cfg_attr(feature = "cli", arg(long, env))]
+    pub snapshot_sha256: Option<String>,
+}
+
+impl SnapshotFetchArgs {
+    pub fn is_set(&self) -> bool {
+        !self.snapshot_url.is_empty() || !self.snapshot_source.is_empty()
+    }
+
+    /// Snapshot archive URLs in the order they are tried.
+    fn archive_urls(&self) -> Vec<String> {
+        self.snapshot_url
+            .iter()
+            .cloned()
+            .chain(
+                self.snapshot_source.iter().map(|source| {
+                    format!("{}/{}", source.trim_end_matches('/'), RPC_SNAPSHOT_PATH)
+                }),
+            )
+            .collect()
+    }
+}
+
+/// Downloads the full snapshot archive and the genesis into the ledger directory,
+/// where `create_bank_from_ledger` picks the archive up and unpacks it while loading the bank.
+/// A ledger directory already containing a full snapshot archive is used as it is.
+pub async fn fetch_snapshot(ledger_path: &Path, args: &SnapshotFetchArgs) -> anyhow::Result<()> {
+    if let Some(archive_path) = find_full_snapshot_archive(ledger_path)? {
+        info!(
+            "Ledger path already contains full snapshot archive {:?}, skipping download",
+            archive_path
+        );
+    } else {
+        let mut errors = vec![];
+        let mut downloaded = false;
+        for url in args.archive_urls() {
+            match download_snapshot_archive(&url, ledger_path, args.snapshot_sha256.as_deref())
+                .await
+            {
+                Ok(archive_path) => {
+                    info!("Full snapshot archive downloaded to {:?}", archive_path);
+                    downloaded = true;
+                    break;
+                }
+                Err(e) => {
+                    warn!("Failed to download snapshot from {}: {:?}", url, e);
+                    errors.push(format!("{}: {}", url, e));
+                }
+            }
+        }
+        if !downloaded {
+            anyhow::bail!(
+                "Snapshot could not be downloaded from any source: {}",
+                errors.join("; ")
+            );
+        }
+    }
+
+    if !ledger_path.join(GENESIS_BIN_NAME).exists()
+        && !ledger_path.join(GENESIS_ARCHIVE_NAME).exists()
+    {
+        fetch_genesis(ledger_path, &args.snapshot_source).await?;
+    }
+    Ok(())
+}
+
+fn find_full_snapshot_archive(ledger_path: &Path) -> anyhow::Result<Option<PathBuf>> {
+    Ok(find_snapshot_archives(ledger_path)?.map(|archives| archives.full.path))
+}
+
+/// The archive name (slot and hash) is taken from the final URL, the RPC endpoint redirects to it.
+async fn download_snapshot_archive(
+    url: &str,
+    ledger_path: &Path,
+    expected_sha256: Option<&str>,
+) -> anyhow::Result<PathBuf> {
+    let response = reqwest::get(url).await?.error_for_status()?;
+    let archive_name = response
+        .url()
+        .path_segments()
+        .and_then(|mut segments| segments.next_back())
+        .map(str::to_string)
+        .unwrap_or_default();
+    let (slot, hash, _) = parse_full_snapshot_archive_filename(&archive_name).map_err(|e| {
+        anyhow::anyhow!(
+            "URL {} does not point to a full snapshot archive: {}",
+            response.url(),
+            e
+        )
+    })?;
+    info!(
+        "Downloading full snapshot archive of slot {} (hash {}) from {}",
+        slot,
+        hash.0,
+        response.url()
+    );
+    let archive_path = ledger_path.join(&archive_name);
+    let sha256 = download_to_file(response, &archive_path).await?;
+    if let Some(expected_sha256) = expected_sha256 {
+        if !sha256.eq_ignore_ascii_case(expected_sha256.trim()) {
+            std::fs::remove_file(&archive_path)?;
+            anyhow::bail!(
+                "SHA-256 of snapshot archive {} is {}, expected {}",
+                archive_name,
+                sha256,
+                expected_sha256
+            );
+        }
+        info!("SHA-256 of snapshot archive {} verified", archive_name);
+    }
+    Ok(archive_path)
+}
+
+async fn fetch_genesis(ledger_path: &Path, sources: &[String]) -> anyhow::Result<()> {
+    for source in sources {
+        let url = format!("{}/{}", source.trim_end_matches('/'), GENESIS_ARCHIVE_NAME);
+        let result = async {
+            let response = reqwest::get(&url).await?.error_for_status()?;
+            download_to_file(response, &ledger_path.join(GENESIS_ARCHIVE_NAME)).await
+        }
+        .await;
+        match result {
+            Ok(_) => {
+                info!("Genesis downloaded from {}", url);
+                return Ok(());
+            }
+            Err(e) => warn!("Failed to download genesis from {}: {:?}", url, e),
+        }
+    }
+    anyhow::bail!(
+        "Ledger path {:?} contains no genesis and it could not be downloaded from any --snapshot-source",
+        ledger_path
+    )
+}
+
+/// Streams the response into a temporary file promoted to `path` once complete
+/// and returns the hex encoded SHA-256 of the content.
+async fn download_to_file(mut response: reqwest::Response, path: &Path) -> anyhow::Result<String> {
+    let file_name = path
+        .file_name()
+        .ok_or_else(|| anyhow::anyhow!("Invalid download path {:?}", path))?
+        .to_string_lossy();
+    let temp_path = path.with_file_name(format!("_{}.tmp", file_name));
+    let mut temp_guard = TempFileGuard::new(temp_path.clone());
+
+    let total_bytes = response.content_length();
+    let mut file = File::create(&temp_path).await?;
+    let mut hasher = Sha256::new();
+    let mut downloaded_bytes = 0u64;
+    let mut next_progress_log = PROGRESS_LOG_BYTES;
+    while let Some(chunk) = response.chunk().await? {
+        hasher.update(&chunk);
+        file.write_all(&chunk).await?;
+        downloaded_bytes += chunk.len() as u64;
+        if downloaded_bytes >= next_progress_log {
+            info!(
+                "Downloaded {} of {:?} bytes of {}",
+                downloaded_bytes, total_bytes, file_name
+            );
+            next_progress_log += PROGRESS_LOG_BYTES;
+        }
+    }
+    file.flush().await?;
+    drop(file);
+    if let Some(total_bytes) = total_bytes {
+        if downloaded_bytes != total_bytes {
+            anyhow::bail!(
+                "Download of {} is incomplete: {} of {} bytes",
+                file_name,
+                downloaded_bytes,
+                total_bytes
+            );
+        }
+    }
+
+    temp_guard.promote(path)?;
+    Ok(hasher
+        .finalize()
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect())
+}
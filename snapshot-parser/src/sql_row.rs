@@ -0,0 +1,351 @@
+use crate::db_message::{DbMessage, OwnedSqlValue, TableColumns};
+use crate::progress_bar::ProgressCounter;
+use crate::schema::{record_table_version, TableRow, TableRows};
+use rusqlite::ToSql;
+use std::sync::Arc;
+use tokio::sync::mpsc::Sender;
+use tokio::sync::oneshot;
+
+/// Row that can be inserted into an output table (a SQLite table or a Parquet file).
+/// Column names, column types and the bound values are defined together by the [`crate::sql_row`] macro
+/// so neither the CREATE TABLE nor the INSERT statement can drift from the parameters.
+/// The row is sent to the output writer as its [`TableRow`] variant, see [`crate::table_rows`].
+pub trait SqlRow: Clone + Into<TableRow> {
+    const TABLE: &'static str;
+    /// Version of the table layout, to be bumped with any change of its columns, see [`crate::schema::SCHEMA_VERSION`].
+    const VERSION: u32;
+    /// Pairs of column name and its SQL type definition.
+    const COLUMNS: &'static [(&'static str, &'static str)];
+
+    fn to_values(&self) -> Vec<OwnedSqlValue>;
+
+    fn to_params(&self) -> Vec<Box<dyn ToSql + Send + Sync>> {
+        self.to_values()
+            .into_iter()
+            .map(|value| Box::new(value) as Box<dyn ToSql + Send + Sync>)
+            .collect()
+    }
+
+    fn column_names() -> Vec<&'static str> {
+        Self::COLUMNS.iter().map(|(name, _)| *name).collect()
+    }
+
+    fn create_table_query() -> String {
+        create_table_query(Self::TABLE, Self::COLUMNS)
+    }
+
+    fn insert_query() -> String {
+        insert_query(Self::TABLE, &Self::column_names())
+    }
+}
+
+pub fn create_table_query(table: &str, columns: &[(&str, &str)]) -> String {
+    format!(
+        "CREATE TABLE IF NOT EXISTS {} (\n{}\n);",
+        table,
+        columns
+            .iter()
+            .map(|(name, sql_type)| format!("    {} {}", name, sql_type))
+            .collect::<Vec<_>>()
+            .join(",\n")
+    )
+}
+
+pub fn insert_query(table: &str, columns: &[&str]) -> String {
+    format!(
+        "INSERT OR REPLACE INTO {} ({}) SELECT {};",
+        table,
+        columns.join(", "),
+        vec!["?"; columns.len()].join(", ")
+    )
+}
+
+/// Multi-row INSERT of `rows` rows, see [`DbMessage::InsertBatch`].
+pub fn insert_rows_query(table: &str, columns: &[&str], rows: usize) -> String {
+    let row_placeholders = format!("({})", vec!["?"; columns.len()].join(", "));
+    format!(
+        "INSERT OR REPLACE INTO {} ({}) VALUES {};",
+        table,
+        columns.join(", "),
+        vec![row_placeholders; rows].join(", ")
+    )
+}
+
+/// Creates the table of the row type and records its version into the `schema_version` table.
+pub async fn create_table<R: SqlRow>(db_sender: &Sender<DbMessage>) -> anyhow::Result<usize> {
+    let created = create_table_with_columns(db_sender, R::TABLE, R::COLUMNS).await?;
+    record_table_version::<R>(db_sender).await?;
+    Ok(created)
+}
+
+/// Creates a table whose columns are known at runtime only, e.g., from the processors config.
+pub async fn create_table_with_columns(
+    db_sender: &Sender<DbMessage>,
+    table: &'static str,
+    columns: TableColumns,
+) -> anyhow::Result<usize> {
+    let (response_tx, response_rx) = oneshot::channel();
+    db_sender
+        .send(DbMessage::CreateTable {
+            table,
+            columns,
+            response: response_tx,
+        })
+        .await?;
+    response_rx.await?
+}
+
+pub async fn insert_row<R: SqlRow>(
+    db_sender: &Sender<DbMessage>,
+    progress_counter: &Arc<ProgressCounter>,
+    row: &R,
+) -> anyhow::Result<usize> {
+    let response_rx = send_insert(db_sender, row).await?;
+    progress_counter.inc();
+    response_rx.await?
+}
+
+/// Inserts a single row that is not reported in the progress, e.g., a metadata row.
+pub async fn insert_single_row<R: SqlRow>(
+    db_sender: &Sender<DbMessage>,
+    row: &R,
+) -> anyhow::Result<usize> {
+    send_insert(db_sender, row).await?.await?
+}
+
+/// Rows inserted at once, see [`RowBatch`].
+pub const INSERT_BATCH_ROWS: usize = 1000;
+
+/// Rows of one table collected by a processor and sent as a single [`DbMessage::InsertBatch`]
+/// once [`INSERT_BATCH_ROWS`] of them are collected. The rows left must be sent by [`RowBatch::flush`].
+pub struct RowBatch<R: SqlRow> {
+    progress_counter: Arc<ProgressCounter>,
+    rows: Vec<R>,
+}
+
+impl<R: SqlRow> RowBatch<R>
+where
+    Vec<R>: Into<TableRows>,
+{
+    pub fn new(progress_counter: Arc<ProgressCounter>) -> Self {
+        Self {
+            progress_counter,
+            rows: Vec::with_capacity(INSERT_BATCH_ROWS),
+        }
+    }
+
+    /// Adds the row to the batch, the full batch is inserted; returns the number of the inserted rows.
+    pub async fn push(&mut self, db_sender: &Sender<DbMessage>, row: R) -> anyhow::Result<usize> {
+        self.rows.push(row);
+        if self.rows.len() < INSERT_BATCH_ROWS {
+            return Ok(0);
+        }
+        self.flush(db_sender).await
+    }
+
+    /// Inserts the collected rows.
+    pub async fn flush(&mut self, db_sender: &Sender<DbMessage>) -> anyhow::Result<usize> {
+        if self.rows.is_empty() {
+            return Ok(0);
+        }
+        let rows = std::mem::replace(&mut self.rows, Vec::with_capacity(INSERT_BATCH_ROWS));
+        let row_count = rows.len() as u64;
+        let (response_tx, response_rx) = oneshot::channel();
+        db_sender
+            .send(DbMessage::InsertBatch {
+                rows: rows.into(),
+                response: response_tx,
+            })
+            .await?;
+        self.progress_counter.add(row_count);
+        response_rx.await?
+    }
+}
+
+/// Row of a table created by [`create_table_with_columns`], the values are bound in the order of its columns.
+#[derive(Clone)]
+pub struct ConfiguredRow {
+    pub table: &'static str,
+    pub columns: TableColumns,
+    pub values: Vec<OwnedSqlValue>,
+}
+
+/// Inserts values bound in the order of the columns of a table created by [`create_table_with_columns`].
+pub async fn insert_values(
+    db_sender: &Sender<DbMessage>,
+    progress_counter: &Arc<ProgressCounter>,
+    table: &'static str,
+    columns: TableColumns,
+    values: Vec<OwnedSqlValue>,
+) -> anyhow::Result<usize> {
+    if values.len() != columns.len() {
+        anyhow::bail!(
+            "Table {} has {} columns, got {} values",
+            table,
+            columns.len(),
+            values.len()
+        );
+    }
+    let row = TableRow::Configured(ConfiguredRow {
+        table,
+        columns,
+        values,
+    });
+    let response_rx = send_row(db_sender, row).await?;
+    progress_counter.inc();
+    response_rx.await?
+}
+
+async fn send_insert<R: SqlRow>(
+    db_sender: &Sender<DbMessage>,
+    row: &R,
+) -> anyhow::Result<oneshot::Receiver<anyhow::Result<usize>>> {
+    send_row(db_sender, row.clone().into()).await
+}
+
+async fn send_row(
+    db_sender: &Sender<DbMessage>,
+    row: TableRow,
+) -> anyhow::Result<oneshot::Receiver<anyhow::Result<usize>>> {
+    let (response_tx, response_rx) = oneshot::channel();
+    db_sender
+        .send(DbMessage::Insert {
+            row: Box::new(row),
+            response: response_tx,
+        })
+        .await?;
+    Ok(response_rx)
+}
+
+/// Implements [`SqlRow`] for a row type of the table version, listing each column with its SQL type
+/// and the expression binding its value.
+///
+/// ```ignore
+/// sql_row!(NativeStakeRow, NATIVE_STAKE_ACCOUNT_TABLE, version = 1, |row| {
+///     pubkey: "TEXT NOT NULL PRIMARY KEY" => row.pubkey.clone(),
+///     amount: "TEXT NOT NULL" => row.amount.to_string(),
+/// });
+/// ```
+#[macro_export]
+macro_rules! sql_row {
+    ($row_type:ty, $table:expr, version = $version:literal, |$row:ident| { $($column:ident: $sql_type:literal => $value:expr),* $(,)? }) => {
+        impl $crate::sql_row::SqlRow for $row_type {
+            const TABLE: &'static str = $table;
+            const VERSION: u32 = $version;
+            const COLUMNS: &'static [(&'static str, &'static str)] =
+                &[$((stringify!($column), $sql_type)),*];
+
+            fn to_values(&self) -> Vec<$crate::db_message::OwnedSqlValue> {
+                let $row = self;
+                vec![$(Into::<$crate::db_message::OwnedSqlValue>::into($value),)*]
+            }
+        }
+    };
+}
+
+/// Defines the [`TableRow`] and [`TableRows`] enums of the schema, a variant per row type implementing [`SqlRow`],
+/// so the processors send typed rows and the output writers convert them to the values of the table columns.
+///
+/// ```ignore
+/// table_rows! {
+///     Token(TokenRow),
+///     Mint(MintRow),
+/// }
+/// ```
+#[macro_export]
+macro_rules! table_rows {
+    ($($variant:ident($row_type:ty)),* $(,)?) => {
+        /// Row of a table of the schema, sent by [`crate::db_message::DbMessage::Insert`].
+        #[derive(Clone, serde::Serialize, serde::Deserialize)]
+        pub enum TableRow {
+            $($variant($row_type),)*
+            /// Row of a table defined at runtime, e.g., by the processors config; it cannot be deserialized.
+            #[serde(skip)]
+            Configured($crate::sql_row::ConfiguredRow),
+        }
+
+        /// Rows of a table of the schema, sent by [`crate::db_message::DbMessage::InsertBatch`].
+        #[derive(Clone, serde::Serialize, serde::Deserialize)]
+        pub enum TableRows {
+            $($variant(Vec<$row_type>),)*
+        }
+
+        impl TableRow {
+            pub fn table(&self) -> &'static str {
+                match self {
+                    $(TableRow::$variant(_) => <$row_type as $crate::sql_row::SqlRow>::TABLE,)*
+                    TableRow::Configured(row) => row.table,
+                }
+            }
+
+            pub fn columns(&self) -> $crate::db_message::TableColumns {
+                match self {
+                    $(TableRow::$variant(_) => <$row_type as $crate::sql_row::SqlRow>::COLUMNS,)*
+                    TableRow::Configured(row) => row.columns,
+                }
+            }
+
+            /// Values bound in the order of the columns.
+            pub fn into_values(self) -> Vec<$crate::db_message::OwnedSqlValue> {
+                match self {
+                    $(TableRow::$variant(row) => $crate::sql_row::SqlRow::to_values(&row),)*
+                    TableRow::Configured(row) => row.values,
+                }
+            }
+        }
+
+        impl TableRows {
+            pub fn table(&self) -> &'static str {
+                match self {
+                    $(TableRows::$variant(_) => <$row_type as $crate::sql_row::SqlRow>::TABLE,)*
+                }
+            }
+
+            pub fn columns(&self) -> $crate::db_message::TableColumns {
+                match self {
+                    $(TableRows::$variant(_) => <$row_type as $crate::sql_row::SqlRow>::COLUMNS,)*
+                }
+            }
+
+            pub fn len(&self) -> usize {
+                match self {
+                    $(TableRows::$variant(rows) => rows.len(),)*
+                }
+            }
+
+            pub fn is_empty(&self) -> bool {
+                self.len() == 0
+            }
+
+            pub fn truncate(&mut self, len: usize) {
+                match self {
+                    $(TableRows::$variant(rows) => rows.truncate(len),)*
+                }
+            }
+
+            /// Values of each of the rows bound in the order of the columns.
+            pub fn into_values(self) -> Vec<Vec<$crate::db_message::OwnedSqlValue>> {
+                match self {
+                    $(TableRows::$variant(rows) => rows
+                        .iter()
+                        .map($crate::sql_row::SqlRow::to_values)
+                        .collect(),)*
+                }
+            }
+        }
+
+        $(
+            impl From<$row_type> for TableRow {
+                fn from(row: $row_type) -> Self {
+                    TableRow::$variant(row)
+                }
+            }
+
+            impl From<Vec<$row_type>> for TableRows {
+                fn from(rows: Vec<$row_type>) -> Self {
+                    TableRows::$variant(rows)
+                }
+            }
+        )*
+    };
+}
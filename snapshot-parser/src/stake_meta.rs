@@ -1,6 +1,9 @@
 use {
-    crate::serde_serialize::{option_pubkey_string_conversion, pubkey_string_conversion},
-    log::{error, info},
+    crate::{
+        serde_serialize::{option_pubkey_string_conversion, pubkey_string_conversion},
+        utils::read_from_json_file,
+    },
+    rayon::prelude::*,
     serde::{Deserialize, Serialize},
     solana_accounts_db::accounts_index::ScanConfig,
     solana_program::{
@@ -10,11 +13,9 @@ use {
         stake_history::{Epoch, StakeHistory, StakeHistoryEntry},
     },
     solana_runtime::bank::Bank,
-    solana_sdk::{
-        account::{Account, AccountSharedData},
-        epoch_info::EpochInfo,
-    },
-    std::{fmt::Debug, sync::Arc},
+    solana_sdk::{account::ReadableAccount, epoch_info::EpochInfo},
+    std::{collections::HashSet, fmt::Debug, path::Path, sync::Arc},
+    tracing::{error, info},
 };
 
 #[derive(Clone, Deserialize, Serialize, Debug, Eq, PartialEq)]
@@ -25,12 +26,22 @@ pub struct StakeMeta {
     pub active_delegation_lamports: u64,
     pub activating_delegation_lamports: u64,
     pub deactivating_delegation_lamports: u64,
+    /// epoch the delegation was activated at, `None` when not delegated
+    pub activation_epoch: Option<Epoch>,
+    /// epoch the delegation was deactivated at, `None` when not delegated or not deactivated
+    pub deactivation_epoch: Option<Epoch>,
     #[serde(with = "option_pubkey_string_conversion")]
     pub validator: Option<Pubkey>,
     #[serde(with = "pubkey_string_conversion")]
     pub stake_authority: Pubkey,
     #[serde(with = "pubkey_string_conversion")]
     pub withdraw_authority: Pubkey,
+    /// lamports of the delegation the activation and deactivation is computed for, `None` when not delegated
+    pub delegated_lamports: Option<u64>,
+    /// vote credits of the validator the stake rewards were last paid for, `None` when not delegated
+    pub credits_observed: Option<u64>,
+    /// lamports of the balance reserved for the rent exemption, `None` when not initialized
+    pub rent_exempt_reserve: Option<u64>,
 }
 
 impl Ord for StakeMeta {
@@ -45,14 +56,127 @@ impl PartialOrd<Self> for StakeMeta {
     }
 }
 
+/// Cluster stake of an epoch of the stake history sysvar, the delegations are activated and deactivated by it.
+#[derive(Clone, Deserialize, Serialize, Debug, Eq, PartialEq)]
+pub struct StakeHistoryEpoch {
+    pub epoch: Epoch,
+    pub effective: u64,
+    pub activating: u64,
+    pub deactivating: u64,
+}
+
 #[derive(Clone, Deserialize, Serialize, Debug)]
 pub struct StakeMetaCollection {
     pub epoch: Epoch,
     pub slot: u64,
     pub stake_metas: Vec<StakeMeta>,
+    /// stake history sysvar of the bank, newest epoch first, to recompute the activation of the delegations;
+    /// empty in the collections written before it was added
+    #[serde(default)]
+    pub stake_history: Vec<StakeHistoryEpoch>,
 }
 
 pub fn generate_stake_meta_collection(bank: &Arc<Bank>) -> anyhow::Result<StakeMetaCollection> {
+    generate_stake_meta_collection_with_callback(bank, || {})
+}
+
+/// Loads a stake meta collection written earlier for the same bank, e.g., by the validator CLI,
+/// instead of scanning the stake accounts again. Fails when the collection is of another slot than the bank.
+pub fn load_stake_meta_collection(
+    bank: &Bank,
+    collection_path: &Path,
+) -> anyhow::Result<StakeMetaCollection> {
+    let collection: StakeMetaCollection = read_from_json_file(&collection_path).map_err(|e| {
+        anyhow::anyhow!(
+            "Could not load stake meta collection {:?}: {}",
+            collection_path,
+            e
+        )
+    })?;
+    if (collection.epoch, collection.slot) != (bank.epoch(), bank.slot()) {
+        anyhow::bail!(
+            "Stake meta collection {:?} is of epoch {} slot {}, the bank of epoch {} slot {}",
+            collection_path,
+            collection.epoch,
+            collection.slot,
+            bank.epoch(),
+            bank.slot()
+        );
+    }
+    info!(
+        "Stake meta collection loaded from {:?}: {} stake accounts",
+        collection_path,
+        collection.stake_metas.len()
+    );
+    Ok(collection)
+}
+
+/// Stake metas kept by [`StakeMetaCollection::filtered`]: the ones of any of the `stake_authorities`
+/// delegated to any of the `vote_accounts`, an empty set does not restrict the stake metas.
+#[derive(Clone, Debug, Default)]
+pub struct StakeMetaFilter {
+    /// stake or withdraw authorities of the kept stake accounts
+    pub stake_authorities: HashSet<Pubkey>,
+    /// vote accounts the kept stake accounts are delegated to
+    pub vote_accounts: HashSet<Pubkey>,
+}
+
+impl StakeMetaFilter {
+    pub fn is_empty(&self) -> bool {
+        self.stake_authorities.is_empty() && self.vote_accounts.is_empty()
+    }
+
+    pub fn matches(&self, stake_meta: &StakeMeta) -> bool {
+        (self.stake_authorities.is_empty()
+            || self.stake_authorities.contains(&stake_meta.stake_authority)
+            || self
+                .stake_authorities
+                .contains(&stake_meta.withdraw_authority))
+            && (self.vote_accounts.is_empty()
+                || stake_meta
+                    .validator
+                    .is_some_and(|validator| self.vote_accounts.contains(&validator)))
+    }
+}
+
+impl StakeMetaCollection {
+    /// Collection of the stake metas matching the filter, the stake history is kept whole.
+    pub fn filtered(&self, filter: &StakeMetaFilter) -> StakeMetaCollection {
+        StakeMetaCollection {
+            epoch: self.epoch,
+            slot: self.slot,
+            stake_metas: self
+                .stake_metas
+                .iter()
+                .filter(|stake_meta| filter.matches(stake_meta))
+                .cloned()
+                .collect(),
+            stake_history: self.stake_history.clone(),
+        }
+    }
+}
+
+/// Same as [`generate_stake_meta_collection`], reporting every decoded stake account to the progress counter.
+#[cfg(feature = "cli")]
+pub fn generate_stake_meta_collection_with_progress(
+    bank: &Arc<Bank>,
+    progress_counter: Option<&crate::progress_bar::ProgressCounter>,
+) -> anyhow::Result<StakeMetaCollection> {
+    generate_stake_meta_collection_with_callback(bank, || {
+        if let Some(progress_counter) = progress_counter {
+            progress_counter.inc();
+        }
+    })
+}
+
+/// Same as [`generate_stake_meta_collection`], calling `on_stake_account` for every decoded stake account.
+pub fn generate_stake_meta_collection_with_callback<F>(
+    bank: &Arc<Bank>,
+    on_stake_account: F,
+) -> anyhow::Result<StakeMetaCollection>
+where
+    F: Fn() + Sync,
+{
     assert!(bank.is_frozen());
 
     let EpochInfo {
@@ -61,11 +185,10 @@ pub fn generate_stake_meta_collection(bank: &Arc<Bank>) -> anyhow::Result<StakeM
         ..
     } = bank.get_epoch_info();
 
-    let history_account = <AccountSharedData as Into<Account>>::into(
-        bank.get_account(&solana_program::sysvar::stake_history::ID)
-            .expect("Failed to fetch the stake history"),
-    );
-    let history: StakeHistory = bincode::deserialize(&history_account.data)?;
+    let history_account = bank
+        .get_account(&solana_program::sysvar::stake_history::ID)
+        .expect("Failed to fetch the stake history");
+    let history: StakeHistory = bincode::deserialize(history_account.data())?;
     info!("Stake history loaded.");
 
     let stake_accounts_raw =
@@ -73,71 +196,38 @@ pub fn generate_stake_meta_collection(bank: &Arc<Bank>) -> anyhow::Result<StakeM
 
     info!("Stake processors loaded: {}", stake_accounts_raw.len());
 
-    let mut stake_metas: Vec<StakeMeta> = Default::default();
-
-    for (pubkey, shared_account) in stake_accounts_raw {
-        let account = <AccountSharedData as Into<Account>>::into(shared_account);
-        let stake_account: StakeStateV2 = match bincode::deserialize(&account.data) {
-            Ok(account) => account,
-            Err(err) => {
-                error!("Error parsing stake account {}: {}", pubkey, err);
-                continue;
-            }
-        };
-
-        let (
-            validator,
-            active_delegation_lamports,
-            activating_delegation_lamports,
-            deactivating_delegation_lamports,
-        ) = match stake_account.stake() {
-            Some(stake) => {
-                let StakeHistoryEntry {
-                    effective,
-                    activating,
-                    deactivating,
-                } = stake
-                    .delegation
-                    .stake_activating_and_deactivating(epoch, &history, None);
-                (
-                    Some(stake.delegation.voter_pubkey),
-                    effective,
-                    activating,
-                    deactivating,
-                )
+    let mut stake_metas: Vec<StakeMeta> = stake_accounts_raw
+        .par_iter()
+        .filter_map(|(pubkey, account)| {
+            on_stake_account();
+            match bincode::deserialize::<StakeStateV2>(account.data()) {
+                Ok(stake_account) => Some(build_stake_meta(
+                    *pubkey,
+                    account.lamports(),
+                    &stake_account,
+                    epoch,
+                    &history,
+                )),
+                Err(err) => {
+                    error!("Error parsing stake account {}: {}", pubkey, err);
+                    None
+                }
             }
-            None => (None, 0, 0, 0),
-        };
-
-        stake_metas.push(StakeMeta {
-            pubkey,
-            balance_lamports: account.lamports,
-            active_delegation_lamports,
-            activating_delegation_lamports,
-            deactivating_delegation_lamports,
-            validator,
-            stake_authority: stake_account.meta().unwrap_or_default().authorized.staker,
-            withdraw_authority: stake_account
-                .meta()
-                .unwrap_or_default()
-                .authorized
-                .withdrawer,
         })
-    }
+        .collect();
+    drop(stake_accounts_raw);
     info!("Collected all stake account metas: {}", stake_metas.len());
 
-    let total_active: u64 = stake_metas
-        .iter()
-        .map(|s| s.active_delegation_lamports)
-        .sum();
-    let total_activating: u64 = stake_metas
-        .iter()
-        .map(|s| s.activating_delegation_lamports)
-        .sum();
-    let total_deactivating: u64 = stake_metas
-        .iter()
-        .map(|s| s.deactivating_delegation_lamports)
-        .sum();
+    let (total_active, total_activating, total_deactivating) = stake_metas.iter().fold(
+        (0u64, 0u64, 0u64),
+        |(active, activating, deactivating), s| {
+            (
+                active + s.active_delegation_lamports,
+                activating + s.activating_delegation_lamports,
+                deactivating + s.deactivating_delegation_lamports,
+            )
+        },
+    );
 
     info!("Total activated stake: {}", lamports_to_sol(total_active));
     info!(
@@ -149,12 +239,82 @@ pub fn generate_stake_meta_collection(bank: &Arc<Bank>) -> anyhow::Result<StakeM
         lamports_to_sol(total_deactivating)
     );
 
-    stake_metas.sort();
+    // pubkeys are unique, the unstable sort gives the same order as the stable one
+    stake_metas.par_sort_unstable();
     info!("Sorted stake account metas");
 
     Ok(StakeMetaCollection {
         epoch,
         slot: absolute_slot,
         stake_metas,
+        stake_history: history
+            .iter()
+            .map(|(epoch, entry)| StakeHistoryEpoch {
+                epoch: *epoch,
+                effective: entry.effective,
+                activating: entry.activating,
+                deactivating: entry.deactivating,
+            })
+            .collect(),
     })
 }
+
+fn build_stake_meta(
+    pubkey: Pubkey,
+    balance_lamports: u64,
+    stake_account: &StakeStateV2,
+    epoch: Epoch,
+    history: &StakeHistory,
+) -> StakeMeta {
+    let (
+        validator,
+        active_delegation_lamports,
+        activating_delegation_lamports,
+        deactivating_delegation_lamports,
+        activation_epoch,
+        deactivation_epoch,
+        delegated_lamports,
+        credits_observed,
+    ) = match stake_account.stake() {
+        Some(stake) => {
+            let StakeHistoryEntry {
+                effective,
+                activating,
+                deactivating,
+            } = stake
+                .delegation
+                .stake_activating_and_deactivating(epoch, history, None);
+            (
+                Some(stake.delegation.voter_pubkey),
+                effective,
+                activating,
+                deactivating,
+                Some(stake.delegation.activation_epoch),
+                // not deactivated delegations have the deactivation epoch set to u64::MAX
+                (stake.delegation.deactivation_epoch != Epoch::MAX)
+                    .then_some(stake.delegation.deactivation_epoch),
+                Some(stake.delegation.stake),
+                Some(stake.credits_observed),
+            )
+        }
+        None => (None, 0, 0, 0, None, None, None, None),
+    };
+    let meta = stake_account.meta();
+    let authorized = meta.unwrap_or_default().authorized;
+
+    StakeMeta {
+        pubkey,
+        balance_lamports,
+        active_delegation_lamports,
+        activating_delegation_lamports,
+        deactivating_delegation_lamports,
+        activation_epoch,
+        deactivation_epoch,
+        validator,
+        stake_authority: authorized.staker,
+        withdraw_authority: authorized.withdrawer,
+        delegated_lamports,
+        credits_observed,
+        rent_exempt_reserve: meta.map(|meta| meta.rent_exempt_reserve),
+    }
+}
@@ -0,0 +1,105 @@
+use async_trait::async_trait;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::Mutex;
+use tokio::time::Instant;
+use tracing::info;
+
+#[async_trait]
+pub trait ProcessorCallback: Send + Sync {
+    async fn get_count(&self) -> (String, u64);
+}
+
+/// Plain named counter reported in the final stats without a progress bar.
+pub struct NamedCounter {
+    name: String,
+    counter: AtomicU64,
+}
+
+impl NamedCounter {
+    pub fn new(name: String) -> Self {
+        Self {
+            name,
+            counter: AtomicU64::new(0),
+        }
+    }
+
+    pub fn get(&self) -> u64 {
+        self.counter.load(Ordering::Relaxed)
+    }
+
+    pub fn add(&self, value: u64) {
+        self.counter.fetch_add(value, Ordering::Relaxed);
+    }
+}
+
+#[async_trait]
+impl ProcessorCallback for NamedCounter {
+    async fn get_count(&self) -> (String, u64) {
+        (self.name.clone(), self.get())
+    }
+}
+
+pub struct Stats {
+    inserts_time: Instant,
+    callbacks: Arc<Mutex<Vec<Arc<dyn ProcessorCallback>>>>,
+    /// named phases run before the processing, e.g., the bank loading
+    phases: std::sync::Mutex<Vec<(String, Duration)>>,
+}
+
+impl Stats {
+    pub fn new() -> Self {
+        Self {
+            inserts_time: Instant::now(),
+            callbacks: Arc::new(Mutex::new(Vec::new())),
+            phases: std::sync::Mutex::new(Vec::new()),
+        }
+    }
+
+    pub async fn add_callback(&self, callback: Arc<dyn ProcessorCallback>) {
+        let mut self_callbacks = self.callbacks.lock().await;
+        self_callbacks.push(callback);
+    }
+
+    pub async fn add_callbacks(&self, callbacks: &[Arc<dyn ProcessorCallback>]) {
+        let mut self_callbacks = self.callbacks.lock().await;
+        self_callbacks.extend(callbacks.iter().cloned());
+    }
+
+    pub fn add_phase(&self, name: &str, duration: Duration) {
+        self.phases
+            .lock()
+            .unwrap()
+            .push((name.to_string(), duration));
+    }
+
+    /// Current values of the counters, e.g., to be exported as metrics.
+    pub async fn counts(&self) -> Vec<(String, u64)> {
+        let callbacks = self.callbacks.lock().await;
+        let mut counts = Vec::with_capacity(callbacks.len());
+        for callback in callbacks.iter() {
+            counts.push(callback.get_count().await);
+        }
+        counts
+    }
+
+    fn info(msg: &str, value: u64) {
+        info!("Dumped {} {} accounts", msg, value);
+    }
+
+    pub async fn print_info(&self) {
+        let insert_duration = Instant::now() - self.inserts_time;
+        info!("Done! (sqlite processing in {:?})", insert_duration);
+
+        for (name, duration) in self.phases.lock().unwrap().iter() {
+            info!("Phase {} took {:?}", name, duration);
+        }
+
+        let callbacks = self.callbacks.lock().await;
+        for callback in callbacks.iter() {
+            let (name, value) = callback.get_count().await;
+            Stats::info(&name, value);
+        }
+    }
+}
@@ -0,0 +1,154 @@
+use {
+    crate::program_registry::{ProgramCategory, ProgramRegistry},
+    crate::serde_serialize::pubkey_string_conversion,
+    serde::{Deserialize, Serialize},
+    solana_accounts_db::accounts_index::ScanConfig,
+    solana_program::{clock::Epoch, pubkey::Pubkey},
+    solana_runtime::bank::Bank,
+    solana_sdk::{account::ReadableAccount, clock::Slot},
+    std::collections::{BTreeMap, HashMap},
+    tracing::info,
+};
+
+/// Accounts of one owner program with data length in `[min_data_len, max_data_len]`.
+/// Bucket 0 holds the accounts without data, bucket `k` the lengths from `2^(k-1)` to `2^k - 1`.
+#[derive(Clone, Deserialize, Serialize, Debug, Eq, PartialEq)]
+pub struct DataSizeBucket {
+    pub min_data_len: u64,
+    pub max_data_len: u64,
+    pub accounts: u64,
+}
+
+#[derive(Clone, Deserialize, Serialize, Debug, Eq, PartialEq)]
+pub struct ProgramStorage {
+    #[serde(with = "pubkey_string_conversion")]
+    pub owner: Pubkey,
+    /// name of the owner in the program registry
+    pub name: Option<String>,
+    pub category: ProgramCategory,
+    pub accounts: u64,
+    pub lamports: u64,
+    pub data_bytes: u64,
+    pub max_data_len: u64,
+    /// non-empty buckets only, ordered by the data length
+    pub data_size_histogram: Vec<DataSizeBucket>,
+}
+
+/// Account counts and data sizes of the whole snapshot per owner program,
+/// compared between epochs to spot unexpected state growth.
+#[derive(Clone, Deserialize, Serialize, Debug, Default, Eq, PartialEq)]
+pub struct StorageReport {
+    pub epoch: Epoch,
+    pub slot: Slot,
+    pub total_accounts: u64,
+    pub total_data_bytes: u64,
+    /// ordered by the data bytes, largest first
+    pub programs: Vec<ProgramStorage>,
+}
+
+#[derive(Default)]
+struct OwnerAccumulator {
+    accounts: u64,
+    lamports: u64,
+    data_bytes: u64,
+    max_data_len: u64,
+    /// bucket index to the number of accounts
+    buckets: BTreeMap<u32, u64>,
+}
+
+fn bucket_index(data_len: u64) -> u32 {
+    u64::BITS - data_len.leading_zeros()
+}
+
+fn bucket_bounds(index: u32) -> (u64, u64) {
+    match index {
+        0 => (0, 0),
+        64 => (1 << 63, u64::MAX),
+        _ => (1 << (index - 1), (1 << index) - 1),
+    }
+}
+
+/// Scans all accounts of the bank and sums them by their owner, labeled by the program registry.
+pub fn generate_storage_report(
+    bank: &Bank,
+    registry: &ProgramRegistry,
+) -> anyhow::Result<StorageReport> {
+    info!("Scanning all accounts for the storage report...");
+    let mut owners: HashMap<Pubkey, OwnerAccumulator> = HashMap::new();
+    bank.rc
+        .accounts
+        .accounts_db
+        .scan_accounts(
+            &bank.ancestors,
+            bank.bank_id(),
+            |scanned_account| {
+                let Some((_, account, _)) = scanned_account else {
+                    return;
+                };
+                // zero lamport accounts are deleted, the same as in `Bank::get_program_accounts`
+                if account.lamports() == 0 {
+                    return;
+                }
+                let data_len = account.data().len() as u64;
+                let owner = owners.entry(*account.owner()).or_default();
+                owner.accounts += 1;
+                owner.lamports = owner.lamports.saturating_add(account.lamports());
+                owner.data_bytes += data_len;
+                owner.max_data_len = owner.max_data_len.max(data_len);
+                *owner.buckets.entry(bucket_index(data_len)).or_default() += 1;
+            },
+            &ScanConfig {
+                abort: None,
+                collect_all_unsorted: true,
+            },
+        )
+        .map_err(|e| anyhow::anyhow!("Scan of accounts for storage report failed: {:?}", e))?;
+
+    let mut programs = owners
+        .into_iter()
+        .map(|(owner, accumulator)| {
+            let known_program = registry.get(&owner);
+            ProgramStorage {
+                owner,
+                name: known_program.map(|program| program.name.clone()),
+                category: registry.owner_category(&owner),
+                accounts: accumulator.accounts,
+                lamports: accumulator.lamports,
+                data_bytes: accumulator.data_bytes,
+                max_data_len: accumulator.max_data_len,
+                data_size_histogram: accumulator
+                    .buckets
+                    .into_iter()
+                    .map(|(index, accounts)| {
+                        let (min_data_len, max_data_len) = bucket_bounds(index);
+                        DataSizeBucket {
+                            min_data_len,
+                            max_data_len,
+                            accounts,
+                        }
+                    })
+                    .collect(),
+            }
+        })
+        .collect::<Vec<_>>();
+    programs.sort_by(|a, b| {
+        b.data_bytes
+            .cmp(&a.data_bytes)
+            .then_with(|| a.owner.cmp(&b.owner))
+    });
+
+    let report = StorageReport {
+        epoch: bank.epoch(),
+        slot: bank.slot(),
+        total_accounts: programs.iter().map(|program| program.accounts).sum(),
+        total_data_bytes: programs.iter().map(|program| program.data_bytes).sum(),
+        programs,
+    };
+    info!(
+        "Storage report: {} accounts with {} data bytes owned by {} programs",
+        report.total_accounts,
+        report.total_data_bytes,
+        report.programs.len()
+    );
+    Ok(report)
+}
@@ -0,0 +1,189 @@
+use crate::snapshot_archive::SnapshotManifestInfo;
+use tracing_subscriber::{
+    filter::LevelFilter, layer::SubscriberExt, util::SubscriberInitExt, EnvFilter,
+};
+
+/// Tracing options shared by the CLIs.
+#[derive(clap::Args, Debug, Clone, Default)]
+pub struct TelemetryArgs {
+    /// OTLP gRPC endpoint the spans of the run are exported to (e.g., http://localhost:4317 of a Tempo),
+    /// requires the `otlp` feature; the spans are not exported when not set
+    #[arg(long, env)]
+    pub otlp_endpoint: Option<String>,
+
+    /// W3C trace context (`00-<trace id>-<parent span id>-<flags>`) of the pipeline the run is a step of,
+    /// the exported spans join its trace instead of starting a new one
+    #[arg(long, env = "TRACEPARENT")]
+    pub otlp_trace_parent: Option<String>,
+}
+
+#[cfg(feature = "otlp")]
+type OtlpLayer = Option<
+    tracing_opentelemetry::OpenTelemetryLayer<
+        tracing_subscriber::Registry,
+        opentelemetry_sdk::trace::Tracer,
+    >,
+>;
+
+/// The OTLP exporter, started once the snapshot to be parsed is known.
+#[cfg(feature = "otlp")]
+struct OtlpExport {
+    service_name: &'static str,
+    endpoint: String,
+    trace_parent: Option<String>,
+    layer: tracing_subscriber::reload::Handle<OtlpLayer, tracing_subscriber::Registry>,
+    started: bool,
+}
+
+/// Exports the spans buffered by the OTLP exporter when dropped, keep it until the end of `main`.
+#[must_use]
+pub struct TelemetryGuard {
+    #[cfg(feature = "otlp")]
+    otlp: Option<OtlpExport>,
+}
+
+impl Drop for TelemetryGuard {
+    fn drop(&mut self) {
+        #[cfg(feature = "otlp")]
+        if self.otlp.as_ref().is_some_and(|otlp| otlp.started) {
+            opentelemetry::global::shutdown_tracer_provider();
+        }
+    }
+}
+
+impl TelemetryGuard {
+    /// Root span of the parsing of the snapshot, tagged by its slot and epoch.
+    /// With the `otlp_endpoint` set, it starts the span export with the slot and epoch as the resource attributes,
+    /// so the traces of the runs are looked up by the epoch, and the span is the child of the `otlp_trace_parent`
+    /// to show the download, the parsing and the settlement of the epoch as one trace;
+    /// only the spans created after the call are exported.
+    pub fn snapshot_span(
+        &mut self,
+        manifest: Option<&SnapshotManifestInfo>,
+    ) -> anyhow::Result<tracing::Span> {
+        #[cfg(feature = "otlp")]
+        if let Some(otlp) = self.otlp.as_mut() {
+            return otlp.start(manifest);
+        }
+        Ok(new_snapshot_span(manifest))
+    }
+}
+
+fn new_snapshot_span(manifest: Option<&SnapshotManifestInfo>) -> tracing::Span {
+    let span = tracing::info_span!(
+        "snapshot",
+        slot = tracing::field::Empty,
+        epoch = tracing::field::Empty
+    );
+    if let Some(manifest) = manifest {
+        span.record("slot", manifest.slot);
+        span.record("epoch", manifest.epoch);
+    }
+    span
+}
+
+#[cfg(feature = "otlp")]
+impl OtlpExport {
+    fn start(&mut self, manifest: Option<&SnapshotManifestInfo>) -> anyhow::Result<tracing::Span> {
+        use opentelemetry::propagation::TextMapPropagator;
+        use opentelemetry::trace::{TraceContextExt, TracerProvider as _};
+        use opentelemetry::KeyValue;
+        use opentelemetry_otlp::WithExportConfig;
+
+        anyhow::ensure!(!self.started, "the span export is already started");
+        let mut resource = vec![KeyValue::new("service.name", self.service_name)];
+        if let Some(manifest) = manifest {
+            resource.push(KeyValue::new("snapshot.slot", manifest.slot as i64));
+            resource.push(KeyValue::new("snapshot.epoch", manifest.epoch as i64));
+        }
+        let tracer_provider = opentelemetry_otlp::new_pipeline()
+            .tracing()
+            .with_exporter(
+                opentelemetry_otlp::new_exporter()
+                    .tonic()
+                    .with_endpoint(&self.endpoint),
+            )
+            .with_trace_config(
+                opentelemetry_sdk::trace::Config::default()
+                    .with_resource(opentelemetry_sdk::Resource::new(resource)),
+            )
+            .install_batch(opentelemetry_sdk::runtime::Tokio)?;
+        let tracer = tracer_provider.tracer(self.service_name);
+        opentelemetry::global::set_tracer_provider(tracer_provider);
+        self.layer
+            .reload(Some(tracing_opentelemetry::layer().with_tracer(tracer)))?;
+        self.started = true;
+        tracing::info!("Exporting the spans to {}", self.endpoint);
+
+        let parent = match &self.trace_parent {
+            Some(trace_parent) => {
+                let carrier = std::collections::HashMap::from([(
+                    "traceparent".to_string(),
+                    trace_parent.clone(),
+                )]);
+                let parent =
+                    opentelemetry_sdk::propagation::TraceContextPropagator::new().extract(&carrier);
+                if !parent.span().span_context().is_valid() {
+                    tracing::warn!(
+                        "Ignoring the trace parent {}, not a W3C trace context",
+                        trace_parent
+                    );
+                }
+                parent
+            }
+            None => opentelemetry::Context::new(),
+        };
+        // the root span takes the current OpenTelemetry context as its parent
+        let _parent = parent.attach();
+        Ok(new_snapshot_span(manifest))
+    }
+}
+
+/// Installs the tracing subscriber writing the events to stderr, filtered by `RUST_LOG` (`info` by default),
+/// with the records of the `log` crate (e.g., of the solana crates) forwarded to it.
+/// With the `otlp_endpoint` set, the spans are exported as well, tagged by the `service_name`,
+/// from the [`TelemetryGuard::snapshot_span`] on; the OTLP exporter runs on the tokio runtime,
+/// so that has to be called within one.
+pub fn init_telemetry(
+    service_name: &'static str,
+    args: &TelemetryArgs,
+) -> anyhow::Result<TelemetryGuard> {
+    let env_filter = EnvFilter::builder()
+        .with_default_directive(LevelFilter::INFO.into())
+        .from_env_lossy()
+        .add_directive("solana_metrics::metrics=error".parse()?);
+    let fmt_layer = tracing_subscriber::fmt::layer().with_writer(std::io::stderr);
+
+    #[cfg(feature = "otlp")]
+    {
+        // the exporter is installed later, with the snapshot slot and epoch known
+        let (otlp_layer, otlp_handle) = tracing_subscriber::reload::Layer::new(OtlpLayer::None);
+        tracing_subscriber::registry()
+            .with(otlp_layer)
+            .with(env_filter)
+            .with(fmt_layer)
+            .try_init()?;
+        Ok(TelemetryGuard {
+            otlp: args.otlp_endpoint.as_ref().map(|endpoint| OtlpExport {
+                service_name,
+                endpoint: endpoint.clone(),
+                trace_parent: args.otlp_trace_parent.clone(),
+                layer: otlp_handle,
+                started: false,
+            }),
+        })
+    }
+
+    #[cfg(not(feature = "otlp"))]
+    {
+        if args.otlp_endpoint.is_some() {
+            anyhow::bail!("--otlp-endpoint requires the parser built with the otlp feature");
+        }
+        let _ = service_name;
+        tracing_subscriber::registry()
+            .with(env_filter)
+            .with(fmt_layer)
+            .try_init()?;
+        Ok(TelemetryGuard {})
+    }
+}
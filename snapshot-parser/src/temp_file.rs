@@ -1,13 +1,26 @@
-use log::error;
 use std::path::{Path, PathBuf};
+use tracing::{error, info};
 
 pub struct TempFileGuard {
     pub path: Option<PathBuf>,
+    /// the file is kept when not promoted, e.g., to resume from it
+    keep: bool,
 }
 
 impl TempFileGuard {
     pub fn new(path: PathBuf) -> Self {
-        Self { path: Some(path) }
+        Self {
+            path: Some(path),
+            keep: false,
+        }
+    }
+
+    /// Guard of a temp file that is not removed when the guard is dropped without being promoted.
+    pub fn persistent(path: PathBuf) -> Self {
+        Self {
+            path: Some(path),
+            keep: true,
+        }
     }
 
     pub fn promote<P: AsRef<Path>>(&mut self, new_name: P) -> std::io::Result<()> {
@@ -21,6 +34,10 @@ impl TempFileGuard {
 impl Drop for TempFileGuard {
     fn drop(&mut self) {
         if let Some(path) = &self.path {
+            if self.keep {
+                info!("Keeping temp file {:?}", path);
+                return;
+            }
             if let Err(e) = std::fs::remove_file(path) {
                 error!("Failed to remove temp DB: {}", e);
             }
@@ -1,25 +1,104 @@
+use crate::temp_file::TempFileGuard;
 use serde::de::DeserializeOwned;
 use serde::Serialize;
 use std::path::Path;
 use std::{
     fs::File,
-    io::{BufReader, BufWriter, Write},
+    io::{BufReader, BufWriter, Read, Write},
 };
 
+/// Compression of the JSON files, picked by the extension of the file name.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "cli", derive(clap::ValueEnum))]
+pub enum JsonCompression {
+    #[default]
+    None,
+    /// `.zst` files
+    Zstd,
+    /// `.gz` files
+    Gzip,
+}
+
+impl JsonCompression {
+    pub fn of_path(path: &Path) -> Self {
+        match path.extension().and_then(|extension| extension.to_str()) {
+            Some("zst") => JsonCompression::Zstd,
+            Some("gz") => JsonCompression::Gzip,
+            _ => JsonCompression::None,
+        }
+    }
+
+    fn extension(&self) -> Option<&'static str> {
+        match self {
+            JsonCompression::None => None,
+            JsonCompression::Zstd => Some("zst"),
+            JsonCompression::Gzip => Some("gz"),
+        }
+    }
+
+    /// The path with the extension of the compression appended, unless it is compressed already
+    /// (e.g., `stakes.json` is `stakes.json.zst` with zstd).
+    pub fn output_path(&self, path: &str) -> String {
+        match self.extension() {
+            Some(extension)
+                if JsonCompression::of_path(Path::new(path)) == JsonCompression::None =>
+            {
+                format!("{}.{}", path, extension)
+            }
+            _ => path.to_string(),
+        }
+    }
+}
+
+/// Writes the JSON into a temporary file that is promoted to `out_path` once complete,
+/// so an interrupted run never leaves a truncated output behind.
+/// The JSON is serialized straight into the file, compressed by the extension of `out_path`, see [`JsonCompression`].
 pub fn write_to_json_file<T: Serialize>(data: &T, out_path: &str) -> anyhow::Result<()> {
-    let file = File::create(out_path)?;
-    let mut writer = BufWriter::new(file);
-    let json = serde_json::to_string_pretty(data)?;
-    writer.write_all(json.as_bytes())?;
+    let out_path = Path::new(out_path);
+    let temp_file_name = format!(
+        "_{}.tmp",
+        out_path
+            .file_name()
+            .ok_or_else(|| anyhow::anyhow!("Invalid output path {:?}", out_path))?
+            .to_string_lossy()
+    );
+    let temp_path = out_path.with_file_name(temp_file_name);
+    let mut temp_guard = TempFileGuard::new(temp_path.clone());
+
+    let writer = BufWriter::new(File::create(&temp_path)?);
+    // the encoders are finished into the file writer, flushed for all compressions
+    let mut writer = match JsonCompression::of_path(out_path) {
+        JsonCompression::None => serialize_json(writer, data)?,
+        JsonCompression::Zstd => {
+            serialize_json(zstd::stream::write::Encoder::new(writer, 0)?, data)?.finish()?
+        }
+        JsonCompression::Gzip => serialize_json(
+            flate2::write::GzEncoder::new(writer, flate2::Compression::default()),
+            data,
+        )?
+        .finish()?,
+    };
     writer.flush()?;
+    drop(writer);
 
+    temp_guard.promote(out_path)?;
     Ok(())
 }
 
+fn serialize_json<W: Write, T: Serialize>(mut writer: W, data: &T) -> anyhow::Result<W> {
+    serde_json::to_writer_pretty(&mut writer, data)?;
+    Ok(writer)
+}
+
+/// Reads the JSON file, decompressed by the extension of `in_path`, see [`JsonCompression`].
 pub fn read_from_json_file<P: AsRef<Path>, T: DeserializeOwned>(in_path: &P) -> anyhow::Result<T> {
     let file = File::open(in_path)?;
-    let reader = BufReader::new(file);
-    let result: T = serde_json::from_reader(reader)?;
+    let reader: Box<dyn Read> = match JsonCompression::of_path(in_path.as_ref()) {
+        JsonCompression::None => Box::new(file),
+        JsonCompression::Zstd => Box::new(zstd::stream::read::Decoder::new(file)?),
+        JsonCompression::Gzip => Box::new(flate2::read::GzDecoder::new(file)),
+    };
+    let result: T = serde_json::from_reader(BufReader::new(reader))?;
 
     Ok(result)
 }